@@ -1,4 +1,255 @@
-#[derive(Debug)]
-pub struct _Opt;
+//! Server settings read from the environment at startup.
+//!
+//! - `ESCAM_HOST`: address the HTTP server binds to. Defaults to `127.0.0.1`; set it to
+//!   `0.0.0.0` to accept connections from other machines (e.g. inside a container).
+//! - `ESCAM_PORT`: port the HTTP server binds to. Defaults to `8080`.
+//! - `ESCAM_DATA_DIR`: directory holding the database, the uploaded CSV files
+//!   (`{template_id}_{md5}.csv`), the generated PDFs and the fonts. Defaults to the working
+//!   directory.
+//! - `ESCAM_DB_PATH`, `ESCAM_PDF_DIR`, `ESCAM_FONTS_DIR`: override the location of the
+//!   SQLite database (`templify.sqlite`), the PDF output directory (`pdfs`) and the fonts
+//!   directory (`fonts`), which otherwise live in the data directory.
+//! - `ESCAM_JOB_TTL_SECS`: how long finished jobs are kept, in seconds. Defaults to 30
+//!   minutes.
+//! - `ESCAM_MAX_CSV_UPLOAD_MB`: largest CSV upload accepted, in megabytes. Defaults to 50.
+//! - `ESCAM_CALLBACK_SECRET`: secret signing job callbacks. Callbacks are refused without
+//!   it.
+//! - `ESCAM_CALLBACK_ALLOWED_HOSTS`: comma-separated callback hosts that may be loopback,
+//!   link-local or private addresses.
+//!
+//! Invalid values are logged and replaced with the defaults. Everything but the bind
+//! address is gathered in `Config`, read once at startup and shared with the handlers as
+//! `web::Data<Config>`.
 
+use std::env;
+use std::fmt;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
+/// Environment variable overriding the bind address.
+const HOST_ENV: &str = "ESCAM_HOST";
+/// Environment variable overriding the bind port.
+const PORT_ENV: &str = "ESCAM_PORT";
+/// Bind address used when `HOST_ENV` is not set.
+const DEFAULT_HOST: &str = "127.0.0.1";
+/// Bind port used when `PORT_ENV` is not set.
+const DEFAULT_PORT: u16 = 8080;
+
+/// Where the HTTP server listens.
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    /// Host name or IP address to bind to.
+    pub host: String,
+    /// TCP port to bind to.
+    pub port: u16,
+}
+
+impl ServerConfig {
+    /// Reads the bind address from `ESCAM_HOST` and `ESCAM_PORT`, falling back to
+    /// `127.0.0.1:8080`.
+    pub fn from_env() -> Self {
+        let host = match env::var(HOST_ENV) {
+            Ok(value) if !value.trim().is_empty() => value.trim().to_string(),
+            Ok(_) => {
+                log::warn!("Ignoring empty {}; using {}", HOST_ENV, DEFAULT_HOST);
+                DEFAULT_HOST.to_string()
+            }
+            Err(_) => DEFAULT_HOST.to_string(),
+        };
+        let port = match env::var(PORT_ENV) {
+            Ok(value) => match value.trim().parse::<u16>() {
+                Ok(port) if port > 0 => port,
+                _ => {
+                    log::warn!(
+                        "Ignoring invalid {}={:?}; using {}",
+                        PORT_ENV,
+                        value,
+                        DEFAULT_PORT
+                    );
+                    DEFAULT_PORT
+                }
+            },
+            Err(_) => DEFAULT_PORT,
+        };
+        ServerConfig { host, port }
+    }
+
+    /// The base URL of the server, e.g. `http://127.0.0.1:8080`. IPv6 addresses are
+    /// wrapped in brackets.
+    pub fn url(&self) -> String {
+        match self.host.parse::<IpAddr>() {
+            Ok(IpAddr::V6(ip)) => format!("http://[{}]:{}", ip, self.port),
+            _ => format!("http://{}:{}", self.host, self.port),
+        }
+    }
+
+    /// Whether the server only listens on the local machine (`localhost` or a loopback
+    /// address). The browser is only opened automatically in that case.
+    pub fn is_local(&self) -> bool {
+        self.host.eq_ignore_ascii_case("localhost")
+            || self.host.parse::<IpAddr>().is_ok_and(|ip| ip.is_loopback())
+    }
+}
+
+/// Environment variable overriding the data directory.
+const DATA_DIR_ENV: &str = "ESCAM_DATA_DIR";
+/// Environment variable overriding the path of the SQLite database.
+const DB_PATH_ENV: &str = "ESCAM_DB_PATH";
+/// Environment variable overriding the PDF output directory.
+const PDF_DIR_ENV: &str = "ESCAM_PDF_DIR";
+/// Environment variable overriding the fonts directory.
+const FONTS_DIR_ENV: &str = "ESCAM_FONTS_DIR";
+/// File name of the database inside the data directory.
+const DB_FILE_NAME: &str = "templify.sqlite";
+/// Name of the PDF output directory inside the data directory.
+const PDF_DIR_NAME: &str = "pdfs";
+/// Name of the fonts directory inside the data directory.
+const FONTS_DIR_NAME: &str = "fonts";
+/// Subdirectory of the fonts directory holding the fonts uploaded for templates.
+const TEMPLATE_FONTS_DIR_NAME: &str = "templates";
+/// Environment variable overriding how long finished jobs are kept, in seconds.
+const JOB_TTL_ENV: &str = "ESCAM_JOB_TTL_SECS";
+/// How long finished jobs are kept when `JOB_TTL_ENV` is not set, in seconds.
+const DEFAULT_JOB_TTL_SECS: u64 = 30 * 60;
+/// Environment variable overriding the largest CSV upload accepted, in megabytes.
+const MAX_CSV_UPLOAD_ENV: &str = "ESCAM_MAX_CSV_UPLOAD_MB";
+/// Largest CSV upload accepted when `MAX_CSV_UPLOAD_ENV` is not set, in megabytes.
+const DEFAULT_MAX_CSV_UPLOAD_MB: u64 = 50;
+/// Environment variable holding the shared secret used to sign callbacks.
+pub const CALLBACK_SECRET_ENV: &str = "ESCAM_CALLBACK_SECRET";
+/// Environment variable listing, separated by commas, the callback hosts that may be
+/// loopback, link-local or private addresses.
+pub const CALLBACK_ALLOWED_HOSTS_ENV: &str = "ESCAM_CALLBACK_ALLOWED_HOSTS";
+
+/// Where the application keeps its files, and the limits and secrets of its jobs.
+#[derive(Clone)]
+pub struct Config {
+    /// Directory of the uploaded CSV files and of their temporary upload files.
+    pub data_dir: PathBuf,
+    /// Path of the SQLite database file.
+    pub db_path: PathBuf,
+    /// Directory the template PDFs are written to.
+    pub pdf_dir: PathBuf,
+    /// Directory searched for Arial and LiberationSans, and holding the uploaded
+    /// template fonts in its `templates` subdirectory.
+    pub fonts_dir: PathBuf,
+    /// How long finished jobs are kept before `start_job_sweeper` evicts them.
+    pub job_ttl: Duration,
+    /// Largest CSV upload accepted, in megabytes.
+    pub max_csv_upload_mb: u64,
+    /// Secret signing job callbacks; callbacks are refused when it is `None`.
+    pub callback_secret: Option<String>,
+    /// Lowercased callback hosts that may be loopback, link-local or private addresses.
+    pub callback_allowed_hosts: Vec<String>,
+}
+
+impl fmt::Debug for Config {
+    /// Formats the configuration without revealing the callback secret.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Config")
+            .field("data_dir", &self.data_dir)
+            .field("db_path", &self.db_path)
+            .field("pdf_dir", &self.pdf_dir)
+            .field("fonts_dir", &self.fonts_dir)
+            .field("job_ttl", &self.job_ttl)
+            .field("max_csv_upload_mb", &self.max_csv_upload_mb)
+            .field(
+                "callback_secret",
+                &self.callback_secret.as_ref().map(|_| "<set>"),
+            )
+            .field("callback_allowed_hosts", &self.callback_allowed_hosts)
+            .finish()
+    }
+}
+
+impl Config {
+    /// Reads the paths from `ESCAM_DATA_DIR`, `ESCAM_DB_PATH`, `ESCAM_PDF_DIR` and
+    /// `ESCAM_FONTS_DIR`. Without any of them, every path is relative to the working
+    /// directory, as before they were configurable. The job settings are read from the
+    /// variables listed in the module documentation.
+    pub fn from_env() -> Self {
+        let data_dir = path_from_env(DATA_DIR_ENV).unwrap_or_else(|| PathBuf::from("."));
+        Config {
+            db_path: path_from_env(DB_PATH_ENV).unwrap_or_else(|| data_dir.join(DB_FILE_NAME)),
+            pdf_dir: path_from_env(PDF_DIR_ENV).unwrap_or_else(|| data_dir.join(PDF_DIR_NAME)),
+            fonts_dir: path_from_env(FONTS_DIR_ENV)
+                .unwrap_or_else(|| data_dir.join(FONTS_DIR_NAME)),
+            data_dir,
+            job_ttl: Duration::from_secs(positive_from_env(JOB_TTL_ENV, DEFAULT_JOB_TTL_SECS)),
+            max_csv_upload_mb: positive_from_env(MAX_CSV_UPLOAD_ENV, DEFAULT_MAX_CSV_UPLOAD_MB),
+            callback_secret: env::var(CALLBACK_SECRET_ENV)
+                .ok()
+                .filter(|secret| !secret.is_empty()),
+            callback_allowed_hosts: env::var(CALLBACK_ALLOWED_HOSTS_ENV)
+                .map(|hosts| parse_host_list(&hosts))
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Path of the CSV data file of `template_id` whose contents hash to `md5`.
+    pub fn csv_path(&self, template_id: &str, md5: &str) -> PathBuf {
+        self.data_dir.join(format!("{}_{}.csv", template_id, md5))
+    }
+
+    /// Path the PDF of `template_id` is written to.
+    pub fn pdf_path(&self, template_id: &str) -> PathBuf {
+        self.pdf_dir.join(format!("{}.pdf", template_id))
+    }
+
+    /// Directory holding the fonts uploaded for templates.
+    pub fn template_fonts_dir(&self) -> PathBuf {
+        self.fonts_dir.join(TEMPLATE_FONTS_DIR_NAME)
+    }
+
+    /// Path of the font uploaded for `template_id`, with the extension `extension`.
+    pub fn template_font_path(&self, template_id: &str, extension: &str) -> PathBuf {
+        self.template_fonts_dir()
+            .join(format!("{}.{}", template_id, extension))
+    }
+
+    /// Creates the data directory and the directory of the database if they are missing,
+    /// so SQLite and the uploads can create their files there.
+    pub fn create_dirs(&self) -> std::io::Result<()> {
+        std::fs::create_dir_all(&self.data_dir)?;
+        match self.db_path.parent() {
+            Some(parent) if parent != Path::new("") => std::fs::create_dir_all(parent),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Reads a path from the environment variable `name`. An empty value is logged and
+/// treated as unset.
+fn path_from_env(name: &str) -> Option<PathBuf> {
+    let value = env::var_os(name)?;
+    if value.is_empty() {
+        log::warn!("Ignoring empty {}", name);
+        return None;
+    }
+    Some(PathBuf::from(value))
+}
+
+/// Reads a positive number from the environment variable `name`, or returns `default` if
+/// it is unset. Other values are logged and replaced with `default`.
+fn positive_from_env(name: &str, default: u64) -> u64 {
+    match env::var(name) {
+        Ok(value) => match value.trim().parse::<u64>() {
+            Ok(number) if number > 0 => number,
+            _ => {
+                log::warn!("Ignoring invalid {}={:?}; using {}", name, value, default);
+                default
+            }
+        },
+        Err(_) => default,
+    }
+}
+
+/// Splits a comma-separated list of hosts, lowercasing them and dropping empty entries.
+fn parse_host_list(hosts: &str) -> Vec<String> {
+    hosts
+        .split(',')
+        .map(|host| host.trim().to_lowercase())
+        .filter(|host| !host.is_empty())
+        .collect()
+}
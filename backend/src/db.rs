@@ -0,0 +1,35 @@
+//! Shared SQLite connection pool.
+//!
+//! Handlers used to open the database file (`Config::db_path`, `templify.sqlite` by
+//! default) on every request. The pool, created once in `main`, keeps connections open and hands them out to handlers through
+//! `web::Data<DbPool>`. Blocking work (inside `web::block` or `spawn_blocking`) checks a
+//! connection out with `pool.get()` and returns it when the guard is dropped.
+//!
+//! Each connection is set up with WAL journaling, so readers are not blocked while a
+//! verification or a save writes, and a busy timeout, so concurrent writers wait for the
+//! lock instead of failing with "database is locked".
+
+use r2d2_sqlite::SqliteConnectionManager;
+use std::path::Path;
+use std::time::Duration;
+
+/// Maximum number of open connections. A verification job holds one for its whole run.
+const POOL_SIZE: u32 = 16;
+/// How long a statement waits for a locked database before failing.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Pool of connections to the SQLite database.
+pub type DbPool = r2d2::Pool<SqliteConnectionManager>;
+
+/// Creates the connection pool for the database file at `path`, creating the file if it
+/// does not exist.
+///
+/// # Returns
+/// The pool, or an error if the first connection cannot be opened or configured.
+pub fn create_pool(path: &Path) -> Result<DbPool, r2d2::Error> {
+    let manager = SqliteConnectionManager::file(path).with_init(|conn| {
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        conn.pragma_update_and_check(None, "journal_mode", "WAL", |_| Ok(()))
+    });
+    r2d2::Pool::builder().max_size(POOL_SIZE).build(manager)
+}
@@ -0,0 +1,508 @@
+//! Webhook callbacks for finished jobs.
+//!
+//! Integrations that start a job can pass a `callback_url` instead of polling
+//! `GET /api/jobs/{job_id}` or following its events. `JobCallbacks` remembers the URL of
+//! each job; when the job reaches a terminal status, `JobsState::set_status` hands it to
+//! `deliver`, which POSTs a JSON `JobCallbackPayload` (the `job_id` and the final
+//! `JobStatus`) to the URL in the background.
+//!
+//! A delivery that fails (connection error or non-2xx response) is retried up to
+//! `MAX_ATTEMPTS` times, waiting `FIRST_RETRY_DELAY` and then twice as long each time.
+//! Every attempt is recorded in the job's log.
+//!
+//! Callbacks are only accepted when a secret is configured (`Config::callback_secret`, from
+//! `ESCAM_CALLBACK_SECRET`). Each request carries a `SIGNATURE_HEADER` of the form `sha256=<hex>`: the
+//! HMAC-SHA256 of the exact request body keyed with the secret, so the receiver can check
+//! the request came from this server.
+//!
+//! So that a callback cannot be used to reach the server's own network, its host may not
+//! be a loopback, link-local or private address unless it is listed in
+//! `Config::callback_allowed_hosts` (`ESCAM_CALLBACK_ALLOWED_HOSTS`). The host is checked when the URL is submitted
+//! (`validate_callback_url`) and again when each delivery resolves it, and redirects are
+//! not followed.
+
+use crate::config::{Config, CALLBACK_ALLOWED_HOSTS_ENV, CALLBACK_SECRET_ENV};
+use crate::job_controller::log::JobLogs;
+use common::jobs::{JobCallbackPayload, JobStatus};
+use hmac::{Hmac, Mac};
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Request header carrying the signature of the body.
+const SIGNATURE_HEADER: &str = "x-templify-signature";
+/// Number of delivery attempts before giving up.
+const MAX_ATTEMPTS: u32 = 5;
+/// Wait before the first retry; doubled after each failed attempt.
+const FIRST_RETRY_DELAY: Duration = Duration::from_secs(1);
+/// Timeout of a single delivery attempt.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The callback URLs of unfinished jobs, keyed by job ID, and the HTTP client that
+/// delivers them.
+///
+/// Like `JobLogs`, it uses a `std::sync::Mutex`: registering and taking a URL are short.
+#[derive(Clone)]
+pub struct JobCallbacks {
+    urls: Arc<Mutex<HashMap<String, String>>>,
+    client: reqwest::Client,
+    /// Secret signing the deliveries; see `Config::callback_secret`.
+    secret: Option<Arc<str>>,
+}
+
+impl JobCallbacks {
+    /// Creates an empty registry that signs deliveries with `secret` and whose client
+    /// resolves hosts with `CallbackResolver`, allowing `allowed_hosts` (see
+    /// `Config::callback_allowed_hosts`), and does not follow redirects.
+    pub fn new(secret: Option<String>, allowed_hosts: Vec<String>) -> Self {
+        let resolver = CallbackResolver {
+            allowed_hosts: Arc::new(allowed_hosts),
+        };
+        let client = reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .dns_resolver(Arc::new(resolver))
+            .build()
+            .expect("callback HTTP client");
+        Self {
+            urls: Arc::default(),
+            client,
+            secret: secret.map(Arc::from),
+        }
+    }
+}
+
+/// Resolves the hosts of callback deliveries, failing for a host that resolves to an
+/// internal address (`is_internal_ip`) and is not in `allowed_hosts`. Checking at delivery
+/// catches a host whose address changed since `validate_callback_url`.
+struct CallbackResolver {
+    allowed_hosts: Arc<Vec<String>>,
+}
+
+impl Resolve for CallbackResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let host = name.as_str().to_string();
+        let allowed_hosts = self.allowed_hosts.clone();
+        Box::pin(async move {
+            let addrs: Vec<SocketAddr> =
+                tokio::net::lookup_host((host.as_str(), 0)).await?.collect();
+            if !allowed_hosts.contains(&host) && addrs.iter().any(|a| is_internal_ip(a.ip())) {
+                return Err(format!("{} resolves to an internal address", host).into());
+            }
+            let addrs: Addrs = Box::new(addrs.into_iter());
+            Ok(addrs)
+        })
+    }
+}
+
+impl JobCallbacks {
+    /// Remembers `url` as the callback of `job_id`.
+    pub fn register(&self, job_id: &str, url: String) {
+        let mut urls = match self.urls.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        urls.insert(job_id.to_string(), url);
+    }
+
+    /// Removes and returns the callback URL of `job_id`, if it has one.
+    fn take(&self, job_id: &str) -> Option<String> {
+        let mut urls = match self.urls.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        urls.remove(job_id)
+    }
+
+    /// Starts delivering the terminal `status` of `job_id` to its callback URL, if it has
+    /// one. Returns immediately; the delivery runs as a background task.
+    pub(crate) fn notify(&self, job_id: &str, status: &JobStatus, logs: &JobLogs) {
+        let Some(url) = self.take(job_id) else {
+            return;
+        };
+        let payload = JobCallbackPayload {
+            job_id: job_id.to_string(),
+            status: status.clone(),
+        };
+        let client = self.client.clone();
+        let secret = self.secret.clone();
+        let logs = logs.clone();
+        tokio::spawn(async move {
+            deliver(&client, secret.as_deref(), &url, &payload, &logs).await;
+        });
+    }
+}
+
+/// POSTs `payload` to `url`, signed with `secret`, retrying with exponential backoff until
+/// a 2xx response or `MAX_ATTEMPTS` failed attempts.
+async fn deliver(
+    client: &reqwest::Client,
+    secret: Option<&str>,
+    url: &str,
+    payload: &JobCallbackPayload,
+    logs: &JobLogs,
+) {
+    let body = match serde_json::to_vec(payload) {
+        Ok(body) => body,
+        Err(e) => {
+            logs.append(&payload.job_id, format!("callback not sent: {}", e));
+            return;
+        }
+    };
+    let signature = secret.map(|secret| sign(secret.as_bytes(), &body));
+
+    let mut delay = FIRST_RETRY_DELAY;
+    for attempt in 1..=MAX_ATTEMPTS {
+        let mut request = client
+            .post(url)
+            .timeout(REQUEST_TIMEOUT)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body.clone());
+        if let Some(signature) = &signature {
+            request = request.header(SIGNATURE_HEADER, signature);
+        }
+        let error = match request.send().await {
+            Ok(response) if response.status().is_success() => {
+                logs.append(&payload.job_id, format!("callback delivered to {}", url));
+                return;
+            }
+            Ok(response) => format!("status {}", response.status()),
+            Err(e) => e.to_string(),
+        };
+        logs.append(
+            &payload.job_id,
+            format!(
+                "callback attempt {}/{} to {} failed: {}",
+                attempt, MAX_ATTEMPTS, url, error
+            ),
+        );
+        if attempt < MAX_ATTEMPTS {
+            tokio::time::sleep(delay).await;
+            delay *= 2;
+        }
+    }
+    log::warn!(
+        "Giving up on the callback of job {} to {} after {} attempts",
+        payload.job_id,
+        url,
+        MAX_ATTEMPTS
+    );
+}
+
+/// Returns the `SIGNATURE_HEADER` value for `body`: `sha256=` followed by the hex
+/// HMAC-SHA256 of the body keyed with `secret`.
+fn sign(secret: &[u8], body: &[u8]) -> String {
+    let mut mac = match Hmac::<Sha256>::new_from_slice(secret) {
+        Ok(mac) => mac,
+        Err(_) => unreachable!("HMAC key of any length is valid"),
+    };
+    mac.update(body);
+    let digest = mac.finalize().into_bytes();
+    let mut out = String::with_capacity(7 + digest.len() * 2);
+    out.push_str("sha256=");
+    for byte in digest {
+        let _ = write!(out, "{:02x}", byte);
+    }
+    out
+}
+
+/// Returns `true` for addresses a callback may only reach if its host is allowed:
+/// unspecified, loopback, private (including the `100.64.0.0/10` shared range and IPv6
+/// unique local addresses), link-local, broadcast and multicast addresses, and IPv4
+/// addresses of those kinds mapped to IPv6.
+fn is_internal_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            a == 0
+                || ip.is_loopback()
+                || ip.is_private()
+                || (a == 100 && (b & 0xc0) == 64)
+                || ip.is_link_local()
+                || ip.is_broadcast()
+                || ip.is_multicast()
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(v4) => is_internal_ip(IpAddr::V4(v4)),
+            None => {
+                let first = ip.segments()[0];
+                ip.is_unspecified()
+                    || ip.is_loopback()
+                    || (first & 0xfe00) == 0xfc00
+                    || (first & 0xffc0) == 0xfe80
+                    || ip.is_multicast()
+            }
+        },
+    }
+}
+
+/// Checks that `url` can be used as a callback: callbacks are configured
+/// (`Config::callback_secret` is set) and `url` is an absolute `http` or `https` URL whose
+/// host resolves to no internal address (`is_internal_ip`), unless the host is listed in
+/// `Config::callback_allowed_hosts`.
+///
+/// # Returns
+/// An error message for a `400 Bad Request` if it cannot.
+pub async fn validate_callback_url(url: &str, config: &Config) -> Result<(), String> {
+    check_callback_url(
+        url,
+        config.callback_secret.is_some(),
+        &config.callback_allowed_hosts,
+    )
+    .await
+}
+
+/// Body of `validate_callback_url`, with the configuration passed in.
+async fn check_callback_url(
+    url: &str,
+    has_secret: bool,
+    allowed_hosts: &[String],
+) -> Result<(), String> {
+    if !has_secret {
+        return Err(format!(
+            "callback_url is not available: {} is not configured",
+            CALLBACK_SECRET_ENV
+        ));
+    }
+    let parsed = reqwest::Url::parse(url).map_err(|e| format!("Invalid callback_url: {}", e))?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err("callback_url must be an http or https URL".to_string());
+    }
+    let Some(host) = parsed.host_str() else {
+        return Err("callback_url must have a host".to_string());
+    };
+    // IPv6 hosts keep their brackets in the URL.
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    if allowed_hosts.iter().any(|allowed| allowed == host) {
+        return Ok(());
+    }
+    let port = parsed.port_or_known_default().unwrap_or(0);
+    let mut addrs = tokio::net::lookup_host((host, port))
+        .await
+        .map_err(|e| format!("callback_url host {} cannot be resolved: {}", host, e))?;
+    if addrs.any(|addr| is_internal_ip(addr.ip())) {
+        return Err(format!(
+            "callback_url host {} is a loopback, link-local or private address; list it in {} to allow it",
+            host, CALLBACK_ALLOWED_HOSTS_ENV
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::job_controller::state::JobsState;
+    use crate::test_support::jobs_state;
+    use actix_web::{web, App, HttpRequest, HttpResponse, HttpServer};
+    use std::time::Instant;
+
+    const SECRET: &str = "test-secret";
+
+    /// A request received by `Receiver`: its signature header and its body.
+    type Received = (Option<String>, Vec<u8>);
+
+    /// A local HTTP server recording the requests it receives. It answers the first
+    /// `failures` requests with `500 Internal Server Error` and the others with `200 OK`.
+    struct Receiver {
+        url: String,
+        received: Arc<Mutex<Vec<Received>>>,
+        handle: actix_web::dev::ServerHandle,
+    }
+
+    impl Receiver {
+        fn start(failures: usize) -> Self {
+            let received: Arc<Mutex<Vec<Received>>> = Arc::default();
+            let data = web::Data::from(received.clone());
+            let server = HttpServer::new(move || {
+                App::new().app_data(data.clone()).default_service(web::to(
+                    move |req: HttpRequest,
+                          body: web::Bytes,
+                          received: web::Data<Mutex<Vec<Received>>>| async move {
+                        let signature = req
+                            .headers()
+                            .get(SIGNATURE_HEADER)
+                            .and_then(|value| value.to_str().ok())
+                            .map(str::to_string);
+                        let mut received = received.lock().unwrap();
+                        received.push((signature, body.to_vec()));
+                        if received.len() <= failures {
+                            HttpResponse::InternalServerError().finish()
+                        } else {
+                            HttpResponse::Ok().finish()
+                        }
+                    },
+                ))
+            })
+            .workers(1)
+            .bind(("127.0.0.1", 0))
+            .expect("listener");
+            let url = format!("http://{}/hook", server.addrs()[0]);
+            let server = server.run();
+            let handle = server.handle();
+            actix_web::rt::spawn(server);
+            Receiver {
+                url,
+                received,
+                handle,
+            }
+        }
+
+        fn received(&self) -> Vec<Received> {
+            self.received.lock().unwrap().clone()
+        }
+    }
+
+    /// A `JobsState` whose callbacks are signed with `SECRET` and may reach this machine.
+    fn callback_state() -> JobsState {
+        JobsState {
+            callbacks: JobCallbacks::new(Some(SECRET.to_string()), vec!["127.0.0.1".to_string()]),
+            ..jobs_state(1)
+        }
+    }
+
+    /// Waits until the log of `job_id` contains `needle`.
+    ///
+    /// # Panics
+    /// If it does not within ten seconds.
+    async fn wait_for_log(state: &JobsState, job_id: &str, needle: &str) {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while !state
+            .logs
+            .render(job_id)
+            .is_some_and(|log| log.contains(needle))
+        {
+            assert!(Instant::now() < deadline, "no {:?} in the log", needle);
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    }
+
+    #[test]
+    fn sign_matches_the_hmac_sha256_test_vector() {
+        // RFC 4231, test case 2.
+        assert_eq!(
+            sign(b"Jefe", b"what do ya want for nothing?"),
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[actix_web::test]
+    async fn callback_url_requires_a_secret() {
+        let err = check_callback_url("https://93.184.216.34/hook", false, &[])
+            .await
+            .unwrap_err();
+        assert!(err.contains(CALLBACK_SECRET_ENV), "{}", err);
+    }
+
+    #[actix_web::test]
+    async fn callback_url_must_be_http() {
+        for url in ["ftp://93.184.216.34/hook", "not a url", "/relative"] {
+            assert!(check_callback_url(url, true, &[]).await.is_err(), "{}", url);
+        }
+    }
+
+    #[actix_web::test]
+    async fn internal_hosts_are_rejected() {
+        for url in [
+            "http://localhost:8080/hook",
+            "http://127.0.0.1/hook",
+            "http://0.0.0.0/hook",
+            "http://10.0.0.5/hook",
+            "http://172.16.3.4/hook",
+            "http://192.168.1.1/hook",
+            "http://100.64.0.1/hook",
+            "http://169.254.169.254/latest/meta-data",
+            "http://[::1]/hook",
+            "http://[fd00::1]/hook",
+            "http://[fe80::1]/hook",
+            "http://[::ffff:127.0.0.1]/hook",
+        ] {
+            assert!(check_callback_url(url, true, &[]).await.is_err(), "{}", url);
+        }
+    }
+
+    #[actix_web::test]
+    async fn public_addresses_are_accepted() {
+        for url in [
+            "https://93.184.216.34/hook",
+            "http://[2606:4700::1111]:8080/hook",
+        ] {
+            assert_eq!(check_callback_url(url, true, &[]).await, Ok(()), "{}", url);
+        }
+    }
+
+    #[actix_web::test]
+    async fn allowed_hosts_may_be_internal() {
+        let allowed = ["localhost".to_string(), "::1".to_string()];
+        for url in ["http://localhost:9000/hook", "http://[::1]/hook"] {
+            assert_eq!(
+                check_callback_url(url, true, &allowed).await,
+                Ok(()),
+                "{}",
+                url
+            );
+        }
+        assert!(check_callback_url("http://127.0.0.1/hook", true, &allowed)
+            .await
+            .is_err());
+    }
+
+    #[actix_web::test]
+    async fn finished_job_is_posted_once_and_signed() {
+        let receiver = Receiver::start(0);
+        let state = callback_state();
+        state.callbacks.register("job", receiver.url.clone());
+
+        state
+            .set_status("job", JobStatus::Completed("done".to_string()))
+            .await;
+        state
+            .set_status("job", JobStatus::Failed("late".to_string()))
+            .await;
+        wait_for_log(&state, "job", "callback delivered").await;
+        // Leave time for a second request, which must not come.
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        let received = receiver.received();
+        assert_eq!(received.len(), 1);
+        let (signature, body) = &received[0];
+        assert_eq!(
+            signature.as_deref(),
+            Some(sign(SECRET.as_bytes(), body).as_str())
+        );
+        let payload: JobCallbackPayload = serde_json::from_slice(body).unwrap();
+        assert_eq!(payload.job_id, "job");
+        assert!(matches!(payload.status, JobStatus::Completed(ref done) if done == "done"));
+        receiver.handle.stop(false).await;
+    }
+
+    #[actix_web::test]
+    async fn delivery_is_retried_after_a_server_error() {
+        let receiver = Receiver::start(1);
+        let state = callback_state();
+        state.callbacks.register("job", receiver.url.clone());
+
+        state
+            .set_status("job", JobStatus::Failed("broken".to_string()))
+            .await;
+        wait_for_log(&state, "job", "callback delivered").await;
+
+        let received = receiver.received();
+        assert_eq!(received.len(), 2);
+        assert_eq!(received[0], received[1]);
+        let (signature, body) = &received[1];
+        assert_eq!(
+            signature.as_deref(),
+            Some(sign(SECRET.as_bytes(), body).as_str())
+        );
+        assert!(state
+            .logs
+            .render("job")
+            .unwrap()
+            .contains("callback attempt 1/5 to"));
+        receiver.handle.stop(false).await;
+    }
+}
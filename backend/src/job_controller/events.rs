@@ -0,0 +1,65 @@
+//! Per-job broadcast of status updates.
+//!
+//! `JobsState.jobs` only answers "what is the status now", so clients used to poll it.
+//! `JobEvents` keeps a `tokio::sync::broadcast` channel for every job that has not
+//! finished yet. `JobsState::set_status` publishes each new status on it, and
+//! `GET /api/jobs/{job_id}/events` forwards what it receives to the client as Server-Sent
+//! Events.
+//!
+//! A channel is opened when the job is scheduled and dropped once a terminal status
+//! (`Completed`, `Failed` or `Cancelled`) has been published, which ends every
+//! subscriber's stream after that last status.
+
+use common::jobs::JobStatus;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use tokio::sync::broadcast;
+
+/// Number of updates buffered per channel. A subscriber that falls further behind skips
+/// the oldest ones; only the latest status matters to it.
+const CHANNEL_CAPACITY: usize = 64;
+
+/// A thread-safe set of status channels, keyed by job ID.
+///
+/// Like `JobLogs`, it uses a `std::sync::Mutex` so that it can be used both from async
+/// tasks and from blocking threads.
+#[derive(Clone, Default)]
+pub struct JobEvents {
+    inner: Arc<Mutex<HashMap<String, broadcast::Sender<JobStatus>>>>,
+}
+
+impl JobEvents {
+    /// Opens the status channel of `job_id`. Called when the job is scheduled.
+    pub fn open(&self, job_id: &str) {
+        self.lock()
+            .entry(job_id.to_string())
+            .or_insert_with(|| broadcast::channel(CHANNEL_CAPACITY).0);
+    }
+
+    /// Subscribes to the status updates of `job_id`.
+    ///
+    /// # Returns
+    /// `None` if the job is unknown or has already finished.
+    pub fn subscribe(&self, job_id: &str) -> Option<broadcast::Receiver<JobStatus>> {
+        self.lock().get(job_id).map(broadcast::Sender::subscribe)
+    }
+
+    /// Sends `status` to the subscribers of `job_id`. A terminal status closes the channel.
+    pub fn publish(&self, job_id: &str, status: &JobStatus) {
+        let mut channels = self.lock();
+        if let Some(sender) = channels.get(job_id) {
+            // Sending only fails when nobody is subscribed, which is fine.
+            let _ = sender.send(status.clone());
+        }
+        if status.is_terminal() {
+            channels.remove(job_id);
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, broadcast::Sender<JobStatus>>> {
+        match self.inner.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+}
@@ -0,0 +1,134 @@
+//! Per-job activity logs.
+//!
+//! Status updates in `JobsState` only keep the latest state of a job, which is not
+//! enough to audit what happened during a run. `JobLogs` keeps a small, capped list of
+//! timestamped entries for every job: status transitions (recorded by
+//! `start_job_updater`) plus notable events reported by the job itself (the verification
+//! fast-path, the first invalid row, timings, ...).
+//!
+//! The logs are served as plain text by `GET /api/jobs/{job_id}/log` and live as long as
+//! the job entry in `JobsState`: `start_job_sweeper` removes both together.
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Write;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Maximum number of entries kept per job. Older entries are dropped first.
+const MAX_LOG_ENTRIES: usize = 1000;
+
+/// The log of a single job.
+struct JobLog {
+    /// When the first entry was recorded; entry timestamps are relative to it.
+    started: Instant,
+    /// `(elapsed, message)` pairs, oldest first.
+    entries: VecDeque<(Duration, String)>,
+    /// Number of entries discarded because the log hit `MAX_LOG_ENTRIES`.
+    dropped: usize,
+}
+
+/// A thread-safe store of job logs, keyed by job ID.
+///
+/// It uses a `std::sync::Mutex` rather than the Tokio `RwLock` used for job statuses so
+/// that entries can be appended both from async tasks and from the blocking threads
+/// that run the actual job work, without needing a runtime handle. Appends are short,
+/// so the lock is never held for long.
+#[derive(Clone, Default)]
+pub struct JobLogs {
+    inner: Arc<Mutex<HashMap<String, JobLog>>>,
+}
+
+impl JobLogs {
+    /// Appends `message` to the log of `job_id`, creating the log if needed.
+    pub fn append(&self, job_id: &str, message: impl Into<String>) {
+        let mut logs = match self.inner.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        let log = logs.entry(job_id.to_string()).or_insert_with(|| JobLog {
+            started: Instant::now(),
+            entries: VecDeque::new(),
+            dropped: 0,
+        });
+        if log.entries.len() == MAX_LOG_ENTRIES {
+            log.entries.pop_front();
+            log.dropped += 1;
+        }
+        log.entries
+            .push_back((log.started.elapsed(), message.into()));
+    }
+
+    /// Removes the log of `job_id`, if any.
+    pub fn remove(&self, job_id: &str) {
+        let mut logs = match self.inner.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        logs.remove(job_id);
+    }
+
+    /// Renders the log of `job_id` as plain text, one entry per line.
+    ///
+    /// # Returns
+    /// `None` if nothing was ever logged for that job.
+    pub fn render(&self, job_id: &str) -> Option<String> {
+        let logs = match self.inner.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        let log = logs.get(job_id)?;
+        let mut out = String::new();
+        if log.dropped > 0 {
+            let _ = writeln!(out, "... {} earlier entries omitted", log.dropped);
+        }
+        for (elapsed, message) in &log.entries {
+            let _ = writeln!(out, "[{:>10.3}s] {}", elapsed.as_secs_f64(), message);
+        }
+        Some(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The messages of a rendered log, without their timestamps.
+    fn messages(log: &str) -> Vec<&str> {
+        log.lines()
+            .map(|line| line.split_once("] ").map_or(line, |(_, message)| message))
+            .collect()
+    }
+
+    #[test]
+    fn renders_entries_in_order() {
+        let logs = JobLogs::default();
+        assert_eq!(logs.render("job"), None);
+        logs.append("job", "started");
+        logs.append("job", format!("row {} skipped", 3));
+        logs.append("other", "unrelated");
+        let log = logs.render("job").unwrap();
+        assert!(log.starts_with("[     0.000s] started\n"));
+        assert_eq!(messages(&log), ["started", "row 3 skipped"]);
+    }
+
+    #[test]
+    fn drops_oldest_entries_over_the_cap() {
+        let logs = JobLogs::default();
+        for i in 0..MAX_LOG_ENTRIES + 2 {
+            logs.append("job", i.to_string());
+        }
+        let log = logs.render("job").unwrap();
+        let messages = messages(&log);
+        assert_eq!(messages.len(), MAX_LOG_ENTRIES + 1);
+        assert_eq!(messages[0], "... 2 earlier entries omitted");
+        assert_eq!(messages[1], "2");
+    }
+
+    #[test]
+    fn remove_forgets_the_job() {
+        let logs = JobLogs::default();
+        logs.append("job", "started");
+        logs.remove("job");
+        assert_eq!(logs.render("job"), None);
+    }
+}
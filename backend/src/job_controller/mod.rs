@@ -1 +1,5 @@
+pub mod callbacks;
+pub mod events;
+pub mod log;
+pub mod registry;
 pub mod state;
\ No newline at end of file
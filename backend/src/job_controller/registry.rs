@@ -0,0 +1,135 @@
+//! Tracking of running jobs and their cancellation flags.
+//!
+//! `JobsState.jobs` only knows job IDs and statuses. `JobRegistry` additionally records,
+//! for every job that has not finished yet, which template it works on and a shared
+//! cancellation flag. Services use it to stop the jobs of a template before replacing
+//! or removing the data those jobs read (see `JobsState::cancel_jobs_for_template`), and
+//! to stop a single job on the user's request (`POST /api/jobs/{job_id}/cancel`).
+//!
+//! Jobs register themselves when they are scheduled and unregister when they reach a
+//! terminal state, so the registry only ever holds running (or queued) jobs.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// A running job, as seen by the registry.
+struct RegisteredJob {
+    /// The template the job works on.
+    template_id: String,
+    /// Set to `true` to ask the job to stop at its next checkpoint.
+    cancel: Arc<AtomicBool>,
+}
+
+/// A thread-safe registry of running jobs, keyed by job ID.
+///
+/// Like `JobLogs`, it uses a `std::sync::Mutex` so that it can be used both from async
+/// tasks and from blocking threads.
+#[derive(Clone, Default)]
+pub struct JobRegistry {
+    inner: Arc<Mutex<HashMap<String, RegisteredJob>>>,
+}
+
+impl JobRegistry {
+    /// Registers a job working on `template_id` and returns its cancellation flag.
+    ///
+    /// The job should check the flag at safe points and stop without touching shared
+    /// state once it is set.
+    pub fn register(&self, job_id: &str, template_id: &str) -> Arc<AtomicBool> {
+        let cancel = Arc::new(AtomicBool::new(false));
+        if let Ok(mut jobs) = self.inner.lock() {
+            jobs.insert(
+                job_id.to_string(),
+                RegisteredJob {
+                    template_id: template_id.to_string(),
+                    cancel: cancel.clone(),
+                },
+            );
+        }
+        cancel
+    }
+
+    /// Removes a job from the registry. Called once the job has reached a terminal status.
+    pub fn unregister(&self, job_id: &str) {
+        if let Ok(mut jobs) = self.inner.lock() {
+            jobs.remove(job_id);
+        }
+    }
+
+    /// Sets the cancellation flag of the job `job_id`.
+    ///
+    /// # Returns
+    /// `true` if the job is registered (i.e. still running or queued), `false` otherwise.
+    pub fn cancel(&self, job_id: &str) -> bool {
+        let Ok(jobs) = self.inner.lock() else {
+            return false;
+        };
+        match jobs.get(job_id) {
+            Some(job) => {
+                job.cancel.store(true, Ordering::Relaxed);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Sets the cancellation flag of every running job of `template_id`.
+    ///
+    /// # Returns
+    /// The IDs of the jobs that were flagged.
+    pub fn cancel_for_template(&self, template_id: &str) -> Vec<String> {
+        let Ok(jobs) = self.inner.lock() else {
+            return Vec::new();
+        };
+        jobs.iter()
+            .filter(|(_, job)| job.template_id == template_id)
+            .map(|(job_id, job)| {
+                job.cancel.store(true, Ordering::Relaxed);
+                job_id.clone()
+            })
+            .collect()
+    }
+
+    /// Returns `true` if any of `job_ids` is still registered (i.e. has not finished).
+    pub fn any_running(&self, job_ids: &[String]) -> bool {
+        self.inner
+            .lock()
+            .map(|jobs| job_ids.iter().any(|id| jobs.contains_key(id)))
+            .unwrap_or(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cancel_flags_only_registered_jobs() {
+        let registry = JobRegistry::default();
+        let flag = registry.register("job", "t");
+        assert!(!flag.load(Ordering::Relaxed));
+        assert!(registry.cancel("job"));
+        assert!(flag.load(Ordering::Relaxed));
+
+        registry.unregister("job");
+        assert!(!registry.cancel("job"));
+        assert!(!registry.any_running(&["job".to_string()]));
+    }
+
+    #[test]
+    fn cancel_for_template_flags_only_its_jobs() {
+        let registry = JobRegistry::default();
+        let first = registry.register("a", "t");
+        let second = registry.register("b", "t");
+        let other = registry.register("c", "u");
+
+        let mut cancelled = registry.cancel_for_template("t");
+        cancelled.sort();
+        assert_eq!(cancelled, ["a", "b"]);
+        assert!(first.load(Ordering::Relaxed));
+        assert!(second.load(Ordering::Relaxed));
+        assert!(!other.load(Ordering::Relaxed));
+        assert!(registry.any_running(&cancelled));
+        assert!(registry.cancel_for_template("missing").is_empty());
+    }
+}
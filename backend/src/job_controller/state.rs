@@ -11,10 +11,25 @@
 //!   job back to the central state manager.
 //! - `start_job_updater`: A long-running task that listens for `JobUpdate` messages
 //!   on an MPSC channel and updates the shared `JobsState` accordingly.
+//! - `JobsState::set_status`: Records a new status in the job's log and publishes it to
+//!   the job's subscribers (`JobsState.events`). Every status change goes through it, so it
+//!   also delivers the callback of a job that finishes (`JobsState.callbacks`). A terminal
+//!   status is final: later updates are dropped.
+//! - `start_job_sweeper`: A long-running task that evicts finished jobs (and their logs)
+//!   once they are older than `Config::job_ttl`, so the map does not grow forever.
+//! - `JobsState::cancel_jobs_for_template`: Stops the running jobs of a template before
+//!   its data is replaced or removed.
+//! - `cpu_permit_count`: The size of the global CPU semaphore (`JobsState.cpu_permits`)
+//!   that bounds how many CPU-bound jobs run at once, across all job types.
 
+use crate::job_controller::callbacks::JobCallbacks;
+use crate::job_controller::events::JobEvents;
+use crate::job_controller::log::JobLogs;
+use crate::job_controller::registry::JobRegistry;
 use common::jobs::JobStatus;
+use std::time::{Duration, Instant};
 use std::{collections::HashMap, sync::Arc};
-use tokio::sync::{mpsc, RwLock};
+use tokio::sync::{mpsc, RwLock, Semaphore};
 
 /// A thread-safe, shareable container for the state of all background jobs.
 ///
@@ -23,13 +38,39 @@ use tokio::sync::{mpsc, RwLock};
 /// the job system in a coordinated way.
 #[derive(Clone)]
 pub struct JobsState {
-    /// A map from a unique job ID (String) to its current `JobStatus`.
+    /// A map from a unique job ID (String) to its current `JobStatus` and when it was set.
     ///
     /// This map is the single source of truth for the status of all jobs.
     /// It is protected by an `Arc<RwLock>` to allow concurrent reads (e.g., by the
-    /// `/api/data_sources/csv/status/{job_id}` endpoint) and exclusive writes
+    /// `/api/jobs/{job_id}` endpoint) and exclusive writes
     /// (by the `start_job_updater` task).
-    pub jobs: Arc<RwLock<HashMap<String, JobStatus>>>,
+    /// Finished jobs are evicted by `start_job_sweeper` once older than `Config::job_ttl`.
+    pub jobs: Arc<RwLock<HashMap<String, JobEntry>>>,
+
+    /// Activity logs of all jobs, served by `GET /api/jobs/{job_id}/log`.
+    ///
+    /// Status transitions are logged automatically by `JobsState::set_status`; jobs can
+    /// append their own entries for events worth keeping (e.g. the first invalid row).
+    pub logs: JobLogs,
+
+    /// Global resource governor for CPU-bound work, sized by `cpu_permit_count`.
+    ///
+    /// Every job type acquires one permit before starting its heavy phase and holds it
+    /// until that phase ends, so the total number of CPU-bound jobs stays bounded no
+    /// matter which services started them. Jobs waiting for a permit remain `Pending`.
+    /// This complements, rather than replaces, any thread limit inside a single job.
+    pub cpu_permits: Arc<Semaphore>,
+
+    /// Running jobs with their template and cancellation flag. See `job_controller::registry`.
+    pub registry: JobRegistry,
+
+    /// Status channels of unfinished jobs, streamed by `GET /api/jobs/{job_id}/events`.
+    /// See `job_controller::events`.
+    pub events: JobEvents,
+
+    /// Callback URLs of unfinished jobs, notified when they reach a terminal status.
+    /// See `job_controller::callbacks`.
+    pub callbacks: JobCallbacks,
 
     /// A multi-producer, single-consumer (MPSC) channel sender.
     ///
@@ -37,9 +78,91 @@ pub struct JobsState {
     /// sender to push `JobUpdate` messages into a channel. This decouples the job
     /// execution logic from the state update logic, allowing tasks to report
     /// progress without needing direct write access to the `jobs` map.
+    ///
+    /// Only progress goes through the channel. The task that runs a job sets its terminal
+    /// status directly with `JobsState::set_status`, once the job has finished.
     pub tx: mpsc::Sender<JobUpdate>,
 }
 
+/// The stored state of a job in `JobsState.jobs`.
+#[derive(Clone, Debug)]
+pub struct JobEntry {
+    /// The latest status of the job.
+    pub status: JobStatus,
+    /// When `status` was set. For a finished job, this is when it finished.
+    pub updated_at: Instant,
+}
+
+/// Longest time between two sweeps of `start_job_sweeper`.
+const MAX_SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How long `cancel_jobs_for_template` waits for cancelled jobs to stop.
+const CANCEL_WAIT: Duration = Duration::from_secs(5);
+/// How often `cancel_jobs_for_template` checks whether cancelled jobs have stopped.
+const CANCEL_POLL: Duration = Duration::from_millis(50);
+
+impl JobsState {
+    /// Sets the status of `job_id`, records it in the job's log and publishes it to the
+    /// job's subscribers.
+    ///
+    /// Once a job has a terminal status, later updates are dropped: a progress update still
+    /// queued in `tx` cannot make a finished job look running again, and the job's callback
+    /// URL, if it registered one, is notified of the first terminal status only.
+    ///
+    /// The status is published while the map is locked, so subscribers receive updates in
+    /// the order they were stored, and a subscriber that reads the map right after
+    /// subscribing never sees an older status than the ones it receives.
+    pub async fn set_status(&self, job_id: &str, status: JobStatus) {
+        let mut jobs = self.jobs.write().await;
+        if jobs
+            .get(job_id)
+            .is_some_and(|entry| entry.status.is_terminal())
+        {
+            return;
+        }
+        let entry = JobEntry {
+            status: status.clone(),
+            updated_at: Instant::now(),
+        };
+        jobs.insert(job_id.to_string(), entry);
+        self.logs.append(job_id, describe_status(&status));
+        self.events.publish(job_id, &status);
+        drop(jobs);
+        if status.is_terminal() {
+            self.callbacks.notify(job_id, &status, &self.logs);
+        }
+    }
+
+    /// Cancels every running job of `template_id` and waits briefly for them to stop.
+    ///
+    /// Jobs are flagged through the `registry` and stop at their next checkpoint, ending
+    /// in `JobStatus::Cancelled`. The wait is bounded by `CANCEL_WAIT`; a job that has not
+    /// stopped by then is logged and left to finish on its own.
+    ///
+    /// # Returns
+    /// The number of jobs that were cancelled.
+    pub async fn cancel_jobs_for_template(&self, template_id: &str) -> usize {
+        let job_ids = self.registry.cancel_for_template(template_id);
+        for job_id in &job_ids {
+            self.logs.append(job_id, "cancellation requested");
+        }
+
+        let deadline = Instant::now() + CANCEL_WAIT;
+        while self.registry.any_running(&job_ids) {
+            if Instant::now() >= deadline {
+                log::warn!(
+                    "Jobs for template {} did not stop within {:?}",
+                    template_id,
+                    CANCEL_WAIT
+                );
+                break;
+            }
+            tokio::time::sleep(CANCEL_POLL).await;
+        }
+        job_ids.len()
+    }
+}
+
 /// Represents a status update for a specific background job.
 ///
 /// These messages are sent by background workers via the `JobsState.tx` sender
@@ -57,11 +180,213 @@ pub struct JobUpdate {
 /// This function should be spawned as a long-running background task (as seen in `main.rs`).
 /// It continuously listens for `JobUpdate` messages on the provided `rx` receiver.
 ///
-/// Upon receiving an update, it acquires a write lock on the `jobs` map in the
-/// shared `JobsState` and updates the status for the corresponding `job_id`.
+/// Upon receiving an update, it stores the new status of the corresponding `job_id`
+/// with `JobsState::set_status`, which also logs it and notifies the job's subscribers.
+/// An update that arrives after the job finished is dropped there.
 pub async fn start_job_updater(state: JobsState, mut rx: mpsc::Receiver<JobUpdate>) {
     while let Some(update) = rx.recv().await {
-        let mut jobs = state.jobs.write().await;
-        jobs.insert(update.job_id.clone(), update.status);
+        state.set_status(&update.job_id, update.status).await;
+    }
+}
+
+/// Starts the task that evicts finished jobs from `JobsState`.
+///
+/// Like `start_job_updater`, it is spawned as a long-running task in `main.rs`. Every
+/// `ttl / 2` (at most `MAX_SWEEP_INTERVAL`) it removes the jobs whose terminal status
+/// (`Completed`, `Failed` or `Cancelled`) was set more than `ttl` ago, together with their
+/// logs. Running jobs are never evicted. A finished job thus stays readable through
+/// `GET /api/jobs/{job_id}` and its log for at least `ttl`, which leaves pollers time to
+/// pick up the result; SSE subscribers receive the terminal status as it is published.
+pub async fn start_job_sweeper(state: JobsState, ttl: Duration) {
+    let mut interval =
+        tokio::time::interval((ttl / 2).clamp(Duration::from_secs(1), MAX_SWEEP_INTERVAL));
+    loop {
+        interval.tick().await;
+        let mut evicted = Vec::new();
+        state.jobs.write().await.retain(|job_id, entry| {
+            let expired = entry.status.is_terminal() && entry.updated_at.elapsed() > ttl;
+            if expired {
+                evicted.push(job_id.clone());
+            }
+            !expired
+        });
+        for job_id in &evicted {
+            state.logs.remove(job_id);
+        }
+        if !evicted.is_empty() {
+            log::info!("Evicted {} finished jobs", evicted.len());
+        }
+    }
+}
+
+/// Returns the number of permits for `JobsState.cpu_permits`: one per available core,
+/// falling back to 1 if the core count cannot be determined.
+pub fn cpu_permit_count() -> usize {
+    std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+}
+
+/// Formats a `JobStatus` as a short log line.
+fn describe_status(status: &JobStatus) -> String {
+    match status {
+        JobStatus::Pending => "status: pending".to_string(),
+        JobStatus::InProgress { lines, percent } => {
+            format!("status: in progress ({} lines, {}%)", lines, percent)
+        }
+        // Completion payloads can be large (e.g. a full column schema), so only
+        // their size is logged.
+        JobStatus::Completed(payload) => format!("status: completed ({} bytes)", payload.len()),
+        JobStatus::Failed(e) => format!("status: failed: {}", e),
+        JobStatus::Cancelled(reason) => format!("status: cancelled: {}", reason),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{job_status, jobs_state};
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    /// Registers a job that stops (unregisters) as soon as it is cancelled.
+    fn spawn_job(state: &JobsState, job_id: &str, template_id: &str) -> Arc<AtomicBool> {
+        let cancel = state.registry.register(job_id, template_id);
+        let (state, job_id, flag) = (state.clone(), job_id.to_string(), cancel.clone());
+        tokio::spawn(async move {
+            while !flag.load(Ordering::Relaxed) {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+            state.registry.unregister(&job_id);
+        });
+        cancel
+    }
+
+    #[actix_web::test]
+    async fn terminal_status_is_final() {
+        let state = jobs_state(1);
+        state.events.open("job");
+        let mut events = state.events.subscribe("job").unwrap();
+
+        state
+            .set_status(
+                "job",
+                JobStatus::InProgress {
+                    lines: 10,
+                    percent: 50,
+                },
+            )
+            .await;
+        state
+            .set_status("job", JobStatus::Completed("{}".to_string()))
+            .await;
+        // A progress update drained late, and a second terminal status.
+        state
+            .set_status(
+                "job",
+                JobStatus::InProgress {
+                    lines: 20,
+                    percent: 99,
+                },
+            )
+            .await;
+        state
+            .set_status("job", JobStatus::Failed("late".to_string()))
+            .await;
+
+        let jobs = state.jobs.read().await;
+        assert!(matches!(&jobs["job"].status, JobStatus::Completed(payload) if payload == "{}"));
+        drop(jobs);
+        assert!(matches!(
+            events.recv().await,
+            Ok(JobStatus::InProgress { lines: 10, .. })
+        ));
+        assert!(matches!(events.recv().await, Ok(JobStatus::Completed(_))));
+        assert!(events.recv().await.is_err());
+        let log = state.logs.render("job").unwrap();
+        assert_eq!(log.matches("status: ").count(), 2, "{}", log);
+        assert!(!log.contains("late"), "{}", log);
+    }
+
+    #[actix_web::test]
+    async fn queued_progress_does_not_reopen_a_finished_job() {
+        let state = jobs_state(1);
+        state
+            .set_status("job", JobStatus::Cancelled("stop".to_string()))
+            .await;
+        for (job_id, lines) in [("job", 1), ("sentinel", 2)] {
+            let update = JobUpdate {
+                job_id: job_id.to_string(),
+                status: JobStatus::InProgress { lines, percent: 1 },
+            };
+            state.tx.send(update).await.unwrap();
+        }
+        // Updates are applied in order, so the job's one has been handled once the
+        // sentinel's shows up.
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while job_status(&state, "sentinel").await.is_none() {
+            assert!(Instant::now() < deadline, "updater did not run");
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        assert!(matches!(
+            job_status(&state, "job").await,
+            Some(JobStatus::Cancelled(_))
+        ));
+    }
+
+    #[actix_web::test]
+    async fn cancel_jobs_for_template_stops_only_its_jobs() {
+        let state = jobs_state(1);
+        let first = spawn_job(&state, "a", "t");
+        let second = spawn_job(&state, "b", "t");
+        let other = spawn_job(&state, "c", "u");
+
+        assert_eq!(state.cancel_jobs_for_template("t").await, 2);
+        assert!(first.load(Ordering::Relaxed) && second.load(Ordering::Relaxed));
+        assert!(!state
+            .registry
+            .any_running(&["a".to_string(), "b".to_string()]));
+        assert!(!other.load(Ordering::Relaxed));
+        assert!(state.registry.any_running(&["c".to_string()]));
+        assert!(state
+            .logs
+            .render("a")
+            .unwrap()
+            .contains("cancellation requested"));
+        assert_eq!(state.logs.render("c"), None);
+
+        assert_eq!(state.cancel_jobs_for_template("t").await, 0);
+    }
+
+    #[actix_web::test]
+    async fn sweeper_evicts_only_expired_finished_jobs() {
+        let ttl = Duration::from_millis(200);
+        let state = jobs_state(1);
+        state
+            .set_status("done", JobStatus::Completed("ok".to_string()))
+            .await;
+        state
+            .set_status(
+                "running",
+                JobStatus::InProgress {
+                    lines: 1,
+                    percent: 10,
+                },
+            )
+            .await;
+        tokio::spawn(start_job_sweeper(state.clone(), ttl));
+
+        // The first sweep runs at once, before the finished job is older than the TTL.
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(job_status(&state, "done").await.is_some());
+
+        // The next sweep, a second later, finds it expired.
+        tokio::time::sleep(Duration::from_millis(1300)).await;
+        assert!(job_status(&state, "done").await.is_none());
+        assert_eq!(state.logs.render("done"), None);
+        assert!(matches!(
+            job_status(&state, "running").await,
+            Some(JobStatus::InProgress { .. })
+        ));
+        assert!(state.logs.render("running").is_some());
     }
 }
@@ -1,9 +1,19 @@
 mod config;
+mod db;
 mod job_controller;
+mod schema;
 mod services;
+#[cfg(test)]
+mod test_support;
 
+use crate::config::{Config, ServerConfig};
+use crate::job_controller::callbacks::JobCallbacks;
+use crate::job_controller::events::JobEvents;
+use crate::job_controller::log::JobLogs;
+use crate::job_controller::registry::JobRegistry;
 use crate::job_controller::state::JobsState;
 use actix_web::{web, App, HttpRequest, HttpResponse, HttpServer};
+use common::model::template::MAX_TEMPLATE_JSON_BYTES;
 use env_logger::Env;
 use include_dir::{include_dir, Dir};
 use log::info;
@@ -12,7 +22,7 @@ use std::collections::HashMap;
 use std::sync::Arc;
 use std::thread;
 use std::time::Duration;
-use tokio::sync::{mpsc, RwLock};
+use tokio::sync::{mpsc, RwLock, Semaphore};
 
 static STATIC_DIR: Dir = include_dir!("$CARGO_MANIFEST_DIR/static/dist");
 
@@ -39,11 +49,12 @@ async fn serve_embedded(req: HttpRequest) -> HttpResponse {
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
     env_logger::init_from_env(Env::default().default_filter_or("info"));
-    let host = "127.0.0.1";
-    let port = 8080;
-    let url = format!("http://{}:{}", host, port);
+    let server_config = ServerConfig::from_env();
+    let url = server_config.url();
 
-    {
+    // Only open a browser when the server runs on this machine only; in a container or
+    // on a LAN there is usually no local browser to open.
+    if server_config.is_local() {
         let _url_clone = url.clone();
         thread::spawn(move || {
             thread::sleep(Duration::from_millis(500));
@@ -51,10 +62,42 @@ async fn main() -> std::io::Result<()> {
         });
     }
 
+    let config = Config::from_env();
+    config.create_dirs().map_err(|e| {
+        std::io::Error::other(format!(
+            "Failed to create the data directory {}: {}",
+            config.data_dir.display(),
+            e
+        ))
+    })?;
+    let pool = db::create_pool(&config.db_path).map_err(|e| {
+        std::io::Error::other(format!(
+            "Failed to open {}: {}",
+            config.db_path.display(),
+            e
+        ))
+    })?;
+
+    // Bring the database to the current schema version before serving any request.
+    pool.get()
+        .map_err(|e| e.to_string())
+        .and_then(|mut conn| schema::ensure_schema(&mut conn).map_err(|e| e.to_string()))
+        .map_err(|e| {
+            std::io::Error::other(format!("Failed to prepare the database schema: {}", e))
+        })?;
+
     // Initialize job controller state
     let (tx, rx) = mpsc::channel(100);
     let jobs_state = JobsState {
         jobs: Arc::new(RwLock::new(HashMap::new())),
+        logs: JobLogs::default(),
+        registry: JobRegistry::default(),
+        events: JobEvents::default(),
+        callbacks: JobCallbacks::new(
+            config.callback_secret.clone(),
+            config.callback_allowed_hosts.clone(),
+        ),
+        cpu_permits: Arc::new(Semaphore::new(job_controller::state::cpu_permit_count())),
         tx,
     };
 
@@ -64,17 +107,29 @@ async fn main() -> std::io::Result<()> {
         job_controller::state::start_job_updater(updater_state, rx).await;
     });
 
+    // Start the task that evicts finished jobs
+    let sweeper_state = jobs_state.clone();
+    let job_ttl = config.job_ttl;
+    tokio::spawn(async move {
+        job_controller::state::start_job_sweeper(sweeper_state, job_ttl).await;
+    });
+
     info!("Server running at {}", url);
 
     HttpServer::new(move || {
         App::new()
-            .app_data(web::JsonConfig::default().limit(10 * 1024 * 1024)) // 10 MB
+            .app_data(web::JsonConfig::default().limit(MAX_TEMPLATE_JSON_BYTES))
             .app_data(web::Data::new(jobs_state.clone()))
+            .app_data(web::Data::new(pool.clone()))
+            .app_data(web::Data::new(config.clone()))
             .service(services::templates::configure_routes())
             .service(services::data_sources::csv::configure_routes())
+            .service(services::jobs::configure_routes())
+            .service(services::render::configure_routes())
+            .service(services::health::configure_routes())
             .default_service(web::route().to(serve_embedded))
     })
-        .bind((host, port))?
+        .bind((server_config.host.as_str(), server_config.port))?
         .run()
         .await
 }
@@ -0,0 +1,296 @@
+//! Database schema setup.
+//!
+//! The schema is versioned with SQLite's `user_version` pragma. `ensure_schema` runs at
+//! startup and applies, in order and each in its own transaction, every entry of
+//! `MIGRATIONS` past the version stored in the database, then records the new version.
+//! A fresh database file therefore gets every table, and an existing one only the steps
+//! it is missing. Schema changes are made by appending a migration, never by editing one
+//! that has shipped.
+//!
+//! Databases created before versioning (version 0) may have any subset of the tables and
+//! columns, since they used to be created with the file or added one by one at startup.
+//! The first migration is written for them: it uses `CREATE TABLE IF NOT EXISTS` and adds
+//! only the columns a table lacks, so it brings any such database to version 1.
+
+use rusqlite::{Connection, Transaction};
+
+/// A schema change, applied once inside a transaction.
+type Migration = fn(&Transaction) -> rusqlite::Result<()>;
+
+/// Every schema change, oldest first. Entry `i` brings the database to version `i + 1`.
+const MIGRATIONS: &[Migration] = &[create_base_schema, add_csv_skip_lines, add_page_size];
+
+/// Columns added to `templates` after it was first created, with their SQL type.
+const TEMPLATE_COLUMNS: &[(&str, &str)] = &[
+    ("margin_top_mm", "REAL"),
+    ("margin_right_mm", "REAL"),
+    ("margin_bottom_mm", "REAL"),
+    ("margin_left_mm", "REAL"),
+    ("orientation", "TEXT"),
+    ("font_path", "TEXT"),
+    ("name", "TEXT"),
+    ("created_at", "TEXT"),
+    ("updated_at", "TEXT"),
+];
+
+/// Columns added to `column_types` after it was first created, with their SQL type.
+const COLUMN_TYPES_COLUMNS: &[(&str, &str)] = &[
+    ("allow_empty", "INTEGER"),
+    ("true_label", "TEXT"),
+    ("false_label", "TEXT"),
+    ("number_display", "TEXT"),
+];
+
+/// Applies the migrations the database behind `conn` has not run yet.
+///
+/// # Returns
+/// `Ok(())` once the database is at the latest version, or the first `rusqlite::Error`.
+/// A failed migration is rolled back and leaves the database at the previous version.
+pub fn ensure_schema(conn: &mut Connection) -> rusqlite::Result<()> {
+    let current: usize = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+    if current > MIGRATIONS.len() {
+        log::warn!(
+            "Database schema version {} is newer than this build ({})",
+            current,
+            MIGRATIONS.len()
+        );
+        return Ok(());
+    }
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(current) {
+        let version = index + 1;
+        let tx = conn.transaction()?;
+        migration(&tx)?;
+        tx.pragma_update(None, "user_version", version)?;
+        tx.commit()?;
+        log::info!("Database schema migrated to version {}", version);
+    }
+    Ok(())
+}
+
+/// Version 1: the `templates`, `images`, `template_vars` and `column_types` tables with
+/// all their columns.
+///
+/// Tables that already exist are kept, and only the columns they lack are added, so it
+/// also upgrades any database created before versioning.
+fn create_base_schema(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "CREATE TABLE IF NOT EXISTS templates (
+             id                TEXT PRIMARY KEY,
+             text              TEXT NOT NULL,
+             datasource_md5    TEXT,
+             last_verified_md5 TEXT,
+             verified          INTEGER NOT NULL DEFAULT 0,
+             margin_top_mm     REAL,
+             margin_right_mm   REAL,
+             margin_bottom_mm  REAL,
+             margin_left_mm    REAL,
+             orientation       TEXT,
+             font_path         TEXT,
+             name              TEXT,
+             created_at        TEXT,
+             updated_at        TEXT
+         );
+         CREATE TABLE IF NOT EXISTS images (
+             id          TEXT PRIMARY KEY,
+             template_id TEXT NOT NULL,
+             base64      TEXT NOT NULL
+         );
+         CREATE TABLE IF NOT EXISTS template_vars (
+             template_id TEXT NOT NULL,
+             name        TEXT NOT NULL,
+             value       TEXT NOT NULL,
+             PRIMARY KEY (template_id, name)
+         );
+         CREATE TABLE IF NOT EXISTS column_types (
+             template_id      TEXT NOT NULL,
+             title            TEXT NOT NULL,
+             placeholder_type TEXT NOT NULL,
+             allow_empty      INTEGER,
+             true_label       TEXT,
+             false_label      TEXT,
+             number_display   TEXT,
+             PRIMARY KEY (template_id, title)
+         );",
+    )?;
+    ensure_columns(tx, "templates", TEMPLATE_COLUMNS)?;
+    ensure_columns(tx, "column_types", COLUMN_TYPES_COLUMNS)
+}
+
+/// Version 2: `templates.csv_skip_lines`, the number of leading lines of the CSV file
+/// that come before its header, kept from the last verification request that set it.
+fn add_csv_skip_lines(tx: &Transaction) -> rusqlite::Result<()> {
+    ensure_columns(
+        tx,
+        "templates",
+        &[("csv_skip_lines", "INTEGER NOT NULL DEFAULT 0")],
+    )
+}
+
+/// Version 3: `templates.page_size`, the paper size saved with the template's other
+/// layout settings. `NULL` means A4.
+fn add_page_size(tx: &Transaction) -> rusqlite::Result<()> {
+    ensure_columns(tx, "templates", &[("page_size", "TEXT")])
+}
+
+/// Adds the columns of `columns` that `table` does not have yet.
+///
+/// Does nothing if the table itself does not exist.
+fn ensure_columns(
+    conn: &Connection,
+    table: &str,
+    columns: &[(&str, &str)],
+) -> rusqlite::Result<()> {
+    let existing: Vec<String> = conn
+        .prepare(&format!("PRAGMA table_info({})", table))?
+        .query_map([], |row| row.get(1))?
+        .collect::<rusqlite::Result<_>>()?;
+    if existing.is_empty() {
+        return Ok(());
+    }
+    for (name, sql_type) in columns {
+        if !existing.iter().any(|c| c == name) {
+            conn.execute_batch(&format!(
+                "ALTER TABLE {} ADD COLUMN {} {};",
+                table, name, sql_type
+            ))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The schema version stored in the database.
+    fn version(conn: &Connection) -> usize {
+        conn.pragma_query_value(None, "user_version", |row| row.get(0))
+            .unwrap()
+    }
+
+    /// The column names of `table`, in order; empty if it does not exist.
+    fn columns(conn: &Connection, table: &str) -> Vec<String> {
+        conn.prepare(&format!("PRAGMA table_info({})", table))
+            .unwrap()
+            .query_map([], |row| row.get(1))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap()
+    }
+
+    /// The columns of every table, to compare two schemas.
+    fn schema(conn: &Connection) -> Vec<Vec<String>> {
+        ["templates", "images", "template_vars", "column_types"]
+            .iter()
+            .map(|table| columns(conn, table))
+            .collect()
+    }
+
+    #[test]
+    fn ensure_schema_is_idempotent() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        ensure_schema(&mut conn).unwrap();
+        assert_eq!(version(&conn), MIGRATIONS.len());
+        let first = schema(&conn);
+        assert!(first.iter().all(|table| !table.is_empty()));
+        conn.execute(
+            "INSERT INTO templates (id, text, csv_skip_lines) VALUES ('t', 'Hola', 2)",
+            [],
+        )
+        .unwrap();
+
+        ensure_schema(&mut conn).unwrap();
+        assert_eq!(version(&conn), MIGRATIONS.len());
+        assert_eq!(schema(&conn), first);
+        let skip_lines: i64 = conn
+            .query_row(
+                "SELECT csv_skip_lines FROM templates WHERE id = 't'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(skip_lines, 2);
+    }
+
+    #[test]
+    fn ensure_schema_upgrades_a_baseline_database() {
+        // The tables as they were before the schema was versioned: no variables, column
+        // types, layout settings or names.
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE templates (
+                 id                TEXT PRIMARY KEY,
+                 text              TEXT NOT NULL,
+                 datasource_md5    TEXT,
+                 last_verified_md5 TEXT,
+                 verified          INTEGER NOT NULL DEFAULT 0
+             );
+             CREATE TABLE images (
+                 id          TEXT PRIMARY KEY,
+                 template_id TEXT NOT NULL,
+                 base64      TEXT NOT NULL
+             );
+             INSERT INTO templates (id, text, datasource_md5, verified)
+                 VALUES ('t', 'Hola [img:logo]', 'abc', 1);
+             INSERT INTO images (id, template_id, base64) VALUES ('logo', 't', 'AAAA');",
+        )
+        .unwrap();
+        assert_eq!(version(&conn), 0);
+
+        ensure_schema(&mut conn).unwrap();
+
+        assert_eq!(version(&conn), MIGRATIONS.len());
+        let templates = columns(&conn, "templates");
+        for (name, _) in TEMPLATE_COLUMNS {
+            assert!(templates.iter().any(|c| c == name), "{}", name);
+        }
+        assert!(templates.iter().any(|c| c == "csv_skip_lines"));
+        assert!(templates.iter().any(|c| c == "page_size"));
+        let column_types = columns(&conn, "column_types");
+        for (name, _) in COLUMN_TYPES_COLUMNS {
+            assert!(column_types.iter().any(|c| c == name), "{}", name);
+        }
+        assert!(!columns(&conn, "template_vars").is_empty());
+
+        // Existing rows are kept, with the defaults of the new columns.
+        let row: (String, Option<String>, i64, i64, Option<String>) = conn
+            .query_row(
+                "SELECT text, datasource_md5, verified, csv_skip_lines, orientation \
+                 FROM templates WHERE id = 't'",
+                [],
+                |row| {
+                    Ok((
+                        row.get(0)?,
+                        row.get(1)?,
+                        row.get(2)?,
+                        row.get(3)?,
+                        row.get(4)?,
+                    ))
+                },
+            )
+            .unwrap();
+        assert_eq!(
+            row,
+            (
+                "Hola [img:logo]".to_string(),
+                Some("abc".to_string()),
+                1,
+                0,
+                None
+            )
+        );
+        let images: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM images WHERE template_id = 't'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(images, 1);
+
+        // A second start finds nothing to do.
+        let upgraded = schema(&conn);
+        ensure_schema(&mut conn).unwrap();
+        assert_eq!(schema(&conn), upgraded);
+    }
+}
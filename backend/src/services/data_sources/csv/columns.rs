@@ -0,0 +1,113 @@
+//! Provides the `GET /api/data_sources/csv/columns/{template_id}` endpoint.
+//!
+//! Returns the inferred column schema (`Vec<ColumnCheck>`) of a template's CSV file
+//! without starting a verification job. Only the header and the first data row are read,
+//! as in the verification fast-path, so the answer is immediate. This lets the editor
+//! show the columns of an already verified data source on page load.
+//!
+//! The schema is only served for a data source that is verified and unchanged since
+//! (`verified = 1` and `datasource_md5 = last_verified_md5`). Any other file must go
+//! through `POST /api/data_sources/csv/verify` first.
+//!
+//! Column types stored through `POST /api/data_sources/csv/types` replace the inferred ones.
+
+use super::types::{apply_column_types, load_column_types};
+use super::verify::{column_checks_for_query, validate_delimiter};
+use crate::config::Config;
+use crate::db::DbPool;
+use actix_web::{web, HttpResponse, Responder};
+use common::model::csv::ColumnCheck;
+use common::requests::CsvColumnsQuery;
+use rusqlite::{params, OptionalExtension};
+
+/// Why the columns could not be returned.
+enum ColumnsError {
+    /// The template, its data source or the file does not exist.
+    NotFound(&'static str),
+    /// The data source has not been verified since it was uploaded.
+    Unverified,
+    /// The file or the database could not be read.
+    Internal(String),
+}
+
+/// Actix web handler for `GET /api/data_sources/csv/columns/{template_id}`.
+///
+/// # Returns
+/// - `200 OK` with a JSON array of `ColumnCheck`.
+/// - `400 Bad Request` if the requested delimiter is invalid.
+/// - `404 Not Found` if the template has no data source or its file is missing.
+/// - `409 Conflict` if the data source is not verified.
+/// - `500 Internal Server Error` if the file or the database cannot be read.
+pub(crate) async fn process(
+    template_id: web::Path<String>,
+    query: web::Query<CsvColumnsQuery>,
+    pool: web::Data<DbPool>,
+    config: web::Data<Config>,
+) -> impl Responder {
+    let query = query.into_inner();
+    if let Err(err) = validate_delimiter(query.delimiter.as_deref(), Some("\"")) {
+        return HttpResponse::BadRequest().body(err);
+    }
+
+    let template_id = template_id.into_inner();
+    let result = web::block(move || verified_columns(&pool, &config, &template_id, &query)).await;
+    match result {
+        Ok(Ok(columns)) => HttpResponse::Ok().json(columns),
+        Ok(Err(ColumnsError::NotFound(msg))) => HttpResponse::NotFound().body(msg),
+        Ok(Err(ColumnsError::Unverified)) => {
+            HttpResponse::Conflict().body("Data source is not verified")
+        }
+        Ok(Err(ColumnsError::Internal(e))) => {
+            HttpResponse::InternalServerError().body(format!("Error: {}", e))
+        }
+        Err(e) => HttpResponse::InternalServerError().body(format!("Error: {}", e)),
+    }
+}
+
+/// Reads the column schema of the template's verified CSV file.
+fn verified_columns(
+    pool: &DbPool,
+    config: &Config,
+    template_id: &str,
+    query: &CsvColumnsQuery,
+) -> Result<Vec<ColumnCheck>, ColumnsError> {
+    let conn = pool
+        .get()
+        .map_err(|e| ColumnsError::Internal(e.to_string()))?;
+    let row = conn
+        .query_row(
+            "SELECT datasource_md5, last_verified_md5, verified, csv_skip_lines
+             FROM templates WHERE id = ?1",
+            params![template_id],
+            |r| {
+                Ok((
+                    r.get::<_, Option<String>>(0)?,
+                    r.get::<_, Option<String>>(1)?,
+                    r.get::<_, i32>(2)?,
+                    r.get::<_, usize>(3)?,
+                ))
+            },
+        )
+        .optional()
+        .map_err(|e| ColumnsError::Internal(e.to_string()))?;
+    let Some((datasource_md5, last_verified_md5, verified, skip_lines)) = row else {
+        return Err(ColumnsError::NotFound("Template not found"));
+    };
+    let Some(ds_md5) = datasource_md5 else {
+        return Err(ColumnsError::NotFound("Template has no data source"));
+    };
+    if verified != 1 || last_verified_md5.as_deref() != Some(ds_md5.as_str()) {
+        return Err(ColumnsError::Unverified);
+    }
+
+    let file_path = config.csv_path(template_id, &ds_md5);
+    if !file_path.exists() {
+        return Err(ColumnsError::NotFound("CSV file not found"));
+    }
+    let mut columns =
+        column_checks_for_query(&file_path, query, skip_lines).map_err(ColumnsError::Internal)?;
+    let stored_types =
+        load_column_types(&conn, template_id).map_err(|e| ColumnsError::Internal(e.to_string()))?;
+    apply_column_types(&mut columns, &stored_types);
+    Ok(columns)
+}
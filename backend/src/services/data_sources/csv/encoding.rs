@@ -0,0 +1,184 @@
+//! Opening stored CSV files as UTF-8 text, whatever their on-disk encoding.
+//!
+//! The verification pipeline works on `String`s, so a file saved as Windows-1252 (the default
+//! of Excel on Windows) would fail at its first accented character. `open_decoded` wraps the
+//! file in a transcoding reader when needed, so everything downstream — the header, the
+//! inferred `ColumnCheck.first_row` values and the row checks — only ever sees UTF-8.
+//!
+//! `check_text_sample` applies the same kind of sniffing at upload time, to reject files
+//! that are clearly binary before they are stored as a data source.
+
+use common::model::csv::CsvEncoding;
+use encoding_rs::WINDOWS_1252;
+use encoding_rs_io::DecodeReaderBytesBuilder;
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, Read},
+    path::Path,
+};
+
+/// Number of bytes inspected by `detect_encoding`.
+const SNIFF_LEN: u64 = 64 * 1024;
+/// Number of bytes at the start of an upload inspected by `check_text_sample`.
+pub(crate) const TEXT_SAMPLE_LEN: usize = SNIFF_LEN as usize;
+/// Largest share of control characters, in percent, tolerated by `check_text_sample`.
+const MAX_CONTROL_PERCENT: usize = 1;
+
+/// Opens the CSV file at `path` as a reader of UTF-8 text.
+///
+/// UTF-8 files are read as they are. Windows-1252 files are transcoded on the fly. With
+/// `CsvEncoding::Auto`, the encoding is first guessed with `detect_encoding`.
+///
+/// # Arguments
+/// * `path` - Path of the stored CSV file.
+/// * `encoding` - The encoding requested by the client.
+///
+/// # Returns
+/// A buffered reader yielding UTF-8 text and the encoding actually used, or an error
+/// `String` if the file cannot be read.
+pub(crate) fn open_decoded(
+    path: &Path,
+    encoding: CsvEncoding,
+) -> Result<(Box<dyn BufRead>, CsvEncoding), String> {
+    let encoding = match encoding {
+        CsvEncoding::Auto => detect_encoding(path)?,
+        other => other,
+    };
+    let file = File::open(path).map_err(|e| e.to_string())?;
+    let reader: Box<dyn BufRead> = match encoding {
+        CsvEncoding::Windows1252 => Box::new(BufReader::new(
+            DecodeReaderBytesBuilder::new()
+                .encoding(Some(WINDOWS_1252))
+                .build(file),
+        )),
+        _ => Box::new(BufReader::new(file)),
+    };
+    Ok((reader, encoding))
+}
+
+/// Checks that `sample`, the first bytes of an uploaded file, looks like text.
+///
+/// Any byte sequence is valid Windows-1252, so the encoding alone cannot tell text from
+/// binary data. Instead, the sample is rejected if it contains a NUL byte (which no
+/// supported encoding produces for text) or if more than `MAX_CONTROL_PERCENT` percent of
+/// it are control characters other than tab, line feed, form feed and carriage return.
+///
+/// # Returns
+/// `Ok(())` if the sample looks like text, or an error `String` describing the problem.
+pub(crate) fn check_text_sample(sample: &[u8]) -> Result<(), String> {
+    if sample.contains(&0) {
+        return Err("The file contains NUL bytes; it looks like a binary file, not a CSV".into());
+    }
+    let control = sample
+        .iter()
+        .filter(|&&b| (b < 0x20 && !matches!(b, b'\t' | b'\n' | 0x0c | b'\r')) || b == 0x7f)
+        .count();
+    if control * 100 > sample.len() * MAX_CONTROL_PERCENT {
+        return Err(format!(
+            "The file contains {} control characters in its first {} bytes; it does not look \
+             like a text CSV",
+            control,
+            sample.len()
+        ));
+    }
+    Ok(())
+}
+
+/// Guesses the encoding of the CSV file at `path` from its first `SNIFF_LEN` bytes.
+///
+/// Windows-1252 text with accented characters is almost never valid UTF-8, so the file is
+/// taken as UTF-8 when those bytes decode cleanly and as Windows-1252 otherwise. A multi-byte
+/// sequence cut off by the end of the sample does not count as invalid.
+fn detect_encoding(path: &Path) -> Result<CsvEncoding, String> {
+    let file = File::open(path).map_err(|e| e.to_string())?;
+    let mut sample = Vec::new();
+    file.take(SNIFF_LEN)
+        .read_to_end(&mut sample)
+        .map_err(|e| e.to_string())?;
+
+    match std::str::from_utf8(&sample) {
+        Ok(_) => Ok(CsvEncoding::Utf8),
+        Err(e) if e.error_len().is_none() => Ok(CsvEncoding::Utf8),
+        Err(_) => Ok(CsvEncoding::Windows1252),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    /// "José" and "5€" in Windows-1252: `0xE9` is `é` and `0x80` is `€`.
+    const WINDOWS_1252_CSV: &[u8] = b"nombre,precio\nJos\xe9,5\x80\n";
+    const DECODED_CSV: &str = "nombre,precio\nJosé,5€\n";
+
+    fn csv_file(content: &[u8]) -> NamedTempFile {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(content).unwrap();
+        file
+    }
+
+    /// Opens `file` with `encoding` and returns its text and the encoding used.
+    fn read_decoded(file: &NamedTempFile, encoding: CsvEncoding) -> (String, CsvEncoding) {
+        let (mut reader, used) = open_decoded(file.path(), encoding).unwrap();
+        let mut text = String::new();
+        reader.read_to_string(&mut text).unwrap();
+        (text, used)
+    }
+
+    #[test]
+    fn windows_1252_is_transcoded() {
+        let file = csv_file(WINDOWS_1252_CSV);
+        assert_eq!(
+            read_decoded(&file, CsvEncoding::Windows1252),
+            (DECODED_CSV.to_string(), CsvEncoding::Windows1252)
+        );
+    }
+
+    #[test]
+    fn auto_detects_windows_1252() {
+        let file = csv_file(WINDOWS_1252_CSV);
+        assert_eq!(
+            read_decoded(&file, CsvEncoding::Auto),
+            (DECODED_CSV.to_string(), CsvEncoding::Windows1252)
+        );
+    }
+
+    #[test]
+    fn auto_detects_utf8() {
+        let file = csv_file(DECODED_CSV.as_bytes());
+        assert_eq!(
+            read_decoded(&file, CsvEncoding::Auto),
+            (DECODED_CSV.to_string(), CsvEncoding::Utf8)
+        );
+    }
+
+    #[test]
+    fn detection_only_reads_the_first_64_kb() {
+        // A Windows-1252 byte past the sample is not seen.
+        let mut content = vec![b'a'; SNIFF_LEN as usize];
+        content.extend_from_slice(b"\xe9\n");
+        assert_eq!(
+            detect_encoding(csv_file(&content).path()),
+            Ok(CsvEncoding::Utf8)
+        );
+
+        // One inside it is.
+        content[SNIFF_LEN as usize - 2] = 0xe9;
+        assert_eq!(
+            detect_encoding(csv_file(&content).path()),
+            Ok(CsvEncoding::Windows1252)
+        );
+    }
+
+    #[test]
+    fn utf8_character_cut_by_the_sample_end_is_not_invalid() {
+        let mut content = vec![b'a'; SNIFF_LEN as usize - 1];
+        content.extend_from_slice("é\n".as_bytes());
+        assert_eq!(
+            detect_encoding(csv_file(&content).path()),
+            Ok(CsvEncoding::Utf8)
+        );
+    }
+}
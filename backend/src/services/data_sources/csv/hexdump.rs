@@ -0,0 +1,168 @@
+//! Provides a diagnostic endpoint that dumps raw bytes of a template's CSV file.
+//!
+//! When verification fails for reasons that are invisible in a text editor (a UTF-8 BOM,
+//! a legacy encoding, stray control characters), looking at the actual bytes is the
+//! quickest way to find the culprit. The `GET /api/data_sources/csv/hexdump/{template_id}`
+//! endpoint reads a slice of the stored `{template_id}_{datasource_md5}.csv` file (see
+//! `Config::csv_path`) and
+//! returns it as a classic hex + ASCII dump in plain text:
+//!
+//! ```text
+//! 00000000  ef bb bf 4e 61 6d 65 3b  45 6d 61 69 6c 0d 0a 4a  |...Name;Email..J|
+//! ```
+//!
+//! The slice is selected with the `offset` and `len` query parameters (see
+//! `common::requests::HexdumpQuery`). `len` is capped at `MAX_DUMP_LEN` bytes.
+
+use crate::config::Config;
+use crate::db::DbPool;
+use actix_web::{web, HttpResponse, Responder};
+use common::requests::HexdumpQuery;
+use rusqlite::params;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+
+/// Number of bytes returned when the request does not specify `len`.
+const DEFAULT_DUMP_LEN: u64 = 512;
+/// Upper bound for the number of bytes a single request can dump.
+const MAX_DUMP_LEN: u64 = 4096;
+/// Number of bytes rendered on each line of the dump.
+const BYTES_PER_LINE: usize = 16;
+
+/// Actix web handler for `GET /api/data_sources/csv/hexdump/{template_id}`.
+///
+/// # Returns
+/// - `200 OK` with a `text/plain` hex dump on success (empty if `offset` is past the end).
+/// - `404 Not Found` if the template has no data source or the file is missing.
+/// - `500 Internal Server Error` on database or I/O failures.
+pub(crate) async fn process(
+    template_id: web::Path<String>,
+    query: web::Query<HexdumpQuery>,
+    pool: web::Data<DbPool>,
+    config: web::Data<Config>,
+) -> impl Responder {
+    let offset = query.offset.unwrap_or(0);
+    let len = query.len.unwrap_or(DEFAULT_DUMP_LEN).min(MAX_DUMP_LEN);
+
+    match dump_csv_slice(&pool, &config, &template_id, offset, len) {
+        Ok(Some(dump)) => HttpResponse::Ok()
+            .content_type("text/plain; charset=utf-8")
+            .body(dump),
+        Ok(None) => HttpResponse::NotFound().body("CSV file not found"),
+        Err(e) => HttpResponse::InternalServerError().body(format!("Error: {}", e)),
+    }
+}
+
+/// Reads `len` bytes starting at `offset` from the template's current CSV file and formats
+/// them with `format_hexdump`.
+///
+/// # Returns
+/// `Ok(None)` if the template does not exist, has no associated CSV, or the file is
+/// missing on disk.
+fn dump_csv_slice(
+    pool: &DbPool,
+    config: &Config,
+    template_id: &str,
+    offset: u64,
+    len: u64,
+) -> Result<Option<String>, String> {
+    let conn = pool.get().map_err(|e| e.to_string())?;
+    let datasource_md5 = match conn.query_row(
+        "SELECT datasource_md5 FROM templates WHERE id = ?1",
+        params![template_id],
+        |row| row.get::<_, Option<String>>(0),
+    ) {
+        Ok(Some(md5)) => md5,
+        Ok(None) | Err(rusqlite::Error::QueryReturnedNoRows) => return Ok(None),
+        Err(e) => return Err(e.to_string()),
+    };
+
+    let file_path = config.csv_path(template_id, &datasource_md5);
+    let mut file = match File::open(&file_path) {
+        Ok(f) => f,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.to_string()),
+    };
+
+    file.seek(SeekFrom::Start(offset))
+        .map_err(|e| e.to_string())?;
+    let mut bytes = Vec::with_capacity(len as usize);
+    file.take(len)
+        .read_to_end(&mut bytes)
+        .map_err(|e| e.to_string())?;
+
+    Ok(Some(format_hexdump(&bytes, offset)))
+}
+
+/// Formats `bytes` as lines of `offset  hex bytes  |ascii|`.
+///
+/// Printable ASCII characters are shown as-is in the right column; everything else
+/// (control characters, bytes of multi-byte sequences) is shown as `.`.
+fn format_hexdump(bytes: &[u8], base_offset: u64) -> String {
+    let mut out = String::new();
+    for (line_idx, line) in bytes.chunks(BYTES_PER_LINE).enumerate() {
+        let line_offset = base_offset + (line_idx * BYTES_PER_LINE) as u64;
+        out.push_str(&format!("{:08x} ", line_offset));
+
+        for i in 0..BYTES_PER_LINE {
+            // Extra gap between the two groups of eight bytes.
+            if i % 8 == 0 {
+                out.push(' ');
+            }
+            match line.get(i) {
+                Some(b) => out.push_str(&format!("{:02x} ", b)),
+                None => out.push_str("   "),
+            }
+        }
+
+        let ascii: String = line
+            .iter()
+            .map(|&b| if b.is_ascii_graphic() || b == b' ' { b as char } else { '.' })
+            .collect();
+        out.push_str(&format!(" |{}|\n", ascii));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestEnv;
+
+    const CSV: &str = "\u{feff}Name;Email\r\nJosé;a@b.c\r\n";
+
+    #[test]
+    fn dumps_known_file() {
+        let env = TestEnv::new();
+        env.insert_template("t", "", Some(CSV));
+        let dump = dump_csv_slice(&env.pool, &env.config, "t", 0, 64).unwrap();
+        assert_eq!(
+            dump.as_deref(),
+            Some(concat!(
+                "00000000  ef bb bf 4e 61 6d 65 3b  45 6d 61 69 6c 0d 0a 4a  |...Name;Email..J|\n",
+                "00000010  6f 73 c3 a9 3b 61 40 62  2e 63 0d 0a              |os..;a@b.c..|\n",
+            ))
+        );
+    }
+
+    #[test]
+    fn dumps_slice_from_offset() {
+        let env = TestEnv::new();
+        env.insert_template("t", "", Some(CSV));
+        let dump = dump_csv_slice(&env.pool, &env.config, "t", 18, 3).unwrap();
+        assert_eq!(
+            dump.as_deref(),
+            Some("00000012  c3 a9 3b                                          |..;|\n")
+        );
+        let past_end = dump_csv_slice(&env.pool, &env.config, "t", 1000, 16).unwrap();
+        assert_eq!(past_end.as_deref(), Some(""));
+    }
+
+    #[test]
+    fn missing_data_source_is_not_found() {
+        let env = TestEnv::new();
+        env.insert_template("t", "", None);
+        assert_eq!(dump_csv_slice(&env.pool, &env.config, "t", 0, 16), Ok(None));
+        assert_eq!(dump_csv_slice(&env.pool, &env.config, "x", 0, 16), Ok(None));
+    }
+}
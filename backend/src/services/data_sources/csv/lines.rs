@@ -0,0 +1,49 @@
+//! Line counting for progress reporting.
+//!
+//! Jobs that stream a CSV file only know how many lines they have processed. Counting the
+//! file's lines up front lets them report progress as a percentage of the whole file.
+
+use std::{
+    fs::File,
+    io::{self, Read},
+    path::Path,
+};
+
+/// Counts the lines of the file at `path` by counting its `\n` bytes.
+///
+/// A last line without a trailing line break is counted too. The count is raw: a quoted
+/// cell spanning several lines adds one line per line break, so it can exceed the number
+/// of records. `\n` is the same byte in UTF-8 and Windows-1252, so the file does not need
+/// to be decoded.
+///
+/// # Returns
+/// The number of lines, or an I/O error if the file cannot be read.
+pub(crate) fn count_lines_raw(path: &Path) -> io::Result<usize> {
+    let mut file = File::open(path)?;
+    let mut buf = vec![0u8; 64 * 1024];
+    let mut lines = 0;
+    let mut last = b'\n';
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        lines += buf[..n].iter().filter(|&&b| b == b'\n').count();
+        last = buf[n - 1];
+    }
+    if last != b'\n' {
+        lines += 1;
+    }
+    Ok(lines)
+}
+
+/// Returns how far along `processed` out of `total` is, as a percentage.
+///
+/// The result is capped at 99 so that 100 is only shown once the job has actually finished,
+/// even if `total` was underestimated.
+pub(crate) fn progress_percent(processed: usize, total: usize) -> u8 {
+    if total == 0 {
+        return 0;
+    }
+    (processed.saturating_mul(100) / total).min(99) as u8
+}
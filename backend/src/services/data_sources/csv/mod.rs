@@ -16,14 +16,32 @@
 //!   header integrity, data type consistency, and structural correctness.
 //!
 //! - `GET /api/data_sources/csv/status/{job_id}`: Allows clients to poll for the status of a
-//!   background job (e.g., the verification job started by `/verify`). It takes a `job_id` as a
-//!   path parameter and returns the current `JobStatus` (`Pending`, `InProgress`, `Completed`, or
-//!   `Failed`) from the shared `JobsState`.
+//!   background job (e.g., the verification job started by `/verify`). It is an alias of the
+//!   generic `GET /api/jobs/{job_id}`, kept for compatibility, and returns the current
+//!   `JobStatus` from the shared `JobsState`.
+//!
+//! - `GET /api/data_sources/csv/columns/{template_id}`: Returns the inferred column schema of
+//!   a verified, unchanged data source right away, read from its header and first data row,
+//!   without starting a verification job. Answers `409 Conflict` if the source is unverified.
+//!
+//! - `POST /api/data_sources/csv/types/{template_id}`: Stores column types corrected by the
+//!   user (a JSON `Vec<ColumnCheck>`). Verification uses them instead of the inferred types
+//!   for the columns with the same title.
+//!
+//! - `GET /api/data_sources/csv/hexdump/{template_id}?offset=0&len=512`: A diagnostic endpoint
+//!   that returns a hex + ASCII dump of a slice of the stored CSV file, so BOMs, wrong
+//!   encodings, or stray control characters can be spotted when verification fails.
 
+use crate::services::jobs::get_status;
 use actix_web::web::{get, post, scope};
 use actix_web::Scope;
 
-mod get_status;
+mod columns;
+mod encoding;
+mod hexdump;
+mod lines;
+mod tokenizer;
+mod types;
 mod upload;
 mod verify;
 
@@ -34,8 +52,14 @@ pub fn configure_routes() -> Scope {
     scope(API_PATH)
         // Route to start a new CSV verification job.
         .route("/verify", post().to(verify::process))
-        // Route to get the status of an ongoing verification job.
+        // Route to get the status of a verification job; alias of `GET /api/jobs/{job_id}`.
         .route("/status/{job_id}", get().to(get_status::process))
+        // Route to read the columns of a verified CSV file without a new job.
+        .route("/columns/{template_id}", get().to(columns::process))
+        // Route to store the column types corrected by the user.
+        .route("/types/{template_id}", post().to(types::process))
         // Route to upload a new CSV file.
         .route("/upload", post().to(upload::process))
+        // Route to inspect the raw bytes of the stored CSV file.
+        .route("/hexdump/{template_id}", get().to(hexdump::process))
 }
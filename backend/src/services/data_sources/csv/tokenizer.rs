@@ -0,0 +1,173 @@
+//! Quote-aware CSV tokenizing, following RFC 4180.
+//!
+//! Splitting lines on the delimiter breaks as soon as a cell contains the delimiter inside
+//! quotes (`"Smith, John"`) or a line break. This module provides the two pieces the
+//! verification pipeline uses instead:
+//!
+//! - `Records` / `read_record`: read one *record* at a time, joining physical lines while a
+//!   quoted field is still open, so embedded newlines stay inside their cell.
+//! - `split_record`: split a record into fields, honoring quoted fields and doubled-quote
+//!   escapes (`""` inside a quoted field is a literal `"`).
+//!
+//! The parser is lenient where RFC 4180 is strict: whitespace before an opening quote is
+//! ignored, and text after a closing quote is appended to the field. With `quote` set to
+//! `None`, quotes have no special meaning and records are plain lines.
+
+use std::io::{self, BufRead};
+
+/// Splits a record into its fields.
+///
+/// A field is quoted when the quote character is its first non-whitespace character. Inside
+/// a quoted field the delimiter and line breaks are literal, and a doubled quote stands for
+/// one quote character. The returned fields have their quotes removed but are otherwise
+/// untrimmed.
+///
+/// # Arguments
+/// * `record` - The record text, as returned by `read_record` (no trailing line break).
+/// * `delimiter` - The field delimiter.
+/// * `quote` - The quote character, or `None` to split on every delimiter.
+///
+/// # Returns
+/// The fields of the record. An empty record yields a single empty field, like `str::split`.
+pub(crate) fn split_record(record: &str, delimiter: char, quote: Option<char>) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = record.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            if Some(c) == quote {
+                if chars.peek() == Some(&c) {
+                    // Doubled quote: a literal quote character.
+                    field.push(c);
+                    chars.next();
+                } else {
+                    in_quotes = false;
+                }
+            } else {
+                field.push(c);
+            }
+        } else if c == delimiter {
+            fields.push(std::mem::take(&mut field));
+        } else if Some(c) == quote && field.trim().is_empty() {
+            field.clear();
+            in_quotes = true;
+        } else {
+            field.push(c);
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+/// Reads the next record from `reader`.
+///
+/// Physical lines are joined (keeping their line breaks) for as long as the record holds an
+/// odd number of quote characters, i.e. a quoted field is still open. An unterminated quote
+/// at the end of the file ends the record there.
+///
+/// # Returns
+/// `Ok(Some(record))` without its trailing line break, `Ok(None)` at end of file, or the
+/// underlying I/O error.
+pub(crate) fn read_record<R: BufRead>(
+    reader: &mut R,
+    quote: Option<char>,
+) -> io::Result<Option<String>> {
+    let mut record = String::new();
+    let mut open_quote = false;
+
+    loop {
+        let start = record.len();
+        if reader.read_line(&mut record)? == 0 {
+            if record.is_empty() {
+                return Ok(None);
+            }
+            break;
+        }
+        if let Some(q) = quote {
+            let quotes = record[start..].chars().filter(|&c| c == q).count();
+            open_quote ^= quotes % 2 == 1;
+        }
+        if !open_quote {
+            break;
+        }
+    }
+
+    let len = record.trim_end_matches(['\n', '\r']).len();
+    record.truncate(len);
+    Ok(Some(record))
+}
+
+/// An iterator over the records of a CSV reader. See `read_record`.
+pub(crate) struct Records<R> {
+    reader: R,
+    quote: Option<char>,
+}
+
+impl<R: BufRead> Records<R> {
+    /// Creates an iterator reading records from `reader` with the given quote character.
+    pub(crate) fn new(reader: R, quote: Option<char>) -> Self {
+        Records { reader, quote }
+    }
+}
+
+impl<R: BufRead> Iterator for Records<R> {
+    type Item = io::Result<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        read_record(&mut self.reader, self.quote).transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn double_quoted_cells() {
+        assert_eq!(
+            split_record(r#""Smith, John",42,"say ""hi""""#, ',', Some('"')),
+            ["Smith, John", "42", r#"say "hi""#]
+        );
+    }
+
+    #[test]
+    fn single_quoted_cells() {
+        assert_eq!(
+            split_record("'Smith, John',42", ',', Some('\'')),
+            ["Smith, John", "42"]
+        );
+        // With the default quote, single quotes are ordinary characters.
+        assert_eq!(
+            split_record("'Smith, John',42", ',', Some('"')),
+            ["'Smith", " John'", "42"]
+        );
+    }
+
+    #[test]
+    fn unquoted_cell_with_leading_apostrophe() {
+        assert_eq!(
+            split_record("'0123;O'Brien;'", ';', Some('"')),
+            ["'0123", "O'Brien", "'"]
+        );
+        assert_eq!(
+            split_record(r#""a;b";c"#, ';', None),
+            [r#""a"#, r#"b""#, "c"]
+        );
+    }
+
+    #[test]
+    fn quoted_line_break_stays_in_record() {
+        let mut reader = "\"line 1\r\nline 2\",x\r\nnext\n".as_bytes();
+        assert_eq!(
+            read_record(&mut reader, Some('"')).unwrap().as_deref(),
+            Some("\"line 1\r\nline 2\",x")
+        );
+        assert_eq!(
+            read_record(&mut reader, Some('"')).unwrap().as_deref(),
+            Some("next")
+        );
+        assert_eq!(read_record(&mut reader, Some('"')).unwrap(), None);
+    }
+}
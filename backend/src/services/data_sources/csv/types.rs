@@ -0,0 +1,180 @@
+//! Provides the `POST /api/data_sources/csv/types/{template_id}` endpoint and the lookup of
+//! the column types it stores.
+//!
+//! `infer_column_checks` guesses the type of each column from the first data row, which is
+//! often wrong: a ZIP code like `08001` or a phone number is guessed as `Number`. The CSV
+//! component lets the user correct those guesses and sends the corrected schema here as a
+//! JSON `Vec<ColumnCheck>`. Only the `title`, `placeholder_type`, `allow_empty`, the
+//! `Boolean` labels (`true_label`, `false_label`) and the `number_display` of each entry
+//! are used.
+//!
+//! The types are stored in the `column_types` table, keyed by template and column title,
+//! and replace the previously stored ones as a whole. Verification (and
+//! `GET /api/data_sources/csv/columns`) then uses a stored type and empty-cell policy
+//! instead of the inferred ones for every column with the same title; columns without a
+//! stored type are still inferred.
+
+use crate::db::DbPool;
+use actix_web::{web, HttpResponse, Responder};
+use common::model::csv::{ColumnCheck, NumberDisplay};
+use common::model::place_holder::PlaceholderType;
+use rusqlite::{params, Connection, OptionalExtension};
+use std::collections::HashMap;
+
+/// The settings stored for one column.
+pub(super) struct StoredColumn {
+    placeholder_type: PlaceholderType,
+    allow_empty: Option<bool>,
+    true_label: Option<String>,
+    false_label: Option<String>,
+    number_display: Option<NumberDisplay>,
+}
+
+/// Actix web handler for `POST /api/data_sources/csv/types/{template_id}`.
+///
+/// # Returns
+/// - `200 OK` once the types are stored.
+/// - `404 Not Found` if the template does not exist.
+/// - `503 Service Unavailable` with an error message if a database operation fails.
+pub(crate) async fn process(
+    template_id: web::Path<String>,
+    columns: web::Json<Vec<ColumnCheck>>,
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let template_id = template_id.into_inner();
+    let columns = columns.into_inner();
+    match web::block(move || save_column_types(&pool, &template_id, &columns)).await {
+        Ok(Ok(true)) => HttpResponse::Ok().body("Column types saved successfully"),
+        Ok(Ok(false)) => HttpResponse::NotFound().body("Template not found"),
+        Ok(Err(e)) => {
+            HttpResponse::ServiceUnavailable().body(format!("Error saving column types: {}", e))
+        }
+        Err(e) => {
+            HttpResponse::ServiceUnavailable().body(format!("Error saving column types: {}", e))
+        }
+    }
+}
+
+/// Replaces the stored column types of the template in a single transaction.
+///
+/// # Returns
+/// `Ok(false)` if the template does not exist (nothing is changed), `Ok(true)` otherwise.
+fn save_column_types(
+    pool: &DbPool,
+    template_id: &str,
+    columns: &[ColumnCheck],
+) -> Result<bool, String> {
+    let mut conn = pool.get().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+
+    let exists = tx
+        .query_row(
+            "SELECT 1 FROM templates WHERE id = ?1",
+            params![template_id],
+            |_| Ok(()),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?
+        .is_some();
+    if !exists {
+        return Ok(false);
+    }
+
+    tx.execute(
+        "DELETE FROM column_types WHERE template_id = ?1",
+        params![template_id],
+    )
+    .map_err(|e| e.to_string())?;
+    for column in columns {
+        let number_display = column
+            .number_display
+            .map(|display| serde_json::to_string(&display))
+            .transpose()
+            .map_err(|e| e.to_string())?;
+        tx.execute(
+            "INSERT OR REPLACE INTO column_types
+                 (template_id, title, placeholder_type, allow_empty, true_label, false_label,
+                  number_display)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                template_id,
+                &column.title,
+                type_name(&column.placeholder_type),
+                column.allow_empty,
+                &column.true_label,
+                &column.false_label,
+                number_display
+            ],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(true)
+}
+
+/// Reads the stored column types of the template, keyed by column title.
+///
+/// Rows whose type is not a known `PlaceholderType` are ignored, and so is a number format
+/// that does not parse.
+pub(super) fn load_column_types(
+    conn: &Connection,
+    template_id: &str,
+) -> rusqlite::Result<HashMap<String, StoredColumn>> {
+    let rows: Vec<(String, Option<StoredColumn>)> = conn
+        .prepare(
+            "SELECT title, placeholder_type, allow_empty, true_label, false_label, number_display
+             FROM column_types
+             WHERE template_id = ?1",
+        )?
+        .query_map(params![template_id], |r| {
+            let name: String = r.get(1)?;
+            let number_display: Option<String> = r.get(5)?;
+            let stored = match parse_type(&name) {
+                Some(placeholder_type) => Some(StoredColumn {
+                    placeholder_type,
+                    allow_empty: r.get(2)?,
+                    true_label: r.get(3)?,
+                    false_label: r.get(4)?,
+                    number_display: number_display
+                        .and_then(|json| serde_json::from_str(&json).ok()),
+                }),
+                None => None,
+            };
+            Ok((r.get(0)?, stored))
+        })?
+        .collect::<rusqlite::Result<_>>()?;
+    Ok(rows
+        .into_iter()
+        .filter_map(|(title, stored)| Some((title, stored?)))
+        .collect())
+}
+
+/// Replaces the inferred type, empty-cell policy, `Boolean` labels and number format of
+/// every column in `columns` that has stored settings.
+pub(super) fn apply_column_types(
+    columns: &mut [ColumnCheck],
+    types: &HashMap<String, StoredColumn>,
+) {
+    for column in columns {
+        if let Some(stored) = types.get(&column.title) {
+            column.placeholder_type = stored.placeholder_type.clone();
+            column.allow_empty = stored.allow_empty;
+            column.true_label = stored.true_label.clone();
+            column.false_label = stored.false_label.clone();
+            column.number_display = stored.number_display;
+        }
+    }
+}
+
+/// The name a `PlaceholderType` is stored under: its serde name (`"Text"`, `"Number"`, ...).
+fn type_name(placeholder_type: &PlaceholderType) -> String {
+    match serde_json::to_value(placeholder_type) {
+        Ok(serde_json::Value::String(name)) => name,
+        _ => "Text".to_string(),
+    }
+}
+
+/// Parses a name written by `type_name`.
+fn parse_type(name: &str) -> Option<PlaceholderType> {
+    serde_json::from_value(serde_json::Value::String(name.to_string())).ok()
+}
@@ -15,37 +15,66 @@
 //!       `template_id` to associate the CSV with.
 //!     - `file`: The raw binary data of the CSV file.
 //!
-//! 2.  **Stream and Hash**: The file is streamed to a temporary location on disk.
-//!     Simultaneously, an MD5 checksum of the file's contents is computed. This avoids
+//! 2.  **Stream and Hash**: The file is streamed to a temporary file on disk, with a unique
+//!     name per request (`upload_*` in the data directory) so that concurrent uploads
+//!     cannot overwrite each other. Simultaneously, an MD5 checksum of the file's contents is computed. This avoids
 //!     loading the entire file into memory and ensures data integrity.
 //!
-//! 3.  **Preserve Previous State for Rollback**: Before updating the template with the new
+//! 3.  **Cancel Running Jobs**: Any verification still running for the template is
+//!     cancelled (`JobsState::cancel_jobs_for_template`) so that it does not keep reading
+//!     the old file or overwrite the state written below.
+//!
+//! 4.  **Preserve Previous State for Rollback**: Before updating the template with the new
 //!     data source, it checks if the existing data source was `verified`. If it was,
 //!     the current `datasource_md5` is copied to the `last_verified_md5` column in the
 //!     `templates` table. This is a critical step that enables the verification service
 //!     (`verify.rs`) to roll back to the last known-good version if the new file fails
 //!     validation.
 //!
-//! 4.  **Persist File**: The temporary file is renamed to its final destination, following
-//!     the convention `{template_id}_{computed_md5}.csv`. This naming scheme ensures
+//! 5.  **Persist File**: The temporary file is renamed to its final destination, following
+//!     the convention `{template_id}_{computed_md5}.csv` in the data directory
+//!     (`Config::csv_path`). This naming scheme ensures
 //!     that each unique file version has a unique path.
 //!
-//! 5.  **Update Database**: The `templates` table is updated for the given `template_id`.
+//! 6.  **Update Database**: The `templates` table is updated for the given `template_id`.
 //!     The `datasource_md5` is set to the newly computed hash, and the `verified` flag
 //!     is set to `0` (false), indicating that the new file requires validation.
+//!
+//! 7.  **Remove Stale Files**: CSV files that the template referenced before the upload
+//!     (as `datasource_md5` or `last_verified_md5`) and no longer references are deleted.
+//!     The file kept for rollback is never removed. A file that is already gone is logged
+//!     and skipped.
 
+use super::encoding::{check_text_sample, TEXT_SAMPLE_LEN};
+use crate::config::Config;
+use crate::db::DbPool;
+use crate::job_controller::state::JobsState;
 use actix_multipart::Multipart;
-use actix_web::{HttpResponse, Responder};
+use actix_web::{web, HttpResponse, Responder};
 use common::model::datasource::DataSource;
 use futures_util::StreamExt;
 use md5::Context;
-use rusqlite::{params, Connection};
+use rusqlite::params;
 use serde_json::from_slice;
-use std::fs::{rename, File};
-use std::io::{BufWriter, Write};
+use std::fs;
+use std::io::{BufWriter, ErrorKind, Write};
+use std::path::Path;
+use tempfile::NamedTempFile;
 
 type DynError = Box<dyn std::error::Error>;
 
+/// The uploaded file is larger than the limit, in megabytes.
+#[derive(Debug)]
+struct FileTooLarge(u64);
+
+impl std::fmt::Display for FileTooLarge {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "CSV file exceeds the {} MB limit", self.0)
+    }
+}
+
+impl std::error::Error for FileTooLarge {}
+
 /// HTTP handler for the CSV upload endpoint (`POST /api/data_sources/csv/upload`).
 ///
 /// Accepts a `multipart/form-data` payload and delegates processing to
@@ -53,11 +82,20 @@ type DynError = Box<dyn std::error::Error>;
 ///
 /// # Returns
 /// - `200 OK` on success.
+/// - `413 Payload Too Large` if the file exceeds `Config::max_csv_upload_mb` megabytes.
 /// - `400 Bad Request` with an error message if the upload fails due to invalid
 ///   data, missing parts, or internal processing errors.
-pub async fn process(payload: Multipart) -> impl Responder {
-    match upload_data_source(payload).await {
+pub async fn process(
+    payload: Multipart,
+    jobs_state: web::Data<JobsState>,
+    pool: web::Data<DbPool>,
+    config: web::Data<Config>,
+) -> impl Responder {
+    match upload_data_source(payload, &jobs_state, &pool, &config).await {
         Ok(_) => HttpResponse::Ok().finish(),
+        Err(e) if e.is::<FileTooLarge>() => {
+            HttpResponse::PayloadTooLarge().body(format!("Error: {}", e))
+        }
         Err(e) => HttpResponse::BadRequest().body(format!("Error: {}", e)),
     }
 }
@@ -69,27 +107,42 @@ pub async fn process(payload: Multipart) -> impl Responder {
 ///
 /// # Behavior
 /// - Expects two multipart fields: `json` (a serialized `DataSource`) and `file` (the CSV).
-/// - Streams the file to a temporary location while computing its MD5 checksum.
+/// - Streams the file to a uniquely named temporary file in the data directory while
+///   computing its MD5 checksum, so concurrent uploads never share a file. The temporary
+///   file is deleted if the upload fails.
+/// - Rejects the file as soon as it exceeds `Config::max_csv_upload_mb` megabytes, or if its first
+///   `TEXT_SAMPLE_LEN` bytes do not look like text (`check_text_sample`).
+/// - Cancels running jobs of the template and waits briefly for them to stop.
 /// - If the template was previously verified (`verified == 1`), it updates
 ///   `last_verified_md5` with the current `datasource_md5` to enable rollbacks.
-/// - Renames the temp file to its final name: `{template_id}_{md5}.csv`.
+/// - Moves the temp file to its final name: `{template_id}_{md5}.csv`.
 /// - Updates the `templates` table, setting `datasource_md5` to the new hash and
 ///   resetting `verified` to `0`.
+/// - Deletes the CSV files the template no longer references (see `remove_stale_csv`).
 ///
 /// # Arguments
 /// * `payload` - The incoming `Multipart` stream from the Actix request.
+/// * `jobs_state` - The shared job state, used to cancel the template's running jobs.
+/// * `pool` - The shared SQLite connection pool.
+/// * `config` - The application settings; the file is stored in its data directory and may
+///   not exceed `max_csv_upload_mb` megabytes.
 ///
 /// # Errors
-/// Returns an error if the `json` or `file` part is missing, or if any
-/// filesystem or database operation fails.
-pub async fn upload_data_source(mut payload: Multipart) -> Result<(), DynError> {
+/// Returns an error if the `json` or `file` part is missing, if the file is too large or
+/// not text, or if any filesystem or database operation fails.
+pub async fn upload_data_source(
+    mut payload: Multipart,
+    jobs_state: &JobsState,
+    pool: &DbPool,
+    config: &Config,
+) -> Result<(), DynError> {
     let mut data_source: Option<DataSource> = None;
     let mut file_received = false;
-    let temp_file_path = "upload_temp_file.csv";
     let mut md5_hasher = Context::new();
 
-    // Prepare a buffered writer for the temporary file.
-    let mut temp_file = BufWriter::new(File::create(temp_file_path)?);
+    // Prepare a buffered writer for a temporary file of this request only. It lives in the
+    // data directory so the final rename stays on the same filesystem.
+    let mut temp_file = BufWriter::new(NamedTempFile::with_prefix_in("upload_", &config.data_dir)?);
 
     // Process each part of the multipart form data.
     while let Some(item) = payload.next().await {
@@ -109,33 +162,62 @@ pub async fn upload_data_source(mut payload: Multipart) -> Result<(), DynError>
             }
             Some("file") => {
                 file_received = true;
+                let max_mb = config.max_csv_upload_mb;
+                let mut size: u64 = 0;
+                let mut sample = Vec::new();
+                let mut sniffed = false;
                 while let Some(chunk) = field.next().await {
                     let data = chunk?;
+                    size += data.len() as u64;
+                    if size > max_mb * 1024 * 1024 {
+                        return Err(FileTooLarge(max_mb).into());
+                    }
+                    // Check the start of the file once enough of it has arrived.
+                    if !sniffed {
+                        let missing = TEXT_SAMPLE_LEN - sample.len();
+                        sample.extend_from_slice(&data[..data.len().min(missing)]);
+                        if sample.len() == TEXT_SAMPLE_LEN {
+                            check_text_sample(&sample)?;
+                            sniffed = true;
+                        }
+                    }
                     md5_hasher.consume(&data); // Update hash.
                     temp_file.write_all(&data)?; // Write to temp file.
                 }
+                if !sniffed {
+                    check_text_sample(&sample)?;
+                }
             }
             _ => {} // Ignore other fields.
         }
     }
-    temp_file.flush()?; // Ensure all buffered data is written to disk.
+    // Ensure all buffered data is written to disk.
+    let temp_file = temp_file.into_inner().map_err(|e| e.into_error())?;
 
     let ds = data_source.ok_or("Missing 'json' part in multipart form")?;
     if !file_received {
         return Err("Missing 'file' part in multipart form".into());
     }
 
-    let conn = Connection::open("templify.sqlite")?;
+    jobs_state.cancel_jobs_for_template(&ds.template_id).await;
+
+    let conn = pool.get()?;
 
-    // Fetch the current verification status and datasource MD5 for the template.
+    // Fetch the current verification status and data source MD5s for the template.
     let row = conn.query_row(
-        "SELECT verified, datasource_md5 FROM templates WHERE id = ?1",
+        "SELECT verified, datasource_md5, last_verified_md5 FROM templates WHERE id = ?1",
         params![ds.template_id],
-        |r| Ok((r.get::<_, i64>(0)?, r.get::<_, Option<String>>(1)?)),
+        |r| {
+            Ok((
+                r.get::<_, i64>(0)?,
+                r.get::<_, Option<String>>(1)?,
+                r.get::<_, Option<String>>(2)?,
+            ))
+        },
     );
 
-    let (verified, datasource_md5) = match row {
-        Ok((v, md5)) => (v, md5),
+    let (verified, datasource_md5, last_verified_md5) = match row {
+        Ok(row) => row,
         Err(rusqlite::Error::QueryReturnedNoRows) => {
             return Err("Template not found".into());
         }
@@ -143,19 +225,22 @@ pub async fn upload_data_source(mut payload: Multipart) -> Result<(), DynError>
     };
 
     // If the existing data source was verified, save its MD5 for potential rollback.
-    if verified == 1 {
+    let kept_md5 = if verified == 1 {
         conn.execute(
             "UPDATE templates SET last_verified_md5 = ?1 WHERE id = ?2",
             params![datasource_md5, ds.template_id],
         )?;
-    }
+        datasource_md5.clone()
+    } else {
+        last_verified_md5.clone()
+    };
 
     // Finalize the MD5 hash and format it as a hex string.
     let computed_md5 = format!("{:x}", md5_hasher.finalize());
 
-    // Rename the temporary file to its permanent name.
-    let final_file_name = format!("{}_{}.csv", ds.template_id, computed_md5);
-    rename(temp_file_path, &final_file_name)?;
+    // Move the temporary file to its permanent name.
+    let final_file_name = config.csv_path(&ds.template_id, &computed_md5);
+    temp_file.persist(&final_file_name)?;
 
     // Update the template record with the new data source MD5 and reset verification status.
     conn.execute(
@@ -163,5 +248,282 @@ pub async fn upload_data_source(mut payload: Multipart) -> Result<(), DynError>
         params![computed_md5, ds.template_id],
     )?;
 
+    for old_md5 in [datasource_md5, last_verified_md5].into_iter().flatten() {
+        if old_md5 != computed_md5 && Some(&old_md5) != kept_md5.as_ref() {
+            remove_stale_csv(&config.csv_path(&ds.template_id, &old_md5));
+        }
+    }
+
     Ok(())
 }
+
+/// Deletes a CSV file no longer referenced by its template, logging instead of failing:
+/// the upload itself has already succeeded.
+fn remove_stale_csv(path: &Path) {
+    match fs::remove_file(path) {
+        Ok(()) => log::info!("Deleted stale CSV {}", path.display()),
+        Err(e) if e.kind() == ErrorKind::NotFound => {
+            log::info!("Stale CSV {} was already absent", path.display())
+        }
+        Err(e) => log::warn!("Could not delete stale CSV {}: {}", path.display(), e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{jobs_state, TestEnv};
+    use actix_web::error::PayloadError;
+    use actix_web::http::header::{HeaderMap, HeaderValue, CONTENT_TYPE};
+    use actix_web::http::StatusCode;
+    use actix_web::test::{call_service, init_service, read_body, TestRequest};
+    use actix_web::web::Bytes;
+    use actix_web::App;
+    use futures_util::future::join_all;
+    use futures_util::stream;
+
+    const BOUNDARY: &str = "templify-test-boundary";
+
+    /// The multipart body uploading `file` for `template_id`.
+    fn multipart_body(template_id: &str, file: &[u8]) -> Vec<u8> {
+        let mut body = format!(
+            "--{b}\r\n\
+             Content-Disposition: form-data; name=\"json\"\r\n\r\n\
+             {{\"template_id\":\"{id}\"}}\r\n\
+             --{b}\r\n\
+             Content-Disposition: form-data; name=\"file\"; filename=\"data.csv\"\r\n\
+             Content-Type: text/csv\r\n\r\n",
+            b = BOUNDARY,
+            id = template_id,
+        )
+        .into_bytes();
+        body.extend_from_slice(file);
+        body.extend_from_slice(format!("\r\n--{}--\r\n", BOUNDARY).as_bytes());
+        body
+    }
+
+    /// A multipart upload of `csv` for `template_id` that arrives in small chunks, yielding
+    /// to the runtime before each one so concurrent uploads interleave.
+    fn chunked_upload(template_id: &str, csv: &str) -> Multipart {
+        let chunks: Vec<Bytes> = multipart_body(template_id, csv.as_bytes())
+            .chunks(512)
+            .map(Bytes::copy_from_slice)
+            .collect();
+        let chunks = stream::iter(chunks).then(|chunk| async move {
+            tokio::task::yield_now().await;
+            Ok::<_, PayloadError>(chunk)
+        });
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            CONTENT_TYPE,
+            HeaderValue::from_str(&format!("multipart/form-data; boundary={}", BOUNDARY)).unwrap(),
+        );
+        Multipart::new(&headers, chunks)
+    }
+
+    #[actix_web::test]
+    async fn concurrent_uploads_keep_their_own_content() {
+        let env = TestEnv::new();
+        let state = jobs_state(1);
+        let uploads: Vec<(String, String)> = (0..8)
+            .map(|i| {
+                let rows: String = (0..500).map(|row| format!("{},{}\n", i, row)).collect();
+                (format!("t{}", i), format!("template,row\n{}", rows))
+            })
+            .collect();
+        for (template_id, _) in &uploads {
+            env.insert_template(template_id, "", None);
+        }
+
+        let results = join_all(uploads.iter().map(|(id, csv)| {
+            upload_data_source(chunked_upload(id, csv), &state, &env.pool, &env.config)
+        }))
+        .await;
+        for result in results {
+            result.unwrap();
+        }
+
+        let conn = env.pool.get().unwrap();
+        for (template_id, csv) in &uploads {
+            let md5: String = conn
+                .query_row(
+                    "SELECT datasource_md5 FROM templates WHERE id = ?1",
+                    params![template_id],
+                    |row| row.get(0),
+                )
+                .unwrap();
+            assert_eq!(md5, format!("{:x}", md5::compute(csv)));
+            let stored = fs::read_to_string(env.config.csv_path(template_id, &md5)).unwrap();
+            assert_eq!(&stored, csv);
+        }
+        let leftovers = fs::read_dir(&env.config.data_dir)
+            .unwrap()
+            .filter(|entry| {
+                let name = entry.as_ref().unwrap().file_name();
+                name.to_string_lossy().starts_with("upload_")
+            })
+            .count();
+        assert_eq!(leftovers, 0, "temporary upload files were left behind");
+    }
+
+    /// POSTs `file` as the data source of template `t` to the upload endpoint.
+    ///
+    /// # Returns
+    /// The response status and body.
+    async fn post_upload(env: &TestEnv, file: &[u8]) -> (StatusCode, String) {
+        let app = init_service(
+            App::new()
+                .app_data(web::Data::new(jobs_state(1)))
+                .app_data(web::Data::new(env.pool.clone()))
+                .app_data(web::Data::new(env.config.clone()))
+                .route("/upload", web::post().to(process)),
+        )
+        .await;
+        let req = TestRequest::post()
+            .uri("/upload")
+            .insert_header((
+                CONTENT_TYPE,
+                format!("multipart/form-data; boundary={}", BOUNDARY),
+            ))
+            .set_payload(multipart_body("t", file))
+            .to_request();
+        let response = call_service(&app, req).await;
+        let status = response.status();
+        let body = read_body(response).await;
+        (status, String::from_utf8_lossy(&body).into_owned())
+    }
+
+    /// The number of files in the data directory.
+    fn data_files(env: &TestEnv) -> usize {
+        fs::read_dir(&env.config.data_dir)
+            .unwrap()
+            .filter(|entry| entry.as_ref().unwrap().path().is_file())
+            .count()
+    }
+
+    #[actix_web::test]
+    async fn oversize_upload_is_rejected_with_413() {
+        let mut env = TestEnv::new();
+        env.config.max_csv_upload_mb = 1;
+        env.insert_template("t", "", None);
+        let files_before = data_files(&env);
+
+        let csv = "name,amount\n".repeat(100_000);
+        let (status, body) = post_upload(&env, csv.as_bytes()).await;
+        assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+        assert!(body.contains("exceeds the 1 MB limit"), "{}", body);
+        assert_eq!(stored_md5s(&env).0, None);
+        assert_eq!(data_files(&env), files_before);
+    }
+
+    #[test]
+    fn binary_samples_are_rejected() {
+        let err = check_text_sample(b"name,amount\nAna,\x0010\n").unwrap_err();
+        assert!(err.contains("NUL bytes"), "{}", err);
+
+        let mut sample = b"name,amount\n".repeat(10);
+        sample.extend_from_slice(&[0x01, 0x02, 0x1b, 0x7f]);
+        let err = check_text_sample(&sample).unwrap_err();
+        assert!(err.contains("4 control characters"), "{}", err);
+
+        assert_eq!(
+            check_text_sample(b"name\tamount\r\nAna\t10\r\n\x0c"),
+            Ok(())
+        );
+    }
+
+    #[actix_web::test]
+    async fn binary_upload_is_rejected_with_400() {
+        let env = TestEnv::new();
+        env.insert_template("t", "", None);
+        let files_before = data_files(&env);
+
+        let (status, body) = post_upload(&env, b"\x89PNG\r\n\x1a\n\x00\x00\x00\rIHDR").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body.contains("NUL bytes"), "{}", body);
+        assert_eq!(stored_md5s(&env).0, None);
+        assert_eq!(data_files(&env), files_before);
+    }
+
+    #[actix_web::test]
+    async fn text_csvs_are_accepted() {
+        let utf8 = "nombre,ciudad\nJosé,Málaga\n".as_bytes();
+        // "Jos\xe9" and "\x80" are "José" and "€" in Windows-1252, and invalid UTF-8.
+        let windows_1252: &[u8] = b"nombre,precio\nJos\xe9,5\x80\n";
+        for file in [utf8, windows_1252] {
+            let env = TestEnv::new();
+            env.insert_template("t", "", None);
+            let (status, body) = post_upload(&env, file).await;
+            assert_eq!(status, StatusCode::OK, "{}", body);
+            let md5 = stored_md5s(&env).0.unwrap();
+            assert_eq!(md5, format!("{:x}", md5::compute(file)));
+            assert_eq!(fs::read(env.config.csv_path("t", &md5)).unwrap(), file);
+        }
+    }
+
+    /// Uploads `csv` as the data source of template `t`.
+    async fn upload(env: &TestEnv, state: &JobsState, csv: &str) {
+        upload_data_source(chunked_upload("t", csv), state, &env.pool, &env.config)
+            .await
+            .unwrap();
+    }
+
+    /// The `datasource_md5` and `last_verified_md5` stored for template `t`.
+    fn stored_md5s(env: &TestEnv) -> (Option<String>, Option<String>) {
+        env.pool
+            .get()
+            .unwrap()
+            .query_row(
+                "SELECT datasource_md5, last_verified_md5 FROM templates WHERE id = 't'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap()
+    }
+
+    #[actix_web::test]
+    async fn reupload_replaces_the_previous_file() {
+        let env = TestEnv::new();
+        let state = jobs_state(1);
+        env.insert_template("t", "", None);
+        let (first, second) = ("name\nAna\n", "name\nLuis\n");
+        let (first_md5, second_md5) = (
+            format!("{:x}", md5::compute(first)),
+            format!("{:x}", md5::compute(second)),
+        );
+
+        upload(&env, &state, first).await;
+        assert!(env.config.csv_path("t", &first_md5).exists());
+        upload(&env, &state, second).await;
+
+        assert!(!env.config.csv_path("t", &first_md5).exists());
+        assert_eq!(
+            fs::read_to_string(env.config.csv_path("t", &second_md5)).unwrap(),
+            second
+        );
+        assert_eq!(stored_md5s(&env), (Some(second_md5), None));
+    }
+
+    #[actix_web::test]
+    async fn reupload_keeps_the_last_verified_file() {
+        let env = TestEnv::new();
+        let state = jobs_state(1);
+        env.insert_template("t", "", None);
+        let md5 = |csv: &str| format!("{:x}", md5::compute(csv));
+        let (verified, rejected, latest) = ("name\nAna\n", "name\nLuis\n", "name\nEva\n");
+
+        upload(&env, &state, verified).await;
+        env.pool
+            .get()
+            .unwrap()
+            .execute("UPDATE templates SET verified = 1 WHERE id = 't'", [])
+            .unwrap();
+        upload(&env, &state, rejected).await;
+        upload(&env, &state, latest).await;
+
+        assert!(env.config.csv_path("t", &md5(verified)).exists());
+        assert!(!env.config.csv_path("t", &md5(rejected)).exists());
+        assert!(env.config.csv_path("t", &md5(latest)).exists());
+        assert_eq!(stored_md5s(&env), (Some(md5(latest)), Some(md5(verified))));
+    }
+}
@@ -14,7 +14,8 @@
 //!     `job_id`, sets the initial job status to `Pending` in the shared `JobsState`, and
 //!     returns the `job_id` to the client immediately.
 //!
-//! 3.  **Background Execution**: A non-blocking Tokio task is spawned. This task, in turn,
+//! 3.  **Background Execution**: A non-blocking Tokio task is spawned. It first waits for a
+//!     permit from the global CPU governor (`JobsState.cpu_permits`), then
 //!     spawns a blocking thread using `tokio::task::spawn_blocking` to execute the CPU-intensive
 //!     `verify_csv_data_blocking` function. This prevents the verification work from stalling
 //!     the Tokio runtime.
@@ -22,139 +23,599 @@
 //! 4.  **Verification Logic**: `verify_csv_data_blocking` performs the validation:
 //!     - It fetches the template's metadata from the database, including the current
 //!       `datasource_md5` and `last_verified_md5`.
+//!     - It resolves `skip_lines`: a value in the request is stored with the template
+//!       (`templates.csv_skip_lines`), and a request without one uses the stored value.
 //!     - It implements a "fast-path" optimization: if the current CSV is already marked as
 //!       verified (`verified == 1` and `datasource_md5 == last_verified_md5`), it simply
 //!       infers column types from the first data row and completes the job successfully
-//!       without a full scan.
-//!     - It reads the CSV file chunk by chunk, validating headers and data rows in parallel
-//!       using Rayon for efficiency.
+//!       without a full scan. Requests with `force = true` bypass this shortcut.
+//!     - Column types stored by the user (`POST /api/data_sources/csv/types`, see
+//!       `types.rs`) replace the inferred ones, both in the report and for validating rows.
+//!     - It hands the file to `verify_reader`, which validates the header, infers the
+//!       columns, then reads the data rows chunk by chunk and validates each chunk in
+//!       parallel using Rayon. That function takes any `BufRead` and touches neither the
+//!       database nor the job channel, so the whole validation can run on in-memory data.
 //!     - It sends `JobStatus::InProgress` updates via the `mpsc::Sender` in `JobsState`
-//!       as it processes chunks.
+//!       as it processes chunks. Each carries the lines processed so far and a percentage
+//!       of the file's line count (`count_lines_raw`, taken before the scan).
 //!
 //! 5.  **Outcome & State Update**:
 //!     - **On Success**: The `templates` table in the database is updated to set `verified = 1`.
-//!       A `JobStatus::Completed` message, containing the inferred column schema as a JSON
-//!       string, is sent to the job controller.
-//!     - **On Failure**: If any validation error occurs (e.g., bad header, invalid data),
-//!       the database is rolled back by restoring the `datasource_md5` from `last_verified_md5`
-//!       (if available). A `JobStatus::Failed` message with a descriptive error is sent.
+//!       A run that only checked the referenced columns does not record the file as fully
+//!       verified, so later runs scan it again instead of taking the fast-path.
+//!       The job ends in `JobStatus::Completed`, containing a `VerifyReport` (the inferred
+//!       column schema plus whether the run used the fast-path or only checked some columns)
+//!       as a JSON string.
+//!     - **On Failure**: If the file is invalid (e.g., bad header, invalid data), the
+//!       database is rolled back by restoring the `datasource_md5` from `last_verified_md5`
+//!       (if available). Errors that do not judge the file (a missing file, a read error,
+//!       referenced columns missing from the header, a cancellation) leave the template
+//!       unchanged. The job ends in `JobStatus::Failed` with a descriptive error.
+//!       With `collect_all_errors`, the scan continues past invalid rows and the `Failed`
+//!       payload is instead a JSON `VerifyErrors` listing up to `MAX_COLLECTED_ERRORS` errors.
 //!
-//! 6.  **Status Polling**: The client uses the `job_id` to poll the
-//!     `GET /api/data_sources/csv/status/{job_id}` endpoint (defined in `get_status.rs`),
+//! 6.  **Status Tracking**: The client uses the `job_id` to follow the job's updates on
+//!     `GET /api/jobs/{job_id}/events` (Server-Sent Events), or to poll the
+//!     `GET /api/jobs/{job_id}` endpoint (defined in `services/jobs/get_status.rs`),
 //!     which reads the job's current status from the shared `JobsState`.
 
+use super::encoding::open_decoded;
+use super::lines::{count_lines_raw, progress_percent};
+use super::tokenizer::{read_record, split_record, Records};
+use super::types::{apply_column_types, load_column_types, StoredColumn};
+use crate::config::Config;
+use crate::db::DbPool;
+use crate::job_controller::callbacks::validate_callback_url;
+use crate::job_controller::log::JobLogs;
 use crate::job_controller::state::{JobUpdate, JobsState};
 use actix_web::{web, HttpResponse, Responder};
 use common::jobs::JobStatus;
-use common::model::csv::ColumnCheck;
-use common::model::place_holder::PlaceholderType;
-use common::requests::VerifyCsvRequest;
+use common::model::csv::{
+    parse_number, ColumnCheck, CsvEncoding, CsvRowError, DateFormat, NumberFormat, VerifyErrors,
+    VerifyReport, CURRENCY_SYMBOLS, MAX_COLLECTED_ERRORS, SAMPLE_ROWS,
+};
+use common::model::place_holder::{parse_boolean, PlaceholderType};
+use common::requests::{CsvColumnsQuery, VerifyCsvRequest};
 use rayon::prelude::*;
 use rusqlite::{params, Connection};
-use serde_json;
 use std::{
     collections::{HashMap, HashSet},
-    fs::File,
-    io::{BufRead, BufReader},
+    fmt,
+    io::BufRead,
     path::Path,
+    sync::atomic::{AtomicBool, Ordering},
+    sync::Arc,
     time::Instant,
 };
 use tokio::sync::mpsc;
 
+/// Row-level validation settings, taken from the `VerifyCsvRequest` that started the job.
+struct VerifyOptions {
+    /// When `true`, every data row must have exactly as many fields as the header.
+    strict_columns: bool,
+    /// Titles of the columns to type-check. `None` means all columns.
+    referenced_columns: Option<HashSet<String>>,
+    /// When `true`, a referenced column missing from the header fails the job.
+    strict_references: bool,
+    /// Character that wraps quoted cells, or `None` if cells are never quoted.
+    quote: Option<char>,
+    /// Explicit column delimiter; `None` means it is detected from the header.
+    delimiter: Option<char>,
+    /// Number of leading lines ignored before the header row.
+    skip_lines: usize,
+    /// Separator convention for `Number` and `Currency` cells.
+    number_format: NumberFormat,
+    /// Format of `Date` cells.
+    date_format: DateFormat,
+    /// Character encoding of the file; it is transcoded to UTF-8 before validation.
+    encoding: CsvEncoding,
+    /// When `true`, errors are collected across the file instead of stopping at the first.
+    collect_all_errors: bool,
+    /// When `true`, the fast-path is bypassed and the full scan always runs.
+    force: bool,
+}
+
+impl VerifyOptions {
+    /// Returns the subset of `columns` whose cells must be validated row by row.
+    fn columns_to_validate(&self, columns: &[ColumnCheck]) -> Vec<ColumnCheck> {
+        match &self.referenced_columns {
+            Some(referenced) => columns
+                .iter()
+                .filter(|c| referenced.contains(&c.title))
+                .cloned()
+                .collect(),
+            None => columns.to_vec(),
+        }
+    }
+
+    /// Checks that every referenced column exists in `columns`, if `strict_references`
+    /// is set.
+    ///
+    /// # Returns
+    /// An error message naming the unknown columns, sorted by title, or `Ok(())` when
+    /// there are none or the check is disabled.
+    fn check_references(&self, columns: &[ColumnCheck]) -> Result<(), String> {
+        if !self.strict_references {
+            return Ok(());
+        }
+        let Some(referenced) = &self.referenced_columns else {
+            return Ok(());
+        };
+        let mut unknown: Vec<&str> = referenced
+            .iter()
+            .filter(|title| !columns.iter().any(|c| &c.title == *title))
+            .map(String::as_str)
+            .collect();
+        if unknown.is_empty() {
+            return Ok(());
+        }
+        unknown.sort_unstable();
+        let noun = if unknown.len() == 1 {
+            "column"
+        } else {
+            "columns"
+        };
+        Err(format!(
+            "template references unknown {}: {}",
+            noun,
+            unknown.join(", ")
+        ))
+    }
+}
+
+/// A validation failure found while scanning the data rows of a CSV file.
+///
+/// Row numbers are 1-based record numbers counting the header and any skipped leading lines.
+/// They equal line numbers in the file unless a quoted cell spans several lines.
+enum RowError {
+    /// A cell is missing or does not match the type inferred for its column.
+    Cell {
+        row: usize,
+        title: String,
+        /// The offending (normalized) cell value, if the cell exists.
+        value: Option<String>,
+        reason: String,
+    },
+    /// The row has more or fewer fields than the header. Only reported in strict mode.
+    ColumnCount {
+        row: usize,
+        found: usize,
+        expected: usize,
+    },
+}
+
+impl fmt::Display for RowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RowError::Cell {
+                row, title, reason, ..
+            } => {
+                write!(f, "row {}, column '{}': {}", row, title, reason)
+            }
+            RowError::ColumnCount {
+                row,
+                found,
+                expected,
+            } => write!(f, "row {} has {} fields, expected {}", row, found, expected),
+        }
+    }
+}
+
+impl RowError {
+    /// Converts the error into its structured form for a `VerifyErrors` payload.
+    fn to_report(&self) -> CsvRowError {
+        match self {
+            RowError::Cell {
+                row,
+                title,
+                value,
+                reason,
+            } => CsvRowError {
+                row: *row,
+                column: Some(title.clone()),
+                value: value.clone(),
+                reason: reason.clone(),
+            },
+            RowError::ColumnCount { row, .. } => CsvRowError {
+                row: *row,
+                column: None,
+                value: None,
+                reason: self.to_string(),
+            },
+        }
+    }
+}
+
+/// Errors gathered by a verification run with `collect_all_errors`.
+#[derive(Default)]
+struct ErrorCollector {
+    /// The errors found so far, in row order, at most `MAX_COLLECTED_ERRORS`.
+    errors: Vec<RowError>,
+    /// Set once more than `MAX_COLLECTED_ERRORS` errors were found; scanning stops then.
+    truncated: bool,
+}
+
+impl ErrorCollector {
+    /// Scans a chunk and appends its errors, marking the collector as truncated if the
+    /// limit is exceeded.
+    ///
+    /// # Returns
+    /// `Err(VerifyError::Cancelled)` if `cancel` was set during the scan.
+    fn scan(
+        &mut self,
+        chunk: &[(usize, String)],
+        columns: &[ColumnCheck],
+        title_to_index: &HashMap<String, usize>,
+        delimiter: char,
+        options: &VerifyOptions,
+        cancel: &AtomicBool,
+    ) -> Result<(), VerifyError> {
+        // Ask for one more than the remaining room to tell "full" from "truncated".
+        let room = MAX_COLLECTED_ERRORS + 1 - self.errors.len();
+        let errors = find_all_invalid(
+            chunk,
+            columns,
+            title_to_index,
+            delimiter,
+            options,
+            room,
+            cancel,
+        )
+        .ok_or(VerifyError::Cancelled)?;
+        self.errors.extend(errors);
+        if self.errors.len() > MAX_COLLECTED_ERRORS {
+            self.errors.truncate(MAX_COLLECTED_ERRORS);
+            self.truncated = true;
+        }
+        Ok(())
+    }
+
+    /// Serializes the collected errors as a `VerifyErrors` JSON payload.
+    fn to_payload(&self) -> Result<String, String> {
+        let report = VerifyErrors {
+            errors: self.errors.iter().map(RowError::to_report).collect(),
+            truncated: self.truncated,
+        };
+        serde_json::to_string(&report).map_err(|e| e.to_string())
+    }
+}
+
+/// Why `verify_reader` rejected a file.
+enum VerifyError {
+    /// Reading a record failed.
+    Read(String),
+    /// The file is empty, has no data rows or has an invalid header.
+    Schema(String),
+    /// The template references columns missing from the header (`strict_references`).
+    References(String),
+    /// The cancellation flag was set during the scan.
+    Cancelled,
+    /// The first invalid row found, when errors are not collected.
+    InvalidRow(RowError),
+    /// Every error found, with `collect_all_errors`.
+    InvalidRows(ErrorCollector),
+}
+
+/// What `verify_reader` read from a valid file.
+struct ScanSummary {
+    /// Number of data records validated.
+    rows: usize,
+    /// The column schema and sample rows, for the job's `Completed` payload.
+    report: VerifyReport,
+}
+
+/// Progress reported by `verify_reader` while it reads a file.
+enum ScanEvent {
+    /// The header was read: its number of columns and the delimiter used.
+    Header { columns: usize, delimiter: char },
+    /// This many data records were validated so far.
+    Rows(usize),
+}
+
+/// Why a verification job (`verify_csv_data_blocking`) did not complete.
+#[derive(Debug, PartialEq)]
+enum VerifyJobError {
+    /// The job was cancelled through the job registry. Ends in `JobStatus::Cancelled`.
+    Cancelled,
+    /// Any other failure, with the message of the job's `JobStatus::Failed`.
+    Failed(String),
+}
+
+impl From<String> for VerifyJobError {
+    fn from(message: String) -> Self {
+        VerifyJobError::Failed(message)
+    }
+}
+
+/// Number of data records validated together, in parallel.
+const CHUNK_SIZE: usize = 250_000;
+
+/// The cancellation flag is checked once every this many records, both while reading them
+/// and while validating a chunk, so a cancelled run stops within a fraction of a chunk.
+const CANCEL_CHECK_INTERVAL: usize = 4096;
+
+/// The UTF-8 byte order mark, as it appears at the start of a decoded line.
+const UTF8_BOM: char = '\u{FEFF}';
+
+/// Error message of a verification stopped through the job registry.
+const CANCELLED: &str = "Verification cancelled";
+
+/// Minimum number of digits in a `Phone` value.
+const MIN_PHONE_DIGITS: usize = 7;
+
+/// Whether `value` has the shape of a phone number: only digits, spaces, `+`, `-`, `(` and
+/// `)`, with at least `MIN_PHONE_DIGITS` digits. Deliberately loose, so `+34 600 123 456`,
+/// `(555) 123-4567` and `600123456` are all accepted.
+fn is_phone(value: &str) -> bool {
+    let value = value.trim();
+    value
+        .chars()
+        .all(|c| c.is_ascii_digit() || matches!(c, ' ' | '+' | '-' | '(' | ')'))
+        && value.chars().filter(char::is_ascii_digit).count() >= MIN_PHONE_DIGITS
+}
+
+/// Parses a date cell written in the given format.
+///
+/// The day must exist in the given month (leap years included), so `2023-02-29` and
+/// `2023-13-45` are rejected. Day and month may have one or two digits; the year must
+/// have four.
+///
+/// # Returns
+/// The `(year, month, day)` triple, or `None` if the cell is not a valid date in that format.
+fn parse_date(value: &str, format: DateFormat) -> Option<(u32, u32, u32)> {
+    let separator = match format {
+        DateFormat::Iso => '-',
+        DateFormat::DayMonthYear | DateFormat::MonthDayYear => '/',
+    };
+    let parts: Vec<&str> = value.trim().split(separator).collect();
+    if parts.len() != 3
+        || parts
+            .iter()
+            .any(|p| p.is_empty() || !p.bytes().all(|b| b.is_ascii_digit()))
+    {
+        return None;
+    }
+    let (year, month, day) = match format {
+        DateFormat::Iso => (parts[0], parts[1], parts[2]),
+        DateFormat::DayMonthYear => (parts[2], parts[1], parts[0]),
+        DateFormat::MonthDayYear => (parts[2], parts[0], parts[1]),
+    };
+    if year.len() != 4 || month.len() > 2 || day.len() > 2 {
+        return None;
+    }
+    let (year, month, day): (u32, u32, u32) =
+        (year.parse().ok()?, month.parse().ok()?, day.parse().ok()?);
+
+    let leap = (year % 4 == 0 && year % 100 != 0) || year % 400 == 0;
+    let days_in_month = match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if leap => 29,
+        2 => 28,
+        _ => return None,
+    };
+    (1..=days_in_month)
+        .contains(&day)
+        .then_some((year, month, day))
+}
+
 /// Validates a single cell value against a `PlaceholderType`.
 ///
 /// # Arguments
 /// * `var_type` - The expected data type for the cell.
 /// * `value` - The string content of the cell to validate.
+/// * `options` - Verification settings (number and date formats).
 ///
 /// # Returns
 /// `true` if the `value` conforms to the `var_type` heuristic, `false` otherwise.
-fn validate_value(var_type: &PlaceholderType, value: &str) -> bool {
+fn validate_value(var_type: &PlaceholderType, value: &str, options: &VerifyOptions) -> bool {
     match var_type {
         PlaceholderType::Text => true,
-        PlaceholderType::Number | PlaceholderType::Currency => value.parse::<f64>().is_ok(),
+        PlaceholderType::Number => parse_number(value, options.number_format, false).is_some(),
+        PlaceholderType::Currency => parse_number(value, options.number_format, true).is_some(),
         PlaceholderType::Email => value.contains('@') && value.contains('.'),
+        PlaceholderType::Date => parse_date(value, options.date_format).is_some(),
+        PlaceholderType::Phone => is_phone(value),
+        PlaceholderType::Boolean => parse_boolean(value).is_some(),
     }
 }
 
-/// Searches a chunk of lines for the first invalid row using parallel iteration.
+/// Validates a single data row and returns every problem found in it.
 ///
-/// This function leverages Rayon's `par_iter` to efficiently scan multiple rows at once.
+/// A row with the wrong number of fields (strict mode only) yields just that error, since
+/// its cells cannot be matched to columns reliably. An empty cell is valid if its column
+/// allows it (`ColumnCheck::allows_empty`) and is never checked against the type.
 ///
 /// # Arguments
-/// * `chunk` - A slice of tuples, where each tuple contains a line's index and its string content.
+/// * `idx` - Zero-based index of the row among the data rows.
+/// * `line` - The raw record.
 /// * `columns` - A slice of `ColumnCheck` structs defining the expected type for each column.
 /// * `title_to_index` - A map from column titles to their zero-based index.
 /// * `delimiter` - The character used to separate columns in the CSV.
+/// * `options` - Row-level validation settings (e.g. strict column counts).
 ///
 /// # Returns
-/// `Some((row_index, column_title, reason))` if an invalid cell is found. The `row_index`
-/// is the 1-based CSV row number. Returns `None` if the entire chunk is valid.
-fn find_first_invalid(
-    chunk: &[(usize, String)],
+/// The errors of the row, in column order; empty if the row is valid.
+fn check_row(
+    idx: usize,
+    line: &str,
     columns: &[ColumnCheck],
     title_to_index: &HashMap<String, usize>,
     delimiter: char,
-) -> Option<(usize, String, String)> {
-    chunk.par_iter().find_map_any(|(idx, line)| {
-        let record: Vec<_> = line.split(delimiter).collect();
-        for col in columns {
-            if let Some(&col_idx) = title_to_index.get(&col.title) {
-                if col_idx >= record.len() {
-                    return Some((
-                        idx + 2,
-                        col.title.clone(),
-                        "column missing in row".to_string(),
-                    ));
-                }
-                let cell = normalize_cell(record[col_idx]);
-                if !validate_value(&col.placeholder_type, &cell) {
-                    let tipo = match col.placeholder_type {
-                        PlaceholderType::Text => "text",
-                        PlaceholderType::Number => "number",
-                        PlaceholderType::Currency => "currency",
-                        PlaceholderType::Email => "email",
-                    };
-                    return Some((
-                        idx + 2,
-                        col.title.clone(),
-                        format!("value '{}' does not match expected type: {}", cell, tipo),
-                    ));
+    options: &VerifyOptions,
+) -> Vec<RowError> {
+    // `idx` counts data rows; add the header and any skipped lines back.
+    let row = idx + 2 + options.skip_lines;
+    let record = split_record(line, delimiter, options.quote);
+    if options.strict_columns && record.len() != title_to_index.len() {
+        return vec![RowError::ColumnCount {
+            row,
+            found: record.len(),
+            expected: title_to_index.len(),
+        }];
+    }
+
+    let mut errors = Vec::new();
+    for col in columns {
+        if let Some(&col_idx) = title_to_index.get(&col.title) {
+            if col_idx >= record.len() {
+                errors.push(RowError::Cell {
+                    row,
+                    title: col.title.clone(),
+                    value: None,
+                    reason: "column missing in row".to_string(),
+                });
+                continue;
+            }
+            let cell = normalize_cell(&record[col_idx]);
+            if cell.is_empty() {
+                if !col.allows_empty() {
+                    errors.push(RowError::Cell {
+                        row,
+                        title: col.title.clone(),
+                        value: Some(cell),
+                        reason: "empty value not allowed".to_string(),
+                    });
                 }
-            } else {
-                return Some((
-                    idx + 2,
-                    col.title.clone(),
-                    "header title not found".to_string(),
-                ));
+                continue;
+            }
+            if !validate_value(&col.placeholder_type, &cell, options) {
+                let tipo = match col.placeholder_type {
+                    PlaceholderType::Text => "text",
+                    PlaceholderType::Number => "number",
+                    PlaceholderType::Currency => "currency",
+                    PlaceholderType::Email => "email",
+                    PlaceholderType::Date => "date",
+                    PlaceholderType::Phone => "phone",
+                    PlaceholderType::Boolean => "boolean",
+                };
+                let reason = if col.placeholder_type == PlaceholderType::Date {
+                    format!(
+                        "value '{}' is not a valid date, expected format {}",
+                        cell,
+                        options.date_format.pattern()
+                    )
+                } else {
+                    format!("value '{}' does not match expected type: {}", cell, tipo)
+                };
+                errors.push(RowError::Cell {
+                    row,
+                    title: col.title.clone(),
+                    value: Some(cell),
+                    reason,
+                });
             }
+        } else {
+            errors.push(RowError::Cell {
+                row,
+                title: col.title.clone(),
+                value: None,
+                reason: "header title not found".to_string(),
+            });
         }
-        None
-    })
+    }
+    errors
+}
+
+/// Whether the record at `idx` is one where the cancellation flag is checked, and the flag
+/// is set.
+fn cancelled_at(idx: usize, cancel: &AtomicBool) -> bool {
+    idx.is_multiple_of(CANCEL_CHECK_INTERVAL) && cancel.load(Ordering::Relaxed)
+}
+
+/// Searches a chunk of lines for the first invalid row using parallel iteration.
+///
+/// This function leverages Rayon's `par_iter` to efficiently scan multiple rows at once.
+/// `cancel` is checked every `CANCEL_CHECK_INTERVAL` records. See `check_row` for the other
+/// arguments.
+///
+/// # Returns
+/// `Some(RowError)` describing the first problem found, or `None` if the entire chunk is
+/// valid; `Err(VerifyError::Cancelled)` if `cancel` was set during the scan.
+fn find_first_invalid(
+    chunk: &[(usize, String)],
+    columns: &[ColumnCheck],
+    title_to_index: &HashMap<String, usize>,
+    delimiter: char,
+    options: &VerifyOptions,
+    cancel: &AtomicBool,
+) -> Result<Option<RowError>, VerifyError> {
+    let found = chunk.par_iter().find_map_any(|(idx, line)| {
+        if cancelled_at(*idx, cancel) {
+            return Some(None);
+        }
+        check_row(*idx, line, columns, title_to_index, delimiter, options)
+            .into_iter()
+            .next()
+            .map(Some)
+    });
+    match found {
+        Some(None) => Err(VerifyError::Cancelled),
+        Some(error) => Ok(error),
+        None => Ok(None),
+    }
+}
+
+/// Collects the validation errors of a chunk, for the `collect_all_errors` mode.
+///
+/// Rows are checked in parallel; the errors are returned sorted by row and cut to `limit`.
+/// `cancel` is checked every `CANCEL_CHECK_INTERVAL` records. See `check_row` for the other
+/// arguments.
+///
+/// # Returns
+/// At most `limit` errors, in row order, or `None` if `cancel` was set during the scan.
+fn find_all_invalid(
+    chunk: &[(usize, String)],
+    columns: &[ColumnCheck],
+    title_to_index: &HashMap<String, usize>,
+    delimiter: char,
+    options: &VerifyOptions,
+    limit: usize,
+    cancel: &AtomicBool,
+) -> Option<Vec<RowError>> {
+    let rows: Vec<Vec<RowError>> = chunk
+        .par_iter()
+        .map(|(idx, line)| {
+            if cancelled_at(*idx, cancel) {
+                return None;
+            }
+            Some(check_row(
+                *idx,
+                line,
+                columns,
+                title_to_index,
+                delimiter,
+                options,
+            ))
+        })
+        .collect::<Option<_>>()?;
+    // `collect` keeps the chunk order, and a row's errors are already in column order.
+    let mut errors: Vec<RowError> = rows.into_iter().flatten().collect();
+    errors.truncate(limit);
+    Some(errors)
 }
 
 /// Trims and normalizes a CSV cell's content.
 ///
-/// This function performs the following operations:
-/// 1. Removes surrounding whitespace.
-/// 2. Strips outer single or double quotes.
-/// 3. Replaces non-breaking spaces (`\u{00A0}`) with regular spaces.
-/// 4. Trims whitespace again.
+/// The cell is expected to come from `split_record`, which already removed quotes and
+/// unescaped doubled quotes. This function replaces non-breaking spaces (`\u{00A0}`) with
+/// regular spaces and trims surrounding whitespace.
 ///
 /// # Arguments
-/// * `cell` - The raw string content of the cell.
+/// * `cell` - The field as returned by `split_record`.
 ///
 /// # Returns
 /// A normalized `String`.
 fn normalize_cell(cell: &str) -> String {
-    let s = cell.trim();
-    let s = s
-        .strip_prefix('"')
-        .and_then(|s| s.strip_suffix('"'))
-        .or_else(|| s.strip_prefix('\'').and_then(|s| s.strip_suffix('\'')))
-        .map(|s| s.to_string())
-        .unwrap_or_else(|| s.to_string());
-    s.replace('\u{00A0}', " ").trim().to_string()
+    cell.replace('\u{00A0}', " ").trim().to_string()
+}
+
+/// Splits a data record into normalized cells for `VerifyReport::sample_rows`.
+fn sample_cells(line: &str, delimiter: char, options: &VerifyOptions) -> Vec<String> {
+    split_record(line, delimiter, options.quote)
+        .iter()
+        .map(|cell| normalize_cell(cell))
+        .collect()
 }
 
 /// Validates the header line of the CSV and normalizes the titles.
@@ -170,16 +631,18 @@ fn normalize_cell(cell: &str) -> String {
 /// # Arguments
 /// * `header_line` - The raw string of the CSV header row.
 /// * `delimiter` - The column delimiter character.
+/// * `quote` - The quote character, or `None` if cells are never quoted.
 ///
 /// # Returns
 /// A `Result` containing a `Vec<String>` of normalized titles on success, or an error `String` on failure.
 fn validate_and_normalize_titles(
     header_line: &str,
     delimiter: char,
+    quote: Option<char>,
 ) -> Result<Vec<String>, String> {
-    let raw_titles: Vec<String> = header_line
-        .split(delimiter)
-        .map(|s| normalize_cell(s))
+    let raw_titles: Vec<String> = split_record(header_line, delimiter, quote)
+        .iter()
+        .map(|cell| normalize_cell(cell))
         .collect();
 
     if raw_titles.is_empty() {
@@ -219,26 +682,46 @@ fn validate_and_normalize_titles(
     Ok(normalized)
 }
 
+/// Whether a value with the shape of a phone number (`is_phone`) should be inferred as
+/// `Phone` rather than `Number`: it is not a plain number, or it starts with `+` or `0`,
+/// which a numeric type would drop (`+34 600 123 456`, `0034600123456`).
+fn looks_like_phone(value: &str, options: &VerifyOptions) -> bool {
+    parse_number(value, options.number_format, false).is_none()
+        || value.starts_with('+')
+        || value.starts_with('0')
+}
+
+/// Whether a value should be inferred as `Boolean`: a yes/no word `parse_boolean` accepts.
+/// `1` and `0` are left to `Number`, since a numeric column often starts with them.
+fn looks_like_boolean(value: &str) -> bool {
+    parse_boolean(value).is_some() && !value.chars().all(|ch| ch.is_ascii_digit())
+}
+
 /// Infers the `PlaceholderType` for each column based on the first data row.
 ///
-/// It uses simple heuristics to guess the data type (Email, Currency, Number, or Text)
-/// and captures the value from the first data row for each column.
+/// It uses simple heuristics to guess the data type (Email, Date, Currency, Phone, Boolean,
+/// Number, or Text) and captures the value from the first data row for each column.
 ///
 /// # Arguments
 /// * `titles` - A slice of normalized header titles.
 /// * `second_line` - The string content of the first data row (the second line of the file).
 /// * `delimiter` - The column delimiter character.
+/// * `options` - Verification settings (quote character, number and date formats).
 ///
 /// # Returns
-/// A `Vec<ColumnCheck>` where each element corresponds to a column, containing its title,
-/// inferred type, and the value from the first data row.
-fn infer_column_checks(titles: &[String], second_line: &str, delimiter: char) -> Vec<ColumnCheck> {
-    let cells: Vec<String> = second_line
-        .split(delimiter)
-        .map(|c| normalize_cell(c))
+/// A `Vec<ColumnCheck>` in header order, where each element contains the column's title,
+/// header index, inferred type, and the value from the first data row.
+fn infer_column_checks(
+    titles: &[String],
+    second_line: &str,
+    delimiter: char,
+    options: &VerifyOptions,
+) -> Vec<ColumnCheck> {
+    let cells: Vec<String> = split_record(second_line, delimiter, options.quote)
+        .iter()
+        .map(|cell| normalize_cell(cell))
         .collect();
 
-    let currency_symbols = ['$', '€', '£', '¥'];
     let mut columns = Vec::with_capacity(titles.len());
 
     for (idx, title) in titles.iter().enumerate() {
@@ -246,9 +729,15 @@ fn infer_column_checks(titles: &[String], second_line: &str, delimiter: char) ->
             let val = cells[idx].trim();
             let placeholder_type = if val.contains('@') && val.contains('.') {
                 PlaceholderType::Email
-            } else if val.chars().any(|ch| currency_symbols.contains(&ch)) {
+            } else if parse_date(val, options.date_format).is_some() {
+                PlaceholderType::Date
+            } else if val.chars().any(|ch| CURRENCY_SYMBOLS.contains(&ch)) {
                 PlaceholderType::Currency
-            } else if val.parse::<f64>().is_ok() {
+            } else if is_phone(val) && looks_like_phone(val, options) {
+                PlaceholderType::Phone
+            } else if looks_like_boolean(val) {
+                PlaceholderType::Boolean
+            } else if parse_number(val, options.number_format, false).is_some() {
                 PlaceholderType::Number
             } else {
                 PlaceholderType::Text
@@ -262,15 +751,35 @@ fn infer_column_checks(titles: &[String], second_line: &str, delimiter: char) ->
             title: title.clone(),
             placeholder_type,
             first_row,
+            index: idx,
+            allow_empty: None,
+            true_label: None,
+            false_label: None,
+            number_display: None,
         });
     }
 
     columns
 }
 
+/// How a verification attempt ended, as recorded by `update_template_verification`.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum VerifyOutcome {
+    /// Every column of every row was checked and is valid.
+    Verified,
+    /// The rows are valid, but only the `referenced_columns` were checked.
+    PartiallyVerified,
+    /// The file is invalid or could not be verified.
+    Failed,
+}
+
 /// Updates the template's verification status in the database after a verification attempt.
 ///
-/// - On success, it sets `verified = 1` and updates `last_verified_md5` to the current `datasource_md5`.
+/// - On full success, it sets `verified = 1` and updates `last_verified_md5` to the current
+///   `datasource_md5`, which lets later runs on the same file take the fast-path.
+/// - On partial success, it only sets `verified = 1`: the unchecked columns may still hold
+///   invalid cells, so `last_verified_md5` is cleared if it names the current file, and a
+///   later run scans the file again.
 /// - On failure, it performs a rollback by setting `verified = 1` but restoring `datasource_md5`
 ///   from the `last_verified_md5` field. This effectively reverts to the last known-good version.
 ///
@@ -279,7 +788,7 @@ fn infer_column_checks(titles: &[String], second_line: &str, delimiter: char) ->
 /// * `id` - The ID of the template to update.
 /// * `datasource_md5` - The MD5 hash of the file that was just verified.
 /// * `last_verified_md5` - The MD5 hash of the previously verified file, used for rollback.
-/// * `success` - A boolean indicating whether the verification was successful.
+/// * `outcome` - How the verification ended.
 ///
 /// # Returns
 /// `Ok(())` on success, or an error `String` if the database operation fails.
@@ -288,109 +797,100 @@ fn update_template_verification(
     id: &str,
     datasource_md5: Option<&str>,
     last_verified_md5: Option<&str>,
-    success: bool,
+    outcome: VerifyOutcome,
 ) -> Result<(), String> {
-    if success {
-        conn.execute(
+    match outcome {
+        VerifyOutcome::Verified => conn.execute(
             "UPDATE templates SET verified = 1, last_verified_md5 = ?1 WHERE id = ?2",
             params![datasource_md5, id],
-        )
-            .map_err(|e| e.to_string())?;
-    } else {
-        conn.execute(
+        ),
+        VerifyOutcome::PartiallyVerified => conn.execute(
+            "UPDATE templates
+             SET verified = 1, last_verified_md5 = NULLIF(last_verified_md5, datasource_md5)
+             WHERE id = ?1",
+            params![id],
+        ),
+        VerifyOutcome::Failed => conn.execute(
             "UPDATE templates SET verified = 1, datasource_md5 = ?1 WHERE id = ?2",
             params![last_verified_md5, id],
-        )
-            .map_err(|e| e.to_string())?;
+        ),
     }
+    .map_err(|e| e.to_string())?;
     Ok(())
 }
 
-/// Sends a `JobStatus::Failed` update via the MPSC channel.
-///
-/// This is a helper to format a failure message and send it using a blocking send,
-/// suitable for use within a synchronous context like `spawn_blocking`.
-///
-/// # Arguments
-/// * `tx` - The MPSC sender for `JobUpdate` messages.
-/// * `job_id` - The ID of the failing job.
-/// * `row` - The row number where the error occurred.
-/// * `title` - The column title where the error occurred.
-/// * `reason` - A string describing the validation failure.
-/// * `start` - The `Instant` when the job started, used for logging total duration.
+/// Returns the number of leading lines to skip before the header of the template's file.
 ///
-/// # Returns
-/// `Ok(())` on success. The underlying send can fail if the receiver is dropped.
-fn handle_first_invalid_sync(
-    tx: &mpsc::Sender<JobUpdate>,
-    job_id: &str,
-    row: usize,
-    title: &str,
-    reason: &str,
-    start: Instant,
-) -> Result<(), String> {
-    let _ = tx.blocking_send(JobUpdate {
-        job_id: job_id.to_string(),
-        status: JobStatus::Failed(format!(
-            "First invalid row at: row {}, column '{}': {}",
-            row, title, reason
-        )),
-    });
-    println!("verify_csv_data finished in: {:.2?}", start.elapsed());
-    Ok(())
+/// A value set by the request becomes the template's setting (`templates.csv_skip_lines`);
+/// without one, the stored setting is used, so later runs read the file the same way.
+fn resolve_skip_lines(
+    conn: &Connection,
+    id: &str,
+    requested: Option<usize>,
+) -> Result<usize, String> {
+    match requested {
+        Some(skip_lines) => {
+            conn.execute(
+                "UPDATE templates SET csv_skip_lines = ?1 WHERE id = ?2",
+                params![skip_lines, id],
+            )
+            .map_err(|e| format!("Failed to store skip_lines: {}", e))?;
+            Ok(skip_lines)
+        }
+        None => conn
+            .query_row(
+                "SELECT csv_skip_lines FROM templates WHERE id = ?1",
+                params![id],
+                |row| row.get(0),
+            )
+            .map_err(|e| format!("Failed to read skip_lines: {}", e)),
+    }
 }
 
-/// Processes a single chunk of CSV lines synchronously.
+/// Reads the header record and the first data record from a CSV file.
 ///
-/// It calls `find_first_invalid` to check for validation errors within the chunk.
+/// The first `skip_lines` physical lines are consumed and discarded before the header is
+/// read. The header and data row are read with `read_record`, so a quoted cell may span
+/// several lines.
 ///
-/// # Arguments
-/// * `chunk` - The slice of lines to process.
-/// * `columns` - The column schema to validate against.
-/// * `title_to_index` - A map from column titles to their index.
-/// * `delimiter` - The CSV delimiter character.
-///
-/// # Returns
-/// `Ok(Some(details))` if an invalid row is found, where `details` is a tuple containing
-/// the row, title, and reason. `Ok(None)` if the chunk is valid. `Err` on internal failure.
-fn process_chunk_sync(
-    chunk: &[(usize, String)],
-    columns: &[ColumnCheck],
-    title_to_index: &HashMap<String, usize>,
-    delimiter: char,
-) -> Result<Option<(usize, String, String)>, String> {
-    Ok(find_first_invalid(
-        chunk,
-        columns,
-        title_to_index,
-        delimiter,
-    ))
-}
-
-/// Reads the header line and the first data line from a CSV file.
+/// A leading UTF-8 byte order mark (`\u{FEFF}`, written by Excel on Windows) is removed
+/// from the header, so it does not end up in the first column title.
 ///
 /// # Arguments
-/// * `reader` - A mutable reference to a `BufReader` for the CSV file.
+/// * `reader` - A mutable reference to a UTF-8 reader for the CSV file.
+/// * `skip_lines` - Number of leading lines to discard.
+/// * `quote` - The quote character, or `None` if cells are never quoted.
 ///
 /// # Returns
 /// A `Result` containing a tuple `(header_line, second_line)` on success, or an
 /// error `String` if the file is empty, contains no data rows, or a read error occurs.
-fn read_header_and_second_line(reader: &mut BufReader<File>) -> Result<(String, String), String> {
-    let mut header_line = String::new();
-    reader
-        .read_line(&mut header_line)
-        .map_err(|e| e.to_string())?;
-    let header_line = header_line.trim_end_matches(&['\n', '\r'][..]).to_string();
+fn read_header_and_second_line(
+    reader: &mut impl BufRead,
+    skip_lines: usize,
+    quote: Option<char>,
+) -> Result<(String, String), String> {
+    let mut skipped = String::new();
+    for _ in 0..skip_lines {
+        skipped.clear();
+        if reader.read_line(&mut skipped).map_err(|e| e.to_string())? == 0 {
+            return Err(format!(
+                "CSV file has fewer than {} lines to skip before the header",
+                skip_lines
+            ));
+        }
+    }
 
-    let mut second_line = String::new();
-    if reader
-        .read_line(&mut second_line)
+    let header_line = read_record(reader, quote)
         .map_err(|e| e.to_string())?
-        == 0
-    {
-        return Err("CSV file does not contain any data rows".to_string());
-    }
-    let second_line = second_line.trim_end_matches(&['\n', '\r'][..]).to_string();
+        .unwrap_or_default();
+    let header_line = header_line
+        .strip_prefix(UTF8_BOM)
+        .map(str::to_string)
+        .unwrap_or(header_line);
+
+    let second_line = read_record(reader, quote)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "CSV file does not contain any data rows".to_string())?;
 
     Ok((header_line, second_line))
 }
@@ -413,29 +913,305 @@ fn detect_delimiter(header_line: &str) -> char {
         .unwrap_or(',')
 }
 
+/// Checks the delimiter and quote character requested for reading a file.
+///
+/// # Returns
+/// The `(delimiter, quote)` characters, each `None` when not given, if `quote` is a single
+/// character and `delimiter` a single ASCII character that is neither a line break nor
+/// the quote character, or an error message otherwise.
+pub(super) fn validate_delimiter(
+    delimiter: Option<&str>,
+    quote: Option<&str>,
+) -> Result<(Option<char>, Option<char>), String> {
+    let quote = match quote {
+        Some(value) => Some(
+            single_char(value)
+                .ok_or_else(|| format!("Quote must be a single character, got {:?}", value))?,
+        ),
+        None => None,
+    };
+    let Some(value) = delimiter else {
+        return Ok((None, quote));
+    };
+    let delimiter = single_char(value).filter(char::is_ascii).ok_or_else(|| {
+        format!(
+            "Delimiter must be a single ASCII character, got {:?}",
+            value
+        )
+    })?;
+    if delimiter == '\n' || delimiter == '\r' {
+        return Err("Delimiter cannot be a line break".to_string());
+    }
+    if Some(delimiter) == quote {
+        return Err(format!(
+            "Delimiter {:?} cannot be the same as the quote character",
+            delimiter
+        ));
+    }
+    Ok((Some(delimiter), quote))
+}
+
+/// The only character of `value`, or `None` if it has none or several.
+fn single_char(value: &str) -> Option<char> {
+    let mut chars = value.chars();
+    chars.next().filter(|_| chars.next().is_none())
+}
+
+/// The column schema of a CSV file, read from its header and first data row.
+struct CsvSchema {
+    /// Every column in header order, with the type inferred from the first data row.
+    columns: Vec<ColumnCheck>,
+    /// A map from every header title to its zero-based index.
+    title_to_index: HashMap<String, usize>,
+    /// The column delimiter, explicit or detected from the header.
+    delimiter: char,
+    /// The raw first data row, from which the types were inferred.
+    first_line: String,
+}
+
+/// Reads the header and the first data row of a CSV file and infers its column schema.
+///
+/// # Arguments
+/// * `reader` - The decoded CSV data, from the start of the file.
+/// * `options` - Verification settings (skipped lines, quote, delimiter, formats).
+///
+/// # Returns
+/// The `CsvSchema`, with `reader` left after the first data row, or an error `String` if
+/// the file is empty, has no data rows, cannot be read or has an invalid header.
+fn read_schema(reader: &mut impl BufRead, options: &VerifyOptions) -> Result<CsvSchema, String> {
+    let (header_line, second_line) =
+        read_header_and_second_line(reader, options.skip_lines, options.quote)?;
+    let delimiter = options
+        .delimiter
+        .unwrap_or_else(|| detect_delimiter(&header_line));
+    let titles = validate_and_normalize_titles(&header_line, delimiter, options.quote)
+        .map_err(|e| format!("Header validation failed: {}", e))?;
+    Ok(CsvSchema {
+        columns: infer_column_checks(&titles, &second_line, delimiter, options),
+        title_to_index: titles
+            .into_iter()
+            .enumerate()
+            .map(|(i, title)| (title, i))
+            .collect(),
+        delimiter,
+        first_line: second_line,
+    })
+}
+
+/// Infers the column schema of the CSV file at `file_path` from its header and first data
+/// row, without scanning the rest of the file.
+///
+/// # Returns
+/// The inferred `ColumnCheck`s in header order and the first `SAMPLE_ROWS` data rows, or an
+/// error `String` if the file cannot be read or its header is invalid.
+fn read_column_checks(
+    file_path: &Path,
+    options: &VerifyOptions,
+) -> Result<(Vec<ColumnCheck>, Vec<Vec<String>>), String> {
+    let (mut reader, _) = open_decoded(file_path, options.encoding)?;
+    let schema = read_schema(&mut reader, options)?;
+
+    let mut sample_rows = vec![sample_cells(&schema.first_line, schema.delimiter, options)];
+    while sample_rows.len() < SAMPLE_ROWS {
+        match read_record(&mut reader, options.quote).map_err(|e| e.to_string())? {
+            Some(line) => sample_rows.push(sample_cells(&line, schema.delimiter, options)),
+            None => break,
+        }
+    }
+    Ok((schema.columns, sample_rows))
+}
+
+/// Infers the column schema of a verified CSV file for `GET /api/data_sources/csv/columns`.
+///
+/// Reads the file like the fast-path of `verify_csv_data_blocking`, with the reading
+/// settings of `query` and the default quote character. `stored_skip_lines` is used when
+/// the query does not set `skip_lines`.
+pub(super) fn column_checks_for_query(
+    file_path: &Path,
+    query: &CsvColumnsQuery,
+    stored_skip_lines: usize,
+) -> Result<Vec<ColumnCheck>, String> {
+    let (delimiter, _) = validate_delimiter(query.delimiter.as_deref(), Some("\""))?;
+    let options = VerifyOptions {
+        strict_columns: false,
+        referenced_columns: None,
+        strict_references: false,
+        quote: Some('"'),
+        delimiter,
+        skip_lines: query.skip_lines.unwrap_or(stored_skip_lines),
+        number_format: query.number_format,
+        date_format: query.date_format,
+        encoding: query.encoding,
+        collect_all_errors: false,
+        force: false,
+    };
+    read_column_checks(file_path, &options).map(|(columns, _)| columns)
+}
+
+/// Verifies a whole CSV file: its header, then every data record against the column schema.
+///
+/// This is the verification without database, file or job handling: it reads any
+/// `BufRead`, so it works the same on a file or an in-memory string. The schema is inferred
+/// from the header and first data row (`read_schema`), then `stored_types` replace the
+/// inferred types. Records are validated in chunks of `CHUNK_SIZE`; without
+/// `collect_all_errors` the scan stops at the first chunk with an invalid row.
+///
+/// # Arguments
+/// * `reader` - The decoded CSV data, from the start of the file.
+/// * `options` - Verification settings.
+/// * `stored_types` - The column types stored by the user, keyed by title.
+/// * `cancel` - Checked every `CANCEL_CHECK_INTERVAL` records, while reading them and
+///   while validating them, and before the last chunk.
+/// * `on_event` - Called once the header is read and after each full chunk.
+///
+/// # Returns
+/// A `ScanSummary` if the file is valid, or the `VerifyError` that stopped the scan.
+fn verify_reader(
+    mut reader: impl BufRead,
+    options: &VerifyOptions,
+    stored_types: &HashMap<String, StoredColumn>,
+    cancel: &AtomicBool,
+    mut on_event: impl FnMut(ScanEvent),
+) -> Result<ScanSummary, VerifyError> {
+    let CsvSchema {
+        mut columns,
+        title_to_index,
+        delimiter,
+        first_line,
+    } = read_schema(&mut reader, options).map_err(VerifyError::Schema)?;
+    on_event(ScanEvent::Header {
+        columns: columns.len(),
+        delimiter,
+    });
+    // Fail before the full scan if the template uses columns the file does not have.
+    options
+        .check_references(&columns)
+        .map_err(VerifyError::References)?;
+    // Types corrected by the user win over the inferred ones.
+    apply_column_types(&mut columns, stored_types);
+    // Only the referenced columns (if any were given) are type-checked; the full
+    // `columns` schema is still returned to the client.
+    let checked_columns = options.columns_to_validate(&columns);
+
+    let scan = |chunk: &[(usize, String)], collector: &mut ErrorCollector| {
+        if options.collect_all_errors {
+            return collector.scan(
+                chunk,
+                &checked_columns,
+                &title_to_index,
+                delimiter,
+                options,
+                cancel,
+            );
+        }
+        let first = find_first_invalid(
+            chunk,
+            &checked_columns,
+            &title_to_index,
+            delimiter,
+            options,
+            cancel,
+        )?;
+        match first {
+            Some(error) => Err(VerifyError::InvalidRow(error)),
+            None => Ok(()),
+        }
+    };
+
+    let mut chunk = Vec::with_capacity(CHUNK_SIZE);
+    let mut collector = ErrorCollector::default();
+    let mut rows = 0;
+    let mut sample_rows = Vec::new();
+
+    // Records rather than lines, so quoted cells with embedded newlines stay whole. The
+    // first data row is validated too, since stored types may not match it.
+    let records = std::iter::once(Ok(first_line)).chain(Records::new(reader, options.quote));
+    for (i, line) in records.enumerate() {
+        if cancelled_at(i, cancel) {
+            return Err(VerifyError::Cancelled);
+        }
+        let line = line.map_err(|e| VerifyError::Read(e.to_string()))?;
+        if sample_rows.len() < SAMPLE_ROWS {
+            sample_rows.push(sample_cells(&line, delimiter, options));
+        }
+        chunk.push((i, line));
+        if chunk.len() == CHUNK_SIZE {
+            scan(&chunk, &mut collector)?;
+            rows += chunk.len();
+            chunk.clear();
+            on_event(ScanEvent::Rows(rows));
+            if collector.truncated {
+                break;
+            }
+        }
+    }
+
+    // Stop before the last chunk if the job was cancelled.
+    if cancel.load(Ordering::Relaxed) {
+        return Err(VerifyError::Cancelled);
+    }
+    if !chunk.is_empty() {
+        scan(&chunk, &mut collector)?;
+        rows += chunk.len();
+    }
+
+    if !collector.errors.is_empty() {
+        return Err(VerifyError::InvalidRows(collector));
+    }
+    Ok(ScanSummary {
+        rows,
+        report: VerifyReport {
+            partial: checked_columns.len() < columns.len(),
+            columns,
+            fast_path: false,
+            sample_rows,
+        },
+    })
+}
+
 /// The main blocking verification function, designed to be run in `spawn_blocking`.
 ///
-/// This function contains the complete, synchronous logic for CSV verification, including
-/// database interaction, file I/O, and data validation. It sends status updates back to the
-/// main async context via the provided MPSC sender.
+/// This function orchestrates the synchronous CSV verification: it reads the template from
+/// the database, opens the file, infers the column schema from the header and first data
+/// row, and hands the remaining records to `verify_reader`. It then records the outcome in
+/// the database. Progress is sent back to the main async context via the provided MPSC
+/// sender; the terminal status is set by `schedule_verify_job` from the returned result,
+/// so a job finishes exactly once.
 ///
 /// # Arguments
-/// * `tx` - The MPSC sender to communicate job status updates.
+/// * `tx` - The MPSC sender to communicate job progress (`JobStatus::InProgress`).
+/// * `pool` - The shared SQLite connection pool; one connection is held for the whole run.
+/// * `config` - The application paths, used to locate the CSV file.
+/// * `logs` - The job log store, for recording notable events of the run.
 /// * `job_id` - The unique ID for this verification job.
 /// * `template_id` - The ID of the template associated with the CSV file.
+/// * `options` - Row-level validation settings from the request.
+/// * `skip_lines` - The request's `skip_lines`, if it sets one (see `resolve_skip_lines`).
+///   It replaces `options.skip_lines`.
+/// * `cancel` - Cancellation flag from the job registry, checked by `verify_reader` while
+///   it reads and validates the records.
 ///
 /// # Returns
-/// A `Result` containing a JSON `String` of the inferred `ColumnCheck` schema on success,
-/// or an error `String` on failure.
+/// A `Result` containing a JSON `String` of the `VerifyReport` on success, or a
+/// `VerifyJobError` on failure. A cancelled run returns `VerifyJobError::Cancelled` and, like
+/// the other errors that do not judge the file, leaves the template's verification state as
+/// it was.
+#[allow(clippy::too_many_arguments)]
 fn verify_csv_data_blocking(
     tx: mpsc::Sender<JobUpdate>,
+    pool: DbPool,
+    config: &Config,
+    logs: JobLogs,
     job_id: String,
     template_id: String,
-) -> Result<String, String> {
+    mut options: VerifyOptions,
+    skip_lines: Option<usize>,
+    cancel: Arc<AtomicBool>,
+) -> Result<String, VerifyJobError> {
     let start = Instant::now();
 
     // Open DB and fetch template row (allow NULLs)
-    let conn = Connection::open("templify.sqlite").map_err(|e| e.to_string())?;
+    let conn = pool.get().map_err(|e| e.to_string())?;
     let mut stmt = conn
         .prepare(
             "SELECT id, datasource_md5, last_verified_md5, verified FROM templates WHERE id = ?1",
@@ -453,32 +1229,33 @@ fn verify_csv_data_blocking(
         .map_err(|e| "Failed to get template from database: ".to_string() + &e.to_string())?;
 
     let (id, datasource_md5, last_verified_md5, verified) = template;
+    options.skip_lines = resolve_skip_lines(&conn, &id, skip_lines)?;
 
-    // Fast-path: If the file is already verified and unchanged, skip the full scan.
+    // Fast-path: If the file is already verified and unchanged, skip the full scan
+    // (unless the client explicitly asked for a forced re-verification).
     if let (Some(ds_md5), Some(last_md5)) =
         (datasource_md5.as_deref(), last_verified_md5.as_deref())
     {
-        if ds_md5 == last_md5 && verified == 1 {
-            let file_path = format!("./{}_{}.csv", id, ds_md5);
-            if !Path::new(&file_path).exists() {
-                return Err("CSV file not found".to_string());
+        if !options.force && ds_md5 == last_md5 && verified == 1 {
+            let file_path = config.csv_path(&id, ds_md5);
+            if !file_path.exists() {
+                return Err("CSV file not found".to_string().into());
             }
-            let file = File::open(&file_path).map_err(|e| e.to_string())?;
-            let mut reader = BufReader::new(file);
-
-            let (header_line, second_line) = read_header_and_second_line(&mut reader)?;
-            let delimiter = detect_delimiter(&header_line);
-
-            let titles = validate_and_normalize_titles(&header_line, delimiter)
-                .map_err(|e| format!("Header validation failed: {}", e))?;
-
-            let columns = infer_column_checks(&titles, &second_line, delimiter);
-            let json_columns = serde_json::to_string(&columns).map_err(|e| e.to_string())?;
-
-            let _ = tx.blocking_send(JobUpdate {
-                job_id: job_id.clone(),
-                status: JobStatus::Completed(json_columns.clone()),
-            });
+            let (mut columns, sample_rows) = read_column_checks(&file_path, &options)?;
+            options.check_references(&columns)?;
+            let stored_types = load_column_types(&conn, &id).map_err(|e| e.to_string())?;
+            apply_column_types(&mut columns, &stored_types);
+            let report = VerifyReport {
+                columns,
+                fast_path: true,
+                partial: false,
+                sample_rows,
+            };
+            let json_columns = serde_json::to_string(&report).map_err(|e| e.to_string())?;
+            logs.append(
+                &job_id,
+                "file unchanged since last verification; skipped full scan (fast-path)",
+            );
 
             println!(
                 "verify_csv_data finished (fast-path) in: {:.2?}",
@@ -488,20 +1265,9 @@ fn verify_csv_data_blocking(
         }
     }
 
-    // If template has a stale verified flag, reset it and proceed with verification.
-    if verified != 0 {
-        conn.execute(
-            "UPDATE templates SET verified = 0 WHERE id = ?1",
-            params![id],
-        )
-            .map_err(|e| format!("Failed to reset verified flag: {}", e))?;
-        println!(
-            "Template '{}' had verified != 0; resetting to 0 and continuing verification.",
-            id
-        );
-    }
-
-    // From here, proceed with full verification.
+    // From here, proceed with full verification. The `verified` flag is left as it is
+    // until the run reaches an outcome (`update_template_verification`), so a run that
+    // stops early does not leave the template half-updated.
     let ds_md5 = match datasource_md5.as_deref() {
         Some(s) => s,
         None => {
@@ -511,114 +1277,129 @@ fn verify_csv_data_blocking(
                 &id,
                 datasource_md5.as_deref(),
                 last_verified_md5.as_deref(),
-                false,
+                VerifyOutcome::Failed,
             )
-                .map_err(|db_err| format!("Datasource MD5 missing; rollback failed: {}", db_err))?;
-            return Err("No associated data file to verify".to_string());
+            .map_err(|db_err| format!("Datasource MD5 missing; rollback failed: {}", db_err))?;
+            return Err("No associated data file to verify".to_string().into());
         }
     };
 
-    let file_path = format!("./{}_{}.csv", id, ds_md5);
-    if !Path::new(&file_path).exists() {
-        return Err("CSV file not found".to_string());
+    let file_path = config.csv_path(&id, ds_md5);
+    if !file_path.exists() {
+        return Err("CSV file not found".to_string().into());
     }
-    let file = File::open(&file_path).map_err(|e| e.to_string())?;
-    let mut reader = BufReader::new(file);
+    let (reader, encoding) = open_decoded(&file_path, options.encoding)?;
+    if encoding == CsvEncoding::Windows1252 {
+        logs.append(&job_id, "reading file as Windows-1252");
+    }
+    let stored_types = load_column_types(&conn, &id).map_err(|e| e.to_string())?;
 
-    let (header_line, second_line) = read_header_and_second_line(&mut reader)?;
-    let delimiter = detect_delimiter(&header_line);
+    // Data lines after the header, used to report progress as a percentage.
+    let total_lines = count_lines_raw(&file_path)
+        .map_err(|e| e.to_string())?
+        .saturating_sub(options.skip_lines + 1);
 
-    // Validate headers. If it fails, roll back and exit.
-    let titles = match validate_and_normalize_titles(&header_line, delimiter) {
-        Ok(t) => t,
-        Err(e) => {
+    let scan = verify_reader(
+        reader,
+        &options,
+        &stored_types,
+        &cancel,
+        |event| match event {
+            ScanEvent::Header { columns, delimiter } => logs.append(
+                &job_id,
+                format!(
+                    "header read: {} columns, delimiter {:?}",
+                    columns, delimiter
+                ),
+            ),
+            ScanEvent::Rows(rows) => {
+                let _ = tx.blocking_send(JobUpdate {
+                    job_id: job_id.clone(),
+                    status: JobStatus::InProgress {
+                        lines: rows as u32,
+                        percent: progress_percent(rows, total_lines),
+                    },
+                });
+            }
+        },
+    );
+    let scan = match scan {
+        Ok(scan) => scan,
+        Err(VerifyError::Read(e)) | Err(VerifyError::References(e)) => return Err(e.into()),
+        // An unreadable or invalid header: roll back and exit.
+        Err(VerifyError::Schema(e)) => {
             update_template_verification(
                 &conn,
                 &id,
                 datasource_md5.as_deref(),
                 last_verified_md5.as_deref(),
-                false,
+                VerifyOutcome::Failed,
             )
-                .map_err(|db_err| {
-                    format!(
-                        "Header validation failed: {}; rollback failed: {}",
-                        e, db_err
-                    )
-                })?;
-            return Err(format!("Header validation failed: {}", e));
+            .map_err(|db_err| format!("{}; rollback failed: {}", e, db_err))?;
+            return Err(e.into());
         }
-    };
-
-    let mut title_to_index = HashMap::new();
-    for (i, t) in titles.iter().enumerate() {
-        title_to_index.insert(t.clone(), i);
-    }
-
-    let columns = infer_column_checks(&titles, &second_line, delimiter);
-
-    // Process file in chunks, sending progress updates.
-    let chunk_size = 250_000;
-    let mut chunk = Vec::with_capacity(chunk_size);
-    let mut lines_processed = 0usize;
-
-    for (i, line) in reader.lines().enumerate() {
-        let line = line.map_err(|e| e.to_string())?;
-        chunk.push((i, line));
-        if chunk.len() == chunk_size {
-            process_and_handle_chunk(
-                &tx,
-                &job_id,
-                &chunk,
-                &columns,
-                &title_to_index,
-                delimiter,
-                start,
+        Err(VerifyError::Cancelled) => return Err(VerifyJobError::Cancelled),
+        Err(VerifyError::InvalidRow(error)) => {
+            // Roll back the verification state and fail with the first invalid row found.
+            update_template_verification(
                 &conn,
                 &id,
                 datasource_md5.as_deref(),
                 last_verified_md5.as_deref(),
+                VerifyOutcome::Failed,
             )?;
-            lines_processed += chunk.len();
-            chunk.clear();
-            let _ = tx.blocking_send(JobUpdate {
-                job_id: job_id.clone(),
-                status: JobStatus::InProgress(lines_processed as u32),
-            });
+            return Err(format!("Verification failed: {}", error).into());
         }
-    }
-
-    // Process the final partial chunk.
-    if !chunk.is_empty() {
-        process_and_handle_chunk(
-            &tx,
-            &job_id,
-            &chunk,
-            &columns,
-            &title_to_index,
-            delimiter,
-            start,
-            &conn,
-            &id,
-            datasource_md5.as_deref(),
-            last_verified_md5.as_deref(),
-        )?;
-    }
+        // In collect mode, fail with the structured list of invalid rows.
+        Err(VerifyError::InvalidRows(collector)) => {
+            update_template_verification(
+                &conn,
+                &id,
+                datasource_md5.as_deref(),
+                last_verified_md5.as_deref(),
+                VerifyOutcome::Failed,
+            )?;
+            logs.append(
+                &job_id,
+                format!(
+                    "{}{} invalid cells or rows found",
+                    collector.errors.len(),
+                    if collector.truncated { "+" } else { "" }
+                ),
+            );
+            let payload = collector.to_payload()?;
+            log::info!(
+                "verify_csv_data finished with {} errors in: {:.2?}",
+                collector.errors.len(),
+                start.elapsed()
+            );
+            return Err(payload.into());
+        }
+    };
 
     // If we reach here, verification was successful.
+    let outcome = if scan.report.partial {
+        VerifyOutcome::PartiallyVerified
+    } else {
+        VerifyOutcome::Verified
+    };
     update_template_verification(
         &conn,
         &id,
         datasource_md5.as_deref(),
         last_verified_md5.as_deref(),
-        true,
+        outcome,
     )?;
 
-    let json_columns = serde_json::to_string(&columns).map_err(|e| e.to_string())?;
-
-    let _ = tx.blocking_send(JobUpdate {
-        job_id: job_id.clone(),
-        status: JobStatus::Completed(json_columns.clone()),
-    });
+    let json_columns = serde_json::to_string(&scan.report).map_err(|e| e.to_string())?;
+    logs.append(
+        &job_id,
+        format!(
+            "full scan of {} data rows finished in {:.2?}",
+            scan.rows,
+            start.elapsed()
+        ),
+    );
 
     println!("verify_csv_data finished in: {:.2?}", start.elapsed());
     Ok(json_columns)
@@ -631,15 +1412,29 @@ fn verify_csv_data_blocking(
 ///
 /// # Arguments
 /// * `jobs_state` - The shared `JobsState` injected by Actix.
+/// * `pool` - The shared SQLite connection pool injected by Actix.
+/// * `config` - The application paths injected by Actix.
 /// * `req` - The JSON payload containing the `template_id` to verify.
 ///
 /// # Returns
-/// An `HttpResponse` with the `job_id` on success, or an `InternalServerError` on failure.
+/// An `HttpResponse` with the `job_id` on success, a `BadRequest` if the requested
+/// delimiter or callback URL is invalid, or an `InternalServerError` on failure.
 pub(crate) async fn process(
     jobs_state: web::Data<JobsState>,
+    pool: web::Data<DbPool>,
+    config: web::Data<Config>,
     req: web::Json<VerifyCsvRequest>,
 ) -> impl Responder {
-    match schedule_verify_job(jobs_state, req.into_inner()).await {
+    let req = req.into_inner();
+    if let Err(err) = validate_delimiter(req.delimiter.as_deref(), req.quote.as_deref()) {
+        return HttpResponse::BadRequest().body(err);
+    }
+    if let Some(url) = &req.callback_url {
+        if let Err(err) = validate_callback_url(url, &config).await {
+            return HttpResponse::BadRequest().body(err);
+        }
+    }
+    match schedule_verify_job(jobs_state, pool.get_ref().clone(), config, req).await {
         Ok(job_id) => HttpResponse::Ok().body(job_id),
         Err(err) => HttpResponse::InternalServerError().body(err),
     }
@@ -653,49 +1448,115 @@ pub(crate) async fn process(
 ///
 /// # Arguments
 /// * `jobs_state` - The application's shared `JobsState`.
+/// * `pool` - The connection pool handed to the blocking verification.
+/// * `config` - The application paths handed to the blocking verification.
 /// * `req` - The `VerifyCsvRequest` containing the template ID.
 ///
 /// # Returns
-/// A `Result` containing the new `job_id` on success, or an error `String` on failure.
+/// A `Result` containing the new `job_id` on success, or an error `String` on failure
+/// (including a delimiter or quote rejected by `validate_delimiter`).
 async fn schedule_verify_job(
     jobs_state: web::Data<JobsState>,
+    pool: DbPool,
+    config: web::Data<Config>,
     req: VerifyCsvRequest,
 ) -> Result<String, String> {
+    let (delimiter, quote) = validate_delimiter(req.delimiter.as_deref(), req.quote.as_deref())?;
     let job_id = uuid::Uuid::new_v4().to_string();
-    jobs_state
-        .jobs
-        .write()
-        .await
-        .insert(job_id.clone(), JobStatus::Pending);
+    jobs_state.events.open(&job_id);
+    if let Some(url) = req.callback_url {
+        jobs_state.callbacks.register(&job_id, url);
+    }
+    jobs_state.set_status(&job_id, JobStatus::Pending).await;
+    jobs_state.logs.append(
+        &job_id,
+        format!("verification requested for template {}", req.uuid),
+    );
     let tx = jobs_state.tx.clone();
     let value = job_id.clone();
     let js = jobs_state.clone();
     let uuid = req.uuid;
+    let cancel = jobs_state.registry.register(&job_id, &uuid);
+    let skip_lines = req.skip_lines;
+    let options = VerifyOptions {
+        strict_columns: req.strict_columns,
+        referenced_columns: req.columns.map(|cols| cols.into_iter().collect()),
+        strict_references: req.strict_references,
+        quote,
+        delimiter,
+        // Resolved against the stored setting by `verify_csv_data_blocking`.
+        skip_lines: 0,
+        number_format: req.number_format,
+        date_format: req.date_format,
+        encoding: req.encoding,
+        collect_all_errors: req.collect_all_errors,
+        force: req.force,
+    };
 
     tokio::spawn(async move {
+        // Wait for a global CPU permit; it is held until the blocking work finishes.
+        if js.cpu_permits.available_permits() == 0 {
+            js.logs.append(&value, "waiting for a CPU permit");
+        }
+        let _permit = match js.cpu_permits.clone().acquire_owned().await {
+            Ok(permit) => permit,
+            Err(e) => {
+                js.registry.unregister(&value);
+                js.set_status(
+                    &value,
+                    JobStatus::Failed(format!("CPU governor closed: {}", e)),
+                )
+                .await;
+                return;
+            }
+        };
+        if cancel.load(Ordering::Relaxed) {
+            js.registry.unregister(&value);
+            js.set_status(&value, JobStatus::Cancelled(CANCELLED.to_string()))
+                .await;
+            return;
+        }
+        let cancel_for_blocking = cancel.clone();
+
         let tx_block = tx.clone();
         let value_for_blocking = value.clone();
         let uuid_for_blocking = uuid.clone();
+        let logs = js.logs.clone();
 
         let handle = tokio::task::spawn_blocking(move || {
-            verify_csv_data_blocking(tx_block, value_for_blocking, uuid_for_blocking)
+            verify_csv_data_blocking(
+                tx_block,
+                pool,
+                &config,
+                logs,
+                value_for_blocking,
+                uuid_for_blocking,
+                options,
+                skip_lines,
+                cancel_for_blocking,
+            )
         });
 
-        match handle.await {
+        let outcome = handle.await;
+        js.registry.unregister(&value);
+        match outcome {
             Ok(Ok(json_columns)) => {
-                js.jobs
-                    .write()
-                    .await
-                    .insert(value, JobStatus::Completed(json_columns));
+                js.set_status(&value, JobStatus::Completed(json_columns))
+                    .await;
+            }
+            Ok(Err(VerifyJobError::Cancelled)) => {
+                js.set_status(&value, JobStatus::Cancelled(CANCELLED.to_string()))
+                    .await;
             }
-            Ok(Err(e)) => {
-                js.jobs.write().await.insert(value, JobStatus::Failed(e));
+            Ok(Err(VerifyJobError::Failed(e))) => {
+                js.set_status(&value, JobStatus::Failed(e)).await;
             }
             Err(join_err) => {
-                js.jobs.write().await.insert(
-                    value,
+                js.set_status(
+                    &value,
                     JobStatus::Failed(format!("task join error: {}", join_err)),
-                );
+                )
+                .await;
             }
         }
     });
@@ -703,52 +1564,743 @@ async fn schedule_verify_job(
     Ok(job_id)
 }
 
-/// Processes a chunk of CSV lines and handles validation failures by initiating a DB rollback.
-///
-/// This function serves as a bridge between the synchronous chunk processing and the
-/// database rollback logic. If `process_chunk_sync` finds an invalid row, this function
-/// ensures the failure is reported and the database state is reverted.
-///
-/// # Arguments
-/// * `tx` - Sender for `JobUpdate` messages.
-/// * `job_id` - The ID of the current job.
-/// * `chunk` - The slice of lines to validate.
-/// * `columns` - The inferred column schema.
-/// * `title_to_index` - Map from title to column index.
-/// * `delimiter` - The CSV delimiter character.
-/// * `start` - The job start time, for logging.
-/// * `conn` - The database connection for performing a rollback.
-/// * `id` - The template ID.
-/// * `datasource_md5` - The MD5 of the current file.
-/// * `last_verified_md5` - The MD5 of the last good file, for rollback.
-///
-/// # Returns
-/// `Ok(())` if the chunk is valid. `Err(String)` if an invalid row was found, which
-/// signals to the caller to stop processing.
-fn process_and_handle_chunk(
-    tx: &mpsc::Sender<JobUpdate>,
-    job_id: &str,
-    chunk: &[(usize, String)],
-    columns: &[ColumnCheck],
-    title_to_index: &HashMap<String, usize>,
-    delimiter: char,
-    start: Instant,
-    conn: &Connection,
-    id: &str,
-    datasource_md5: Option<&str>,
-    last_verified_md5: Option<&str>,
-) -> Result<(), String> {
-    if let Some((row, title, reason)) =
-        process_chunk_sync(chunk, columns, title_to_index, delimiter)?
-    {
-        // Report the first invalid row found.
-        handle_first_invalid_sync(tx, job_id, row, &title, &reason, start)?;
-        // Roll back the template verification state in the database.
-        update_template_verification(conn, id, datasource_md5, last_verified_md5, false)?;
-        return Err(format!(
-            "Verification failed: row {}, column '{}': {}",
-            row, title, reason
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{job_status, jobs_state, wait_until_finished, TestEnv};
+    use actix_web::http::StatusCode;
+    use actix_web::test::{call_service, init_service, read_body, TestRequest};
+    use actix_web::App;
+    use std::time::Duration;
+
+    /// The options of a plain verification request: lenient columns, every column checked.
+    fn options() -> VerifyOptions {
+        VerifyOptions {
+            strict_columns: false,
+            referenced_columns: None,
+            strict_references: false,
+            quote: Some('"'),
+            delimiter: None,
+            skip_lines: 0,
+            number_format: NumberFormat::default(),
+            date_format: DateFormat::default(),
+            encoding: CsvEncoding::default(),
+            collect_all_errors: false,
+            force: false,
+        }
+    }
+
+    fn verify(csv: &str, options: &VerifyOptions) -> Result<ScanSummary, VerifyError> {
+        verify_reader(
+            csv.as_bytes(),
+            options,
+            &HashMap::new(),
+            &AtomicBool::new(false),
+            |_| {},
+        )
+    }
+
+    /// Runs the verification job of the template `t` of `env`.
+    fn run_job(
+        env: &TestEnv,
+        logs: &JobLogs,
+        options: VerifyOptions,
+    ) -> Result<String, VerifyJobError> {
+        run_flagged_job(env, logs, options, false)
+    }
+
+    /// Like `run_job`, with the cancellation flag set to `cancelled` from the start.
+    fn run_flagged_job(
+        env: &TestEnv,
+        logs: &JobLogs,
+        options: VerifyOptions,
+        cancelled: bool,
+    ) -> Result<String, VerifyJobError> {
+        let (tx, _rx) = mpsc::channel(16);
+        verify_csv_data_blocking(
+            tx,
+            env.pool.clone(),
+            &env.config,
+            logs.clone(),
+            "job".to_string(),
+            "t".to_string(),
+            options,
+            None,
+            Arc::new(AtomicBool::new(cancelled)),
+        )
+    }
+
+    /// Schedules a default verification of `template_id` on `state`.
+    async fn schedule(env: &TestEnv, state: &web::Data<JobsState>, template_id: &str) -> String {
+        schedule_request(env, state, serde_json::json!({ "uuid": template_id })).await
+    }
+
+    /// Schedules the verification described by the JSON `request` on `state`.
+    async fn schedule_request(
+        env: &TestEnv,
+        state: &web::Data<JobsState>,
+        request: serde_json::Value,
+    ) -> String {
+        let request = serde_json::from_value(request).unwrap();
+        schedule_verify_job(
+            state.clone(),
+            env.pool.clone(),
+            web::Data::new(env.config.clone()),
+            request,
+        )
+        .await
+        .unwrap()
+    }
+
+    /// Inserts the template `t` with `csv` as a data source already marked as verified.
+    fn insert_verified(env: &TestEnv, csv: &str) {
+        let md5 = env.insert_template("t", "", Some(csv));
+        env.pool
+            .get()
+            .unwrap()
+            .execute(
+                "UPDATE templates SET verified = 1, last_verified_md5 = ?1 WHERE id = 't'",
+                params![md5],
+            )
+            .unwrap();
+    }
+
+    /// The message of a verification that was expected to fail on its first invalid row.
+    fn invalid_row(result: Result<ScanSummary, VerifyError>) -> String {
+        match result {
+            Err(VerifyError::InvalidRow(error)) => error.to_string(),
+            Err(_) => panic!("expected an invalid row, got another error"),
+            Ok(_) => panic!("expected an invalid row, the file was valid"),
+        }
+    }
+
+    #[test]
+    fn valid_file_infers_columns_and_samples() {
+        let csv = "name,amount,email\nAna,10,ana@example.com\nLuis,20,luis@example.com\n";
+        let summary = verify(csv, &options()).ok().expect("valid file");
+        assert_eq!(summary.rows, 2);
+        let report = summary.report;
+        let titles: Vec<&str> = report.columns.iter().map(|c| c.title.as_str()).collect();
+        assert_eq!(titles, ["name", "amount", "email"]);
+        assert_eq!(report.columns[1].placeholder_type, PlaceholderType::Number);
+        assert_eq!(report.columns[2].placeholder_type, PlaceholderType::Email);
+        assert_eq!(report.sample_rows.len(), 2);
+        assert_eq!(report.sample_rows[1], ["Luis", "20", "luis@example.com"]);
+        assert!(!report.partial && !report.fast_path);
+    }
+
+    #[test]
+    fn referenced_columns_skip_unreferenced_errors() {
+        // 50 numeric columns; the last row is invalid everywhere but in `c1` and `c2`.
+        let titles: Vec<String> = (0..50).map(|i| format!("c{}", i)).collect();
+        let numbers = vec!["1"; 50].join(",");
+        let mut bad = vec!["x"; 50];
+        bad[1] = "2";
+        bad[2] = "3";
+        let csv = format!("{}\n{}\n{}\n", titles.join(","), numbers, bad.join(","));
+
+        assert!(verify(&csv, &options()).is_err());
+        let subset = VerifyOptions {
+            referenced_columns: Some(["c1".to_string(), "c2".to_string()].into()),
+            ..options()
+        };
+        let report = verify(&csv, &subset)
+            .ok()
+            .expect("referenced columns are valid")
+            .report;
+        assert!(report.partial);
+        assert_eq!(report.columns.len(), 50);
+    }
+
+    #[test]
+    fn strict_references_fail_on_missing_columns() {
+        let csv = "name,amount\nAna,10\nLuis,20\n";
+        let referencing = |titles: &[&str], strict_references: bool| VerifyOptions {
+            referenced_columns: Some(titles.iter().map(|t| t.to_string()).collect()),
+            strict_references,
+            ..options()
+        };
+
+        match verify(csv, &referencing(&["name", "city"], true)) {
+            Err(VerifyError::References(e)) => {
+                assert_eq!(e, "template references unknown column: city")
+            }
+            _ => panic!("expected a references error"),
+        }
+        let report = verify(csv, &referencing(&["name", "city"], false))
+            .ok()
+            .expect("missing references are ignored")
+            .report;
+        assert!(report.partial);
+
+        match verify(csv, &referencing(&["zip", "city"], true)) {
+            Err(VerifyError::References(e)) => {
+                assert_eq!(e, "template references unknown columns: city, zip")
+            }
+            _ => panic!("expected a references error"),
+        }
+    }
+
+    #[test]
+    fn partial_verification_does_not_enable_fast_path() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        crate::schema::ensure_schema(&mut conn).unwrap();
+        conn.execute(
+            "INSERT INTO templates (id, text, datasource_md5, last_verified_md5, verified)
+             VALUES ('t', '', 'new', 'new', 0)",
+            [],
+        )
+        .unwrap();
+        let state = |conn: &Connection| -> (Option<String>, i32) {
+            conn.query_row(
+                "SELECT last_verified_md5, verified FROM templates WHERE id = 't'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap()
+        };
+
+        let update =
+            |outcome| update_template_verification(&conn, "t", Some("new"), Some("new"), outcome);
+        update(VerifyOutcome::PartiallyVerified).unwrap();
+        assert_eq!(state(&conn), (None, 1));
+        update(VerifyOutcome::Verified).unwrap();
+        assert_eq!(state(&conn), (Some("new".to_string()), 1));
+    }
+
+    #[test]
+    fn skip_lines_ignores_junk_before_header() {
+        let csv = "Informe mensual\nGenerado: 2026-01-01\nname,amount\nAna,10\nLuis,x\n";
+        let options = VerifyOptions {
+            skip_lines: 2,
+            ..options()
+        };
+        // Row numbers count the skipped lines, so they match the file.
+        assert_eq!(
+            invalid_row(verify(csv, &options)),
+            "row 5, column 'amount': value 'x' does not match expected type: number"
+        );
+        let valid = verify(&csv.replace(",x", ",20"), &options)
+            .ok()
+            .expect("valid file");
+        let titles: Vec<&str> = valid
+            .report
+            .columns
+            .iter()
+            .map(|c| c.title.as_str())
+            .collect();
+        assert_eq!(titles, ["name", "amount"]);
+    }
+
+    #[test]
+    fn skip_lines_is_stored_with_the_template() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        crate::schema::ensure_schema(&mut conn).unwrap();
+        conn.execute("INSERT INTO templates (id, text) VALUES ('t', '')", [])
+            .unwrap();
+        assert_eq!(resolve_skip_lines(&conn, "t", None), Ok(0));
+        assert_eq!(resolve_skip_lines(&conn, "t", Some(2)), Ok(2));
+        assert_eq!(resolve_skip_lines(&conn, "t", None), Ok(2));
+    }
+
+    #[test]
+    fn cancelled_run_stops() {
+        let csv = "name,amount\nAna,10\nLuis,20\n";
+        let cancel = AtomicBool::new(true);
+        let run = |options: &VerifyOptions| {
+            verify_reader(csv.as_bytes(), options, &HashMap::new(), &cancel, |_| {})
+        };
+        assert!(matches!(run(&options()), Err(VerifyError::Cancelled)));
+
+        let collect = VerifyOptions {
+            collect_all_errors: true,
+            ..options()
+        };
+        assert!(matches!(run(&collect), Err(VerifyError::Cancelled)));
+
+        // The validation loops stop too, not only the reading.
+        let chunk = vec![(0, "Ana,10".to_string())];
+        let index = HashMap::from([("name".to_string(), 0), ("amount".to_string(), 1)]);
+        let first = find_first_invalid(&chunk, &[], &index, ',', &options(), &cancel);
+        assert!(matches!(first, Err(VerifyError::Cancelled)));
+        assert!(find_all_invalid(&chunk, &[], &index, ',', &options(), 10, &cancel).is_none());
+    }
+
+    #[test]
+    fn force_runs_the_full_scan() {
+        let env = TestEnv::new();
+        // The bad row is past the first data row, so only a full scan finds it.
+        insert_verified(&env, "name,amount\nAna,10\nLuis,x\n");
+        let logs = JobLogs::default();
+
+        let report = run_job(&env, &logs, options()).expect("fast-path");
+        let report: VerifyReport = serde_json::from_str(&report).unwrap();
+        assert!(report.fast_path);
+
+        let forced = VerifyOptions {
+            force: true,
+            ..options()
+        };
+        let error = run_job(&env, &logs, forced).expect_err("full scan");
+        assert_eq!(
+            error,
+            VerifyJobError::Failed(
+                "Verification failed: row 3, column 'amount': value 'x' does not match expected type: number"
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn cancel_flag_does_not_hide_other_errors() {
+        let logs = JobLogs::default();
+        let env = TestEnv::new();
+        env.insert_template("t", "", None);
+        assert_eq!(
+            run_flagged_job(&env, &logs, options(), true),
+            Err(VerifyJobError::Failed(
+                "No associated data file to verify".to_string()
+            ))
+        );
+
+        let env = TestEnv::new();
+        env.insert_template("t", "", Some("name,amount\nAna,10\n"));
+        assert_eq!(
+            run_flagged_job(&env, &logs, options(), true),
+            Err(VerifyJobError::Cancelled)
+        );
+    }
+
+    #[actix_web::test]
+    async fn early_failure_leaves_the_verification_state_unchanged() {
+        let env = TestEnv::new();
+        insert_verified(&env, "name,amount\nAna,10\n");
+        let before: (Option<String>, Option<String>, i32) = env
+            .pool
+            .get()
+            .unwrap()
+            .query_row(
+                "SELECT datasource_md5, last_verified_md5, verified FROM templates WHERE id = 't'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .unwrap();
+
+        let state = web::Data::new(jobs_state(1));
+        let request = serde_json::json!({
+            "uuid": "t",
+            "force": true,
+            "columns": ["missing"],
+            "strict_references": true,
+        });
+        let job_id = schedule_request(&env, &state, request).await;
+        let status = wait_until_finished(&state, &job_id).await;
+        assert!(
+            matches!(&status, JobStatus::Failed(e) if e == "template references unknown column: missing"),
+            "{:?}",
+            status
+        );
+
+        let after: (Option<String>, Option<String>, i32) = env
+            .pool
+            .get()
+            .unwrap()
+            .query_row(
+                "SELECT datasource_md5, last_verified_md5, verified FROM templates WHERE id = 't'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .unwrap();
+        assert_eq!(after, before);
+        let log = state.logs.render(&job_id).unwrap();
+        assert_eq!(log.matches("status: failed").count(), 1, "{}", log);
+    }
+
+    #[test]
+    fn job_log_records_skipped_scan() {
+        let env = TestEnv::new();
+        insert_verified(&env, "name,amount\nAna,10\n");
+        let logs = JobLogs::default();
+        run_job(&env, &logs, options()).expect("fast-path");
+        let log = logs.render("job").unwrap();
+        assert!(log.contains("skipped full scan (fast-path)"), "{}", log);
+    }
+
+    #[test]
+    fn bad_header_is_rejected() {
+        let result = verify("name,name\nAna,Luis\n", &options());
+        match result {
+            Err(VerifyError::Schema(e)) => assert!(e.starts_with("Header validation failed")),
+            _ => panic!("expected a header error"),
+        }
+    }
+
+    #[test]
+    fn type_mismatch_reports_row_and_column() {
+        let csv = "name,amount\nAna,10\nLuis,20\nEva,diez\n";
+        assert_eq!(
+            invalid_row(verify(csv, &options())),
+            "row 4, column 'amount': value 'diez' does not match expected type: number"
+        );
+    }
+
+    #[test]
+    fn impossible_dates_are_rejected() {
+        assert_eq!(
+            parse_date("2024-02-29", DateFormat::Iso),
+            Some((2024, 2, 29))
+        );
+        assert_eq!(
+            parse_date("31/12/2023", DateFormat::DayMonthYear),
+            Some((2023, 12, 31))
+        );
+        for value in [
+            "2023-13-45",
+            "2023-02-29",
+            "2023-04-31",
+            "2023-00-10",
+            "23-01-01",
+        ] {
+            assert_eq!(parse_date(value, DateFormat::Iso), None, "{}", value);
+        }
+
+        let csv = "name,birth\nAna,2023-01-15\nLuis,2023-13-45\n";
+        assert_eq!(
+            invalid_row(verify(csv, &options())),
+            "row 3, column 'birth': value '2023-13-45' is not a valid date, expected format \
+             yyyy-mm-dd"
+        );
+    }
+
+    #[test]
+    fn phone_values_are_accepted_and_rejected() {
+        for value in [
+            "+34 600 123 456",
+            "(555) 123-4567",
+            "600123456",
+            "0034600123456",
+        ] {
+            assert!(is_phone(value), "{}", value);
+        }
+        for value in [
+            "123456",
+            "600 abc 123",
+            "600.123.456",
+            "+34 600 123 456 ext. 2",
+        ] {
+            assert!(!is_phone(value), "{}", value);
+        }
+
+        let csv = "name,phone\nAna,+34 600 123 456\nLuis,(555) 123-4567\nEva,600-abc\n";
+        assert_eq!(
+            invalid_row(verify(csv, &options())),
+            "row 4, column 'phone': value '600-abc' does not match expected type: phone"
+        );
+        let summary = verify("name,phone\nAna,0034600123456\n", &options())
+            .ok()
+            .expect("valid file");
+        assert_eq!(
+            summary.report.columns[1].placeholder_type,
+            PlaceholderType::Phone
+        );
+    }
+
+    #[test]
+    fn boolean_values_are_accepted_and_rejected() {
+        for (value, expected) in [
+            ("true", true),
+            ("false", false),
+            ("yes", true),
+            ("no", false),
+            ("1", true),
+            ("0", false),
+            (" TRUE ", true),
+            ("No", false),
+            ("sí", true),
+        ] {
+            assert_eq!(parse_boolean(value), Some(expected), "{:?}", value);
+        }
+        for value in ["maybe", "y", "n", "2", "verdadero", ""] {
+            assert_eq!(parse_boolean(value), None, "{:?}", value);
+        }
+
+        let csv = "name,active\nAna,yes\nLuis,No\nEva,1\nSol,quizá\n";
+        assert_eq!(
+            invalid_row(verify(csv, &options())),
+            "row 5, column 'active': value 'quizá' does not match expected type: boolean"
+        );
+    }
+
+    #[test]
+    fn number_format_decides_which_numbers_are_valid() {
+        let comma = VerifyOptions {
+            delimiter: Some(';'),
+            number_format: NumberFormat::DecimalComma,
+            ..options()
+        };
+        let point = VerifyOptions {
+            delimiter: Some(';'),
+            number_format: NumberFormat::DecimalPoint,
+            ..options()
+        };
+
+        let summary = verify("name;amount\nAna;1.234,56\nLuis;2.000\n", &comma)
+            .ok()
+            .expect("valid file");
+        assert_eq!(
+            summary.report.columns[1].placeholder_type,
+            PlaceholderType::Number
+        );
+        let summary = verify("name;amount\nAna;1,234.56\nLuis;2,000\n", &point)
+            .ok()
+            .expect("valid file");
+        assert_eq!(
+            summary.report.columns[1].placeholder_type,
+            PlaceholderType::Number
+        );
+
+        // A value written in the other format does not match the column's type.
+        assert_eq!(
+            invalid_row(verify("name;amount\nAna;10\nLuis;1.234,56\n", &point)),
+            "row 3, column 'amount': value '1.234,56' does not match expected type: number"
+        );
+        assert_eq!(
+            invalid_row(verify("name;amount\nAna;10\nLuis;1,234.56\n", &comma)),
+            "row 3, column 'amount': value '1,234.56' does not match expected type: number"
+        );
+    }
+
+    #[test]
+    fn delimiter_and_quote_must_be_single_characters() {
+        assert_eq!(validate_delimiter(None, None), Ok((None, None)));
+        assert_eq!(
+            validate_delimiter(Some(";"), Some("'")),
+            Ok((Some(';'), Some('\'')))
+        );
+        assert_eq!(validate_delimiter(Some("\t"), None), Ok((Some('\t'), None)));
+
+        for delimiter in [";;", "", "¦"] {
+            assert_eq!(
+                validate_delimiter(Some(delimiter), Some("\"")),
+                Err(format!(
+                    "Delimiter must be a single ASCII character, got {:?}",
+                    delimiter
+                ))
+            );
+        }
+        assert_eq!(
+            validate_delimiter(Some(";"), Some("\"\"")),
+            Err(format!(
+                "Quote must be a single character, got {:?}",
+                "\"\""
+            ))
+        );
+        assert_eq!(
+            validate_delimiter(Some("\n"), None),
+            Err("Delimiter cannot be a line break".to_string())
+        );
+        assert_eq!(
+            validate_delimiter(Some("|"), Some("|")),
+            Err("Delimiter '|' cannot be the same as the quote character".to_string())
+        );
+    }
+
+    #[actix_web::test]
+    async fn multi_character_delimiter_gets_a_clear_message() {
+        let env = TestEnv::new();
+        env.insert_template("t", "", Some("name;amount\nAna;10\n"));
+        let app = init_service(
+            App::new()
+                .app_data(web::Data::new(jobs_state(1)))
+                .app_data(web::Data::new(env.pool.clone()))
+                .app_data(web::Data::new(env.config.clone()))
+                .route("/verify", web::post().to(process)),
+        )
+        .await;
+        let req = TestRequest::post()
+            .uri("/verify")
+            .set_json(serde_json::json!({ "uuid": "t", "delimiter": ";;" }))
+            .to_request();
+        let response = call_service(&app, req).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = read_body(response).await;
+        assert_eq!(
+            body,
+            "Delimiter must be a single ASCII character, got \";;\""
+        );
+    }
+
+    #[test]
+    fn collect_all_errors_reports_every_invalid_row() {
+        let csv = "name,amount\nAna,10\nLuis,x\nEva,20\nSol,y\n";
+        let collect = VerifyOptions {
+            collect_all_errors: true,
+            ..options()
+        };
+        let Err(VerifyError::InvalidRows(collector)) = verify(csv, &collect) else {
+            panic!("expected the collected errors");
+        };
+        let errors: Vec<CsvRowError> = collector.errors.iter().map(RowError::to_report).collect();
+        let rows: Vec<usize> = errors.iter().map(|e| e.row).collect();
+        assert_eq!(rows, [3, 5]);
+        assert_eq!(errors[1].column.as_deref(), Some("amount"));
+        assert_eq!(errors[1].value.as_deref(), Some("y"));
+        assert!(!collector.truncated);
+    }
+
+    #[test]
+    fn ragged_row_fails_in_strict_mode() {
+        let csv = "name,amount\nAna,10\nLuis,20,extra\n";
+        let strict = VerifyOptions {
+            strict_columns: true,
+            ..options()
+        };
+        assert_eq!(
+            invalid_row(verify(csv, &strict)),
+            "row 3 has 3 fields, expected 2"
+        );
+    }
+
+    #[test]
+    fn column_count_in_strict_and_lenient_modes() {
+        let extra = "name,amount\nAna,10\nLuis,20,extra\n";
+        let missing = "name,amount\nAna,10\nLuis\n";
+        let strict = VerifyOptions {
+            strict_columns: true,
+            ..options()
+        };
+
+        // Lenient: extra fields are ignored, a missing one fails its column's check.
+        assert!(verify(extra, &options()).is_ok());
+        assert_eq!(
+            invalid_row(verify(missing, &options())),
+            "row 3, column 'amount': column missing in row"
+        );
+        // Strict: both fail the column count.
+        assert_eq!(
+            invalid_row(verify(extra, &strict)),
+            "row 3 has 3 fields, expected 2"
+        );
+        assert_eq!(
+            invalid_row(verify(missing, &strict)),
+            "row 3 has 1 fields, expected 2"
+        );
+    }
+
+    #[test]
+    fn empty_file_is_rejected() {
+        assert!(matches!(
+            verify("", &options()),
+            Err(VerifyError::Schema(_))
+        ));
+        assert!(matches!(
+            verify("name,amount\n", &options()),
+            Err(VerifyError::Schema(_))
         ));
     }
-    Ok(())
+
+    #[actix_web::test]
+    async fn verify_job_waits_for_a_cpu_permit() {
+        let env = TestEnv::new();
+        env.insert_template("t", "", Some("name,amount\nAna,10\n"));
+        let state = web::Data::new(jobs_state(1));
+        let held = state.cpu_permits.clone().acquire_owned().await.unwrap();
+
+        let job_id = schedule(&env, &state, "t").await;
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(matches!(
+            job_status(&state, &job_id).await,
+            Some(JobStatus::Pending)
+        ));
+        let log = state.logs.render(&job_id).unwrap();
+        assert!(log.contains("waiting for a CPU permit"), "{}", log);
+
+        drop(held);
+        let status = wait_until_finished(&state, &job_id).await;
+        assert!(matches!(status, JobStatus::Completed(_)), "{:?}", status);
+        assert_eq!(state.cpu_permits.available_permits(), 1);
+    }
+
+    #[actix_web::test]
+    async fn cpu_permits_stay_within_the_cap() {
+        let env = TestEnv::new();
+        env.insert_template("t", "", Some("name,amount\nAna,10\nLuis,x\n"));
+        let state = web::Data::new(jobs_state(2));
+
+        let mut job_ids = Vec::new();
+        for template_id in ["t", "missing", "t", "t", "missing", "t"] {
+            job_ids.push(schedule(&env, &state, template_id).await);
+        }
+        for job_id in &job_ids {
+            assert!(state.cpu_permits.available_permits() <= 2);
+            wait_until_finished(&state, job_id).await;
+        }
+        // Every job, failed or not, gave its permit back, and none was released twice.
+        assert_eq!(state.cpu_permits.available_permits(), 2);
+    }
+
+    #[test]
+    fn reordered_columns_keep_their_title_and_stored_type() {
+        let env = TestEnv::new();
+        env.insert_template("t", "", None);
+        let conn = env.pool.get().unwrap();
+        conn.execute(
+            "INSERT INTO column_types (template_id, title, placeholder_type) VALUES ('t', 'code', 'Text')",
+            [],
+        )
+        .unwrap();
+        let stored = load_column_types(&conn, "t").unwrap();
+
+        let columns = |csv: &str| -> Vec<(String, usize, PlaceholderType, Option<String>)> {
+            let summary = verify_reader(
+                csv.as_bytes(),
+                &options(),
+                &stored,
+                &AtomicBool::new(false),
+                |_| {},
+            )
+            .ok()
+            .expect("valid file");
+            summary
+                .report
+                .columns
+                .into_iter()
+                .map(|c| (c.title, c.index, c.placeholder_type, c.first_row))
+                .collect()
+        };
+        let code = |index| {
+            (
+                "code".to_string(),
+                index,
+                PlaceholderType::Text,
+                Some("007".to_string()),
+            )
+        };
+        let amount = |index| {
+            (
+                "amount".to_string(),
+                index,
+                PlaceholderType::Number,
+                Some("10".to_string()),
+            )
+        };
+
+        assert_eq!(columns("code,amount\n007,10\n"), [code(0), amount(1)]);
+        assert_eq!(columns("amount,code\n10,007\n"), [amount(0), code(1)]);
+    }
+
+    #[test]
+    fn bom_is_stripped_from_the_first_title() {
+        let csv = "\u{FEFF}name,amount\nAna,10\n";
+        let summary = verify(csv, &options()).ok().expect("valid file");
+        assert_eq!(summary.report.columns[0].title, "name");
+
+        // The same file read from disk by a verification job.
+        let env = TestEnv::new();
+        env.insert_template("t", "", Some(csv));
+        let json = run_job(&env, &JobLogs::default(), options()).expect("valid file");
+        let report: common::model::csv::VerifyReport = serde_json::from_str(&json).unwrap();
+        let titles: Vec<_> = report.columns.iter().map(|c| c.title.as_str()).collect();
+        assert_eq!(titles, ["name", "amount"]);
+    }
 }
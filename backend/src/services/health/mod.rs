@@ -0,0 +1,21 @@
+//! # Health Service Module
+//!
+//! Exposes `GET /api/health`, a lightweight endpoint that answers `200 OK` without touching
+//! the database. The frontend polls it after losing the connection to detect when the
+//! backend is back (see `connection_monitor.rs` in the frontend).
+
+use actix_web::web::{get, scope};
+use actix_web::{HttpResponse, Responder, Scope};
+
+/// The base path for the health endpoint.
+const API_PATH: &str = "/api/health";
+
+/// Configures and returns the Actix `Scope` for the health endpoint.
+pub fn configure_routes() -> Scope {
+    scope(API_PATH).route("", get().to(process))
+}
+
+/// Actix web handler for `GET /api/health`. Always returns `200 OK`.
+async fn process() -> impl Responder {
+    HttpResponse::Ok().body("ok")
+}
@@ -0,0 +1,29 @@
+//! Provides the `POST /api/jobs/{job_id}/cancel` endpoint.
+
+use crate::job_controller::state::JobsState;
+use actix_web::{web, HttpResponse, Responder};
+
+/// Actix web handler for `POST /api/jobs/{job_id}/cancel`.
+///
+/// Sets the job's cancellation flag in the `JobRegistry`. The job checks the flag between
+/// chunks, stops without committing its results and ends in `JobStatus::Cancelled`; follow
+/// the job's status to see when it has stopped.
+///
+/// # Returns
+/// - `202 Accepted` if the cancellation was requested.
+/// - `409 Conflict` if the job has already finished.
+/// - `404 Not Found` if the job ID is unknown.
+pub(crate) async fn process(
+    job_id: web::Path<String>,
+    state: web::Data<JobsState>,
+) -> impl Responder {
+    if state.registry.cancel(&job_id) {
+        state.logs.append(&job_id, "cancellation requested by user");
+        return HttpResponse::Accepted().finish();
+    }
+    if state.jobs.read().await.contains_key(job_id.as_str()) {
+        HttpResponse::Conflict().body("Job already finished")
+    } else {
+        HttpResponse::NotFound().body("Job ID not found")
+    }
+}
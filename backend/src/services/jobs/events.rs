@@ -0,0 +1,72 @@
+//! Provides the `GET /api/jobs/{job_id}/events` endpoint.
+//!
+//! Streams the status of a job as Server-Sent Events, so clients no longer need to poll
+//! `GET /api/data_sources/csv/status/{job_id}`. Each event carries one `JobStatus` as JSON
+//! in its `data` field. The current status is sent as soon as the client connects, then
+//! every update published through `JobsState::set_status`. The stream ends after a
+//! terminal status (`Completed`, `Failed` or `Cancelled`).
+
+use crate::job_controller::state::JobsState;
+use actix_web::web::Bytes;
+use actix_web::{web, HttpResponse, Responder};
+use common::jobs::JobStatus;
+use futures_util::{stream, StreamExt};
+use tokio::sync::broadcast::{error::RecvError, Receiver};
+
+/// Actix web handler for `GET /api/jobs/{job_id}/events`.
+///
+/// # Returns
+/// - `200 OK` with a `text/event-stream` body.
+/// - `404 Not Found` if the job ID is unknown.
+pub(crate) async fn process(
+    job_id: web::Path<String>,
+    state: web::Data<JobsState>,
+) -> impl Responder {
+    // Subscribe before reading the current status, so no update falls in between.
+    let receiver = state.events.subscribe(&job_id);
+    let current = state.jobs.read().await.get(job_id.as_str()).map(|e| e.status.clone());
+    let Some(current) = current else {
+        return HttpResponse::NotFound().body("Job ID not found");
+    };
+    let receiver = receiver.filter(|_| !current.is_terminal());
+
+    let updates = stream::unfold(receiver, |receiver| async move {
+        let (status, receiver) = next_status(receiver?).await?;
+        Some((sse_event(&status), receiver))
+    });
+    let events = stream::once(async move { sse_event(&current) })
+        .chain(updates)
+        .map(Ok::<_, actix_web::Error>);
+
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(("Cache-Control", "no-cache"))
+        .streaming(events)
+}
+
+/// Waits for the next status on `receiver`, skipping updates lost because the client fell
+/// behind.
+///
+/// # Returns
+/// The status and the receiver to keep reading from (`None` after a terminal status), or
+/// `None` if the channel was closed.
+async fn next_status(
+    mut receiver: Receiver<JobStatus>,
+) -> Option<(JobStatus, Option<Receiver<JobStatus>>)> {
+    loop {
+        match receiver.recv().await {
+            Ok(status) => {
+                let receiver = Some(receiver).filter(|_| !status.is_terminal());
+                return Some((status, receiver));
+            }
+            Err(RecvError::Lagged(_)) => continue,
+            Err(RecvError::Closed) => return None,
+        }
+    }
+}
+
+/// Formats `status` as a single SSE `data` event.
+fn sse_event(status: &JobStatus) -> Bytes {
+    let json = serde_json::to_string(status).unwrap_or_default();
+    Bytes::from(format!("data: {}\n\n", json))
+}
@@ -0,0 +1,21 @@
+//! Provides the `GET /api/jobs/{job_id}/log` endpoint.
+
+use crate::job_controller::state::JobsState;
+use actix_web::{web, HttpResponse, Responder};
+
+/// Actix web handler for `GET /api/jobs/{job_id}/log`.
+///
+/// # Returns
+/// - `200 OK` with the job's log as `text/plain`.
+/// - `404 Not Found` if no log exists for the job ID.
+pub(crate) async fn process(
+    job_id: web::Path<String>,
+    state: web::Data<JobsState>,
+) -> impl Responder {
+    match state.logs.render(&job_id) {
+        Some(log) => HttpResponse::Ok()
+            .content_type("text/plain; charset=utf-8")
+            .body(log),
+        None => HttpResponse::NotFound().body("Job ID not found"),
+    }
+}
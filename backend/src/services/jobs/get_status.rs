@@ -0,0 +1,36 @@
+//! Provides the `GET /api/jobs/{job_id}` endpoint.
+//!
+//! Returns the current `JobStatus` of any background job, whichever service started it.
+//! The handler reads from the shared, thread-safe `JobsState` (defined in
+//! `job_controller/state.rs`), which acts as the single source of truth for the status of
+//! all ongoing and recently finished jobs. Finished jobs are evicted after a while (see
+//! `start_job_sweeper`), after which their ID is reported as not found.
+//!
+//! The same handler also serves `GET /api/data_sources/csv/status/{job_id}`, the original
+//! route for CSV verification jobs, kept as an alias for existing clients. Clients that can
+//! keep a connection open should follow `GET /api/jobs/{job_id}/events` instead, which
+//! pushes each update as it happens.
+
+use crate::job_controller::state::JobsState;
+use actix_web::{web, HttpResponse, Responder};
+
+/// Actix web handler for `GET /api/jobs/{job_id}` and its alias
+/// `GET /api/data_sources/csv/status/{job_id}`.
+///
+/// # Arguments
+/// * `job_id` - The unique identifier of the job, provided as a path parameter.
+/// * `state` - A `web::Data` wrapper around the application's shared `JobsState`.
+///
+/// # Returns
+/// - `200 OK` with a JSON body containing the `JobStatus` if the job ID is found.
+/// - `404 Not Found` with a plain text body if the job ID does not exist in the state.
+pub(crate) async fn process(
+    job_id: web::Path<String>,
+    state: web::Data<JobsState>,
+) -> impl Responder {
+    let jobs = state.jobs.read().await;
+    match jobs.get(job_id.as_str()) {
+        Some(entry) => HttpResponse::Ok().json(&entry.status),
+        None => HttpResponse::NotFound().body("Job ID not found"),
+    }
+}
@@ -0,0 +1,36 @@
+//! # Jobs Service Module
+//!
+//! Endpoints that apply to any background job, regardless of which service started it.
+//!
+//! - `GET /api/jobs/{job_id}`: Returns the current `JobStatus` of a job as JSON.
+//!   `GET /api/data_sources/csv/status/{job_id}` is an alias kept for compatibility.
+//! - `GET /api/jobs/{job_id}/log`: Returns the activity log of a job as plain text. See
+//!   `job_controller::log` for what gets recorded.
+//! - `POST /api/jobs/{job_id}/cancel`: Asks a running or queued job to stop. The job ends
+//!   in `JobStatus::Cancelled` at its next checkpoint.
+//! - `GET /api/jobs/{job_id}/events`: Streams the job's `JobStatus` updates as Server-Sent
+//!   Events until it finishes. See `job_controller::events`.
+
+mod cancel;
+mod events;
+mod get_log;
+pub(crate) mod get_status;
+
+use actix_web::web::{get, post, scope};
+use actix_web::Scope;
+
+/// The base path for all job-related API endpoints.
+const API_PATH: &str = "/api/jobs";
+
+/// Configures and returns the Actix `Scope` for job-related routes.
+pub fn configure_routes() -> Scope {
+    scope(API_PATH)
+        // Route to get the current status of a job.
+        .route("/{job_id}", get().to(get_status::process))
+        // Route to download the activity log of a job.
+        .route("/{job_id}/log", get().to(get_log::process))
+        // Route to cancel a running job.
+        .route("/{job_id}/cancel", post().to(cancel::process))
+        // Route to stream the status updates of a job.
+        .route("/{job_id}/events", get().to(events::process))
+}
@@ -1,2 +1,5 @@
 pub(crate) mod templates;
-pub(crate) mod data_sources;
\ No newline at end of file
+pub(crate) mod data_sources;
+pub(crate) mod health;
+pub(crate) mod jobs;
+pub(crate) mod render;
\ No newline at end of file
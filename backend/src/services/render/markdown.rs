@@ -0,0 +1,210 @@
+//! # Markdown to PDF Service
+//!
+//! Handles `POST /api/render/markdown`. The body is a `RenderMarkdownRequest`; the response
+//! is the rendered PDF, produced by the same core as the template PDF endpoints
+//! (`templates::pdf::render_to_bytes`). Requests exceeding the size limits below, or with
+//! more than `MAX_IMAGES` images, are rejected with `413 Payload Too Large` before any
+//! rendering happens.
+
+use crate::config::Config;
+use crate::services::templates::pdf::{
+    pdf_bytes_response, render_to_bytes, PdfError, RenderOptions,
+};
+use actix_web::{web, HttpResponse, Responder};
+use common::model::image::MAX_IMAGES;
+use common::requests::RenderMarkdownRequest;
+
+/// Maximum length of `text`, in bytes.
+const MAX_TEXT_BYTES: usize = 1024 * 1024;
+/// Maximum length of a single image's Base64 data, in bytes.
+const MAX_IMAGE_BASE64_BYTES: usize = 4 * 1024 * 1024;
+
+/// Actix web handler for `POST /api/render/markdown`.
+///
+/// # Returns
+/// - `200 OK` with an `application/pdf` body on success (with `X-PDF-Warnings` if some
+///   elements had to be replaced).
+/// - `413 Payload Too Large` if the request exceeds a size limit.
+/// - A JSON `PdfErrorBody` with a `500 Internal Server Error` status if rendering fails.
+pub async fn process(
+    request: web::Json<RenderMarkdownRequest>,
+    config: web::Data<Config>,
+) -> impl Responder {
+    let request = request.into_inner();
+    if let Some(reason) = check_limits(&request) {
+        return HttpResponse::PayloadTooLarge().body(reason);
+    }
+
+    let result = web::block(move || {
+        render_to_bytes(
+            &request.text,
+            request.images,
+            &request.vars,
+            &RenderOptions {
+                strict: request.settings.strict,
+                page_size: request.settings.page_size,
+                margins: request.settings.margins,
+                orientation: request.settings.orientation,
+                font_path: None,
+                fonts_dir: Some(&config.fonts_dir),
+                autolink: request.settings.autolink,
+            },
+        )
+    })
+    .await;
+
+    match result {
+        Ok(Ok((bytes, warnings))) => pdf_bytes_response(bytes, &warnings, "render.pdf"),
+        Ok(Err(e)) => e.response(),
+        Err(e) => PdfError::render(e).response(),
+    }
+}
+
+/// Checks the request against the size limits.
+///
+/// # Returns
+/// `None` if the request is within limits, or a description of the first limit exceeded.
+fn check_limits(request: &RenderMarkdownRequest) -> Option<String> {
+    if request.text.len() > MAX_TEXT_BYTES {
+        return Some(format!("text exceeds {} bytes", MAX_TEXT_BYTES));
+    }
+    if request.images.len() > MAX_IMAGES {
+        return Some(format!("more than {} images", MAX_IMAGES));
+    }
+    request
+        .images
+        .iter()
+        .find(|img| img.base64.len() > MAX_IMAGE_BASE64_BYTES)
+        .map(|img| {
+            format!(
+                "image '{}' exceeds {} bytes",
+                img.id, MAX_IMAGE_BASE64_BYTES
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestEnv;
+    use actix_web::test::{call_service, init_service, read_body, TestRequest};
+    use actix_web::App;
+    use base64::engine::general_purpose::STANDARD as BASE64;
+    use base64::Engine;
+    use common::model::image::Image;
+    use std::io::Cursor;
+
+    fn request(text: &str, images: Vec<Image>) -> RenderMarkdownRequest {
+        RenderMarkdownRequest {
+            text: text.to_string(),
+            images,
+            vars: Vec::new(),
+            settings: Default::default(),
+        }
+    }
+
+    fn image(id: &str, base64: String) -> Image {
+        Image {
+            id: id.to_string(),
+            base64,
+        }
+    }
+
+    /// A small red square as a Base64 PNG.
+    fn png_base64() -> String {
+        let mut bytes = Vec::new();
+        image::RgbImage::from_pixel(4, 4, image::Rgb([255, 0, 0]))
+            .write_to(&mut Cursor::new(&mut bytes), image::ImageFormat::Png)
+            .unwrap();
+        BASE64.encode(bytes)
+    }
+
+    #[test]
+    fn check_limits_accepts_requests_within_limits() {
+        let mut images: Vec<Image> = (1..MAX_IMAGES)
+            .map(|i| image(&i.to_string(), String::new()))
+            .collect();
+        images.push(image("grande", "x".repeat(MAX_IMAGE_BASE64_BYTES)));
+        assert_eq!(
+            check_limits(&request(&"x".repeat(MAX_TEXT_BYTES), images)),
+            None
+        );
+    }
+
+    #[test]
+    fn check_limits_rejects_the_first_limit_exceeded() {
+        let text = "x".repeat(MAX_TEXT_BYTES + 1);
+        assert_eq!(
+            check_limits(&request(&text, Vec::new())),
+            Some(format!("text exceeds {} bytes", MAX_TEXT_BYTES))
+        );
+
+        let images = (0..=MAX_IMAGES)
+            .map(|i| image(&i.to_string(), String::new()))
+            .collect();
+        assert_eq!(
+            check_limits(&request("", images)),
+            Some(format!("more than {} images", MAX_IMAGES))
+        );
+
+        let images = vec![
+            image("ok", String::new()),
+            image("grande", "x".repeat(MAX_IMAGE_BASE64_BYTES + 1)),
+        ];
+        assert_eq!(
+            check_limits(&request("", images)),
+            Some(format!(
+                "image 'grande' exceeds {} bytes",
+                MAX_IMAGE_BASE64_BYTES
+            ))
+        );
+    }
+
+    #[actix_web::test]
+    async fn renders_bold_text_and_an_image() {
+        let env = TestEnv::new();
+        let app = init_service(
+            App::new()
+                .app_data(web::Data::new(env.config.clone()))
+                .route("/markdown", web::post().to(process)),
+        )
+        .await;
+        let body = serde_json::json!({
+            "text": "**Hola** mundo\n[img:logo|center]",
+            "images": [{ "id": "logo", "base64": png_base64() }],
+        });
+        let req = TestRequest::post()
+            .uri("/markdown")
+            .set_json(body)
+            .to_request();
+        let response = call_service(&app, req).await;
+
+        assert_eq!(response.status(), 200);
+        assert_eq!(
+            response.headers().get("content-type").unwrap(),
+            "application/pdf"
+        );
+        assert!(response.headers().get("x-pdf-warnings").is_none());
+        let bytes = read_body(response).await;
+        assert!(bytes.starts_with(b"%PDF-"));
+        assert!(bytes.trim_ascii_end().ends_with(b"%%EOF"));
+    }
+
+    #[actix_web::test]
+    async fn oversized_request_is_rejected_before_rendering() {
+        let env = TestEnv::new();
+        let app = init_service(
+            App::new()
+                .app_data(web::Data::new(env.config.clone()))
+                .app_data(web::JsonConfig::default().limit(2 * MAX_TEXT_BYTES))
+                .route("/markdown", web::post().to(process)),
+        )
+        .await;
+        let req = TestRequest::post()
+            .uri("/markdown")
+            .set_json(serde_json::json!({ "text": "x".repeat(MAX_TEXT_BYTES + 1) }))
+            .to_request();
+        let response = call_service(&app, req).await;
+        assert_eq!(response.status(), 413);
+    }
+}
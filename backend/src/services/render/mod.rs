@@ -0,0 +1,22 @@
+//! # Render Service Module
+//!
+//! Stateless conversions that do not involve a saved template.
+//!
+//! - `POST /api/render/markdown`: Renders posted markdown-like text (plus images and
+//!   variables) into a PDF with the same styling as template PDFs. Nothing is read from or
+//!   written to the database.
+
+mod markdown;
+
+use actix_web::web::{post, scope};
+use actix_web::Scope;
+
+/// The base path for all render API endpoints.
+const API_PATH: &str = "/api/render";
+
+/// Configures and returns the Actix `Scope` for the render routes.
+pub fn configure_routes() -> Scope {
+    scope(API_PATH)
+        // Route to render arbitrary markdown into a PDF.
+        .route("/markdown", post().to(markdown::process))
+}
@@ -0,0 +1,305 @@
+//! Handles the duplication of a template.
+//!
+//! `POST /api/templates/{template_id}/clone` copies a saved template into a new one, so a
+//! family of similar letters can start from an existing one. The response body is the id
+//! of the new template, a freshly generated UUID.
+//!
+//! ## What is copied
+//!
+//! - The text, page margins, orientation and page size, and the name prefixed with "Copia de ".
+//! - Every image. Each copy gets a new id and the `[img:...]` tags of the text are
+//!   rewritten to match, so the copy never shares `images` rows with the original.
+//! - The template variables.
+//! - The custom font, copied to its own file in `Config::template_fonts_dir` once the rows are
+//!   committed, so deleting either template does not remove the font of the other. If the
+//!   file cannot be copied, the clone is left without a custom font.
+//!
+//! The CSV data source (`datasource_md5`, `last_verified_md5`) and the `verified` flag are
+//! not copied: the new template starts without a data source.
+
+use crate::config::Config;
+use crate::db::DbPool;
+use actix_web::{web, HttpResponse, Responder};
+use rusqlite::{params, OptionalExtension};
+use std::fs;
+use std::path::Path;
+
+/// Prefix added to the name of the original template.
+const CLONE_NAME_PREFIX: &str = "Copia de ";
+
+/// Actix web handler for `POST /api/templates/{template_id}/clone`.
+///
+/// # Returns
+/// - `201 Created` with the id of the new template as a `text/plain` body.
+/// - `404 Not Found` if the template does not exist.
+/// - `503 Service Unavailable` with an error message if a database operation fails.
+pub async fn process(
+    template_id: web::Path<String>,
+    pool: web::Data<DbPool>,
+    config: web::Data<Config>,
+) -> impl Responder {
+    let template_id = template_id.into_inner();
+    match web::block(move || clone_template(&pool, &config, &template_id)).await {
+        Ok(Ok(Some(new_id))) => HttpResponse::Created()
+            .content_type("text/plain; charset=utf-8")
+            .body(new_id),
+        Ok(Ok(None)) => HttpResponse::NotFound().body("Template not found"),
+        Ok(Err(e)) => {
+            HttpResponse::ServiceUnavailable().body(format!("Error cloning template: {}", e))
+        }
+        Err(e) => HttpResponse::ServiceUnavailable().body(format!("Error cloning template: {}", e)),
+    }
+}
+
+/// Copies the template, its images and its variables in a single transaction, then its
+/// font file.
+///
+/// # Returns
+/// - `Ok(Some(new_id))` with the id of the new template.
+/// - `Ok(None)` if the template does not exist; nothing is changed.
+/// - `Err(String)` if a database error occurs; the transaction is rolled back.
+fn clone_template(
+    pool: &DbPool,
+    config: &Config,
+    template_id: &str,
+) -> Result<Option<String>, String> {
+    let mut conn = pool.get().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+
+    let row = tx
+        .query_row(
+            "SELECT text, name, font_path FROM templates WHERE id = ?1",
+            params![template_id],
+            |r| {
+                Ok((
+                    r.get::<_, String>(0)?,
+                    r.get::<_, Option<String>>(1)?,
+                    r.get::<_, Option<String>>(2)?,
+                ))
+            },
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+    let Some((mut text, name, font_path)) = row else {
+        return Ok(None);
+    };
+
+    let new_id = uuid::Uuid::new_v4().to_string();
+
+    // Copy the images under new ids, pointing the text at the copies.
+    let images: Vec<(String, String)> = tx
+        .prepare("SELECT id, base64 FROM images WHERE template_id = ?1 ORDER BY id")
+        .map_err(|e| e.to_string())?
+        .query_map(params![template_id], |r| Ok((r.get(0)?, r.get(1)?)))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<_, _>>()
+        .map_err(|e| e.to_string())?;
+    let mut image_copies = Vec::with_capacity(images.len());
+    for (image_id, base64) in images {
+        let copy_id = uuid::Uuid::new_v4().to_string();
+        // Tags are `[img:ID]` or, with an alignment, `[img:ID|center]`.
+        for end in ["]", "|"] {
+            text = text.replace(
+                &format!("[img:{}{}", image_id, end),
+                &format!("[img:{}{}", copy_id, end),
+            );
+        }
+        image_copies.push((copy_id, base64));
+    }
+
+    tx.execute(
+        "INSERT INTO templates
+             (id, text, margin_top_mm, margin_right_mm, margin_bottom_mm, margin_left_mm,
+              orientation, page_size, name, created_at, updated_at)
+         SELECT ?1, ?2, margin_top_mm, margin_right_mm, margin_bottom_mm, margin_left_mm,
+                orientation, page_size, ?3,
+                strftime('%Y-%m-%dT%H:%M:%SZ', 'now'), strftime('%Y-%m-%dT%H:%M:%SZ', 'now')
+         FROM templates WHERE id = ?4",
+        params![
+            &new_id,
+            &text,
+            name.map(|n| format!("{}{}", CLONE_NAME_PREFIX, n)),
+            template_id,
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+    for (copy_id, base64) in &image_copies {
+        tx.execute(
+            "INSERT INTO images (id, template_id, base64) VALUES (?1, ?2, ?3)",
+            params![copy_id, &new_id, base64],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    tx.execute(
+        "INSERT INTO template_vars (template_id, name, value)
+         SELECT ?1, name, value FROM template_vars WHERE template_id = ?2",
+        params![&new_id, template_id],
+    )
+    .map_err(|e| e.to_string())?;
+
+    tx.commit().map_err(|e| e.to_string())?;
+
+    if let Some(font_copy) = font_path.and_then(|path| copy_font(config, &path, &new_id)) {
+        conn.execute(
+            "UPDATE templates SET font_path = ?1 WHERE id = ?2",
+            params![font_copy, &new_id],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    Ok(Some(new_id))
+}
+
+/// Copies the font file at `path` to `Config::template_fonts_dir` under the new template's
+/// id.
+///
+/// # Returns
+/// The path of the copy, or `None` (logged) if the file cannot be copied.
+fn copy_font(config: &Config, path: &str, new_id: &str) -> Option<String> {
+    let extension = Path::new(path).extension()?.to_str()?;
+    let copy_path = config.template_font_path(new_id, extension);
+    match fs::copy(path, &copy_path) {
+        Ok(_) => Some(copy_path.to_string_lossy().into_owned()),
+        Err(e) => {
+            log::warn!(
+                "Could not copy the font {} to {}: {}",
+                path,
+                copy_path.display(),
+                e
+            );
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestEnv;
+    use std::collections::HashMap;
+
+    /// The images of `template_id`, as a map from their base64 content to their id.
+    fn images_by_content(env: &TestEnv, template_id: &str) -> HashMap<String, String> {
+        env.pool
+            .get()
+            .unwrap()
+            .prepare("SELECT base64, id FROM images WHERE template_id = ?1")
+            .unwrap()
+            .query_map(params![template_id], |r| Ok((r.get(0)?, r.get(1)?)))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap()
+    }
+
+    /// The variables of `template_id`, as `(name, value)` pairs sorted by name.
+    fn vars(env: &TestEnv, template_id: &str) -> Vec<(String, String)> {
+        env.pool
+            .get()
+            .unwrap()
+            .prepare("SELECT name, value FROM template_vars WHERE template_id = ?1 ORDER BY name")
+            .unwrap()
+            .query_map(params![template_id], |r| Ok((r.get(0)?, r.get(1)?)))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap()
+    }
+
+    /// The text, name, font path, data source MD5 and verified flag of `template_id`.
+    fn template_row(
+        env: &TestEnv,
+        template_id: &str,
+    ) -> (String, Option<String>, Option<String>, Option<String>, i64) {
+        env.pool
+            .get()
+            .unwrap()
+            .query_row(
+                "SELECT text, name, font_path, datasource_md5, verified FROM templates \
+                 WHERE id = ?1",
+                params![template_id],
+                |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?, r.get(4)?)),
+            )
+            .unwrap()
+    }
+
+    #[test]
+    fn clone_copies_images_vars_and_font() {
+        let env = TestEnv::new();
+        let text = "Hola [img:logo] y [img:logo|center], firma [img:logo2|right]";
+        env.insert_template("t", text, Some("name\nAna\n"));
+        fs::create_dir_all(env.config.template_fonts_dir()).unwrap();
+        let font = env.config.template_font_path("t", "ttf");
+        fs::write(&font, b"font bytes").unwrap();
+        {
+            let conn = env.pool.get().unwrap();
+            conn.execute(
+                "UPDATE templates SET name = 'Carta', font_path = ?1, verified = 1 WHERE id = 't'",
+                params![font.to_string_lossy()],
+            )
+            .unwrap();
+            conn.execute_batch(
+                "INSERT INTO images (id, template_id, base64) VALUES ('logo', 't', 'AAAA');
+                 INSERT INTO images (id, template_id, base64) VALUES ('logo2', 't', 'BBBB');
+                 INSERT INTO template_vars (template_id, name, value) VALUES ('t', 'city', 'Lima');
+                 INSERT INTO template_vars (template_id, name, value) VALUES ('t', 'date', 'hoy');",
+            )
+            .unwrap();
+        }
+        let original = template_row(&env, "t");
+
+        let new_id = clone_template(&env.pool, &env.config, "t")
+            .unwrap()
+            .expect("template exists");
+
+        // The tags point at the copies of the images, which have new ids.
+        let copies = images_by_content(&env, &new_id);
+        assert_eq!(copies.len(), 2);
+        let (logo, logo2) = (&copies["AAAA"], &copies["BBBB"]);
+        assert!(logo != "logo" && logo2 != "logo2");
+        let (copy_text, copy_name, copy_font, copy_md5, copy_verified) =
+            template_row(&env, &new_id);
+        assert_eq!(
+            copy_text,
+            format!(
+                "Hola [img:{0}] y [img:{0}|center], firma [img:{1}|right]",
+                logo, logo2
+            )
+        );
+        assert_eq!(copy_name.as_deref(), Some("Copia de Carta"));
+        assert_eq!(vars(&env, &new_id), vars(&env, "t"));
+        assert_eq!(vars(&env, &new_id).len(), 2);
+
+        // The font is copied to a file of its own.
+        let copy_font = copy_font.expect("font copied");
+        assert_eq!(
+            Path::new(&copy_font),
+            env.config.template_font_path(&new_id, "ttf")
+        );
+        assert_eq!(fs::read(&copy_font).unwrap(), b"font bytes");
+
+        // The data source is not copied.
+        assert_eq!((copy_md5, copy_verified), (None, 0));
+
+        // The original is untouched.
+        assert_eq!(template_row(&env, "t"), original);
+        assert_eq!(
+            images_by_content(&env, "t"),
+            HashMap::from([
+                ("AAAA".to_string(), "logo".to_string()),
+                ("BBBB".to_string(), "logo2".to_string()),
+            ])
+        );
+        assert_eq!(fs::read(&font).unwrap(), b"font bytes");
+    }
+
+    #[test]
+    fn cloning_a_missing_template_changes_nothing() {
+        let env = TestEnv::new();
+        assert_eq!(clone_template(&env.pool, &env.config, "missing"), Ok(None));
+        let count: i64 = env
+            .pool
+            .get()
+            .unwrap()
+            .query_row("SELECT COUNT(*) FROM templates", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(count, 0);
+    }
+}
@@ -0,0 +1,97 @@
+//! Provides the `POST /api/templates/images/compress` endpoint.
+//!
+//! The editor stores every image of a template as Base64, in the template payload and in
+//! the `images` table. Photos straight from a camera are several megabytes and thousands
+//! of pixels wide, far more than a template ever renders (`pdf.rs` scales images down to
+//! about 200 CSS pixels). Before inserting an image, the editor sends the raw file here
+//! and stores the Base64 it gets back instead of encoding the original.
+//!
+//! Images whose longer side exceeds `MAX_IMAGE_DIMENSION` are scaled down to it, keeping
+//! their aspect ratio. The result is re-encoded as PNG when it has an alpha channel and as
+//! JPEG otherwise. An image that is already small enough is returned unchanged if
+//! re-encoding does not make it smaller.
+
+use actix_web::web::Bytes;
+use actix_web::{web, HttpResponse, Responder};
+use base64::{engine::general_purpose, Engine as _};
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::png::PngEncoder;
+use image::imageops::FilterType;
+use image::{load_from_memory, DynamicImage, GenericImageView};
+
+/// Longest side, in pixels, an image is stored with.
+const MAX_IMAGE_DIMENSION: u32 = 1600;
+/// JPEG quality used when re-encoding opaque images.
+const JPEG_QUALITY: u8 = 85;
+
+/// Actix web handler for `POST /api/templates/images/compress`.
+///
+/// The request body is the raw image file (PNG or JPEG), up to `MAX_IMAGE_BYTES`.
+///
+/// # Returns
+/// - `200 OK` with the Base64 of the compressed image as `text/plain`.
+/// - `400 Bad Request` if the body is empty or cannot be decoded as an image.
+/// - `500 Internal Server Error` if the image cannot be re-encoded.
+pub async fn process(body: Bytes) -> impl Responder {
+    if body.is_empty() {
+        return HttpResponse::BadRequest().body("Error: empty request body");
+    }
+    match web::block(move || compress_image(&body)).await {
+        Ok(Ok(bytes)) => HttpResponse::Ok()
+            .content_type("text/plain")
+            .body(general_purpose::STANDARD.encode(bytes)),
+        Ok(Err(CompressError::Invalid(e))) => {
+            HttpResponse::BadRequest().body(format!("Error: invalid image: {}", e))
+        }
+        Ok(Err(CompressError::Encode(e))) => {
+            HttpResponse::InternalServerError().body(format!("Error: {}", e))
+        }
+        Err(e) => HttpResponse::InternalServerError().body(format!("Error: {}", e)),
+    }
+}
+
+/// Why an image could not be compressed.
+enum CompressError {
+    /// The upload is not a decodable image.
+    Invalid(image::ImageError),
+    /// Re-encoding the image failed.
+    Encode(image::ImageError),
+}
+
+/// Scales `original` down to `MAX_IMAGE_DIMENSION` and re-encodes it.
+///
+/// # Returns
+/// The bytes to store: the re-encoded image, or `original` itself if it was not scaled
+/// and re-encoding would not make it smaller.
+fn compress_image(original: &[u8]) -> Result<Vec<u8>, CompressError> {
+    let img = load_from_memory(original).map_err(CompressError::Invalid)?;
+    let (width, height) = img.dimensions();
+    let scaled = width.max(height) > MAX_IMAGE_DIMENSION;
+    let img = if scaled {
+        img.resize(
+            MAX_IMAGE_DIMENSION,
+            MAX_IMAGE_DIMENSION,
+            FilterType::Lanczos3,
+        )
+    } else {
+        img
+    };
+
+    let encoded = encode(&img).map_err(CompressError::Encode)?;
+    if !scaled && encoded.len() >= original.len() {
+        return Ok(original.to_vec());
+    }
+    Ok(encoded)
+}
+
+/// Encodes `img` as PNG if it has an alpha channel, or as JPEG otherwise.
+fn encode(img: &DynamicImage) -> Result<Vec<u8>, image::ImageError> {
+    let mut out = Vec::new();
+    if img.color().has_alpha() {
+        img.write_with_encoder(PngEncoder::new(&mut out))?;
+    } else {
+        let rgb = DynamicImage::ImageRgb8(img.to_rgb8());
+        rgb.write_with_encoder(JpegEncoder::new_with_quality(&mut out, JPEG_QUALITY))?;
+    }
+    Ok(out)
+}
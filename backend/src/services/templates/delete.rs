@@ -0,0 +1,246 @@
+//! Handles the deletion of a template and everything stored for it.
+//!
+//! This module provides the `DELETE /api/templates/{template_id}` endpoint. Nothing else
+//! ever removes a template, so without it the database and the data directory only grow.
+//!
+//! ## Workflow
+//!
+//! 1.  **Cancel Running Jobs**: Any verification still running for the template is
+//!     cancelled (`JobsState::cancel_jobs_for_template`), so it does not keep reading a CSV
+//!     that is about to be deleted.
+//!
+//! 2.  **Database Cleanup**: In a single SQLite transaction, the template's `images`,
+//!     `template_vars` and `column_types` rows and its `templates` row are deleted. The file
+//!     names needed for the next step (data source MD5s and custom font path) are read in
+//!     the same transaction.
+//!
+//! 3.  **File Cleanup**: The template's CSV files (`{template_id}_{md5}.csv`, for both the
+//!     current and the last verified data source), its generated PDF
+//!     (`{template_id}.pdf` in the PDF directory) and its custom font are removed. This step is
+//!     best-effort: the rows are already gone, so a file that is missing or cannot be
+//!     removed is logged and does not fail the request.
+
+use crate::config::Config;
+use crate::db::DbPool;
+use crate::job_controller::state::JobsState;
+use actix_web::{web, HttpResponse, Responder};
+use rusqlite::{params, OptionalExtension};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+/// Actix web handler for `DELETE /api/templates/{template_id}`.
+///
+/// # Returns
+/// - `204 No Content` once the template is deleted.
+/// - `404 Not Found` if the template does not exist.
+/// - `503 Service Unavailable` with an error message if a database operation fails.
+pub async fn process(
+    template_id: web::Path<String>,
+    jobs_state: web::Data<JobsState>,
+    pool: web::Data<DbPool>,
+    config: web::Data<Config>,
+) -> impl Responder {
+    let template_id = template_id.into_inner();
+    jobs_state.cancel_jobs_for_template(&template_id).await;
+
+    let id = template_id.clone();
+    let result = web::block(move || delete_template(&pool, &config, &id)).await;
+    match result {
+        Ok(Ok(Some(files))) => {
+            for file in files {
+                remove_file_best_effort(&file);
+            }
+            HttpResponse::NoContent().finish()
+        }
+        Ok(Ok(None)) => HttpResponse::NotFound().body("Template not found"),
+        Ok(Err(e)) => {
+            HttpResponse::ServiceUnavailable().body(format!("Error deleting template: {}", e))
+        }
+        Err(e) => {
+            HttpResponse::ServiceUnavailable().body(format!("Error deleting template: {}", e))
+        }
+    }
+}
+
+/// Deletes the template's rows from the database in a single transaction.
+///
+/// # Returns
+/// - `Ok(Some(files))` with the paths of the files that belonged to the template.
+/// - `Ok(None)` if the template does not exist; nothing is changed.
+/// - `Err(String)` if a database error occurs; the transaction is rolled back.
+fn delete_template(
+    pool: &DbPool,
+    config: &Config,
+    template_id: &str,
+) -> Result<Option<Vec<PathBuf>>, String> {
+    let mut conn = pool.get().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+
+    let row = tx
+        .query_row(
+            "SELECT datasource_md5, last_verified_md5, font_path FROM templates WHERE id = ?1",
+            params![template_id],
+            |r| {
+                Ok((
+                    r.get::<_, Option<String>>(0)?,
+                    r.get::<_, Option<String>>(1)?,
+                    r.get::<_, Option<String>>(2)?,
+                ))
+            },
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+    let Some((datasource_md5, last_verified_md5, font_path)) = row else {
+        return Ok(None);
+    };
+
+    tx.execute(
+        "DELETE FROM images WHERE template_id = ?1",
+        params![template_id],
+    )
+    .map_err(|e| e.to_string())?;
+    tx.execute(
+        "DELETE FROM template_vars WHERE template_id = ?1",
+        params![template_id],
+    )
+    .map_err(|e| e.to_string())?;
+    tx.execute(
+        "DELETE FROM column_types WHERE template_id = ?1",
+        params![template_id],
+    )
+    .map_err(|e| e.to_string())?;
+    tx.execute("DELETE FROM templates WHERE id = ?1", params![template_id])
+        .map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())?;
+
+    let mut files: Vec<PathBuf> = [datasource_md5, last_verified_md5]
+        .into_iter()
+        .flatten()
+        .map(|md5| config.csv_path(template_id, &md5))
+        .collect();
+    files.dedup();
+    files.push(config.pdf_path(template_id));
+    files.extend(font_path.map(PathBuf::from));
+    Ok(Some(files))
+}
+
+/// Removes `path`, logging instead of failing. A file that does not exist is not an error.
+fn remove_file_best_effort(path: &Path) {
+    match fs::remove_file(path) {
+        Ok(()) => log::info!("Deleted {}", path.display()),
+        Err(e) if e.kind() == ErrorKind::NotFound => {}
+        Err(e) => log::warn!("Could not delete {}: {}", path.display(), e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{jobs_state, TestEnv};
+    use actix_web::http::StatusCode;
+    use actix_web::test::{call_service, init_service, TestRequest};
+    use actix_web::App;
+
+    /// Inserts an image, a variable and a column type for `template_id`.
+    fn insert_children(env: &TestEnv, template_id: &str) {
+        let conn = env.pool.get().unwrap();
+        conn.execute(
+            "INSERT INTO images (id, template_id, base64) VALUES (?1, ?2, 'AAAA')",
+            params![format!("{}-logo", template_id), template_id],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO template_vars (template_id, name, value) VALUES (?1, 'city', 'Lima')",
+            params![template_id],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO column_types (template_id, title, placeholder_type) \
+             VALUES (?1, 'amount', 'Number')",
+            params![template_id],
+        )
+        .unwrap();
+    }
+
+    /// The number of rows of `template_id` in each table, in the order templates, images,
+    /// template_vars, column_types.
+    fn row_counts(env: &TestEnv, template_id: &str) -> [i64; 4] {
+        let conn = env.pool.get().unwrap();
+        let count = |sql: &str| -> i64 {
+            conn.query_row(sql, params![template_id], |row| row.get(0))
+                .unwrap()
+        };
+        [
+            count("SELECT COUNT(*) FROM templates WHERE id = ?1"),
+            count("SELECT COUNT(*) FROM images WHERE template_id = ?1"),
+            count("SELECT COUNT(*) FROM template_vars WHERE template_id = ?1"),
+            count("SELECT COUNT(*) FROM column_types WHERE template_id = ?1"),
+        ]
+    }
+
+    #[actix_web::test]
+    async fn delete_removes_rows_and_files() {
+        let env = TestEnv::new();
+        let csv_md5 = env
+            .insert_template("t", "Hola", Some("name\nAna\n"))
+            .unwrap();
+        let other_csv_md5 = env
+            .insert_template("u", "Adiós", Some("name\nLuis\n"))
+            .unwrap();
+        insert_children(&env, "t");
+        insert_children(&env, "u");
+
+        // A last verified CSV, a generated PDF and a custom font.
+        let verified_md5 = format!("{:x}", md5::compute("name\nEva\n"));
+        fs::write(env.config.csv_path("t", &verified_md5), "name\nEva\n").unwrap();
+        fs::create_dir_all(&env.config.pdf_dir).unwrap();
+        fs::write(env.config.pdf_path("t"), b"%PDF").unwrap();
+        fs::create_dir_all(env.config.template_fonts_dir()).unwrap();
+        let font = env.config.template_font_path("t", "ttf");
+        fs::write(&font, b"font").unwrap();
+        env.pool
+            .get()
+            .unwrap()
+            .execute(
+                "UPDATE templates SET last_verified_md5 = ?1, font_path = ?2 WHERE id = 't'",
+                params![verified_md5, font.to_string_lossy()],
+            )
+            .unwrap();
+
+        let app = init_service(
+            App::new()
+                .app_data(web::Data::new(jobs_state(1)))
+                .app_data(web::Data::new(env.pool.clone()))
+                .app_data(web::Data::new(env.config.clone()))
+                .route("/templates/{template_id}", web::delete().to(process)),
+        )
+        .await;
+        let req = TestRequest::delete().uri("/templates/t").to_request();
+        assert_eq!(
+            call_service(&app, req).await.status(),
+            StatusCode::NO_CONTENT
+        );
+
+        assert_eq!(row_counts(&env, "t"), [0, 0, 0, 0]);
+        assert!(!env.config.csv_path("t", &csv_md5).exists());
+        assert!(!env.config.csv_path("t", &verified_md5).exists());
+        assert!(!env.config.pdf_path("t").exists());
+        assert!(!font.exists());
+
+        // Another template is left alone.
+        assert_eq!(row_counts(&env, "u"), [1, 1, 1, 1]);
+        assert!(env.config.csv_path("u", &other_csv_md5).exists());
+
+        let req = TestRequest::delete().uri("/templates/t").to_request();
+        assert_eq!(
+            call_service(&app, req).await.status(),
+            StatusCode::NOT_FOUND
+        );
+        let req = TestRequest::delete().uri("/templates/missing").to_request();
+        assert_eq!(
+            call_service(&app, req).await.status(),
+            StatusCode::NOT_FOUND
+        );
+    }
+}
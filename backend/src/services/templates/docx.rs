@@ -0,0 +1,798 @@
+//! Provides the `GET /api/templates/docx/{template_id}` endpoint, which exports a saved
+//! template as a Word document (`.docx`) for users who need to keep editing the output.
+//!
+//! The template is read like for the PDF (`[var:NAME]` tags substituted, the saved page
+//! size, margins and orientation applied) and parsed with `common::template_ast::parse_template`, so the
+//! formats show the same content. Each node is mapped to its WordprocessingML equivalent:
+//! - Paragraphs and placeholder lines become paragraphs of runs; bold, italic, underline,
+//!   strikethrough and `{color:...}` spans become the matching run properties. Unlike in the
+//!   PDF, underline and strikethrough are drawn.
+//! - `[text](url)` links become clickable hyperlinks, so the URL is not repeated after them.
+//! - `#` headings become paragraphs with the built-in `Heading1` to `Heading6` styles, bold
+//!   and sized like in the PDF (`heading_font_size_pt`), so Word lists them in its
+//!   navigation pane.
+//! - `- ` list items become bulleted list paragraphs (`word/numbering.xml`).
+//! - `[img:...]` tags become inline pictures, aligned like in the PDF and with the same size
+//!   (`layout::image_scale`). The picture is the original image re-encoded as PNG.
+//! - Pipe tables become bordered tables with a bold, repeated header row.
+//!
+//! The query parameters are the ones of the PDF endpoint (`PdfQuery`): `page_size`,
+//! `orientation`, `autolink` and `strict`. Elements that fail to render are handled as in
+//! the PDF (see the `pdf` module docs) and listed in the same `X-PDF-Warnings` header.
+//!
+//! Text uses Arial 11 pt, the default PDF font. A custom template font is not embedded:
+//! Word substitutes fonts that are not installed, so embedding it would not be reliable.
+//!
+//! The package is written directly with `zip`: `[Content_Types].xml`, the package
+//! relationships, `word/document.xml` with its relationships, `word/styles.xml`,
+//! `word/numbering.xml` and one `word/media/imageN.png` per picture.
+
+use super::get::{load_margins, load_orientation, load_page_size, load_vars};
+use super::layout::{image_scale, IMAGE_DPI};
+use super::pdf::{load_images, warnings_header_value, PdfError, RenderOptions, WARNINGS_HEADER};
+use crate::db::DbPool;
+use actix_web::http::header::{ContentDisposition, DispositionParam, DispositionType, HeaderName};
+use actix_web::{web, HttpResponse, Responder};
+use common::model::pdf::Orientation;
+use common::model::template_var::substitute_vars;
+use common::requests::PdfQuery;
+use common::template_ast::{
+    heading_font_size_pt, parse_template, ImageAlignment, ImageTag, Inline, LocatedNode, Span,
+    TemplateNode, TextStyle,
+};
+use image::codecs::png::PngEncoder;
+use image::{load_from_memory, GenericImageView};
+use rusqlite::OptionalExtension;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::Write as _;
+use std::io::{Cursor, Write};
+use zip::write::SimpleFileOptions;
+use zip::ZipWriter;
+
+/// MIME type of a `.docx` file.
+const DOCX_CONTENT_TYPE: &str =
+    "application/vnd.openxmlformats-officedocument.wordprocessingml.document";
+/// Twentieths of a point (the unit of page sizes, margins and table widths) per inch.
+const TWIPS_PER_INCH: f64 = 1440.0;
+/// Twentieths of a point per millimeter.
+const TWIPS_PER_MM: f64 = TWIPS_PER_INCH / 25.4;
+/// English Metric Units (the unit of picture sizes) per inch.
+const EMU_PER_INCH: f64 = 914_400.0;
+/// Color of link text, as in the PDF.
+const LINK_COLOR: &str = "1976D2";
+/// Text that replaces an element that failed to render, as in the PDF.
+const UNRENDERABLE_ELEMENT: &str = "[elemento no renderizable]";
+/// Namespace of WordprocessingML.
+const W_NS: &str = "http://schemas.openxmlformats.org/wordprocessingml/2006/main";
+/// Namespace and type prefix of relationships.
+const R_NS: &str = "http://schemas.openxmlformats.org/officeDocument/2006/relationships";
+
+/// Actix web handler for `GET /api/templates/docx/{template_id}`.
+///
+/// # Returns
+/// - `200 OK` with the `.docx` file as an attachment named `{template_id}.docx`. Elements
+///   replaced during defensive rendering are listed in the `X-PDF-Warnings` header.
+/// - A `PdfError` response with a JSON body if no document can be produced: `404 Not Found`
+///   for an unknown template, `503 Service Unavailable` for other database errors and
+///   `500 Internal Server Error` if the document cannot be written.
+pub async fn process(
+    template_id: web::Path<String>,
+    query: web::Query<PdfQuery>,
+    pool: web::Data<DbPool>,
+) -> impl Responder {
+    let id = template_id.into_inner();
+    let query = query.into_inner();
+    let template_id = id.clone();
+    let result = web::block(move || generate_docx(&pool, &template_id, &query)).await;
+
+    match result {
+        Ok(Ok((bytes, warnings))) => {
+            let mut response = HttpResponse::Ok();
+            response
+                .content_type(DOCX_CONTENT_TYPE)
+                .insert_header(ContentDisposition {
+                    disposition: DispositionType::Attachment,
+                    parameters: vec![DispositionParam::Filename(format!("{}.docx", id))],
+                });
+            if let Some(value) = warnings_header_value(&warnings) {
+                response.insert_header((HeaderName::from_static(WARNINGS_HEADER), value));
+            }
+            response.body(bytes)
+        }
+        Ok(Err(e)) => {
+            log::error!("DOCX generation failed for template {}: {}", id, e);
+            e.response()
+        }
+        Err(e) => PdfError::render(e).response(),
+    }
+}
+
+/// Reads a template from the database and renders it into `.docx` bytes.
+///
+/// # Returns
+/// The document bytes and the rendering warnings on success, or a `PdfError` naming the
+/// failed stage.
+fn generate_docx(
+    pool: &DbPool,
+    template_id: &str,
+    query: &PdfQuery,
+) -> Result<(Vec<u8>, Vec<String>), PdfError> {
+    let conn = pool.get().map_err(PdfError::db)?;
+
+    let template_text: String = conn
+        .query_row(
+            "SELECT text FROM templates WHERE id = ?1",
+            [template_id],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(PdfError::db)?
+        .ok_or_else(PdfError::template_not_found)?;
+    let vars = load_vars(&conn, template_id).map_err(PdfError::db)?;
+    let template_text = substitute_vars(&template_text, &vars);
+    let options = RenderOptions {
+        strict: query.strict,
+        page_size: match query.page_size {
+            Some(page_size) => page_size,
+            None => load_page_size(&conn, template_id)
+                .map_err(PdfError::db)?
+                .unwrap_or_default(),
+        },
+        autolink: query.autolink,
+        margins: load_margins(&conn, template_id)
+            .map_err(PdfError::db)?
+            .unwrap_or_default(),
+        orientation: match query.orientation {
+            Some(orientation) => orientation,
+            None => load_orientation(&conn, template_id)
+                .map_err(PdfError::db)?
+                .unwrap_or_default(),
+        },
+        ..RenderOptions::default()
+    };
+    let images_map = load_images(&conn, template_id).map_err(PdfError::db)?;
+
+    render_template_docx(&template_text, &images_map, &options)
+}
+
+/// Renders template text into `.docx` bytes.
+///
+/// Unless `options.strict` is set, an element that fails to render is replaced by an
+/// `UNRENDERABLE_ELEMENT` paragraph, and a warning naming the template line is returned.
+///
+/// # Returns
+/// The document bytes and the warnings on success, or a `PdfError` with the `render` stage.
+fn render_template_docx(
+    template_text: &str,
+    images_map: &HashMap<String, Vec<u8>>,
+    options: &RenderOptions,
+) -> Result<(Vec<u8>, Vec<String>), PdfError> {
+    options.validate().map_err(PdfError::render)?;
+    let mut doc = DocxBody::default();
+    let mut warnings: Vec<String> = Vec::new();
+
+    for LocatedNode { line, node } in parse_template(template_text, options.autolink) {
+        // The element that can fail, named for the warning, and its result.
+        let (element, result) = match node {
+            TemplateNode::LineBreak => {
+                doc.body.push_str("<w:p/>");
+                continue;
+            }
+            TemplateNode::Table { header, rows } => {
+                doc.push_table(&header, &rows, options);
+                continue;
+            }
+            TemplateNode::Heading { level, content } => {
+                let properties = format!("<w:pStyle w:val=\"Heading{}\"/>", level);
+                doc.push_paragraph(&properties, &content);
+                continue;
+            }
+            TemplateNode::ListItem(inlines) => {
+                doc.push_paragraph(
+                    "<w:numPr><w:ilvl w:val=\"0\"/><w:numId w:val=\"1\"/></w:numPr>",
+                    &inlines,
+                );
+                continue;
+            }
+            TemplateNode::Image { source, tag } => {
+                (source, doc.push_image(&tag, images_map, options))
+            }
+            TemplateNode::Placeholder(Some(lines)) => {
+                // Lines are separated by an empty paragraph, like the breaks of the PDF.
+                for (i, spans) in lines.iter().enumerate() {
+                    if i > 0 {
+                        doc.body.push_str("<w:p/>");
+                    }
+                    let inlines: Vec<Inline> = spans.iter().cloned().map(Inline::Text).collect();
+                    doc.push_paragraph("", &inlines);
+                }
+                continue;
+            }
+            TemplateNode::Placeholder(None) => {
+                doc.push_plain_paragraph("[invalid placeholder]");
+                continue;
+            }
+            TemplateNode::Paragraph(inlines) => {
+                doc.push_paragraph("", &inlines);
+                continue;
+            }
+        };
+        if let Err(e) = result {
+            if options.strict {
+                return Err(PdfError::render(e));
+            }
+            let warning = format!("line {}: {}: {}", line + 1, element, e);
+            log::warn!("DOCX element replaced: {}", warning);
+            warnings.push(warning);
+            doc.push_plain_paragraph(UNRENDERABLE_ELEMENT);
+        }
+    }
+
+    let bytes = doc.finish(options).map_err(PdfError::render)?;
+    Ok((bytes, warnings))
+}
+
+/// The content of `word/document.xml` being built, and the parts it refers to.
+#[derive(Default)]
+struct DocxBody {
+    /// The XML of the body elements written so far.
+    body: String,
+    /// External hyperlink targets; the relationship of the link at index `i` is
+    /// `link_relationship_id(i)`.
+    links: Vec<String>,
+    /// PNG bytes of the pictures; the one at index `i` is `word/media/image{i + 1}.png`.
+    media: Vec<Vec<u8>>,
+}
+
+impl DocxBody {
+    /// Appends a paragraph with the given paragraph properties (the inside of `w:pPr`,
+    /// possibly empty) and inline content.
+    fn push_paragraph(&mut self, properties: &str, inlines: &[Inline]) {
+        self.body.push_str("<w:p>");
+        if !properties.is_empty() {
+            let _ = write!(self.body, "<w:pPr>{}</w:pPr>", properties);
+        }
+        self.push_inlines(inlines, false);
+        self.body.push_str("</w:p>");
+    }
+
+    /// Appends a paragraph of unstyled text.
+    fn push_plain_paragraph(&mut self, text: &str) {
+        let span = Span {
+            text: text.to_string(),
+            style: TextStyle::Regular,
+            color: None,
+            column: None,
+        };
+        self.push_paragraph("", &[Inline::Text(span)]);
+    }
+
+    /// Appends the runs of `inlines`. Links become `w:hyperlink` elements pointing to a new
+    /// external relationship. With `bold`, every run is made bold (see `TextStyle::bolded`).
+    fn push_inlines(&mut self, inlines: &[Inline], bold: bool) {
+        for inline in inlines {
+            match inline {
+                Inline::Text(span) => {
+                    let color = span
+                        .color
+                        .map(|(r, g, b)| format!("{:02X}{:02X}{:02X}", r, g, b));
+                    push_run(&mut self.body, span, bold, color.as_deref(), false);
+                }
+                Inline::Link { url, spans, .. } => {
+                    self.links.push(url.clone());
+                    let id = link_relationship_id(self.links.len() - 1);
+                    let _ = write!(self.body, "<w:hyperlink r:id=\"{}\">", id);
+                    for span in spans {
+                        push_run(&mut self.body, span, bold, Some(LINK_COLOR), true);
+                    }
+                    self.body.push_str("</w:hyperlink>");
+                }
+            }
+        }
+    }
+
+    /// Appends an image as an inline picture in its own paragraph, aligned as the tag says
+    /// and sized like in the PDF. A missing image becomes an `[image not found: ID]`
+    /// paragraph.
+    ///
+    /// # Returns
+    /// An error if the image cannot be decoded or re-encoded.
+    fn push_image(
+        &mut self,
+        tag: &ImageTag,
+        images_map: &HashMap<String, Vec<u8>>,
+        options: &RenderOptions,
+    ) -> Result<(), Box<dyn Error>> {
+        let Some(bytes) = images_map.get(&tag.id) else {
+            self.push_plain_paragraph(&format!("[image not found: {}]", tag.id));
+            return Ok(());
+        };
+        let img = load_from_memory(bytes)?;
+        let (width, height) = img.dimensions();
+        let mut png = Vec::new();
+        img.write_with_encoder(PngEncoder::new(&mut png))?;
+
+        let scale = image_scale(width, height, tag.width_px, options.content_width_in());
+        let to_emu = |px: u32| ((px as f64 * scale / IMAGE_DPI) * EMU_PER_INCH).round() as u64;
+        let (cx, cy) = (to_emu(width).max(1), to_emu(height).max(1));
+
+        self.media.push(png);
+        let number = self.media.len();
+        let alignment = match tag.alignment {
+            ImageAlignment::Left => "left",
+            ImageAlignment::Center => "center",
+            ImageAlignment::Right => "right",
+        };
+        let _ = write!(
+            self.body,
+            "<w:p><w:pPr><w:jc w:val=\"{alignment}\"/></w:pPr><w:r><w:drawing>\
+             <wp:inline distT=\"0\" distB=\"0\" distL=\"0\" distR=\"0\">\
+             <wp:extent cx=\"{cx}\" cy=\"{cy}\"/>\
+             <wp:docPr id=\"{number}\" name=\"Imagen {number}\"/>\
+             <a:graphic><a:graphicData uri=\"http://schemas.openxmlformats.org/drawingml/2006/picture\">\
+             <pic:pic><pic:nvPicPr><pic:cNvPr id=\"{number}\" name=\"image{number}.png\"/><pic:cNvPicPr/></pic:nvPicPr>\
+             <pic:blipFill><a:blip r:embed=\"{rel}\"/><a:stretch><a:fillRect/></a:stretch></pic:blipFill>\
+             <pic:spPr><a:xfrm><a:off x=\"0\" y=\"0\"/><a:ext cx=\"{cx}\" cy=\"{cy}\"/></a:xfrm>\
+             <a:prstGeom prst=\"rect\"><a:avLst/></a:prstGeom></pic:spPr></pic:pic>\
+             </a:graphicData></a:graphic></wp:inline></w:drawing></w:r></w:p>",
+            rel = image_relationship_id(number - 1),
+        );
+        Ok(())
+    }
+
+    /// Appends a bordered table with equal-width columns spanning the content width. The
+    /// header row is bold and repeated on every page the table spans.
+    fn push_table(
+        &mut self,
+        header: &[Vec<Inline>],
+        rows: &[Vec<Vec<Inline>>],
+        options: &RenderOptions,
+    ) {
+        let content_width = (options.content_width_in() * TWIPS_PER_INCH) as usize;
+        let column_width = content_width / header.len().max(1);
+
+        self.body.push_str(
+            "<w:tbl><w:tblPr><w:tblW w:w=\"0\" w:type=\"auto\"/><w:tblBorders>\
+             <w:top w:val=\"single\" w:sz=\"4\" w:space=\"0\" w:color=\"000000\"/>\
+             <w:left w:val=\"single\" w:sz=\"4\" w:space=\"0\" w:color=\"000000\"/>\
+             <w:bottom w:val=\"single\" w:sz=\"4\" w:space=\"0\" w:color=\"000000\"/>\
+             <w:right w:val=\"single\" w:sz=\"4\" w:space=\"0\" w:color=\"000000\"/>\
+             <w:insideH w:val=\"single\" w:sz=\"4\" w:space=\"0\" w:color=\"000000\"/>\
+             <w:insideV w:val=\"single\" w:sz=\"4\" w:space=\"0\" w:color=\"000000\"/>\
+             </w:tblBorders></w:tblPr><w:tblGrid>",
+        );
+        for _ in header {
+            let _ = write!(self.body, "<w:gridCol w:w=\"{}\"/>", column_width);
+        }
+        self.body.push_str("</w:tblGrid>");
+
+        let push_row = |doc: &mut DocxBody, cells: &[Vec<Inline>], is_header: bool| {
+            doc.body.push_str("<w:tr>");
+            if is_header {
+                doc.body.push_str("<w:trPr><w:tblHeader/></w:trPr>");
+            }
+            for cell in cells {
+                let _ = write!(
+                    doc.body,
+                    "<w:tc><w:tcPr><w:tcW w:w=\"{}\" w:type=\"dxa\"/></w:tcPr><w:p>",
+                    column_width
+                );
+                doc.push_inlines(cell, is_header);
+                doc.body.push_str("</w:p></w:tc>");
+            }
+            doc.body.push_str("</w:tr>");
+        };
+        push_row(self, header, true);
+        for row in rows {
+            push_row(self, row, false);
+        }
+        self.body.push_str("</w:tbl>");
+    }
+
+    /// Writes the `.docx` package: the body followed by the page setup, and the other parts.
+    fn finish(self, options: &RenderOptions) -> zip::result::ZipResult<Vec<u8>> {
+        let (width_mm, height_mm) = options.page_dimensions_mm();
+        let twips = |mm: f64| (mm * TWIPS_PER_MM).round() as u64;
+        let orientation = match options.orientation {
+            Orientation::Portrait => "",
+            Orientation::Landscape => " w:orient=\"landscape\"",
+        };
+        let margins = options.margins;
+        let document = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+             <w:document xmlns:w=\"{W_NS}\" xmlns:r=\"{R_NS}\" \
+             xmlns:wp=\"http://schemas.openxmlformats.org/drawingml/2006/wordprocessingDrawing\" \
+             xmlns:a=\"http://schemas.openxmlformats.org/drawingml/2006/main\" \
+             xmlns:pic=\"http://schemas.openxmlformats.org/drawingml/2006/picture\">\
+             <w:body>{body}<w:sectPr>\
+             <w:pgSz w:w=\"{width}\" w:h=\"{height}\"{orientation}/>\
+             <w:pgMar w:top=\"{top}\" w:right=\"{right}\" w:bottom=\"{bottom}\" w:left=\"{left}\" \
+             w:header=\"0\" w:footer=\"0\" w:gutter=\"0\"/>\
+             </w:sectPr></w:body></w:document>",
+            body = self.body,
+            width = twips(width_mm),
+            height = twips(height_mm),
+            top = twips(margins.top),
+            right = twips(margins.right),
+            bottom = twips(margins.bottom),
+            left = twips(margins.left),
+        );
+
+        let mut relationships = String::from(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+             <Relationships xmlns=\"http://schemas.openxmlformats.org/package/2006/relationships\">",
+        );
+        let _ = write!(
+            relationships,
+            "<Relationship Id=\"rIdStyles\" Type=\"{R_NS}/styles\" Target=\"styles.xml\"/>\
+             <Relationship Id=\"rIdNumbering\" Type=\"{R_NS}/numbering\" Target=\"numbering.xml\"/>"
+        );
+        for (i, url) in self.links.iter().enumerate() {
+            let _ = write!(
+                relationships,
+                "<Relationship Id=\"{}\" Type=\"{R_NS}/hyperlink\" Target=\"{}\" \
+                 TargetMode=\"External\"/>",
+                link_relationship_id(i),
+                xml_escape(url)
+            );
+        }
+        for i in 0..self.media.len() {
+            let _ = write!(
+                relationships,
+                "<Relationship Id=\"{}\" Type=\"{R_NS}/image\" Target=\"media/image{}.png\"/>",
+                image_relationship_id(i),
+                i + 1
+            );
+        }
+        relationships.push_str("</Relationships>");
+
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        let file_options = SimpleFileOptions::default();
+        let parts: [(&str, &str); 6] = [
+            ("[Content_Types].xml", CONTENT_TYPES_XML),
+            ("_rels/.rels", PACKAGE_RELS_XML),
+            ("word/document.xml", &document),
+            ("word/_rels/document.xml.rels", &relationships),
+            ("word/styles.xml", &styles_xml()),
+            ("word/numbering.xml", NUMBERING_XML),
+        ];
+        for (name, content) in parts {
+            zip.start_file(name, file_options)?;
+            zip.write_all(content.as_bytes())?;
+        }
+        for (i, png) in self.media.iter().enumerate() {
+            // PNG data is already compressed.
+            let stored = file_options.compression_method(zip::CompressionMethod::Stored);
+            zip.start_file(format!("word/media/image{}.png", i + 1), stored)?;
+            zip.write_all(png)?;
+        }
+        Ok(zip.finish()?.into_inner())
+    }
+}
+
+/// Appends a `w:r` run holding the text of `span`.
+///
+/// # Arguments
+/// * `bold` - Whether the run is made bold regardless of its style (table headers).
+/// * `color` - Hex color of the text (`RRGGBB`), or `None` for the default color.
+/// * `underline` - Whether the run is underlined regardless of its style (links).
+fn push_run(out: &mut String, span: &Span, bold: bool, color: Option<&str>, underline: bool) {
+    let style = if bold {
+        span.style.bolded()
+    } else {
+        span.style
+    };
+    // Run properties must follow the order of the schema.
+    let mut properties = String::new();
+    if matches!(style, TextStyle::Bold | TextStyle::BoldItalic) {
+        properties.push_str("<w:b/>");
+    }
+    if matches!(style, TextStyle::Italic | TextStyle::BoldItalic) {
+        properties.push_str("<w:i/>");
+    }
+    if style == TextStyle::Strikethrough {
+        properties.push_str("<w:strike/>");
+    }
+    if let Some(color) = color {
+        let _ = write!(properties, "<w:color w:val=\"{}\"/>", color);
+    }
+    if underline || style == TextStyle::Underline {
+        properties.push_str("<w:u w:val=\"single\"/>");
+    }
+
+    out.push_str("<w:r>");
+    if !properties.is_empty() {
+        let _ = write!(out, "<w:rPr>{}</w:rPr>", properties);
+    }
+    let _ = write!(
+        out,
+        "<w:t xml:space=\"preserve\">{}</w:t></w:r>",
+        xml_escape(&span.text)
+    );
+}
+
+/// Relationship id of the hyperlink at index `i` of `DocxBody::links`.
+fn link_relationship_id(i: usize) -> String {
+    format!("rIdLink{}", i + 1)
+}
+
+/// Relationship id of the picture at index `i` of `DocxBody::media`.
+fn image_relationship_id(i: usize) -> String {
+    format!("rIdImage{}", i + 1)
+}
+
+/// Escapes `text` for use in XML content or an attribute value. Characters that XML 1.0
+/// does not allow at all (most control characters) are dropped.
+fn xml_escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            '\t' | '\n' | '\r' => out.push(c),
+            c if c < ' ' || c == '\u{FFFE}' || c == '\u{FFFF}' => {}
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// `[Content_Types].xml`: the content type of every part of the package.
+const CONTENT_TYPES_XML: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+<Types xmlns=\"http://schemas.openxmlformats.org/package/2006/content-types\">\
+<Default Extension=\"rels\" ContentType=\"application/vnd.openxmlformats-package.relationships+xml\"/>\
+<Default Extension=\"xml\" ContentType=\"application/xml\"/>\
+<Default Extension=\"png\" ContentType=\"image/png\"/>\
+<Override PartName=\"/word/document.xml\" \
+ContentType=\"application/vnd.openxmlformats-officedocument.wordprocessingml.document.main+xml\"/>\
+<Override PartName=\"/word/styles.xml\" \
+ContentType=\"application/vnd.openxmlformats-officedocument.wordprocessingml.styles+xml\"/>\
+<Override PartName=\"/word/numbering.xml\" \
+ContentType=\"application/vnd.openxmlformats-officedocument.wordprocessingml.numbering+xml\"/>\
+</Types>";
+
+/// `_rels/.rels`: points to the main document part.
+const PACKAGE_RELS_XML: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+<Relationships xmlns=\"http://schemas.openxmlformats.org/package/2006/relationships\">\
+<Relationship Id=\"rId1\" \
+Type=\"http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument\" \
+Target=\"word/document.xml\"/>\
+</Relationships>";
+
+/// `word/styles.xml`: Arial 11 pt with 1.25 line spacing and no space between paragraphs,
+/// like the PDF, and the `Heading1` to `Heading6` paragraph styles.
+fn styles_xml() -> String {
+    let mut styles = STYLES_XML.to_string();
+    for level in 1..=6u8 {
+        // Font sizes are in half-points.
+        let size = u32::from(heading_font_size_pt(level)) * 2;
+        let _ = write!(
+            styles,
+            "<w:style w:type=\"paragraph\" w:styleId=\"Heading{level}\">\
+             <w:name w:val=\"heading {level}\"/><w:basedOn w:val=\"Normal\"/>\
+             <w:next w:val=\"Normal\"/><w:qFormat/>\
+             <w:pPr><w:keepNext/><w:outlineLvl w:val=\"{}\"/></w:pPr>\
+             <w:rPr><w:b/><w:bCs/><w:sz w:val=\"{size}\"/><w:szCs w:val=\"{size}\"/></w:rPr>\
+             </w:style>",
+            level - 1
+        );
+    }
+    styles.push_str("</w:styles>");
+    styles
+}
+
+/// The start of `word/styles.xml`, up to the `Normal` style. `styles_xml` adds the rest.
+const STYLES_XML: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+<w:styles xmlns:w=\"http://schemas.openxmlformats.org/wordprocessingml/2006/main\">\
+<w:docDefaults><w:rPrDefault><w:rPr>\
+<w:rFonts w:ascii=\"Arial\" w:hAnsi=\"Arial\" w:eastAsia=\"Arial\" w:cs=\"Arial\"/>\
+<w:sz w:val=\"22\"/><w:szCs w:val=\"22\"/>\
+</w:rPr></w:rPrDefault><w:pPrDefault><w:pPr>\
+<w:spacing w:after=\"0\" w:line=\"300\" w:lineRule=\"auto\"/>\
+</w:pPr></w:pPrDefault></w:docDefaults>\
+<w:style w:type=\"paragraph\" w:default=\"1\" w:styleId=\"Normal\"><w:name w:val=\"Normal\"/></w:style>";
+
+/// `word/numbering.xml`: the bullet list used by `- ` list items (`w:numId` 1).
+const NUMBERING_XML: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+<w:numbering xmlns:w=\"http://schemas.openxmlformats.org/wordprocessingml/2006/main\">\
+<w:abstractNum w:abstractNumId=\"0\"><w:multiLevelType w:val=\"singleLevel\"/>\
+<w:lvl w:ilvl=\"0\"><w:start w:val=\"1\"/><w:numFmt w:val=\"bullet\"/>\
+<w:lvlText w:val=\"\u{2022}\"/><w:lvlJc w:val=\"left\"/>\
+<w:pPr><w:ind w:left=\"360\" w:hanging=\"360\"/></w:pPr></w:lvl>\
+</w:abstractNum>\
+<w:num w:numId=\"1\"><w:abstractNumId w:val=\"0\"/></w:num>\
+</w:numbering>";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use std::io::Read;
+
+    /// A template with every element `render_template_docx` maps.
+    const ALL_ELEMENTS: &str = "# Título\n\
+        Texto **negrita** *cursiva* __subrayado__ ~~tachado~~ {color:#FF0000}rojo{/color} <b>&\n\
+        Ver [el sitio](https://example.com/?a=1&b=2)\n\
+        \n\
+        - Elemento\n\
+        [img:logo|center]\n\
+        [img:falta]\n\
+        | Nombre | Edad |\n\
+        | --- | --- |\n\
+        | Ana | 30 |\n\
+        [ph:Nota:QW5hCkJlYQ==]\n\
+        [ph:Nota:!!!]";
+
+    /// Renders `text` with a 4x4 PNG as the image `logo` and returns the parts of the
+    /// package by name.
+    fn render_parts(text: &str) -> BTreeMap<String, Vec<u8>> {
+        let mut png = Vec::new();
+        image::RgbImage::from_pixel(4, 4, image::Rgb([255, 0, 0]))
+            .write_to(&mut Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+        let images_map = HashMap::from([("logo".to_string(), png)]);
+        let (bytes, warnings) =
+            render_template_docx(text, &images_map, &RenderOptions::default()).unwrap();
+        assert!(warnings.is_empty(), "{:?}", warnings);
+
+        let mut archive = zip::ZipArchive::new(Cursor::new(bytes)).unwrap();
+        let mut parts = BTreeMap::new();
+        for i in 0..archive.len() {
+            let mut file = archive.by_index(i).unwrap();
+            let mut content = Vec::new();
+            file.read_to_end(&mut content).unwrap();
+            parts.insert(file.name().to_string(), content);
+        }
+        parts
+    }
+
+    fn part<'a>(parts: &'a BTreeMap<String, Vec<u8>>, name: &str) -> &'a str {
+        std::str::from_utf8(&parts[name]).unwrap()
+    }
+
+    /// Checks that every element of `xml` is closed, in order. Enough to catch a broken
+    /// tag in the hand-written XML.
+    fn assert_balanced(name: &str, xml: &str) {
+        let mut open: Vec<&str> = Vec::new();
+        let mut rest = xml;
+        while let Some(start) = rest.find('<') {
+            let end = start + rest[start..].find('>').unwrap();
+            let tag = &rest[start + 1..end];
+            rest = &rest[end + 1..];
+            if tag.starts_with('?') || tag.ends_with('/') {
+                continue;
+            }
+            match tag.strip_prefix('/') {
+                Some(closing) => assert_eq!(open.pop(), Some(closing), "{}", name),
+                None => open.push(tag.split_whitespace().next().unwrap()),
+            }
+        }
+        assert!(open.is_empty(), "{}: unclosed {:?}", name, open);
+    }
+
+    #[test]
+    fn package_has_every_part() {
+        let parts = render_parts(ALL_ELEMENTS);
+        let names: Vec<&str> = parts.keys().map(String::as_str).collect();
+        assert_eq!(
+            names,
+            [
+                "[Content_Types].xml",
+                "_rels/.rels",
+                "word/_rels/document.xml.rels",
+                "word/document.xml",
+                "word/media/image1.png",
+                "word/numbering.xml",
+                "word/styles.xml",
+            ]
+        );
+        assert!(parts["word/media/image1.png"].starts_with(b"\x89PNG\r\n\x1a\n"));
+        for (name, content) in &parts {
+            if name.ends_with(".xml") || name.ends_with(".rels") {
+                assert_balanced(name, std::str::from_utf8(content).unwrap());
+            }
+        }
+
+        let package_rels = part(&parts, "_rels/.rels");
+        assert!(
+            package_rels.contains("relationships/officeDocument\" Target=\"word/document.xml\"")
+        );
+        // Every relationship of the document points to an existing part or is external.
+        let rels = part(&parts, "word/_rels/document.xml.rels");
+        for target in rels.split("Target=\"").skip(1) {
+            let (target, attributes) = target.split_once('"').unwrap();
+            if !attributes.starts_with(" TargetMode=\"External\"") {
+                assert!(
+                    parts.contains_key(&format!("word/{}", target)),
+                    "{}",
+                    target
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn every_part_has_a_content_type() {
+        let parts = render_parts(ALL_ELEMENTS);
+        let types = part(&parts, "[Content_Types].xml");
+        for name in parts.keys().filter(|name| *name != "[Content_Types].xml") {
+            let extension = name.rsplit('.').next().unwrap();
+            let content_type = match types.split_once(&format!("PartName=\"/{}\" ", name)) {
+                Some((_, rest)) => rest,
+                None => {
+                    types
+                        .split_once(&format!("Extension=\"{}\" ", extension))
+                        .unwrap_or_else(|| panic!("no content type for {}", name))
+                        .1
+                }
+            };
+            let content_type = content_type
+                .strip_prefix("ContentType=\"")
+                .and_then(|rest| rest.split('"').next())
+                .unwrap();
+            let expected = match name.as_str() {
+                "word/document.xml" => {
+                    "application/vnd.openxmlformats-officedocument.wordprocessingml.document.main+xml"
+                }
+                "word/styles.xml" => {
+                    "application/vnd.openxmlformats-officedocument.wordprocessingml.styles+xml"
+                }
+                "word/numbering.xml" => {
+                    "application/vnd.openxmlformats-officedocument.wordprocessingml.numbering+xml"
+                }
+                "word/media/image1.png" => "image/png",
+                _ => "application/vnd.openxmlformats-package.relationships+xml",
+            };
+            assert_eq!(content_type, expected, "{}", name);
+        }
+    }
+
+    #[test]
+    fn document_xml_maps_every_element() {
+        let parts = render_parts(ALL_ELEMENTS);
+        let document = part(&parts, "word/document.xml");
+        let rels = part(&parts, "word/_rels/document.xml.rels");
+        for expected in [
+            // Heading.
+            "<w:pStyle w:val=\"Heading1\"/></w:pPr><w:r><w:t xml:space=\"preserve\">Título</w:t>",
+            // Styled spans, with escaped text.
+            "<w:rPr><w:b/></w:rPr><w:t xml:space=\"preserve\">negrita</w:t>",
+            "<w:rPr><w:i/></w:rPr><w:t xml:space=\"preserve\">cursiva</w:t>",
+            "<w:rPr><w:u w:val=\"single\"/></w:rPr><w:t xml:space=\"preserve\">subrayado</w:t>",
+            "<w:rPr><w:strike/></w:rPr><w:t xml:space=\"preserve\">tachado</w:t>",
+            "<w:rPr><w:color w:val=\"FF0000\"/></w:rPr><w:t xml:space=\"preserve\">rojo</w:t>",
+            "&lt;b&gt;&amp;",
+            // Link.
+            "<w:hyperlink r:id=\"rIdLink1\"><w:r><w:rPr><w:color w:val=\"1976D2\"/>\
+             <w:u w:val=\"single\"/></w:rPr><w:t xml:space=\"preserve\">el sitio</w:t>",
+            // Empty line.
+            "<w:p/>",
+            // List item.
+            "<w:numPr><w:ilvl w:val=\"0\"/><w:numId w:val=\"1\"/></w:numPr>",
+            // Image, and a missing one.
+            "<w:jc w:val=\"center\"/>",
+            "<a:blip r:embed=\"rIdImage1\"/>",
+            "[image not found: falta]",
+            // Table.
+            "<w:tr><w:trPr><w:tblHeader/></w:trPr>",
+            "<w:rPr><w:b/></w:rPr><w:t xml:space=\"preserve\">Nombre</w:t>",
+            "<w:t xml:space=\"preserve\">Ana</w:t></w:r></w:p></w:tc>",
+            // Placeholder lines, separated by an empty paragraph, and an invalid one.
+            "<w:t xml:space=\"preserve\">Ana</w:t></w:r></w:p><w:p/><w:p><w:r><w:t xml:space=\"preserve\">Bea</w:t>",
+            "[invalid placeholder]",
+            // Page setup.
+            "<w:sectPr><w:pgSz ",
+        ] {
+            assert!(document.contains(expected), "missing {}", expected);
+        }
+        assert!(rels.contains(
+            "Id=\"rIdLink1\" Type=\"http://schemas.openxmlformats.org/officeDocument/2006/relationships/hyperlink\" \
+             Target=\"https://example.com/?a=1&amp;b=2\" TargetMode=\"External\""
+        ));
+        assert!(rels.contains("Id=\"rIdImage1\""));
+    }
+}
@@ -0,0 +1,278 @@
+//! Handles the upload of a custom font for a template.
+//!
+//! `POST /api/templates/{template_id}/font` takes a `multipart/form-data` request with a
+//! single `file` part holding a TrueType (`.ttf`) or OpenType (`.otf`) font. The file is
+//! parsed before anything is written, so a corrupt or non-font upload is rejected with
+//! `400 Bad Request`. Valid fonts are stored as `{template_id}.{ext}` in
+//! `Config::template_fonts_dir` and
+//! the path is recorded in the `font_path` column of the template, replacing any font
+//! uploaded before.
+//!
+//! The PDF renderer (`pdf.rs`) uses the template font for every style (regular, bold,
+//! italic), and falls back to the default fonts when the template has none or its file can
+//! no longer be loaded.
+
+use crate::config::Config;
+use crate::db::DbPool;
+use actix_multipart::Multipart;
+use actix_web::{web, HttpResponse, Responder};
+use futures_util::StreamExt;
+use genpdf::fonts::FontData;
+use rusqlite::{params, OptionalExtension};
+use std::fs;
+use std::path::Path;
+
+type DynError = Box<dyn std::error::Error>;
+
+/// Largest font file accepted, in bytes.
+const MAX_FONT_BYTES: usize = 10 * 1024 * 1024;
+
+/// Why an upload was rejected.
+enum UploadError {
+    /// The template does not exist.
+    NotFound,
+    /// The request or the file is invalid.
+    Invalid(String),
+    /// Storing the font failed.
+    Internal(DynError),
+}
+
+/// Wraps an I/O, multipart or database error as `UploadError::Internal`.
+fn internal(e: impl Into<DynError>) -> UploadError {
+    UploadError::Internal(e.into())
+}
+
+/// HTTP handler for `POST /api/templates/{template_id}/font`.
+///
+/// # Returns
+/// - `200 OK` once the font is stored and recorded against the template.
+/// - `400 Bad Request` if the `file` part is missing, too large, not a `.ttf`/`.otf` file,
+///   or cannot be parsed as a font.
+/// - `404 Not Found` if the template does not exist.
+/// - `500 Internal Server Error` if the font cannot be stored.
+pub async fn process(
+    path: web::Path<String>,
+    payload: Multipart,
+    pool: web::Data<DbPool>,
+    config: web::Data<Config>,
+) -> impl Responder {
+    let template_id = path.into_inner();
+    match upload_font(&pool, &config, &template_id, payload).await {
+        Ok(()) => HttpResponse::Ok().finish(),
+        Err(UploadError::NotFound) => HttpResponse::NotFound().body("Template not found"),
+        Err(UploadError::Invalid(msg)) => {
+            HttpResponse::BadRequest().body(format!("Error: {}", msg))
+        }
+        Err(UploadError::Internal(e)) => {
+            log::error!(
+                "Failed to store the font of template {}: {}",
+                template_id,
+                e
+            );
+            HttpResponse::InternalServerError().body(format!("Error: {}", e))
+        }
+    }
+}
+
+/// Reads the uploaded font, validates it and records it against the template.
+///
+/// # Arguments
+/// * `pool` - The shared SQLite connection pool.
+/// * `config` - The application paths; the font is stored in its template fonts directory.
+/// * `template_id` - The template the font belongs to.
+/// * `payload` - The incoming `Multipart` stream, with the font in its `file` part.
+async fn upload_font(
+    pool: &DbPool,
+    config: &Config,
+    template_id: &str,
+    mut payload: Multipart,
+) -> Result<(), UploadError> {
+    let mut upload: Option<(String, Vec<u8>)> = None;
+
+    while let Some(item) = payload.next().await {
+        let mut field = item.map_err(internal)?;
+        let disposition = field.content_disposition().cloned();
+        if disposition.as_ref().and_then(|cd| cd.get_name()) != Some("file") {
+            continue; // Ignore other fields.
+        }
+        let extension = disposition
+            .as_ref()
+            .and_then(|cd| cd.get_filename())
+            .and_then(|name| Path::new(name).extension())
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext.to_ascii_lowercase())
+            .unwrap_or_default();
+
+        let mut bytes = Vec::new();
+        while let Some(chunk) = field.next().await {
+            bytes.extend_from_slice(&chunk.map_err(internal)?);
+            if bytes.len() > MAX_FONT_BYTES {
+                return Err(UploadError::Invalid(format!(
+                    "Font file exceeds {} bytes",
+                    MAX_FONT_BYTES
+                )));
+            }
+        }
+        upload = Some((extension, bytes));
+    }
+
+    let (extension, bytes) = upload
+        .ok_or_else(|| UploadError::Invalid("Missing 'file' part in multipart form".into()))?;
+    if extension != "ttf" && extension != "otf" {
+        return Err(UploadError::Invalid(
+            "Only .ttf and .otf fonts are accepted".into(),
+        ));
+    }
+    if let Err(e) = FontData::new(bytes.clone(), None) {
+        return Err(UploadError::Invalid(format!("Invalid font file: {}", e)));
+    }
+
+    let conn = pool.get().map_err(internal)?;
+    let exists = conn
+        .query_row(
+            "SELECT 1 FROM templates WHERE id = ?1",
+            [template_id],
+            |_| Ok(()),
+        )
+        .optional()
+        .map_err(internal)?
+        .is_some();
+    if !exists {
+        return Err(UploadError::NotFound);
+    }
+
+    fs::create_dir_all(config.template_fonts_dir()).map_err(internal)?;
+    let font_path = config.template_font_path(template_id, &extension);
+    fs::write(&font_path, &bytes).map_err(internal)?;
+
+    conn.execute(
+        "UPDATE templates SET font_path = ?1 WHERE id = ?2",
+        params![font_path.to_string_lossy(), template_id],
+    )
+    .map_err(internal)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestEnv;
+    use actix_web::http::header::CONTENT_TYPE;
+    use actix_web::http::StatusCode;
+    use actix_web::test::{call_service, init_service, read_body, TestRequest};
+    use actix_web::App;
+
+    const BOUNDARY: &str = "templify-test-boundary";
+    const FONT: &[u8] = include_bytes!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/fonts/LiberationSans-Regular.ttf"
+    ));
+
+    /// POSTs `bytes` as the font file `filename` of `template_id`.
+    ///
+    /// # Returns
+    /// The response status and body.
+    async fn post_font(
+        env: &TestEnv,
+        template_id: &str,
+        filename: &str,
+        bytes: &[u8],
+    ) -> (StatusCode, String) {
+        let app = init_service(
+            App::new()
+                .app_data(web::Data::new(env.pool.clone()))
+                .app_data(web::Data::new(env.config.clone()))
+                .route("/templates/{template_id}/font", web::post().to(process)),
+        )
+        .await;
+        let mut body = format!(
+            "--{b}\r\n\
+             Content-Disposition: form-data; name=\"file\"; filename=\"{f}\"\r\n\
+             Content-Type: application/octet-stream\r\n\r\n",
+            b = BOUNDARY,
+            f = filename
+        )
+        .into_bytes();
+        body.extend_from_slice(bytes);
+        body.extend_from_slice(format!("\r\n--{}--\r\n", BOUNDARY).as_bytes());
+        let req = TestRequest::post()
+            .uri(&format!("/templates/{}/font", template_id))
+            .insert_header((
+                CONTENT_TYPE,
+                format!("multipart/form-data; boundary={}", BOUNDARY),
+            ))
+            .set_payload(body)
+            .to_request();
+        let response = call_service(&app, req).await;
+        let status = response.status();
+        let body = read_body(response).await;
+        (status, String::from_utf8_lossy(&body).into_owned())
+    }
+
+    /// The `font_path` stored for `template_id`.
+    fn font_path(env: &TestEnv, template_id: &str) -> Option<String> {
+        env.pool
+            .get()
+            .unwrap()
+            .query_row(
+                "SELECT font_path FROM templates WHERE id = ?1",
+                [template_id],
+                |row| row.get(0),
+            )
+            .unwrap()
+    }
+
+    #[actix_web::test]
+    async fn valid_font_is_stored() {
+        let env = TestEnv::new();
+        env.insert_template("t", "", None);
+        let (status, body) = post_font(&env, "t", "Letra.TTF", FONT).await;
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        let stored = env.config.template_font_path("t", "ttf");
+        assert_eq!(
+            font_path(&env, "t"),
+            Some(stored.to_string_lossy().into_owned())
+        );
+        assert_eq!(fs::read(&stored).unwrap(), FONT);
+    }
+
+    #[actix_web::test]
+    async fn non_font_bytes_are_rejected() {
+        let env = TestEnv::new();
+        env.insert_template("t", "", None);
+
+        let (status, body) = post_font(&env, "t", "letra.ttf", b"not a font at all").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body.starts_with("Error: Invalid font file"), "{}", body);
+
+        let (status, body) = post_font(&env, "t", "letra.txt", FONT).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body, "Error: Only .ttf and .otf fonts are accepted");
+
+        assert_eq!(font_path(&env, "t"), None);
+        assert!(!env.config.template_fonts_dir().exists());
+    }
+
+    #[actix_web::test]
+    async fn oversize_font_is_rejected() {
+        let env = TestEnv::new();
+        env.insert_template("t", "", None);
+        let (status, body) = post_font(&env, "t", "letra.ttf", &vec![0; MAX_FONT_BYTES + 1]).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(
+            body,
+            format!("Error: Font file exceeds {} bytes", MAX_FONT_BYTES)
+        );
+        assert_eq!(font_path(&env, "t"), None);
+        assert!(!env.config.template_fonts_dir().exists());
+    }
+
+    #[actix_web::test]
+    async fn unknown_template_is_rejected() {
+        let env = TestEnv::new();
+        let (status, body) = post_font(&env, "missing", "letra.ttf", FONT).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body, "Template not found");
+        assert!(!env.config.template_fonts_dir().exists());
+    }
+}
@@ -11,11 +11,15 @@
 //!
 //! 2.  **Data Fetching**: It delegates the core logic to the `get_template` function.
 //!
-//! 3.  **Database Query**: `get_template` connects to the `templify.sqlite` database and performs
+//! 3.  **Database Query**: `get_template` takes a connection from the shared pool and performs
 //!     two main queries:
-//!     - It first retrieves the template's `id` and `text` from the `templates` table.
+//!     - It first retrieves the template's `id`, `name`, `text`, page margins, orientation and
+//!       page size from the `templates` table.
 //!     - It then fetches all associated images (their `id` and `base64` content) from the
-//!       `images` table using the `template_id`.
+//!       `images` table using the `template_id`, ordered by `id`. The fixed order makes a
+//!       `GET` after a `POST /save` return the same structure no matter how the client
+//!       ordered the images, so templates can be compared or versioned without spurious diffs.
+//!     - Finally, it fetches the template's `[var:NAME]` variables from `template_vars`.
 //!
 //! 4.  **Model Assembly**: The results are assembled into a `common::model::template::Template`
 //!     struct. This struct contains the template's text and an `Option<Vec<Image>>` for its images.
@@ -27,10 +31,13 @@
 //! This module exclusively handles the retrieval of template content and does not interact with
 //! data source-related fields like `datasource_md5` or `verified`, which are managed by other services.
 
+use crate::db::DbPool;
 use actix_web::web;
 use common::model::image::Image;
+use common::model::pdf::{Orientation, PageMargins, PageSize};
 use common::model::template::Template;
-use rusqlite::{params, Connection};
+use common::model::template_var::TemplateVar;
+use rusqlite::{params, Connection, OptionalExtension};
 
 /// Actix web handler for the `GET /api/templates/{template_id}` endpoint.
 ///
@@ -39,12 +46,16 @@ use rusqlite::{params, Connection};
 ///
 /// # Arguments
 /// * `template_id` - The unique identifier of the template, extracted from the URL path.
+/// * `pool` - The shared SQLite connection pool.
 ///
 /// # Returns
 /// - `200 OK` with the `Template` object as a JSON payload on success.
 /// - `503 Service Unavailable` with an error message if the template cannot be retrieved.
-pub async fn process(template_id: web::Path<String>) -> impl actix_web::Responder {
-    match get_template(&template_id).await {
+pub async fn process(
+    template_id: web::Path<String>,
+    pool: web::Data<DbPool>,
+) -> impl actix_web::Responder {
+    match get_template(&pool, &template_id).await {
         Ok(template) => actix_web::HttpResponse::Ok().json(template),
         Err(e) => actix_web::HttpResponse::ServiceUnavailable()
             .body(format!("Error retrieving template: {}", e)),
@@ -53,29 +64,38 @@ pub async fn process(template_id: web::Path<String>) -> impl actix_web::Responde
 
 /// Fetches a template and its associated images from the database.
 ///
-/// Connects to the SQLite database, queries for the template text and all related
+/// Takes a connection from `pool`, queries for the template text and all related
 /// images, and constructs a `Template` model.
 ///
 /// # Arguments
+/// * `pool` - The shared SQLite connection pool.
 /// * `template_id` - The ID of the template to fetch.
 ///
 /// # Returns
 /// - `Ok(Template)` containing the complete template data if found.
 /// - `Err(String)` if the template is not found or a database error occurs.
-pub async fn get_template(template_id: &str) -> Result<Template, String> {
-    // Open a SQLite connection to the file templify.sqlite
-    let conn = Connection::open("templify.sqlite").map_err(|e| e.to_string())?;
+pub async fn get_template(pool: &DbPool, template_id: &str) -> Result<Template, String> {
+    let conn = pool.get().map_err(|e| e.to_string())?;
 
     // Query the template by ID
     let mut stmt = conn
-        .prepare("SELECT id, text FROM templates WHERE id = ?1")
+        .prepare(
+            "SELECT id, text, margin_top_mm, margin_right_mm, margin_bottom_mm, margin_left_mm,
+                    orientation, name, page_size
+             FROM templates WHERE id = ?1",
+        )
         .map_err(|e| e.to_string())?;
     let template_iter = stmt
         .query_map(params![template_id], |row| {
             Ok(Template {
                 id: row.get(0)?,
+                name: row.get(7)?,
                 text: row.get(1)?,
                 images: None,
+                vars: None,
+                margins: margins_from_row(row, 2)?,
+                orientation: orientation_from_row(row, 6)?,
+                page_size: page_size_from_row(row, 8)?,
             })
         })
         .map_err(|e| e.to_string())?;
@@ -87,9 +107,9 @@ pub async fn get_template(template_id: &str) -> Result<Template, String> {
         None => return Err("Template not found".to_string()),
     };
 
-    // Query associated images
+    // Query associated images, in canonical (id) order
     let mut img_stmt = conn
-        .prepare("SELECT id, base64 FROM images WHERE template_id = ?1")
+        .prepare("SELECT id, base64 FROM images WHERE template_id = ?1 ORDER BY id")
         .map_err(|e| e.to_string())?;
     let image_iter = img_stmt
         .query_map(params![template_id], |row| {
@@ -107,5 +127,130 @@ pub async fn get_template(template_id: &str) -> Result<Template, String> {
         template.images = Some(images);
     }
 
+    // Query template variables
+    let vars = load_vars(&conn, template_id).map_err(|e| e.to_string())?;
+    if !vars.is_empty() {
+        template.vars = Some(vars);
+    }
+
     Ok(template)
 }
+
+/// Loads the `[var:NAME]` variables defined for a template, ordered by name.
+///
+/// Shared with the PDF service, which substitutes them before rendering.
+pub(crate) fn load_vars(conn: &Connection, template_id: &str) -> rusqlite::Result<Vec<TemplateVar>> {
+    let mut stmt =
+        conn.prepare("SELECT name, value FROM template_vars WHERE template_id = ?1 ORDER BY name")?;
+    let vars = stmt
+        .query_map(params![template_id], |row| {
+            Ok(TemplateVar {
+                name: row.get(0)?,
+                value: row.get(1)?,
+            })
+        })?
+        .collect();
+    vars
+}
+
+/// Loads the page margins saved for a template.
+///
+/// Shared with the PDF service, which applies them when rendering.
+///
+/// # Returns
+/// `Ok(None)` if the template uses the default margins.
+pub(crate) fn load_margins(
+    conn: &Connection,
+    template_id: &str,
+) -> rusqlite::Result<Option<PageMargins>> {
+    conn.query_row(
+        "SELECT margin_top_mm, margin_right_mm, margin_bottom_mm, margin_left_mm
+         FROM templates WHERE id = ?1",
+        params![template_id],
+        |row| margins_from_row(row, 0),
+    )
+}
+
+/// Reads the four margin columns of a `templates` row, starting at column `first`
+/// (top, right, bottom, left).
+///
+/// Margins are saved all together or not at all, so a `NULL` top margin means the
+/// template uses the defaults.
+fn margins_from_row(row: &rusqlite::Row, first: usize) -> rusqlite::Result<Option<PageMargins>> {
+    let Some(top) = row.get::<_, Option<f64>>(first)? else {
+        return Ok(None);
+    };
+    Ok(Some(PageMargins {
+        top,
+        right: row.get(first + 1)?,
+        bottom: row.get(first + 2)?,
+        left: row.get(first + 3)?,
+    }))
+}
+
+/// Loads the page orientation saved for a template.
+///
+/// Shared with the PDF service, which uses it unless the request overrides it.
+///
+/// # Returns
+/// `Ok(None)` if the template has no saved orientation (portrait).
+pub(crate) fn load_orientation(
+    conn: &Connection,
+    template_id: &str,
+) -> rusqlite::Result<Option<Orientation>> {
+    conn.query_row(
+        "SELECT orientation FROM templates WHERE id = ?1",
+        params![template_id],
+        |row| orientation_from_row(row, 0),
+    )
+}
+
+/// Loads the paper size saved for a template.
+///
+/// Shared with the PDF service, which uses it unless the request overrides it.
+///
+/// # Returns
+/// `Ok(None)` if the template has no saved page size (A4).
+pub(crate) fn load_page_size(
+    conn: &Connection,
+    template_id: &str,
+) -> rusqlite::Result<Option<PageSize>> {
+    conn.query_row(
+        "SELECT page_size FROM templates WHERE id = ?1",
+        params![template_id],
+        |row| page_size_from_row(row, 0),
+    )
+}
+
+/// Loads the path of the custom font uploaded for a template (see `font.rs`).
+///
+/// # Returns
+/// `Ok(None)` if the template has no custom font or does not exist.
+pub(crate) fn load_font_path(
+    conn: &Connection,
+    template_id: &str,
+) -> rusqlite::Result<Option<String>> {
+    conn.query_row(
+        "SELECT font_path FROM templates WHERE id = ?1",
+        params![template_id],
+        |row| row.get(0),
+    )
+    .optional()
+    .map(Option::flatten)
+}
+
+/// Reads the `orientation` column of a `templates` row. Unknown names are treated as unset.
+fn orientation_from_row(row: &rusqlite::Row, index: usize) -> rusqlite::Result<Option<Orientation>> {
+    Ok(row
+        .get::<_, Option<String>>(index)?
+        .as_deref()
+        .and_then(Orientation::from_name))
+}
+
+/// Reads the `page_size` column of a `templates` row. Unknown names are treated as unset.
+fn page_size_from_row(row: &rusqlite::Row, index: usize) -> rusqlite::Result<Option<PageSize>> {
+    Ok(row
+        .get::<_, Option<String>>(index)?
+        .as_deref()
+        .and_then(PageSize::from_name))
+}
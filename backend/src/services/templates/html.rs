@@ -0,0 +1,92 @@
+//! Handles the HTML export of a template.
+//!
+//! `GET /api/templates/html/{template_id}` renders a saved template to a standalone HTML
+//! document, e.g. to embed it in an email. The body is produced by
+//! `common::preview::render_preview_html`, the same pipeline as the editor's preview pane,
+//! so the export looks like the preview: `[var:NAME]` tags are substituted, placeholders
+//! show their sample values, and images are inlined as `data:` URLs. No other request is
+//! needed to display the document.
+//!
+//! The body is wrapped in a page with the preview's styles (`EXPORT_STYLE`), titled with
+//! the template name, or its id when it has none.
+
+use super::get::load_vars;
+use crate::db::DbPool;
+use actix_web::{web, HttpResponse, Responder};
+use common::model::image::Image;
+use common::preview::{escape_html, render_preview_html};
+use rusqlite::{params, OptionalExtension};
+
+/// Styles of the exported page, matching the editor's `.markdown-preview` pane.
+const EXPORT_STYLE: &str = "body{font-family:Arial,sans-serif;font-size:11px;}\
+table{border-collapse:collapse;}\
+th,td{border:1px solid #333;padding:2px 4px;}";
+
+/// Actix web handler for `GET /api/templates/html/{template_id}`.
+///
+/// # Returns
+/// - `200 OK` with the HTML document as a `text/html` body.
+/// - `404 Not Found` if the template does not exist.
+/// - `503 Service Unavailable` with an error message if a database operation fails.
+pub async fn process(template_id: web::Path<String>, pool: web::Data<DbPool>) -> impl Responder {
+    let template_id = template_id.into_inner();
+    match web::block(move || render_template_html(&pool, &template_id)).await {
+        Ok(Ok(Some(html))) => HttpResponse::Ok()
+            .content_type("text/html; charset=utf-8")
+            .body(html),
+        Ok(Ok(None)) => HttpResponse::NotFound().body("Template not found"),
+        Ok(Err(e)) => {
+            HttpResponse::ServiceUnavailable().body(format!("Error exporting template: {}", e))
+        }
+        Err(e) => {
+            HttpResponse::ServiceUnavailable().body(format!("Error exporting template: {}", e))
+        }
+    }
+}
+
+/// Reads the template with its variables and images and renders it to an HTML document.
+///
+/// # Returns
+/// - `Ok(Some(html))` with the complete document.
+/// - `Ok(None)` if the template does not exist.
+/// - `Err(String)` if a database error occurs.
+fn render_template_html(pool: &DbPool, template_id: &str) -> Result<Option<String>, String> {
+    let conn = pool.get().map_err(|e| e.to_string())?;
+
+    let Some((text, name)) = conn
+        .query_row(
+            "SELECT text, name FROM templates WHERE id = ?1",
+            params![template_id],
+            |r| Ok((r.get::<_, String>(0)?, r.get::<_, Option<String>>(1)?)),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?
+    else {
+        return Ok(None);
+    };
+    let vars = load_vars(&conn, template_id).map_err(|e| e.to_string())?;
+    let images: Vec<Image> = conn
+        .prepare("SELECT id, base64 FROM images WHERE template_id = ?1 ORDER BY id")
+        .and_then(|mut stmt| {
+            stmt.query_map(params![template_id], |r| {
+                Ok(Image {
+                    id: r.get(0)?,
+                    base64: r.get(1)?,
+                })
+            })?
+            .collect()
+        })
+        .map_err(|e| e.to_string())?;
+
+    let title = name
+        .filter(|name| !name.trim().is_empty())
+        .unwrap_or_else(|| template_id.to_string());
+    let body = render_preview_html(&text, &vars, &images);
+    Ok(Some(format!(
+        "<!DOCTYPE html>\n<html lang=\"es\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>{}</title>\n<style>{}</style>\n</head>\n<body>\n{}</body>\n</html>\n",
+        escape_html(&title),
+        EXPORT_STYLE,
+        body
+    )))
+}
@@ -0,0 +1,46 @@
+//! Image layout shared by the PDF (`pdf.rs`) and DOCX (`docx.rs`) renderers.
+//!
+//! Both size an image the same way, so it has the same size in either format and in the
+//! editor preview, whose CSS follows the same rules.
+
+/// Resolution images are laid out at: an image is shown at its pixel size divided by it.
+pub(super) const IMAGE_DPI: f64 = 150.0;
+/// Largest width and height, in CSS pixels, of an image whose tag sets no width.
+const DEFAULT_IMAGE_BOX_PX: f64 = 200.0;
+
+/// Returns the factor by which an image of `width` x `height` pixels is scaled, so that
+/// shown at `IMAGE_DPI` it fits the layout rules.
+///
+/// Without a width in the tag, the image fits a `DEFAULT_IMAGE_BOX_PX` square; with one,
+/// that width replaces the box (the height follows the aspect ratio). The content width
+/// bounds the image in both cases. Images are never scaled up, so the factor is at most 1.
+///
+/// # Arguments
+/// * `width`, `height` - Size of the image, in pixels.
+/// * `width_px` - Width requested by the tag, in CSS pixels.
+/// * `content_width_in` - Width between the page margins, in inches.
+pub(super) fn image_scale(
+    width: u32,
+    height: u32,
+    width_px: Option<f64>,
+    content_width_in: f64,
+) -> f64 {
+    // Calculate the maximum available width on the page in pixels.
+    let content_target_px = content_width_in * IMAGE_DPI;
+
+    // These values simulate max-width/max-height from CSS for consistent rendering.
+    let (css_max_width_px, css_max_height_px) = match width_px {
+        Some(width) => (width, f64::INFINITY),
+        None => (DEFAULT_IMAGE_BOX_PX, DEFAULT_IMAGE_BOX_PX),
+    };
+    let css_to_px = IMAGE_DPI / 96.0; // Convert CSS pixels (96 DPI) to layout pixels (IMAGE_DPI).
+    let css_max_width_target_px = css_max_width_px * css_to_px;
+    let css_max_height_target_px = css_max_height_px * css_to_px;
+
+    // Respect all constraints (page width, css max-width, css max-height).
+    let (width, height) = (width as f64, height as f64);
+    let scale_by_content = (content_target_px / width).min(1.0);
+    let scale_by_css_w = (css_max_width_target_px / width).min(1.0);
+    let scale_by_css_h = (css_max_height_target_px / height).min(1.0);
+    scale_by_content.min(scale_by_css_w).min(scale_by_css_h)
+}
@@ -0,0 +1,80 @@
+//! # Template Listing Service
+//!
+//! Provides `GET /api/templates`, which lists every stored template so the editor can offer
+//! a picker instead of requiring the template UUID. Each entry is a
+//! `common::model::template::TemplateSummary`: the id, the name, a short preview of the
+//! text, the number of images and the time of the last save. The most recently saved
+//! templates come first; templates saved before timestamps were recorded follow, ordered by
+//! name and preview text, so the list is stable between calls.
+
+use crate::db::DbPool;
+use common::model::template::{TemplateSummary, TEXT_PREVIEW_CHARS};
+
+/// Actix web handler for the `GET /api/templates` endpoint.
+///
+/// # Returns
+/// - `200 OK` with a JSON array of `TemplateSummary` on success (empty if there are none).
+/// - `503 Service Unavailable` with an error message if the database cannot be read.
+pub async fn process(pool: actix_web::web::Data<DbPool>) -> impl actix_web::Responder {
+    match actix_web::web::block(move || list_templates(&pool)).await {
+        Ok(Ok(templates)) => actix_web::HttpResponse::Ok().json(templates),
+        Ok(Err(e)) => actix_web::HttpResponse::ServiceUnavailable()
+            .body(format!("Error listing templates: {}", e)),
+        Err(e) => actix_web::HttpResponse::ServiceUnavailable()
+            .body(format!("Error listing templates: {}", e)),
+    }
+}
+
+/// Reads the summaries of all templates from the database.
+///
+/// # Returns
+/// - `Ok(Vec<TemplateSummary>)` with one entry per template.
+/// - `Err(String)` if a database error occurs.
+fn list_templates(pool: &DbPool) -> Result<Vec<TemplateSummary>, String> {
+    let conn = pool.get().map_err(|e| e.to_string())?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT t.id, t.text,
+                    (SELECT COUNT(*) FROM images i WHERE i.template_id = t.id),
+                    t.name, t.updated_at
+             FROM templates t",
+        )
+        .map_err(|e| e.to_string())?;
+
+    let mut templates = stmt
+        .query_map([], |row| {
+            let text: String = row.get(1)?;
+            Ok(TemplateSummary {
+                id: row.get(0)?,
+                name: row.get(3)?,
+                text_preview: text_preview(&text),
+                image_count: row.get(2)?,
+                updated_at: row.get(4)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    // Newest first; `None` sorts before `Some`, so reversing it puts untimed templates last.
+    templates.sort_by(|a, b| {
+        b.updated_at
+            .cmp(&a.updated_at)
+            .then_with(|| a.name.cmp(&b.name))
+            .then_with(|| a.text_preview.cmp(&b.text_preview))
+            .then_with(|| a.id.cmp(&b.id))
+    });
+    Ok(templates)
+}
+
+/// Returns the first `TEXT_PREVIEW_CHARS` characters of `text` on a single line.
+///
+/// Runs of whitespace, line breaks included, are collapsed into one space. A `…` is
+/// appended when the text was cut.
+fn text_preview(text: &str) -> String {
+    let flat = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    match flat.char_indices().nth(TEXT_PREVIEW_CHARS) {
+        Some((cut, _)) => format!("{}…", &flat[..cut]),
+        None => flat,
+    }
+}
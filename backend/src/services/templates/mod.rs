@@ -5,16 +5,33 @@
 //! path to the appropriate handler logic defined in its sub-modules.
 //!
 //! ## Sub-modules:
+//! - `list`: Lists all stored templates (id, text preview and image count).
 //! - `get`: Handles the retrieval of a specific template's data from the database.
 //! - `save`: Manages the creation and updating of templates and their associated images.
+//! - `delete`: Deletes a template with its images, variables and files.
+//! - `clone`: Duplicates a template with its images, variables and font.
 //! - `pdf`: Responsible for generating and serving a PDF document from a given template.
+//! - `docx`: Exports a template as a Word document, parsed the same way as for `pdf`.
+//! - `layout`: Image sizing shared by `pdf` and `docx`.
+//! - `html`: Exports a template as standalone HTML, rendered like the editor's preview.
+//! - `font`: Stores a custom font uploaded for a template, used by `pdf`.
+//! - `compress`: Scales down and re-encodes an image before the editor stores it.
 
+mod clone;
+mod compress;
+mod delete;
+mod docx;
+mod font;
 mod get;
-mod pdf;
+mod html;
+mod layout;
+mod list;
+pub(crate) mod pdf;
 mod save;
 
-use actix_web::web::{get, post, scope};
+use actix_web::web::{self, get, post, scope};
 use actix_web::Scope;
+use common::model::image::MAX_IMAGE_BYTES;
 
 /// The base path for all template-related API endpoints.
 const API_PATH: &str = "/api/templates";
@@ -25,6 +42,12 @@ const API_PATH: &str = "/api/templates";
 ///
 /// # Registered Routes:
 ///
+/// *   **`GET /`** (i.e. `GET /api/templates`):
+///     - **Handler**: `list::process`
+///     - **Description**: Lists every stored template as a JSON array of `TemplateSummary`
+///       (`id`, `name`, `text_preview`, `image_count` and `updated_at`), newest first, for
+///       the editor's template picker.
+///
 /// *   **`POST /save`**:
 ///     - **Handler**: `save::process`
 ///     - **Description**: Creates a new template or updates an existing one. It expects a
@@ -38,14 +61,66 @@ const API_PATH: &str = "/api/templates";
 ///       `template_id` in the URL path. It returns a JSON object containing the template's
 ///       text and all its associated images.
 ///
+/// *   **`DELETE /{template_id}`**:
+///     - **Handler**: `delete::process`
+///     - **Description**: Deletes the template, its images and variables in one transaction,
+///       then removes its CSV, PDF and font files on a best-effort basis. Returns
+///       `204 No Content`, or `404 Not Found` if the template does not exist.
+///
+/// *   **`POST /{template_id}/clone`**:
+///     - **Handler**: `clone::process`
+///     - **Description**: Copies the template's text, layout, images, variables and font
+///       into a new template named "Copia de ...", without its CSV data source. Returns
+///       `201 Created` with the new id, or `404 Not Found` if the template does not exist.
+///
 /// *   **`GET /pdf/{template_id}`**:
 ///     - **Handler**: `pdf::process`
 ///     - **Description**: Generates a PDF document from the specified template and serves it
 ///       to the client. The handler fetches the template's text and images, renders them
 ///       into a PDF file, and returns the file for inline display in the browser.
+///
+/// *   **`POST /pdf/preview`**:
+///     - **Handler**: `pdf::process_preview`
+///     - **Description**: Renders a PDF from a `Template` sent as JSON, without saving it.
+///       Used by the editor to preview unsaved changes. Returns the PDF bytes inline.
+///
+/// *   **`GET /docx/{template_id}`**:
+///     - **Handler**: `docx::process`
+///     - **Description**: Exports the template as a Word document (`.docx`) with the same
+///       content as the PDF, served as an attachment. Takes the same query options as the
+///       PDF endpoint.
+///
+/// *   **`GET /html/{template_id}`**:
+///     - **Handler**: `html::process`
+///     - **Description**: Renders the template to a standalone HTML document with the
+///       editor's preview pipeline (`common::preview`), images inlined as data URLs, for
+///       embedding in emails. Returns `404 Not Found` if the template does not exist.
+///
+/// *   **`POST /{template_id}/font`**:
+///     - **Handler**: `font::process`
+///     - **Description**: Uploads a `.ttf`/`.otf` font (multipart `file` part) for the
+///       template. The file must parse as a font; it is then used for the template's PDFs.
+///
+/// *   **`POST /images/compress`**:
+///     - **Handler**: `compress::process`
+///     - **Description**: Takes a raw PNG or JPEG file as the request body, scales it down
+///       to at most 1600 px on its longer side and re-encodes it. Returns the Base64 the
+///       editor stores for the image, as `text/plain`.
 pub fn configure_routes() -> Scope {
     scope(API_PATH)
+        .route("", get().to(list::process))
         .route("/save", post().to(save::process))
         .route("/{template_id}", get().to(get::process))
+        .route("/{template_id}", web::delete().to(delete::process))
+        .route("/pdf/preview", post().to(pdf::process_preview))
         .route("/pdf/{template_id}", get().to(pdf::process))
+        .route("/docx/{template_id}", get().to(docx::process))
+        .route("/html/{template_id}", get().to(html::process))
+        .route("/{template_id}/font", post().to(font::process))
+        .route("/{template_id}/clone", post().to(clone::process))
+        .service(
+            web::resource("/images/compress")
+                .app_data(web::PayloadConfig::new(MAX_IMAGE_BYTES as usize))
+                .route(post().to(compress::process)),
+        )
 }
@@ -8,70 +8,266 @@
 //! ## Core Features:
 //! - **Template Parsing**: It processes template text line by line, interpreting different formatting cues.
 //! - **Styled Text**: Supports Markdown-like syntax for bold (`**text**`), italic (`*text*`),
-//!   and bold-italic (`***text***`) styling.
+//!   bold-italic (`***text***`), underline (`__text__`) and strikethrough (`~~text~~`)
+//!   styling, and `{color:#1976d2}text{/color}` for colored text. An invalid color code
+//!   falls back to the default text color. `genpdf` cannot draw text decorations, so
+//!   underline and strikethrough markers are removed but the text is printed undecorated.
 //! - **Image Handling**: Embeds images referenced in the template (e.g., `[img:image_id]`).
 //!   It performs resizing to fit page constraints and converts images to a PDF-compatible format.
-//! - **Placeholder Substitution**: Decodes and inserts Base64-encoded content from placeholders
-//!   (e.g., `[ph:BASE64_DATA]`), which may themselves contain simple `<b>` and `<i>` tags for styling.
+//!   `[img:image_id|center]` or `[img:image_id|right]` aligns the image; images are
+//!   left-aligned by default. `[img:image_id|w=400]` sets the image width in CSS pixels
+//!   instead of the default 200 px box; options can be combined (`[img:image_id|center|w=400]`).
+//! - **Placeholder Substitution**: Decodes and inserts Base64-encoded content from placeholders.
+//!   Author-written `[ph:BASE64_DATA]` content may contain simple `<b>` and `<i>` tags for
+//!   styling. Column placeholders (`[ph:TITLE:BASE64]`) carry CSV data and are drawn
+//!   literally, tags included.
+//! - **Column References**: Hand-written `{{TITLE}}` references are replaced with the sample
+//!   value of the `[ph:TITLE:BASE64]` tag for the same column, so both syntaxes render the
+//!   same. `{{TITLE|fallback}}` sets the text used when the column has no value; without a
+//!   fallback an unknown column renders as an empty string. Column values are always drawn
+//!   as plain text: markers such as `**`, `- `, `|` or `[img:...]` inside a CSV cell are
+//!   not interpreted.
+//! - **Links**: `[text](url)` renders `text` in the link color followed by the URL in
+//!   parentheses, since `genpdf` cannot emit clickable links. The URL is omitted when it is
+//!   the text itself (or the address of a `mailto:` link). Only `http://`, `https://` and
+//!   `mailto:` targets are links; any other renders as plain text. With `?autolink=true`,
+//!   bare `http://` and `https://` URLs are linked too.
+//! - **Headings**: Renders lines starting with `# ` to `###### ` as bold headings, larger for
+//!   the first three levels (`heading_font_size_pt`).
 //! - **List Formatting**: Renders lines starting with `- ` as bulleted list items.
+//! - **Tables**: Renders GitHub-style pipe tables (a header row, a `---|---` separator and
+//!   data rows) as framed tables with a bold header row.
 //!
 //! ## Workflow:
 //! 1.  A `GET` request is made to `/api/templates/pdf/{template_id}`.
 //! 2.  The `process` handler is invoked.
 //! 3.  `generate_pdf_from_template_to_path` is called, which orchestrates the PDF creation.
-//! 4.  It connects to the database to fetch the template's text and associated images (as Base64).
-//! 5.  The template text is parsed. Each line is processed based on its format (image, placeholder, list, or plain text).
+//! 4.  It connects to the database to fetch the template's text, variables, and associated images
+//!     (as Base64). `[var:NAME]` tags are replaced with the variable values before parsing.
+//! 5.  The template text is parsed into `common::template_ast` nodes, the same ones the DOCX
+//!     export and the editor preview render. Each node is processed based on its kind
+//!     (image, placeholder, heading, list item, table, or plain text).
 //! 6.  Images are decoded, resized, converted to RGB PNG, and saved to temporary files.
 //! 7.  The `genpdf` `Document` is assembled with all elements (paragraphs, images, breaks).
-//! 8.  The document is rendered and saved to a file in the `./pdfs` directory.
+//! 8.  The document is rendered and saved to a file in the PDF directory (`Config::pdf_dir`).
 //! 9.  The `process` handler serves the generated file with a `Content-Disposition: inline` header,
 //!     allowing browsers to display it directly.
+//!
+//! ## Errors:
+//! When no PDF can be produced, the endpoints answer with a JSON `PdfErrorBody`
+//! (`{"error": "...", "stage": "font_load|db|render"}`) instead of plain text, so the editor
+//! can show the reason: `404 Not Found` (stage `db`) for an unknown template,
+//! `503 Service Unavailable` for other database errors, and `500 Internal Server Error` when
+//! no font can be loaded or the document cannot be rendered.
+//!
+//! ## Defensive rendering:
+//! By default, an element that fails to render (e.g. an image that decodes but cannot be
+//! resized or embedded) does not abort the document. It is replaced with a
+//! "[elemento no renderizable]" paragraph, the error is logged, and a summary is returned in
+//! the `X-PDF-Warnings` response header. Pass `?strict=true` to fail on the first such error.
+//!
+//! ## Page layout:
+//! Page size, margins and orientation are template settings: `Template::page_size` (A4 by
+//! default; `Letter`, `Legal` or `A5`), `Template::margins` (10 mm on every side by
+//! default) and `Template::orientation` (portrait by default). `?page_size=Letter` and
+//! `?orientation=Landscape` (or the other values) override the saved page size and
+//! orientation for one request. Images are scaled to fit the content width left between
+//! the margins.
+//!
+//! ## Fonts:
+//! Text uses Arial from the fonts directory (`Config::fonts_dir`, `./fonts` by default), or
+//! LiberationSans when Arial is missing. If neither can
+//! be loaded (e.g. the directory was not deployed), a copy of LiberationSans compiled into
+//! the binary is used and a warning names the directory, so PDFs always render. A template can
+//! have its own font, uploaded through `POST /api/templates/{template_id}/font` (`font.rs`);
+//! it is used for every style and falls back to the defaults if it cannot be loaded.
+//!
+//! ## Preview of unsaved content:
+//! `POST /api/templates/pdf/preview` (handled by `process_preview`) takes a `Template` as JSON
+//! instead of reading it from the database, and returns the rendered PDF bytes directly. It
+//! lets the editor show a PDF of unsaved changes without persisting them. Both endpoints share
+//! `render_template_pdf`, so the output is identical.
+//!
+//! `render_to_bytes` and `pdf_bytes_response` are also used by the stateless
+//! `POST /api/render/markdown` endpoint (`services::render`).
 
+use super::get::{load_font_path, load_margins, load_orientation, load_page_size, load_vars};
+use super::layout::{image_scale, IMAGE_DPI};
+use crate::config::Config;
+use crate::db::DbPool;
 use actix_files::NamedFile;
-use actix_web::http::header::{ContentDisposition, DispositionParam, DispositionType};
+use actix_web::http::header::{
+    ContentDisposition, DispositionParam, DispositionType, HeaderName, HeaderValue,
+};
+use actix_web::http::StatusCode;
 use actix_web::mime;
-use actix_web::{web, Error as ActixError, HttpRequest, Responder};
+use actix_web::{web, Error as ActixError, HttpRequest, HttpResponse, Responder};
 use base64::engine::general_purpose::STANDARD as BASE64;
 use base64::Engine;
-use genpdf::elements::{Break, Image as PdfImage, Paragraph};
-use genpdf::style::{Style, StyledString};
-use genpdf::Document;
+use common::model::image::Image;
+use common::model::pdf::{Orientation, PageMargins, PageSize, PdfErrorBody, PdfErrorStage};
+use common::model::template::Template;
+use common::model::template_var::{substitute_vars, TemplateVar};
+use common::requests::PdfQuery;
+use common::template_ast::{
+    heading_font_size_pt, parse_template, ImageAlignment, ImageTag, Inline, LocatedNode, Span,
+    TemplateNode, TextStyle,
+};
+use genpdf::elements::{Break, FrameCellDecorator, Image as PdfImage, Paragraph, TableLayout};
+use genpdf::style::{Color, Style, StyledString};
+use genpdf::{Alignment, Document, Element as _, Margins, Size};
 use image::imageops::FilterType;
 use image::{load_from_memory, DynamicImage, GenericImageView};
 use png::{BitDepth as PngBitDepth, ColorType as PngColorType, Encoder as PngEncoder};
-use rusqlite::Connection;
+use rusqlite::{Connection, OptionalExtension};
 use std::collections::HashMap;
 use std::error::Error;
+use std::fmt;
 use std::fs;
+use std::io::Write;
 use std::path::{Path, PathBuf};
 use tempfile::NamedTempFile;
 
 // --- Constants ---
 
-/// The width of the PDF page in inches (standard US Letter size).
-const PAGE_WIDTH_INCH: f64 = 8.5;
-/// The margin for the PDF page in millimeters.
-const MARGIN_MM: f64 = 10.0;
-/// The DPI (dots per inch) used for scaling images within the PDF to ensure print quality.
-const IMAGE_DPI: f64 = 150.0;
-
-/// Represents the text style for a segment of text within a paragraph.
-enum TextStyle {
-    /// Standard, unstyled text.
-    Regular,
-    /// Bold text.
-    Bold,
-    /// Italic text.
-    Italic,
-    /// Bold and italic text.
-    BoldItalic,
+/// Millimeters per inch.
+const MM_PER_INCH: f64 = 25.4;
+/// Text that replaces an element that failed to render in defensive mode.
+const UNRENDERABLE_ELEMENT: &str = "[elemento no renderizable]";
+/// Response header listing the elements that were replaced in defensive mode.
+pub(super) const WARNINGS_HEADER: &str = "x-pdf-warnings";
+/// Color of link text, the accent color of the editor.
+const LINK_COLOR: Color = Color::Rgb(25, 118, 210);
+/// LiberationSans compiled into the binary, used when the fonts directory has no usable font.
+const BUNDLED_FONT_REGULAR: &[u8] = include_bytes!(concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/fonts/LiberationSans-Regular.ttf"
+));
+const BUNDLED_FONT_BOLD: &[u8] = include_bytes!(concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/fonts/LiberationSans-Bold.ttf"
+));
+const BUNDLED_FONT_ITALIC: &[u8] = include_bytes!(concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/fonts/LiberationSans-Italic.ttf"
+));
+const BUNDLED_FONT_BOLD_ITALIC: &[u8] = include_bytes!(concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/fonts/LiberationSans-BoldItalic.ttf"
+));
+
+/// Options that control how a document is rendered.
+#[derive(Clone, Copy, Default)]
+pub(crate) struct RenderOptions<'a> {
+    /// When `true`, abort on the first element that fails to render.
+    pub(crate) strict: bool,
+    /// Paper size of the document. Also bounds the width of embedded images.
+    pub(crate) page_size: PageSize,
+    /// Page margins. The horizontal ones also bound the width of embedded images.
+    pub(crate) margins: PageMargins,
+    /// Page orientation. Landscape swaps the width and height of `page_size`.
+    pub(crate) orientation: Orientation,
+    /// Path of the template's custom font, if it has one.
+    pub(crate) font_path: Option<&'a str>,
+    /// Directory the default fonts (Arial, LiberationSans) are loaded from
+    /// (`Config::fonts_dir`). With `None`, the bundled LiberationSans is used.
+    pub(crate) fonts_dir: Option<&'a Path>,
+    /// When `true`, bare `http://` and `https://` URLs are rendered as links.
+    pub(crate) autolink: bool,
+}
+
+impl RenderOptions<'_> {
+    /// Returns the `(width, height)` of the page in millimeters, taking the orientation
+    /// into account.
+    pub(super) fn page_dimensions_mm(&self) -> (f64, f64) {
+        let (width, height) = self.page_size.dimensions_mm();
+        match self.orientation {
+            Orientation::Portrait => (width, height),
+            Orientation::Landscape => (height, width),
+        }
+    }
+
+    /// Returns the width available for content between the left and right margins, in
+    /// inches.
+    pub(super) fn content_width_in(&self) -> f64 {
+        let (page_width_mm, _) = self.page_dimensions_mm();
+        (page_width_mm - self.margins.left - self.margins.right) / MM_PER_INCH
+    }
+
+    /// Checks that the margins are valid and leave room for content on the page.
+    pub(super) fn validate(&self) -> Result<(), String> {
+        self.margins.validate()?;
+        let (width_mm, height_mm) = self.page_dimensions_mm();
+        if self.margins.left + self.margins.right >= width_mm
+            || self.margins.top + self.margins.bottom >= height_mm
+        {
+            return Err(format!(
+                "Margins leave no room for content on a {:?} {:?} page",
+                self.page_size, self.orientation
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// A failure to produce a PDF, with the stage it happened in and the status to answer with.
+#[derive(Debug)]
+pub(crate) struct PdfError {
+    status: StatusCode,
+    stage: PdfErrorStage,
+    message: String,
+}
+
+impl PdfError {
+    /// The template does not exist.
+    pub(super) fn template_not_found() -> Self {
+        PdfError {
+            status: StatusCode::NOT_FOUND,
+            stage: PdfErrorStage::Db,
+            message: "Template not found".to_string(),
+        }
+    }
+
+    /// Reading the template from the database failed.
+    pub(super) fn db(e: impl fmt::Display) -> Self {
+        PdfError {
+            status: StatusCode::SERVICE_UNAVAILABLE,
+            stage: PdfErrorStage::Db,
+            message: format!("Database error: {}", e),
+        }
+    }
+
+    /// No font could be loaded.
+    fn font_load(e: impl fmt::Display) -> Self {
+        PdfError {
+            status: StatusCode::INTERNAL_SERVER_ERROR,
+            stage: PdfErrorStage::FontLoad,
+            message: format!("Could not load a font: {}", e),
+        }
+    }
+
+    /// Laying out or writing the document failed.
+    pub(crate) fn render(e: impl fmt::Display) -> Self {
+        PdfError {
+            status: StatusCode::INTERNAL_SERVER_ERROR,
+            stage: PdfErrorStage::Render,
+            message: format!("PDF generation failed: {}", e),
+        }
+    }
+
+    /// Builds the error response, with a JSON `PdfErrorBody`.
+    pub(crate) fn response(&self) -> HttpResponse {
+        HttpResponse::build(self.status).json(PdfErrorBody {
+            error: self.message.clone(),
+            stage: self.stage,
+        })
+    }
 }
 
-/// Represents a segment of text with a specific style.
-/// This is used to construct paragraphs with mixed styling (e.g., "This is **bold** text.").
-struct TextSegment {
-    text: String,
-    style: TextStyle,
+impl fmt::Display for PdfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
 }
 
 /// Actix web handler for `GET /api/templates/pdf/{template_id}`.
@@ -80,26 +276,48 @@ struct TextSegment {
 ///
 /// # Arguments
 /// * `template_id` - The ID of the template to use, extracted from the URL path.
+/// * `query` - Rendering options (`strict`, `page_size`, `orientation`, `autolink`).
 /// * `req` - The incoming `HttpRequest`, used to build the response.
+/// * `pool` - The shared SQLite connection pool.
+/// * `config` - The application paths: where the PDF is written and the fonts directory.
 ///
 /// # Returns
-/// A `Result` containing an `impl Responder` (the PDF file response) on success,
-/// or an `ActixError` on failure (e.g., PDF generation error or file not found).
+/// The PDF file response on success. Elements replaced during defensive rendering are
+/// listed in the `X-PDF-Warnings` header. If no PDF can be produced, a `PdfError` response
+/// with a JSON body (see the module docs). An `ActixError` only if the generated file
+/// cannot be opened.
 pub async fn process(
     template_id: web::Path<String>,
+    query: web::Query<PdfQuery>,
     req: HttpRequest,
-) -> Result<impl Responder, ActixError> {
+    pool: web::Data<DbPool>,
+    config: web::Data<Config>,
+) -> Result<HttpResponse, ActixError> {
     let id = template_id.into_inner();
     let filename = format!("{}.pdf", id);
-    let file_path = Path::new("./pdfs").join(&filename);
+    let file_path = config.pdf_path(&id);
 
     // Generate the PDF file and save it to the designated path.
-    if let Err(e) = generate_pdf_from_template_to_path(&id, &file_path) {
-        return Err(actix_web::error::ErrorServiceUnavailable(format!(
-            "PDF generation failed: {}",
-            e
-        )));
-    }
+    let options = RenderOptions {
+        strict: query.strict,
+        autolink: query.autolink,
+        fonts_dir: Some(&config.fonts_dir),
+        ..RenderOptions::default()
+    };
+    let warnings = match generate_pdf_from_template_to_path(
+        &pool,
+        &id,
+        &file_path,
+        &options,
+        query.orientation,
+        query.page_size,
+    ) {
+        Ok(warnings) => warnings,
+        Err(e) => {
+            log::error!("PDF generation failed for template {}: {}", id, e);
+            return Ok(e.response());
+        }
+    };
 
     // Serve the generated PDF file.
     if file_path.exists() {
@@ -110,214 +328,433 @@ pub async fn process(
                 disposition: DispositionType::Inline, // Suggests the browser should display the file.
                 parameters: vec![DispositionParam::Filename(filename)],
             });
-        Ok(named_file.into_response(&req))
+        let mut response = named_file.into_response(&req);
+        if let Some(value) = warnings_header_value(&warnings) {
+            response
+                .headers_mut()
+                .insert(HeaderName::from_static(WARNINGS_HEADER), value);
+        }
+        Ok(response)
     } else {
-        Err(actix_web::error::ErrorNotFound("File not found"))
+        Ok(PdfError::render("the generated file was not found").response())
     }
 }
 
 /// Generates a PDF from a template and saves it to the specified output path.
 ///
-/// This is the main orchestration function. It connects to the database, fetches template
-/// content, parses it line by line, and uses `genpdf` to build and render the document.
+/// This is the main orchestration function. It takes a connection from the pool, fetches
+/// template content, parses it line by line, and uses `genpdf` to build and render the document.
+/// The document is rendered into a temporary file that then atomically replaces
+/// `output_path`, so concurrent generations of the same file never expose a partial PDF.
 ///
 /// # Arguments
+/// * `pool` - The shared SQLite connection pool.
 /// * `template_id` - The ID of the template to retrieve from the database.
 /// * `output_path` - The file system path where the generated PDF will be saved.
+/// * `options` - Rendering options (strict mode, autolink). Its page size, margins, orientation
+///   and font are replaced with the ones saved for the template.
+/// * `orientation` - Orientation to render with, or `None` to use the template's saved one.
+/// * `page_size` - Page size to render with, or `None` to use the template's saved one.
 ///
 /// # Returns
-/// An empty `Result` on success, or a `Box<dyn Error>` on failure.
-pub fn generate_pdf_from_template_to_path(
+/// The warnings for elements replaced during rendering on success, or a `PdfError` naming
+/// the failed stage.
+pub(crate) fn generate_pdf_from_template_to_path(
+    pool: &DbPool,
     template_id: &str,
     output_path: &Path,
-) -> Result<(), Box<dyn Error>> {
-    let conn = Connection::open("templify.sqlite")?;
-
-    let mut stmt = conn.prepare("SELECT text FROM templates WHERE id = ?1")?;
-    let template_text: String = stmt.query_row([template_id], |row| row.get(0))?;
-
-    let images_map = load_images(&conn, template_id)?;
+    options: &RenderOptions,
+    orientation: Option<Orientation>,
+    page_size: Option<PageSize>,
+) -> Result<Vec<String>, PdfError> {
+    let conn = pool.get().map_err(PdfError::db)?;
 
-    let mut doc = configure_document()?;
-    let mut temp_files: Vec<NamedTempFile> = Vec::new(); // Holds temp files for images to ensure they live long enough.
+    let template_text: String = conn
+        .query_row(
+            "SELECT text FROM templates WHERE id = ?1",
+            [template_id],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(PdfError::db)?
+        .ok_or_else(PdfError::template_not_found)?;
+    let vars = load_vars(&conn, template_id).map_err(PdfError::db)?;
+    let template_text = substitute_vars(&template_text, &vars);
+    let font_path = load_font_path(&conn, template_id).map_err(PdfError::db)?;
+    let options = RenderOptions {
+        margins: load_margins(&conn, template_id)
+            .map_err(PdfError::db)?
+            .unwrap_or_default(),
+        orientation: match orientation {
+            Some(orientation) => orientation,
+            None => load_orientation(&conn, template_id)
+                .map_err(PdfError::db)?
+                .unwrap_or_default(),
+        },
+        page_size: match page_size {
+            Some(page_size) => page_size,
+            None => load_page_size(&conn, template_id)
+                .map_err(PdfError::db)?
+                .unwrap_or_default(),
+        },
+        font_path: font_path.as_deref(),
+        ..*options
+    };
 
-    // Process the template content line by line.
-    for raw_line in template_text.lines() {
-        let line = raw_line.trim();
-        if line.is_empty() {
-            doc.push(Break::new(1)); // Add vertical space for empty lines.
-            continue;
-        }
+    let images_map = load_images(&conn, template_id).map_err(PdfError::db)?;
 
-        if line.starts_with("- ") {
-            handle_list_item(&mut doc, &line[2..]);
-            continue;
-        }
+    // Ensure the output directory exists.
+    let parent = match output_path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    fs::create_dir_all(parent).map_err(PdfError::render)?;
 
-        if line.starts_with("[img:") && line.ends_with(']') {
-            handle_image_line(line, &images_map, &mut temp_files, &mut doc)?;
-            continue;
-        }
+    // Render into a temporary file next to the output and move it into place once complete.
+    // Concurrent requests for the same template each write their own file, and the rename
+    // replaces the output atomically, so a reader never sees a half-written PDF.
+    let mut tmp = NamedTempFile::new_in(parent).map_err(PdfError::render)?;
+    let warnings = render_template_pdf(&template_text, &images_map, tmp.as_file_mut(), &options)?;
+    tmp.persist(output_path).map_err(PdfError::render)?;
+    Ok(warnings)
+}
 
-        if line.starts_with("[ph:") && line.ends_with(']') {
-            handle_placeholder_line(line, &mut doc);
-            continue;
-        }
+/// Actix web handler for `POST /api/templates/pdf/preview`.
+///
+/// Renders the `Template` sent in the request body (typically with unsaved edits) without
+/// saving it, and returns the PDF bytes for inline display. Only the custom font uploaded
+/// for the template, if any, is read from the database.
+///
+/// # Returns
+/// - `200 OK` with an `application/pdf` body on success.
+/// - A `PdfError` response with a JSON body if rendering fails.
+pub async fn process_preview(
+    template: web::Json<Template>,
+    pool: web::Data<DbPool>,
+    config: web::Data<Config>,
+) -> impl Responder {
+    let template = template.into_inner();
+    let result = web::block(move || {
+        let font_path = saved_font_path(&pool, &template.id);
+        render_to_bytes(
+            &template.text,
+            template.images.unwrap_or_default(),
+            template.vars.as_deref().unwrap_or_default(),
+            &RenderOptions {
+                margins: template.margins.unwrap_or_default(),
+                orientation: template.orientation.unwrap_or_default(),
+                page_size: template.page_size.unwrap_or_default(),
+                font_path: font_path.as_deref(),
+                fonts_dir: Some(&config.fonts_dir),
+                ..RenderOptions::default()
+            },
+        )
+    })
+    .await;
 
-        // If no other format matches, treat it as a normal paragraph.
-        handle_normal_line(line, &mut doc);
+    match result {
+        Ok(Ok((bytes, warnings))) => pdf_bytes_response(bytes, &warnings, "preview.pdf"),
+        Ok(Err(e)) => e.response(),
+        Err(e) => PdfError::render(e).response(),
     }
+}
 
-    // Ensure the output directory exists.
-    if let Some(parent) = output_path.parent() {
-        fs::create_dir_all(parent)?;
+/// Looks up the custom font of a saved template for `process_preview`.
+///
+/// Unsaved templates (empty `template_id`) have none. Database errors are logged and
+/// treated as "no custom font", so the preview still renders with the default one.
+fn saved_font_path(pool: &DbPool, template_id: &str) -> Option<String> {
+    if template_id.is_empty() {
+        return None;
     }
-
-    // Render the document to the output file.
-    let mut out_file = fs::File::create(output_path)?;
-    doc.render(&mut out_file)?;
-
-    Ok(())
+    let result: Result<_, Box<dyn Error>> = pool
+        .get()
+        .map_err(Into::into)
+        .and_then(|conn| load_font_path(&conn, template_id).map_err(Into::into));
+    result.unwrap_or_else(|e| {
+        log::warn!("Could not read the font of template {}: {}", template_id, e);
+        None
+    })
 }
 
-/// Pushes a slice of `TextSegment`s into a `genpdf::Paragraph`.
+/// Renders in-memory content (not read from the database) into PDF bytes.
 ///
-/// This function iterates through styled text segments and adds them to a `genpdf`
-/// paragraph, applying the correct bold/italic styling for each part.
+/// Images whose Base64 data cannot be decoded are skipped, as if they were missing.
+/// `[var:NAME]` tags are substituted before rendering. This is blocking work; callers in
+/// async handlers should run it through `web::block`.
 ///
-/// # Arguments
-/// * `p` - The `Paragraph` to which the styled text will be added.
-/// * `segments` - A slice of `TextSegment`s to add.
-fn push_segments_into_paragraph(p: &mut Paragraph, segments: &[TextSegment]) {
-    for seg in segments {
-        let styled = match seg.style {
-            TextStyle::Regular => StyledString::new(seg.text.clone(), Style::new()),
-            TextStyle::Bold => StyledString::new(seg.text.clone(), Style::new().bold()),
-            TextStyle::Italic => StyledString::new(seg.text.clone(), Style::new().italic()),
-            TextStyle::BoldItalic => {
-                StyledString::new(seg.text.clone(), Style::new().bold().italic())
-            }
-        };
-        p.push(styled);
+/// # Returns
+/// The PDF bytes and the rendering warnings on success, or a `PdfError` on failure.
+pub(crate) fn render_to_bytes(
+    text: &str,
+    images: Vec<Image>,
+    vars: &[TemplateVar],
+    options: &RenderOptions,
+) -> Result<(Vec<u8>, Vec<String>), PdfError> {
+    let images_map: HashMap<String, Vec<u8>> = images
+        .into_iter()
+        .filter_map(|img| BASE64.decode(img.base64).ok().map(|bytes| (img.id, bytes)))
+        .collect();
+    let text = substitute_vars(text, vars);
+    let mut buffer = Vec::new();
+    let warnings = render_template_pdf(&text, &images_map, &mut buffer, options)?;
+    Ok((buffer, warnings))
+}
+
+/// Builds a `200 OK` response serving `bytes` as an inline PDF named `filename`, with the
+/// `X-PDF-Warnings` header set when there are warnings.
+pub(crate) fn pdf_bytes_response(bytes: Vec<u8>, warnings: &[String], filename: &str) -> HttpResponse {
+    let mut response = HttpResponse::Ok();
+    response
+        .content_type(mime::APPLICATION_PDF)
+        .insert_header(ContentDisposition {
+            disposition: DispositionType::Inline,
+            parameters: vec![DispositionParam::Filename(filename.to_string())],
+        });
+    if let Some(value) = warnings_header_value(warnings) {
+        response.insert_header((HeaderName::from_static(WARNINGS_HEADER), value));
     }
+    response.body(bytes)
 }
 
-/// Parses a line of text for Markdown-like styling (`*`, `**`, `***`) and returns a vector of `TextSegment`s.
+/// Renders template text into a PDF and writes it to `out`.
+///
+/// This is the rendering core shared by the saved-template and preview endpoints: it
+/// parses the text with `common::template_ast::parse_template` and builds the `genpdf` document.
+///
+/// Unless `options.strict` is set, an element that fails to render is replaced by an
+/// `UNRENDERABLE_ELEMENT` paragraph instead of aborting the document; each replacement is
+/// logged and returned as a warning naming the template line.
 ///
 /// # Arguments
-/// * `line` - The string slice to parse.
+/// * `template_text` - The raw template text.
+/// * `images_map` - Decoded image bytes keyed by image ID, for `[img:...]` lines.
+/// * `out` - Destination of the rendered PDF.
+/// * `options` - Rendering options. With `strict`, the first element error is returned as
+///   the overall error; `page_size`, `orientation` and `margins` set the page layout and
+///   the maximum image width; `autolink` turns bare URLs into links.
 ///
 /// # Returns
-/// A `Vec<TextSegment>` representing the parsed line with styles.
-fn parse_styles(line: &str) -> Vec<TextSegment> {
-    let mut segments = Vec::new();
-    let chars: Vec<char> = line.chars().collect();
-    let mut i: usize = 0;
-
-    while i < chars.len() {
-        // Check for BoldItalic `***...***` first, as it's the longest token.
-        if i + 5 < chars.len() && chars[i..i + 3] == ['*', '*', '*'] {
-            if let Some(end_pos) = line[i + 3..].find("***") {
-                let text = line[i + 3..i + 3 + end_pos].to_string();
-                segments.push(TextSegment {
-                    text,
-                    style: TextStyle::BoldItalic,
-                });
-                i += 3 + end_pos + 3;
+/// The list of warnings (empty if every element rendered) on success, or a `PdfError`
+/// with the `font_load` or `render` stage on failure.
+fn render_template_pdf(
+    template_text: &str,
+    images_map: &HashMap<String, Vec<u8>>,
+    out: &mut impl Write,
+    options: &RenderOptions,
+) -> Result<Vec<String>, PdfError> {
+    options.validate().map_err(PdfError::render)?;
+    let mut doc = configure_document(options).map_err(PdfError::font_load)?;
+    let mut temp_files: Vec<NamedTempFile> = Vec::new(); // Holds temp files for images to ensure they live long enough.
+
+    let mut warnings: Vec<String> = Vec::new();
+
+    // Render the parsed template block by block.
+    for LocatedNode { line, node } in parse_template(template_text, options.autolink) {
+        // The element that can fail, named for the warning, and its result.
+        let (element, result) = match node {
+            TemplateNode::LineBreak => {
+                doc.push(Break::new(1)); // Add vertical space for empty lines.
                 continue;
             }
-        }
-
-        // Check for Bold `**...**`.
-        if i + 3 < chars.len() && chars[i..i + 2] == ['*', '*'] {
-            if let Some(end_pos) = line[i + 2..].find("**") {
-                let text = line[i + 2..i + 2 + end_pos].to_string();
-                segments.push(TextSegment {
-                    text,
-                    style: TextStyle::Bold,
-                });
-                i += 2 + end_pos + 2;
+            TemplateNode::Table { header, rows } => {
+                ("table".to_string(), handle_table(&header, &rows, &mut doc))
+            }
+            TemplateNode::Heading { level, content } => {
+                handle_heading(level, &content, &mut doc);
                 continue;
             }
-        }
-
-        // Check for Italic `*...*`.
-        if i + 1 < chars.len() && chars[i] == '*' {
-            if let Some(end_pos) = line[i + 1..].find('*') {
-                let text = line[i + 1..i + 1 + end_pos].to_string();
-                segments.push(TextSegment {
-                    text,
-                    style: TextStyle::Italic,
-                });
-                i += 1 + end_pos + 1;
+            TemplateNode::ListItem(inlines) => {
+                handle_list_item(&mut doc, &inlines);
                 continue;
             }
+            TemplateNode::Image { source, tag } => {
+                let result = handle_image(&tag, images_map, options, &mut temp_files, &mut doc);
+                (source, result)
+            }
+            TemplateNode::Placeholder(lines) => {
+                handle_placeholder(lines.as_deref(), &mut doc);
+                continue;
+            }
+            TemplateNode::Paragraph(inlines) => {
+                handle_normal_line(&inlines, &mut doc);
+                continue;
+            }
+        };
+        if let Err(e) = result {
+            if options.strict {
+                return Err(PdfError::render(e));
+            }
+            let warning = format!("line {}: {}: {}", line + 1, element, e);
+            log::warn!("PDF element replaced: {}", warning);
+            warnings.push(warning);
+            doc.push(Paragraph::new(UNRENDERABLE_ELEMENT));
         }
-
-        // Find the next segment of plain text (up to the next '*').
-        let mut j = i;
-        while j < chars.len() && chars[j] != '*' {
-            j += 1;
-        }
-        let text: String = chars[i..j].iter().collect();
-        if !text.is_empty() {
-            segments.push(TextSegment {
-                text,
-                style: TextStyle::Regular,
-            });
-        }
-        i = j;
     }
 
-    segments
+    doc.render(out).map_err(PdfError::render)?;
+
+    Ok(warnings)
 }
 
-/// Decodes a Base64 string from a placeholder tag.
+/// Builds the `X-PDF-Warnings` header value from the rendering warnings.
 ///
-/// The placeholder format is expected to be `[ph:BASE64_STRING]`.
-/// This function extracts and decodes the `BASE64_STRING`.
-///
-/// # Arguments
-/// * `ph` - The inner content of the placeholder tag (e.g., `ph:BASE64_STRING`).
+/// Warnings are joined with `; ` and any character that is not printable ASCII is replaced
+/// with `?`, since header values must be visible ASCII.
 ///
 /// # Returns
-/// An `Option<String>` containing the decoded text, or `None` if decoding fails.
-fn decode_placeholder(ph: &str) -> Option<String> {
-    let parts: Vec<&str> = ph.split(':').collect();
-    parts.last().and_then(|last| {
-        BASE64
-            .decode(last)
-            .ok()
-            .and_then(|b| String::from_utf8(b).ok())
-    })
+/// `None` if there are no warnings.
+pub(super) fn warnings_header_value(warnings: &[String]) -> Option<HeaderValue> {
+    if warnings.is_empty() {
+        return None;
+    }
+    let joined: String = warnings
+        .join("; ")
+        .chars()
+        .map(|c| if c == ' ' || c.is_ascii_graphic() { c } else { '?' })
+        .collect();
+    HeaderValue::from_str(&joined).ok()
+}
+
+/// Pushes inline content into a `genpdf::Paragraph`.
+///
+/// Each span gets its bold/italic styling and color. Links are drawn in `LINK_COLOR`;
+/// `genpdf` cannot make them clickable, so the URL follows in parentheses, unless it is the
+/// link text itself. For `mailto:` links the bare address is what gets compared and shown.
+///
+/// # Arguments
+/// * `p` - The `Paragraph` to which the styled text will be added.
+/// * `inlines` - The inline content to add.
+/// * `bold` - Whether all of it is made bold, as in a table header (see `TextStyle::bolded`).
+fn push_inlines_into_paragraph(p: &mut Paragraph, inlines: &[Inline], bold: bool) {
+    let push = |p: &mut Paragraph, text: &str, style: TextStyle, color: Option<Color>| {
+        let style = if bold { style.bolded() } else { style };
+        let mut pdf_style = pdf_style(style);
+        if let Some(color) = color {
+            pdf_style.set_color(color);
+        }
+        p.push(StyledString::new(text.to_string(), pdf_style));
+    };
+    for inline in inlines {
+        match inline {
+            Inline::Text(span) => {
+                let color = span.color.map(|(r, g, b)| Color::Rgb(r, g, b));
+                push(p, &span.text, span.style, color);
+            }
+            Inline::Link { label, url, spans } => {
+                for span in spans {
+                    push(p, &span.text, span.style, Some(LINK_COLOR));
+                }
+                let shown = if url.to_ascii_lowercase().starts_with("mailto:") {
+                    &url["mailto:".len()..]
+                } else {
+                    url
+                };
+                if label.trim() != shown {
+                    push(p, &format!(" ({})", shown), TextStyle::Regular, None);
+                }
+            }
+        }
+    }
+}
+
+/// Returns the `genpdf` style of a `TextStyle`. `genpdf` has no text decorations, so
+/// underlined and struck-through text is drawn as regular text.
+fn pdf_style(style: TextStyle) -> Style {
+    match style {
+        TextStyle::Regular | TextStyle::Underline | TextStyle::Strikethrough => Style::new(),
+        TextStyle::Bold => Style::new().bold(),
+        TextStyle::Italic => Style::new().italic(),
+        TextStyle::BoldItalic => Style::new().bold().italic(),
+    }
 }
 
 /// Loads the font family for the PDF document.
 ///
-/// Tries to load "Arial", falling back to "LiberationSans" if not found.
+/// Prefers the template's custom font, used for every style since only one file is
+/// uploaded. Without one, or if it fails to load, tries "Arial" from `fonts_dir`, then
+/// "LiberationSans", and finally the LiberationSans bundled in the binary, logging a
+/// warning that names the directory.
+///
+/// # Arguments
+/// * `font_path` - Path of the template's custom font, if it has one.
+/// * `fonts_dir` - Directory of the default fonts, or `None` to use the bundled font.
 ///
 /// # Returns
 /// A `Result` containing the `FontFamily` or a `Box<dyn Error>` on failure.
-fn load_font() -> Result<genpdf::fonts::FontFamily<genpdf::fonts::FontData>, Box<dyn Error>> {
+fn load_font(
+    font_path: Option<&str>,
+    fonts_dir: Option<&Path>,
+) -> Result<genpdf::fonts::FontFamily<genpdf::fonts::FontData>, Box<dyn Error>> {
+    if let Some(path) = font_path {
+        match fs::read(path)
+            .map_err(Box::<dyn Error>::from)
+            .and_then(|bytes| genpdf::fonts::FontData::new(bytes, None).map_err(Into::into))
+        {
+            Ok(font) => {
+                return Ok(genpdf::fonts::FontFamily {
+                    regular: font.clone(),
+                    bold: font.clone(),
+                    italic: font.clone(),
+                    bold_italic: font,
+                })
+            }
+            Err(e) => log::warn!("Could not load template font {}: {}", path, e),
+        }
+    }
+
+    let Some(fonts_dir) = fonts_dir else {
+        return bundled_font_family().map_err(Into::into);
+    };
     // Attempt to load Arial first, as it's a common and preferred font.
-    if let Ok(family) = genpdf::fonts::from_files("./fonts", "Arial", None) {
+    if let Ok(family) = genpdf::fonts::from_files(fonts_dir, "Arial", None) {
         return Ok(family);
     }
     // Fall back to LiberationSans, a common open-source alternative.
-    genpdf::fonts::from_files("./fonts", "LiberationSans", None).map_err(Into::into)
+    match genpdf::fonts::from_files(fonts_dir, "LiberationSans", None) {
+        Ok(family) => Ok(family),
+        Err(e) => {
+            if fonts_dir.is_dir() {
+                log::warn!(
+                    "No usable Arial or LiberationSans font in {} ({}); using the bundled LiberationSans",
+                    fonts_dir.display(),
+                    e
+                );
+            } else {
+                log::warn!(
+                    "Fonts directory {} not found; using the bundled LiberationSans",
+                    fonts_dir.display()
+                );
+            }
+            bundled_font_family().map_err(Into::into)
+        }
+    }
 }
 
-/// Parses text containing `<b>` and `<i>` tags and adds it to the document, preserving line breaks.
-///
-/// This is used for content from placeholders, which may contain simple HTML-like tags.
+/// Loads the LiberationSans family compiled into the binary.
+fn bundled_font_family(
+) -> Result<genpdf::fonts::FontFamily<genpdf::fonts::FontData>, genpdf::error::Error> {
+    let load = |bytes: &[u8]| genpdf::fonts::FontData::new(bytes.to_vec(), None);
+    Ok(genpdf::fonts::FontFamily {
+        regular: load(BUNDLED_FONT_REGULAR)?,
+        bold: load(BUNDLED_FONT_BOLD)?,
+        italic: load(BUNDLED_FONT_ITALIC)?,
+        bold_italic: load(BUNDLED_FONT_BOLD_ITALIC)?,
+    })
+}
+
+/// Adds the lines of a placeholder's content to the document, one paragraph per line,
+/// separated by line breaks.
 ///
 /// # Arguments
 /// * `doc` - The `Document` to which the text will be added.
-/// * `text` - The text to parse and add.
-fn push_styled_text_with_breaks_to_doc(doc: &mut Document, text: &str) {
-    let lines: Vec<&str> = text.split('\n').collect();
-    for (i, line) in lines.iter().enumerate() {
-        doc.push(parse_styled_paragraph(line));
+/// * `lines` - The spans of each line.
+fn push_lines_with_breaks_to_doc(doc: &mut Document, lines: &[Vec<Span>]) {
+    for (i, spans) in lines.iter().enumerate() {
+        let inlines: Vec<Inline> = spans.iter().cloned().map(Inline::Text).collect();
+        let mut p = Paragraph::new("");
+        push_inlines_into_paragraph(&mut p, &inlines, false);
+        doc.push(p);
         // Add a line break after each line except the last one.
         if i < lines.len() - 1 {
             doc.push(Break::new(1));
@@ -336,7 +773,7 @@ fn push_styled_text_with_breaks_to_doc(doc: &mut Document, text: &str) {
 /// # Returns
 /// A `Result` containing a `HashMap` mapping image IDs to their raw byte data,
 /// or a `Box<dyn Error>` on failure.
-fn load_images(
+pub(super) fn load_images(
     conn: &Connection,
     template_id: &str,
 ) -> Result<HashMap<String, Vec<u8>>, Box<dyn Error>> {
@@ -355,84 +792,93 @@ fn load_images(
 
 /// Creates and configures a new `genpdf::Document` with default settings.
 ///
-/// Sets the font, title, font size, line spacing, and page margins.
+/// Sets the font, title, paper size, font size, line spacing, and page margins.
+///
+/// # Arguments
+/// * `options` - Rendering options providing the paper size, orientation, margins and font.
 ///
 /// # Returns
 /// A `Result` containing the configured `Document` or a `Box<dyn Error>` on failure.
-fn configure_document() -> Result<Document, Box<dyn Error>> {
-    let font_family = load_font()?;
+fn configure_document(options: &RenderOptions) -> Result<Document, Box<dyn Error>> {
+    let font_family = load_font(options.font_path, options.fonts_dir)?;
     let mut doc = Document::new(font_family);
     doc.set_title("Output from template");
 
+    let (width_mm, height_mm) = options.page_dimensions_mm();
+    doc.set_paper_size(Size::new(width_mm as f32, height_mm as f32));
+
     let font_size_pt: u8 = 11;
     doc.set_font_size(font_size_pt);
 
     doc.set_line_spacing(1.25);
 
     let mut decorator = genpdf::SimplePageDecorator::new();
-    decorator.set_margins(MARGIN_MM);
+    let margins = options.margins;
+    decorator.set_margins(Margins::trbl(
+        margins.top as f32,
+        margins.right as f32,
+        margins.bottom as f32,
+        margins.left as f32,
+    ));
     doc.set_page_decorator(decorator);
     Ok(doc)
 }
 
-/// Handles a line representing a list item (e.g., "- Item text").
+/// Handles a heading (e.g., "# Title"): a bold paragraph at the level's font size
+/// (`heading_font_size_pt`).
+///
+/// # Arguments
+/// * `level` - The heading level, 1 to 6.
+/// * `inlines` - The content of the heading (without the `#` markers).
+/// * `doc` - The `Document` to which the heading will be added.
+fn handle_heading(level: u8, inlines: &[Inline], doc: &mut Document) {
+    let mut p = Paragraph::new("");
+    push_inlines_into_paragraph(&mut p, inlines, true);
+    doc.push(p.styled(Style::new().with_font_size(heading_font_size_pt(level))));
+}
+
+/// Handles a list item (e.g., "- Item text").
 ///
 /// It adds a bullet point and the item text (with styling) to the document.
 ///
 /// # Arguments
 /// * `doc` - The `Document` to which the list item will be added.
-/// * `item_text` - The text of the list item (without the "- " prefix).
-fn handle_list_item(doc: &mut Document, item_text: &str) {
-    let segments = parse_styles(item_text);
+/// * `inlines` - The content of the list item (without the "- " prefix).
+fn handle_list_item(doc: &mut Document, inlines: &[Inline]) {
     let mut p = Paragraph::new("");
     p.push("• "); // Add a bullet point prefix.
-    push_segments_into_paragraph(&mut p, &segments);
+    push_inlines_into_paragraph(&mut p, inlines, false);
     doc.push(p);
 }
 
-/// Handles a line representing an image tag (e.g., `[img:image_id]`).
+/// Handles an image tag (e.g., `[img:image_id]` or `[img:image_id|center|w=400]`).
 ///
-/// This function retrieves the image data, resizes it to fit page width and
-/// CSS-like constraints, converts it to a compatible format (RGB PNG), saves it
-/// to a temporary file, and adds it to the PDF document.
+/// This function retrieves the image data, resizes it to the layout size given by
+/// `layout::image_scale`, converts it to a compatible format (RGB PNG), saves it to a
+/// temporary file, and adds it to the PDF document with the tag's alignment.
 ///
 /// # Arguments
-/// * `line` - The full line containing the image tag.
+/// * `tag` - The parsed image tag.
 /// * `images_map` - A map of image IDs to their byte data.
+/// * `options` - Rendering options; the page size and horizontal margins bound the image
+///   width.
 /// * `temp_files` - A vector to hold `NamedTempFile`s, ensuring they are not deleted prematurely.
 /// * `doc` - The `Document` to which the image will be added.
 ///
 /// # Returns
 /// An empty `Result` on success, or a `Box<dyn Error>` on failure.
-fn handle_image_line(
-    line: &str,
+fn handle_image(
+    tag: &ImageTag,
     images_map: &HashMap<String, Vec<u8>>,
+    options: &RenderOptions,
     temp_files: &mut Vec<NamedTempFile>,
     doc: &mut Document,
 ) -> Result<(), Box<dyn Error>> {
-    let inner = &line[5..line.len() - 1];
-    if let Some(bytes) = images_map.get(inner) {
-        // Calculate the maximum available width on the page in pixels.
-        let margin_in = MARGIN_MM / 25.4_f64;
-        let content_width_in = PAGE_WIDTH_INCH - 2.0 * margin_in;
-        let content_target_px = content_width_in * IMAGE_DPI;
-
-        // These values simulate max-width/max-height from CSS for consistent rendering.
-        let css_max_width_px: f64 = 200.0;
-        let css_max_height_px: f64 = 200.0;
-        let css_to_px = IMAGE_DPI / 96.0; // Convert CSS pixels (96 DPI) to PDF pixels (IMAGE_DPI).
-        let css_max_width_target_px = css_max_width_px * css_to_px;
-        let css_max_height_target_px = css_max_height_px * css_to_px;
-
+    if let Some(bytes) = images_map.get(&tag.id) {
         let img = load_from_memory(bytes)?;
         let (orig_w, orig_h) = img.dimensions();
         let (orig_w_f, orig_h_f) = (orig_w as f64, orig_h as f64);
-
-        // Determine the final scaling factor by respecting all constraints (page width, css max-width, css max-height).
-        let scale_by_content = (content_target_px / orig_w_f).min(1.0);
-        let scale_by_css_w = (css_max_width_target_px / orig_w_f).min(1.0);
-        let scale_by_css_h = (css_max_height_target_px / orig_h_f).min(1.0);
-        let scale = scale_by_content.min(scale_by_css_w).min(scale_by_css_h);
+        let scale = image_scale(orig_w, orig_h, tag.width_px, options.content_width_in());
 
         // Resize the image only if it's larger than the target dimensions.
         let resized = if scale < 1.0 {
@@ -467,112 +913,328 @@ fn handle_image_line(
         let path: PathBuf = tmp.path().to_path_buf();
         let mut img_elem = PdfImage::from_path(path)?;
         img_elem.set_dpi(IMAGE_DPI);
+        img_elem.set_alignment(match tag.alignment {
+            ImageAlignment::Left => Alignment::Left,
+            ImageAlignment::Center => Alignment::Center,
+            ImageAlignment::Right => Alignment::Right,
+        });
         doc.push(img_elem);
         temp_files.push(tmp); // Keep the temp file alive until the function scope ends.
     } else {
-        doc.push(Paragraph::new(format!("[image not found: {}]", inner)));
+        doc.push(Paragraph::new(format!("[image not found: {}]", tag.id)));
     }
     Ok(())
 }
 
-/// Handles a line representing a placeholder tag (e.g., `[ph:BASE64_STRING]`).
-///
-/// Decodes the Base64 content and adds it to the document, parsing any nested
-/// `<b>` or `<i>` tags within the decoded text.
+/// Handles a placeholder tag (e.g., `[ph:BASE64_STRING]`): adds its decoded content to
+/// the document, or an `[invalid placeholder]` paragraph if it could not be decoded.
 ///
 /// # Arguments
-/// * `line` - The full line containing the placeholder tag.
-/// * `doc` - The `Document` to which the decoded content will be added.
-fn handle_placeholder_line(line: &str, doc: &mut Document) {
-    let inner = &line[4..line.len() - 1];
-    if let Some(decoded) = decode_placeholder(inner) {
-        push_styled_text_with_breaks_to_doc(doc, &decoded);
-    } else {
-        doc.push(Paragraph::new("[invalid placeholder]"));
+/// * `lines` - The spans of each line of the decoded content, if it could be decoded.
+/// * `doc` - The `Document` to which the content will be added.
+fn handle_placeholder(lines: Option<&[Vec<Span>]>, doc: &mut Document) {
+    match lines {
+        Some(lines) => push_lines_with_breaks_to_doc(doc, lines),
+        None => doc.push(Paragraph::new("[invalid placeholder]")),
     }
 }
 
-/// Handles a normal line of text without special formatting prefixes.
-///
-/// Parses the line for Markdown-like styles and adds it to the document as a paragraph.
+/// Renders a pipe table as a `genpdf` `TableLayout` with equal-width, framed columns.
 ///
-/// # Arguments
-/// * `line` - The line of text to process.
-/// * `doc` - The `Document` to which the paragraph will be added.
-fn handle_normal_line(line: &str, doc: &mut Document) {
-    let segments = parse_styles(line);
-    let mut p = Paragraph::new("");
-    push_segments_into_paragraph(&mut p, &segments);
-    doc.push(p);
-}
-
-/// Finds the first occurrence of a `<b>` or `<i>` tag in a string.
+/// The header row is printed in bold.
 ///
 /// # Arguments
-/// * `text` - The string to search in.
+/// * `header` - The content of the header cells.
+/// * `rows` - The content of the cells of each data row.
+/// * `doc` - The `Document` to which the table will be added.
 ///
 /// # Returns
-/// An `Option` containing the tag name ("b" or "i") and its starting position,
-/// or `None` if no tags are found.
-fn find_next_tag(text: &str) -> Option<(&str, usize)> {
-    let b_pos = text.find("<b>");
-    let i_pos = text.find("<i>");
-    match (b_pos, i_pos) {
-        (Some(b), Some(i)) if b < i => Some(("b", b)),
-        (Some(_), Some(i)) => Some(("i", i)),
-        (Some(b), None) => Some(("b", b)),
-        (None, Some(i)) => Some(("i", i)),
-        (None, None) => None,
+/// An empty `Result` on success, or a `Box<dyn Error>` if a row cannot be laid out.
+fn handle_table(
+    header: &[Vec<Inline>],
+    rows: &[Vec<Vec<Inline>>],
+    doc: &mut Document,
+) -> Result<(), Box<dyn Error>> {
+    let mut table = TableLayout::new(vec![1; header.len()]);
+    table.set_cell_decorator(FrameCellDecorator::new(true, true, false));
+
+    let mut push_row = |cells: &[Vec<Inline>], bold: bool| -> Result<(), Box<dyn Error>> {
+        let mut row = table.row();
+        for cell in cells {
+            let mut p = Paragraph::new("");
+            push_inlines_into_paragraph(&mut p, cell, bold);
+            row.push_element(p.padded(1));
+        }
+        row.push()?;
+        Ok(())
+    };
+
+    push_row(header, true)?;
+    for row in rows {
+        push_row(row, false)?;
     }
+    doc.push(table);
+    Ok(())
 }
 
-/// Parses a string with `<b>` and `<i>` tags into a `genpdf::Paragraph`.
-///
-/// This is used for content from placeholders which may contain simple HTML-like tags.
+/// Handles a normal line of text without special formatting prefixes, adding it to the
+/// document as a paragraph.
 ///
 /// # Arguments
-/// * `text` - The string to parse.
-///
-/// # Returns
-/// A `Paragraph` with styled text.
-fn parse_styled_paragraph(text: &str) -> Paragraph {
-    let mut paragraph = Paragraph::new("");
-    let mut rest = text;
-
-    while let Some((next_tag, start)) = find_next_tag(rest) {
-        // Add any plain text before the tag.
-        if start > 0 {
-            paragraph.push(&rest[..start]);
+/// * `inlines` - The content of the line.
+/// * `doc` - The `Document` to which the paragraph will be added.
+fn handle_normal_line(inlines: &[Inline], doc: &mut Document) {
+    let mut p = Paragraph::new("");
+    push_inlines_into_paragraph(&mut p, inlines, false);
+    doc.push(p);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::templates::save::save_template;
+    use crate::test_support::TestEnv;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// An image whose data is not a decodable picture, so rendering it fails.
+    fn broken_image(id: &str) -> Image {
+        Image {
+            id: id.to_string(),
+            base64: BASE64.encode(b"not an image"),
+        }
+    }
+
+    fn var(name: &str, value: &str) -> TemplateVar {
+        TemplateVar {
+            name: name.to_string(),
+            value: value.to_string(),
         }
+    }
+
+    fn assert_is_pdf(bytes: &[u8]) {
+        assert!(bytes.starts_with(b"%PDF-"), "missing PDF header");
+        assert!(
+            bytes.trim_ascii_end().ends_with(b"%%EOF"),
+            "missing PDF trailer"
+        );
+    }
 
-        let (tag_open, tag_close) = match next_tag {
-            "b" => ("<b>", "</b>"),
-            "i" => ("<i>", "</i>"),
-            _ => unreachable!(),
+    #[actix_web::test]
+    async fn saved_vars_are_substituted_before_parsing() {
+        let env = TestEnv::new();
+        // The variable expands to an image tag, so the tag is only parsed (and its broken
+        // image only reported) if the variable was substituted.
+        let template = Template {
+            id: "t".to_string(),
+            name: None,
+            text: "# [var:Empresa]\n[var:Logo]".to_string(),
+            images: Some(vec![broken_image("logo")]),
+            vars: Some(vec![var("Empresa", "ACME"), var("Logo", "[img:logo]")]),
+            margins: None,
+            orientation: None,
+            page_size: None,
         };
+        save_template(&env.pool, &template).await.unwrap();
 
-        // Find the corresponding closing tag.
-        if let Some(rel_end) = rest[start + tag_open.len()..].find(tag_close) {
-            let styled_text = &rest[start + tag_open.len()..start + tag_open.len() + rel_end];
-            let styled = match next_tag {
-                "b" => StyledString::new(styled_text, Style::new().bold()),
-                "i" => StyledString::new(styled_text, Style::new().italic()),
-                _ => StyledString::new(styled_text, Style::new()),
-            };
-            paragraph.push(styled);
-            // Move past the processed segment.
-            rest = &rest[start + tag_open.len() + rel_end + tag_close.len()..];
-        } else {
-            // If a tag is unclosed, treat the rest of the line as plain text.
-            paragraph.push(&rest[start..]);
-            return paragraph;
-        }
+        let path = env.config.pdf_path("t");
+        let warnings = generate_pdf_from_template_to_path(
+            &env.pool,
+            "t",
+            &path,
+            &RenderOptions::default(),
+            None,
+            None,
+        )
+        .unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert!(
+            warnings[0].starts_with("line 2: [img:logo]: "),
+            "{}",
+            warnings[0]
+        );
+        assert_is_pdf(&fs::read(&path).unwrap());
+    }
+
+    #[test]
+    fn undefined_vars_are_left_in_the_text() {
+        let vars = [var("Logo", "[img:logo]")];
+        let (bytes, warnings) = render_to_bytes(
+            "[var:Otro]",
+            vec![broken_image("logo")],
+            &vars,
+            &RenderOptions::default(),
+        )
+        .unwrap();
+        assert!(warnings.is_empty());
+        assert_is_pdf(&bytes);
+    }
+
+    /// A template with one broken image among elements that render fine.
+    const MIXED_TEMPLATE: &str =
+        "# Informe\n**Cliente:** ACME\n- uno\n- dos\n\n[img:roto|center]\n| a | b |\n|---|---|\n| 1 | 2 |";
+
+    #[test]
+    fn broken_element_is_replaced_with_a_warning() {
+        let (bytes, warnings) = render_to_bytes(
+            MIXED_TEMPLATE,
+            vec![broken_image("roto")],
+            &[],
+            &RenderOptions::default(),
+        )
+        .unwrap();
+        assert_is_pdf(&bytes);
+        assert_eq!(warnings.len(), 1);
+        assert!(
+            warnings[0].starts_with("line 6: [img:roto|center]: "),
+            "{}",
+            warnings[0]
+        );
+    }
+
+    #[test]
+    fn strict_mode_fails_on_the_broken_element() {
+        let options = RenderOptions {
+            strict: true,
+            ..RenderOptions::default()
+        };
+        let error =
+            render_to_bytes(MIXED_TEMPLATE, vec![broken_image("roto")], &[], &options).unwrap_err();
+        assert_eq!(error.stage, PdfErrorStage::Render);
+        assert_eq!(error.status, StatusCode::INTERNAL_SERVER_ERROR);
+
+        let (bytes, warnings) =
+            render_to_bytes("# Informe\nSin imágenes", vec![], &[], &options).unwrap();
+        assert_is_pdf(&bytes);
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn warnings_header_joins_warnings_as_ascii() {
+        assert_eq!(warnings_header_value(&[]), None);
+        let warnings = [
+            "line 2: [img:logo]: bad image".to_string(),
+            "line 5: [img:año]: bad image".to_string(),
+        ];
+        assert_eq!(
+            warnings_header_value(&warnings).unwrap(),
+            "line 2: [img:logo]: bad image; line 5: [img:a?o]: bad image"
+        );
+    }
+
+    #[test]
+    fn concurrent_renders_never_expose_a_partial_pdf() {
+        let env = TestEnv::new();
+        env.insert_template("t", "# Informe\n**Cliente:** ACME\n- uno\n- dos", None);
+        let path = env.config.pdf_path("t");
+        let writing = AtomicUsize::new(4);
+
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    for _ in 0..2 {
+                        generate_pdf_from_template_to_path(
+                            &env.pool,
+                            "t",
+                            &path,
+                            &RenderOptions::default(),
+                            None,
+                            None,
+                        )
+                        .unwrap();
+                    }
+                    writing.fetch_sub(1, Ordering::SeqCst);
+                });
+            }
+            scope.spawn(|| {
+                let mut reads = 0;
+                while writing.load(Ordering::SeqCst) > 0 {
+                    if let Ok(bytes) = fs::read(&path) {
+                        assert_is_pdf(&bytes);
+                        reads += 1;
+                    }
+                    std::thread::sleep(std::time::Duration::from_millis(1));
+                }
+                assert!(reads > 0, "the PDF was never read while being written");
+            });
+        });
+        assert_is_pdf(&fs::read(&path).unwrap());
     }
 
-    // Add any remaining plain text at the end.
-    if !rest.is_empty() {
-        paragraph.push(rest);
+    #[test]
+    fn content_width_follows_page_size_margins_and_orientation() {
+        let width = |page_size, margins, orientation| {
+            RenderOptions {
+                page_size,
+                margins,
+                orientation,
+                ..RenderOptions::default()
+            }
+            .content_width_in()
+        };
+        let margins = PageMargins {
+            top: 10.0,
+            right: 30.0,
+            bottom: 10.0,
+            left: 20.0,
+        };
+        let defaults = PageMargins::default();
+        let cases = [
+            (PageSize::A4, defaults, Orientation::Portrait, 190.0),
+            (PageSize::A4, margins, Orientation::Portrait, 160.0),
+            (PageSize::Letter, margins, Orientation::Portrait, 165.9),
+            (PageSize::A5, defaults, Orientation::Portrait, 128.0),
+            (PageSize::A5, defaults, Orientation::Landscape, 190.0),
+            (PageSize::Legal, margins, Orientation::Landscape, 305.6),
+        ];
+        for (page_size, margins, orientation, expected_mm) in cases {
+            let actual = width(page_size, margins, orientation);
+            assert!(
+                (actual - expected_mm / MM_PER_INCH).abs() < 1e-9,
+                "{:?} {:?}: {} in",
+                page_size,
+                orientation,
+                actual
+            );
+        }
     }
 
-    paragraph
+    #[actix_web::test]
+    async fn saved_page_size_is_used_unless_overridden() {
+        let env = TestEnv::new();
+        let template = Template {
+            id: "t".to_string(),
+            name: None,
+            text: "Hola".to_string(),
+            images: None,
+            vars: None,
+            margins: None,
+            orientation: None,
+            page_size: Some(PageSize::Letter),
+        };
+        save_template(&env.pool, &template).await.unwrap();
+        let conn = env.pool.get().unwrap();
+        assert_eq!(load_page_size(&conn, "t").unwrap(), Some(PageSize::Letter));
+        drop(conn);
+
+        // The first `MediaBox` of the PDF, in points: Letter is 612 × 792 and A5 is
+        // 419.53 × 595.28.
+        let path = env.config.pdf_path("t");
+        let media_box = |page_size| {
+            generate_pdf_from_template_to_path(
+                &env.pool,
+                "t",
+                &path,
+                &RenderOptions::default(),
+                None,
+                page_size,
+            )
+            .unwrap();
+            let pdf = String::from_utf8_lossy(&fs::read(&path).unwrap()).into_owned();
+            let start = pdf.find("MediaBox[").expect("no MediaBox") + "MediaBox[".len();
+            let end = start + pdf[start..].find(']').unwrap();
+            pdf[start..end].to_string()
+        };
+        assert_eq!(media_box(None), "0 0 612.00 792.00");
+        assert_eq!(media_box(Some(PageSize::A5)), "0 0 419.53 595.28");
+    }
 }
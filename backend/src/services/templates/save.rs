@@ -13,8 +13,9 @@
 //!     and an optional list of `Image` objects.
 //!
 //! 2.  **Database Upsert**: The `save_template` function performs an "upsert" operation on the
-//!     `templates` table. It inserts a new row if the `id` doesn't exist or updates the `text`
-//!     if it does. Note that this operation only modifies the `text` field, leaving other
+//!     `templates` table. It inserts a new row if the `id` doesn't exist or updates the `text`,
+//!     `name` and page layout if it does (`null` margins, orientation or page size reset the
+//!     template to the defaults). `updated_at` is set on every save and `created_at` only on insert. Note that this operation only modifies those fields, leaving other
 //!     template-related columns (like `datasource_md5` or `verified`) untouched, as those
 //!     are managed by other services (e.g., `data_sources::csv`).
 //!
@@ -23,16 +24,27 @@
 //!     - If the payload contains an `images` array, it compares the incoming image IDs with
 //!       those already in the database for the given `template_id`.
 //!     - Images present in the database but not in the payload are deleted (orphan removal).
-//!     - Images in the payload are inserted or updated using `INSERT OR REPLACE`.
+//!     - Images in the payload are inserted or updated using `INSERT OR REPLACE`, in
+//!       ascending `id` order (the same order `get` returns them in), regardless of the
+//!       order of the payload.
 //!     - If the payload's `images` field is `null` or omitted, all existing images for that
 //!       template are deleted.
 //!
-//! This ensures that the database state for a template's images perfectly mirrors the
-//! state sent by the client on each save operation.
+//! 4.  **Variable Synchronization**: The template's `[var:NAME]` variables are replaced as a
+//!     whole in the `template_vars` table: existing rows are deleted and the payload's `vars`
+//!     (if any) are inserted.
+//!
+//! This ensures that the database state for a template's images and variables perfectly
+//! mirrors the state sent by the client on each save operation.
+//!
+//! Steps 2 to 4 run in one SQLite transaction, so a save that fails midway (for example
+//! on an image insert) leaves the database exactly as it was before.
 
+use crate::db::DbPool;
 use actix_web::{web, Responder};
+use common::model::image::MAX_IMAGES;
 use common::model::template::Template;
-use rusqlite::{params, Connection};
+use rusqlite::params;
 
 /// Handles the HTTP POST request to save a template.
 ///
@@ -42,12 +54,13 @@ use rusqlite::{params, Connection};
 ///
 /// # Arguments
 /// * `payload` - A `web::Json<Template>` containing the template data sent by the client.
+/// * `pool` - The shared SQLite connection pool.
 ///
 /// # Returns
 /// - `200 OK` with a success message if the template is saved correctly.
 /// - `503 Service Unavailable` with an error message if any database operation fails.
-pub async fn process(payload: web::Json<Template>) -> impl Responder {
-    match save_template(&payload).await {
+pub async fn process(payload: web::Json<Template>, pool: web::Data<DbPool>) -> impl Responder {
+    match save_template(&pool, &payload).await {
         Ok(_) => actix_web::HttpResponse::Ok().body("Template saved successfully"),
         Err(e) => actix_web::HttpResponse::ServiceUnavailable()
             .body(format!("Error saving template: {}", e)),
@@ -57,31 +70,81 @@ pub async fn process(payload: web::Json<Template>) -> impl Responder {
 /// Saves or updates a template and its associated images in the database.
 ///
 /// This function contains the core logic for persisting template data. It performs
-/// a transaction-like sequence of operations:
-/// 1. Validates that the template ID is not empty.
-/// 2. Inserts or updates the template's main text content.
+/// the following steps, with every write in a single SQLite transaction that is committed
+/// only at the end (any error rolls back the whole save):
+/// 1. Validates that the template ID is not empty, that it has at most `MAX_IMAGES` images
+///    and that its margins (if any) are valid.
+/// 2. Inserts or updates the template's main text content and page layout (margins,
+///    orientation and page size).
 /// 3. Synchronizes the associated images by deleting orphans and upserting new/updated ones.
+/// 4. Replaces the template's variables with the ones in the payload.
 ///
 /// # Arguments
+/// * `pool` - The connection pool the transaction's connection is taken from.
 /// * `payload` - A reference to the `Template` object to be saved.
 ///
 /// # Returns
 /// - `Ok(())` on successful completion of all database operations.
-/// - `Err(String)` if the template ID is invalid or if any database query fails.
-pub async fn save_template(payload: &Template) -> Result<(), String> {
+/// - `Err(String)` if the template ID is invalid, if there are too many images, if a margin
+///   is negative, or if any database query fails.
+pub async fn save_template(pool: &DbPool, payload: &Template) -> Result<(), String> {
     if payload.id.trim().is_empty() {
         return Err("Template id cannot be empty".to_string());
     }
+    let image_count = payload.images.as_ref().map_or(0, Vec::len);
+    if image_count > MAX_IMAGES {
+        return Err(format!(
+            "Template has {} images; the maximum is {}",
+            image_count, MAX_IMAGES
+        ));
+    }
+    if let Some(margins) = &payload.margins {
+        margins.validate()?;
+    }
 
-    let conn = Connection::open("templify.sqlite").map_err(|e| e.to_string())?;
+    let mut conn = pool.get().map_err(|e| e.to_string())?;
+    // Every write below goes through `tx`. An early return drops it uncommitted, which rolls
+    // back the whole save, so a failure never leaves the text saved with half-synced images.
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
 
-    // Insert or update the template's text.
-    // This uses `ON CONFLICT` to perform an "upsert". It only touches the `text` column,
-    // preserving other data like data source info which is managed by other services.
-    conn.execute(
-        "INSERT INTO templates (id, text) VALUES (?1, ?2)
-         ON CONFLICT(id) DO UPDATE SET text = excluded.text",
-        params![&payload.id, &payload.text],
+    // Insert or update the template's text, name and page layout (margins, orientation,
+    // page size). This uses `ON CONFLICT` to perform an "upsert". It only touches the
+    // content columns, preserving other data like data source info which is managed by
+    // other services.
+    // `created_at` is left out of the update so it keeps the time of the first save.
+    let margins = payload.margins;
+    let name = payload
+        .name
+        .as_deref()
+        .map(str::trim)
+        .filter(|n| !n.is_empty());
+    tx.execute(
+        "INSERT INTO templates
+             (id, text, margin_top_mm, margin_right_mm, margin_bottom_mm, margin_left_mm,
+              orientation, page_size, name, created_at, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9,
+                 strftime('%Y-%m-%dT%H:%M:%SZ', 'now'), strftime('%Y-%m-%dT%H:%M:%SZ', 'now'))
+         ON CONFLICT(id) DO UPDATE SET
+             text = excluded.text,
+             margin_top_mm = excluded.margin_top_mm,
+             margin_right_mm = excluded.margin_right_mm,
+             margin_bottom_mm = excluded.margin_bottom_mm,
+             margin_left_mm = excluded.margin_left_mm,
+             orientation = excluded.orientation,
+             page_size = excluded.page_size,
+             name = excluded.name,
+             updated_at = excluded.updated_at",
+        params![
+            &payload.id,
+            &payload.text,
+            margins.map(|m| m.top),
+            margins.map(|m| m.right),
+            margins.map(|m| m.bottom),
+            margins.map(|m| m.left),
+            payload.orientation.map(|o| o.as_str()),
+            payload.page_size.map(|p| p.as_str()),
+            name,
+        ],
     )
         .map_err(|e| e.to_string())?;
 
@@ -89,7 +152,7 @@ pub async fn save_template(payload: &Template) -> Result<(), String> {
         Some(images) => {
             // If images are provided, sync them.
             // First, get all existing image IDs for this template.
-            let existing_ids: Vec<String> = conn
+            let existing_ids: Vec<String> = tx
                 .prepare("SELECT id FROM images WHERE template_id = ?1")
                 .map_err(|e| e.to_string())?
                 .query_map(params![&payload.id], |row| row.get(0))
@@ -100,7 +163,7 @@ pub async fn save_template(payload: &Template) -> Result<(), String> {
             // Delete any images that are no longer in the payload (orphans).
             for old_id in &existing_ids {
                 if !images.iter().any(|img| &img.id == old_id) {
-                    conn.execute(
+                    tx.execute(
                         "DELETE FROM images WHERE id = ?1 AND template_id = ?2",
                         params![old_id, &payload.id],
                     )
@@ -108,9 +171,11 @@ pub async fn save_template(payload: &Template) -> Result<(), String> {
                 }
             }
 
-            // Insert or replace all images from the payload.
-            for image in images {
-                conn.execute(
+            // Insert or replace all images from the payload, in canonical (id) order.
+            let mut ordered: Vec<_> = images.iter().collect();
+            ordered.sort_by(|a, b| a.id.cmp(&b.id));
+            for image in ordered {
+                tx.execute(
                     "INSERT OR REPLACE INTO images (id, template_id, base64) VALUES (?1, ?2, ?3)",
                     params![&image.id, &payload.id, &image.base64],
                 )
@@ -119,7 +184,7 @@ pub async fn save_template(payload: &Template) -> Result<(), String> {
         }
         None => {
             // If no images are provided in the payload, delete all associated images.
-            conn.execute(
+            tx.execute(
                 "DELETE FROM images WHERE template_id = ?1",
                 params![&payload.id],
             )
@@ -127,5 +192,173 @@ pub async fn save_template(payload: &Template) -> Result<(), String> {
         }
     }
 
-    Ok(())
+    // Replace the template's variables with the payload's set. Rows left without a name
+    // in the editor are skipped.
+    tx.execute(
+        "DELETE FROM template_vars WHERE template_id = ?1",
+        params![&payload.id],
+    )
+    .map_err(|e| e.to_string())?;
+    for var in payload.vars.iter().flatten().filter(|v| !v.name.is_empty()) {
+        tx.execute(
+            "INSERT OR REPLACE INTO template_vars (template_id, name, value) VALUES (?1, ?2, ?3)",
+            params![&payload.id, &var.name, &var.value],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+
+    tx.commit().map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::templates::get::get_template;
+    use crate::test_support::TestEnv;
+    use common::model::image::Image;
+    use common::model::template_var::TemplateVar;
+
+    fn template(id: &str, text: &str) -> Template {
+        Template {
+            id: id.to_string(),
+            name: None,
+            text: text.to_string(),
+            images: None,
+            vars: None,
+            margins: None,
+            orientation: None,
+            page_size: None,
+        }
+    }
+
+    fn image(id: &str) -> Image {
+        Image {
+            id: id.to_string(),
+            base64: format!("data-{}", id),
+        }
+    }
+
+    fn image_ids(template: &Template) -> Vec<String> {
+        template
+            .images
+            .iter()
+            .flatten()
+            .map(|image| image.id.clone())
+            .collect()
+    }
+
+    fn var(name: &str, value: &str) -> TemplateVar {
+        TemplateVar {
+            name: name.to_string(),
+            value: value.to_string(),
+        }
+    }
+
+    #[actix_web::test]
+    async fn vars_are_replaced_on_each_save() {
+        let env = TestEnv::new();
+        let mut payload = template("t", "[var:Empresa], [var:Ciudad]");
+        payload.vars = Some(vec![
+            var("Empresa", "ACME"),
+            var("Ciudad", "Quito"),
+            var("", "sin nombre"),
+        ]);
+        save_template(&env.pool, &payload).await.unwrap();
+        let saved = get_template(&env.pool, "t").await.unwrap();
+        assert_eq!(
+            saved.vars,
+            Some(vec![var("Ciudad", "Quito"), var("Empresa", "ACME")])
+        );
+
+        payload.vars = Some(vec![var("Empresa", "ACME S.A.")]);
+        save_template(&env.pool, &payload).await.unwrap();
+        let saved = get_template(&env.pool, "t").await.unwrap();
+        assert_eq!(saved.vars, Some(vec![var("Empresa", "ACME S.A.")]));
+
+        payload.vars = None;
+        save_template(&env.pool, &payload).await.unwrap();
+        assert_eq!(get_template(&env.pool, "t").await.unwrap().vars, None);
+    }
+
+    #[actix_web::test]
+    async fn images_come_back_in_id_order() {
+        let env = TestEnv::new();
+        let mut payload = template("t", "[img:b]\n[img:c]\n[img:a]");
+        payload.images = Some(vec![image("b"), image("c"), image("a")]);
+        save_template(&env.pool, &payload).await.unwrap();
+        let saved = get_template(&env.pool, "t").await.unwrap();
+        assert_eq!(image_ids(&saved), ["a", "b", "c"]);
+
+        // Saving what was read back, in any order, gives the same result.
+        payload.images = Some(vec![image("c"), image("a"), image("b")]);
+        save_template(&env.pool, &payload).await.unwrap();
+        let again = get_template(&env.pool, "t").await.unwrap();
+        assert_eq!(
+            serde_json::to_string(&saved).unwrap(),
+            serde_json::to_string(&again).unwrap()
+        );
+
+        payload.images = Some(vec![image("c"), image("a")]);
+        save_template(&env.pool, &payload).await.unwrap();
+        let saved = get_template(&env.pool, "t").await.unwrap();
+        assert_eq!(image_ids(&saved), ["a", "c"]);
+    }
+
+    #[actix_web::test]
+    async fn more_than_max_images_is_rejected() {
+        let env = TestEnv::new();
+        let mut payload = template("t", "");
+        payload.images = Some((0..MAX_IMAGES).map(|i| image(&format!("{:03}", i))).collect());
+        save_template(&env.pool, &payload).await.unwrap();
+
+        payload.text = "cambiado".to_string();
+        payload
+            .images
+            .as_mut()
+            .unwrap()
+            .push(image(&MAX_IMAGES.to_string()));
+        let error = save_template(&env.pool, &payload).await.unwrap_err();
+        assert_eq!(
+            error,
+            format!(
+                "Template has {} images; the maximum is {}",
+                MAX_IMAGES + 1,
+                MAX_IMAGES
+            )
+        );
+        let saved = get_template(&env.pool, "t").await.unwrap();
+        assert_eq!(saved.text, "");
+        assert_eq!(image_ids(&saved).len(), MAX_IMAGES);
+    }
+
+    #[actix_web::test]
+    async fn failed_image_insert_rolls_back_the_whole_save() {
+        let env = TestEnv::new();
+        let mut payload = template("t", "original");
+        payload.images = Some(vec![image("a")]);
+        payload.vars = Some(vec![var("Empresa", "ACME")]);
+        save_template(&env.pool, &payload).await.unwrap();
+
+        // Make the insert of the image `bad` fail, after the text has been updated and the
+        // image `a` deleted.
+        env.pool
+            .get()
+            .unwrap()
+            .execute_batch(
+                "CREATE TRIGGER fail_bad_image BEFORE INSERT ON images
+                 WHEN NEW.id = 'bad'
+                 BEGIN SELECT RAISE(ABORT, 'injected failure'); END;",
+            )
+            .unwrap();
+        payload.text = "cambiado".to_string();
+        payload.images = Some(vec![image("bad")]);
+        payload.vars = None;
+        let error = save_template(&env.pool, &payload).await.unwrap_err();
+        assert!(error.contains("injected failure"), "{}", error);
+
+        let saved = get_template(&env.pool, "t").await.unwrap();
+        assert_eq!(saved.text, "original");
+        assert_eq!(image_ids(&saved), ["a"]);
+        assert_eq!(saved.vars, Some(vec![var("Empresa", "ACME")]));
+    }
 }
@@ -0,0 +1,111 @@
+//! Helpers shared by the unit tests.
+
+use crate::config::Config;
+use crate::db::{self, DbPool};
+use crate::job_controller::callbacks::JobCallbacks;
+use crate::job_controller::events::JobEvents;
+use crate::job_controller::log::JobLogs;
+use crate::job_controller::registry::JobRegistry;
+use crate::job_controller::state::{start_job_updater, JobsState};
+use crate::schema;
+use common::jobs::JobStatus;
+use rusqlite::params;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tempfile::TempDir;
+use tokio::sync::{mpsc, RwLock, Semaphore};
+
+/// A data directory in a temporary folder, with a database at the latest schema version.
+/// Everything is deleted when the value is dropped.
+pub struct TestEnv {
+    /// Keeps the folder alive.
+    _dir: TempDir,
+    pub config: Config,
+    pub pool: DbPool,
+}
+
+impl TestEnv {
+    pub fn new() -> Self {
+        let dir = tempfile::tempdir().expect("temporary directory");
+        let config = Config {
+            data_dir: dir.path().to_path_buf(),
+            db_path: dir.path().join("test.sqlite"),
+            pdf_dir: dir.path().join("pdfs"),
+            fonts_dir: dir.path().join("fonts"),
+            job_ttl: Duration::from_secs(30 * 60),
+            max_csv_upload_mb: 50,
+            callback_secret: None,
+            callback_allowed_hosts: Vec::new(),
+        };
+        let pool = db::create_pool(&config.db_path).expect("database pool");
+        schema::ensure_schema(&mut pool.get().expect("connection")).expect("schema");
+        TestEnv {
+            _dir: dir,
+            config,
+            pool,
+        }
+    }
+
+    /// Inserts a template with the given text and, if `csv` is given, a data source file
+    /// with that content.
+    ///
+    /// # Returns
+    /// The MD5 of the data source, if there is one.
+    pub fn insert_template(&self, id: &str, text: &str, csv: Option<&str>) -> Option<String> {
+        let md5 = csv.map(|csv| {
+            let md5 = format!("{:x}", md5::compute(csv));
+            std::fs::write(self.config.csv_path(id, &md5), csv).expect("CSV file");
+            md5
+        });
+        self.pool
+            .get()
+            .expect("connection")
+            .execute(
+                "INSERT INTO templates (id, text, datasource_md5) VALUES (?1, ?2, ?3)",
+                params![id, text, md5],
+            )
+            .expect("template row");
+        md5
+    }
+}
+
+/// A `JobsState` with `cpu_permits` CPU permits, built as in `main.rs`, with its
+/// `start_job_updater` task running. Must be called from within a Tokio runtime.
+pub fn jobs_state(cpu_permits: usize) -> JobsState {
+    let (tx, rx) = mpsc::channel(100);
+    let state = JobsState {
+        jobs: Arc::new(RwLock::new(HashMap::new())),
+        logs: JobLogs::default(),
+        registry: JobRegistry::default(),
+        events: JobEvents::default(),
+        callbacks: JobCallbacks::new(None, Vec::new()),
+        cpu_permits: Arc::new(Semaphore::new(cpu_permits)),
+        tx,
+    };
+    tokio::spawn(start_job_updater(state.clone(), rx));
+    state
+}
+
+/// The current status of `job_id`, if it is known.
+pub async fn job_status(state: &JobsState, job_id: &str) -> Option<JobStatus> {
+    let jobs = state.jobs.read().await;
+    jobs.get(job_id).map(|entry| entry.status.clone())
+}
+
+/// Waits until `job_id` reaches a terminal status and returns it.
+///
+/// # Panics
+/// If the job has not finished within ten seconds.
+pub async fn wait_until_finished(state: &JobsState, job_id: &str) -> JobStatus {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    loop {
+        if let Some(status) = job_status(state, job_id).await {
+            if status.is_terminal() {
+                return status;
+            }
+        }
+        assert!(Instant::now() < deadline, "job {} did not finish", job_id);
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+}
@@ -3,7 +3,48 @@ use serde::{Deserialize, Serialize};
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum JobStatus {
     Pending,
-    InProgress(u32),
+    /// The job is running. `lines` is the number of data lines processed so far and
+    /// `percent` the estimated progress, from 0 to 100.
+    InProgress { lines: u32, percent: u8 },
     Completed(String),
     Failed(String),
+    /// The job was stopped before finishing, e.g. because its template's data was replaced.
+    Cancelled(String),
+}
+
+impl JobStatus {
+    /// Whether the job has finished (`Completed`, `Failed` or `Cancelled`) and its status
+    /// will not change again.
+    pub fn is_terminal(&self) -> bool {
+        matches!(
+            self,
+            JobStatus::Completed(_) | JobStatus::Failed(_) | JobStatus::Cancelled(_)
+        )
+    }
+}
+
+/// The JSON body POSTed to a job's callback URL once the job reaches a terminal status.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct JobCallbackPayload {
+    /// The ID of the finished job.
+    pub job_id: String,
+    /// Its final status: `Completed`, `Failed` or `Cancelled`.
+    pub status: JobStatus,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cancelled_status_round_trips_through_json() {
+        let status = JobStatus::Cancelled("template data replaced".to_string());
+        let json = serde_json::to_string(&status).unwrap();
+        assert_eq!(json, r#"{"Cancelled":"template data replaced"}"#);
+        let parsed: JobStatus = serde_json::from_str(&json).unwrap();
+        assert!(
+            matches!(&parsed, JobStatus::Cancelled(reason) if reason == "template data replaced")
+        );
+        assert!(parsed.is_terminal());
+    }
 }
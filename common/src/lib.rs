@@ -1,3 +1,5 @@
 pub mod model;
 pub mod requests;
-pub mod jobs;
\ No newline at end of file
+pub mod jobs;
+pub mod preview;
+pub mod template_ast;
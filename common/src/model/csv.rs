@@ -1,4 +1,6 @@
-use crate::model::place_holder::PlaceholderType;
+use crate::model::place_holder::{
+    parse_boolean, PlaceholderType, DEFAULT_FALSE_LABEL, DEFAULT_TRUE_LABEL,
+};
 use serde::{Deserialize, Serialize};
 
 /// Represents the inferred schema of a single CSV column, generated during the
@@ -9,8 +11,9 @@ use serde::{Deserialize, Serialize};
 /// of the columns. A vector of `ColumnCheck` structs, `Vec<ColumnCheck>`, is created
 /// to represent this inferred schema.
 ///
-/// This vector is then serialized into a JSON string and sent to the frontend as the
-/// payload of a `JobStatus::Completed` message. The frontend deserializes this JSON
+/// This vector is then wrapped in a `VerifyReport`, serialized into a JSON string and sent
+/// to the frontend as the payload of a `JobStatus::Completed` message. The frontend
+/// deserializes this JSON
 /// to display the column details to the user, allowing them to review and confirm
 /// the detected schema before linking the data source to a template.
 #[derive(Deserialize, Serialize, Clone, Debug)]
@@ -18,11 +21,365 @@ pub struct ColumnCheck {
     /// The normalized column header title from the CSV file.
     /// Spaces are typically replaced with underscores for consistency.
     pub title: String,
-    /// The data type (`Text`, `Number`, `Currency`, `Email`) inferred from the
-    /// content of the first data row for this column.
+    /// The data type (`Text`, `Number`, `Currency`, `Email`, `Date`, `Phone`, `Boolean`) inferred
+    /// from the content of the first data row for this column.
     pub placeholder_type: PlaceholderType,
     /// The actual value from the first data row for this column.
     /// This is used on the frontend to provide the user with a concrete example
     /// of the data in the column, helping them validate the inferred type.
     pub first_row: Option<String>,
+    /// Zero-based position of the column in the CSV header.
+    ///
+    /// The position changes if the file is re-uploaded with its columns reordered, so
+    /// it must not be used to identify a column across uploads; use `title` for that.
+    /// Defaults to `0` when deserializing payloads produced before this field existed.
+    #[serde(default)]
+    pub index: usize,
+    /// Whether an empty cell is valid in this column, as chosen by the user. `None` (the
+    /// default) means the policy of the type: see `allows_empty`.
+    #[serde(default)]
+    pub allow_empty: Option<bool>,
+    /// Text a true value of a `Boolean` column renders as. `None` (the default) means
+    /// `DEFAULT_TRUE_LABEL`.
+    #[serde(default)]
+    pub true_label: Option<String>,
+    /// Text a false value of a `Boolean` column renders as. `None` (the default) means
+    /// `DEFAULT_FALSE_LABEL`.
+    #[serde(default)]
+    pub false_label: Option<String>,
+    /// How a template writes the values of a `Number` or `Currency` column. `None` (the
+    /// default) inserts them as they are in the CSV.
+    #[serde(default)]
+    pub number_display: Option<NumberDisplay>,
+}
+
+impl ColumnCheck {
+    /// Returns `true` if an empty cell is valid in this column: `allow_empty` if set,
+    /// otherwise only for `Text` columns.
+    pub fn allows_empty(&self) -> bool {
+        self.allow_empty
+            .unwrap_or(self.placeholder_type == PlaceholderType::Text)
+    }
+
+    /// Returns `value` as a template shows it: the true or false label for a `Boolean`
+    /// column, the value formatted with `number_display` for a `Number` or `Currency`
+    /// column, and `value` unchanged otherwise or if it cannot be parsed as its type.
+    ///
+    /// Numbers are read with `NumberFormat::DecimalPoint`, the format the editor verifies
+    /// CSV files with.
+    pub fn display_value(&self, value: &str) -> String {
+        match (&self.placeholder_type, &self.number_display) {
+            (PlaceholderType::Number, Some(display)) => {
+                return parse_number(value, NumberFormat::DecimalPoint, false)
+                    .map(|number| display.format(number))
+                    .unwrap_or_else(|| value.to_string());
+            }
+            (PlaceholderType::Currency, Some(display)) => {
+                return display
+                    .format_currency(value, NumberFormat::DecimalPoint)
+                    .unwrap_or_else(|| value.to_string());
+            }
+            (PlaceholderType::Boolean, _) => {}
+            _ => return value.to_string(),
+        }
+        match parse_boolean(value) {
+            Some(true) => self
+                .true_label
+                .clone()
+                .unwrap_or_else(|| DEFAULT_TRUE_LABEL.to_string()),
+            Some(false) => self
+                .false_label
+                .clone()
+                .unwrap_or_else(|| DEFAULT_FALSE_LABEL.to_string()),
+            None => value.to_string(),
+        }
+    }
+}
+
+/// The result of a successful CSV verification: the `JobStatus::Completed` payload.
+///
+/// Besides the inferred schema, it says how thoroughly the file was checked, which the
+/// frontend shows as the freshness of the verification (see `is_complete`).
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct VerifyReport {
+    /// The inferred schema, one entry per header column, in header order.
+    pub columns: Vec<ColumnCheck>,
+    /// `true` if the data rows were not scanned in this run because the file is unchanged
+    /// since its last successful verification (the "fast-path").
+    pub fast_path: bool,
+    /// `true` if the rows were scanned but only some columns were type-checked (the ones
+    /// referenced by the template).
+    pub partial: bool,
+    /// The first data rows of the file (at most `SAMPLE_ROWS`), as normalized cells in file
+    /// order, so the user can check they picked the right file.
+    #[serde(default)]
+    pub sample_rows: Vec<Vec<String>>,
+}
+
+impl VerifyReport {
+    /// Returns `true` if this run scanned every row and type-checked every column.
+    pub fn is_complete(&self) -> bool {
+        !self.fast_path && !self.partial
+    }
+}
+
+/// A single validation error reported by a verification run with `collect_all_errors`.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct CsvRowError {
+    /// 1-based row number in the file, counting the header and any skipped lines.
+    pub row: usize,
+    /// Title of the offending column, or `None` for row-level errors (wrong column count).
+    pub column: Option<String>,
+    /// The offending cell value, if the cell exists.
+    pub value: Option<String>,
+    /// Human-readable description of the problem.
+    pub reason: String,
+}
+
+/// The `JobStatus::Failed` payload of a verification run with `collect_all_errors`,
+/// serialized as JSON.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct VerifyErrors {
+    /// The errors found, in row order, at most `MAX_COLLECTED_ERRORS`.
+    pub errors: Vec<CsvRowError>,
+    /// `true` if the scan stopped early because the limit was reached, so the file may
+    /// contain more errors than listed.
+    pub truncated: bool,
+}
+
+/// Maximum number of errors collected by a verification run with `collect_all_errors`.
+pub const MAX_COLLECTED_ERRORS: usize = 100;
+
+/// Maximum number of data rows returned in `VerifyReport::sample_rows`.
+pub const SAMPLE_ROWS: usize = 5;
+
+/// How numeric cells in a CSV are written, used when validating `Number` and
+/// `Currency` columns.
+///
+/// Spreadsheets exported with a Spanish (or most European) locale write numbers as
+/// `1.234,56`, which a plain `f64` parse rejects. Choosing `DecimalComma` makes the
+/// verification normalize those values before parsing.
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NumberFormat {
+    /// `.` as decimal separator and `,` as thousands separator (e.g. `1,234.56`).
+    #[default]
+    DecimalPoint,
+    /// `,` as decimal separator and `.` as thousands separator (e.g. `1.234,56`).
+    DecimalComma,
+}
+
+impl NumberFormat {
+    /// Returns the `(decimal, thousands)` separator characters for this format.
+    pub fn separators(&self) -> (char, char) {
+        match self {
+            NumberFormat::DecimalPoint => ('.', ','),
+            NumberFormat::DecimalComma => (',', '.'),
+        }
+    }
+}
+
+/// Currency symbols recognized in `Currency` columns.
+pub const CURRENCY_SYMBOLS: [char; 4] = ['$', '€', '£', '¥'];
+
+/// Parses a numeric cell written with the given separator convention.
+///
+/// Thousands separators are removed and the decimal separator is mapped to `.` before
+/// parsing. A thousands separator is only accepted between groups of three digits of the
+/// integer part, so with `DecimalPoint` neither `1,5` nor `1,2,3` is a number. When
+/// `allow_currency` is `true`, one currency symbol at either end of the value (`€ 56,00`,
+/// `56,00 €`) is stripped first.
+///
+/// # Returns
+/// The parsed value, or `None` if the cell is not a number in that format.
+pub fn parse_number(value: &str, format: NumberFormat, allow_currency: bool) -> Option<f64> {
+    let mut s = value.trim();
+    if allow_currency {
+        s = strip_currency_symbol(s);
+    }
+    let (decimal, thousands) = format.separators();
+    if !has_valid_grouping(s, decimal, thousands) {
+        return None;
+    }
+    let normalized: String = s
+        .chars()
+        .filter(|&c| c != thousands)
+        .map(|c| if c == decimal { '.' } else { c })
+        .collect();
+    normalized.parse::<f64>().ok()
+}
+
+/// Removes one currency symbol from the start or the end of `value`, and the whitespace
+/// between it and the number.
+fn strip_currency_symbol(value: &str) -> &str {
+    let is_symbol = |c: char| CURRENCY_SYMBOLS.contains(&c);
+    if let Some(rest) = value.strip_prefix(is_symbol) {
+        rest.trim_start()
+    } else if let Some(rest) = value.strip_suffix(is_symbol) {
+        rest.trim_end()
+    } else {
+        value
+    }
+}
+
+/// Whether every `thousands` separator of `number` sits in its integer part, after one to
+/// three digits and before groups of exactly three. A number without separators passes.
+fn has_valid_grouping(number: &str, decimal: char, thousands: char) -> bool {
+    if !number.contains(thousands) {
+        return true;
+    }
+    let (int_part, frac_part) = number.split_once(decimal).unwrap_or((number, ""));
+    if frac_part.contains(thousands) {
+        return false;
+    }
+    let mut groups = int_part.trim_start_matches(['-', '+']).split(thousands);
+    let first = groups.next().unwrap_or_default();
+    (1..=3).contains(&first.len()) && groups.all(|group| group.len() == 3)
+}
+
+/// How the values of a `Number` or `Currency` column are written in a template, chosen by
+/// the user for the column (`ColumnCheck::number_display`).
+///
+/// With `DecimalComma` and two decimals, `1234567.5` is written `1.234.567,50`.
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NumberDisplay {
+    /// Decimal and thousands separators of the output.
+    pub separators: NumberFormat,
+    /// Digits after the decimal separator; the value is rounded to them.
+    pub decimals: u8,
+}
+
+impl NumberDisplay {
+    /// Writes `number` with these separators and decimals.
+    pub fn format(&self, number: f64) -> String {
+        let (decimal, thousands) = self.separators.separators();
+        let fixed = format!("{:.*}", usize::from(self.decimals), number.abs());
+        let (int_part, frac_part) = fixed.split_once('.').unwrap_or((fixed.as_str(), ""));
+
+        let mut out = String::new();
+        if number < 0.0 && fixed.bytes().any(|b| matches!(b, b'1'..=b'9')) {
+            out.push('-');
+        }
+        for (i, digit) in int_part.chars().enumerate() {
+            if i > 0 && (int_part.len() - i) % 3 == 0 {
+                out.push(thousands);
+            }
+            out.push(digit);
+        }
+        if !frac_part.is_empty() {
+            out.push(decimal);
+            out.push_str(frac_part);
+        }
+        out
+    }
+
+    /// Writes a `Currency` cell with these separators and decimals, keeping its currency
+    /// symbol where it was: `€1234.5` becomes `€1.234,50` and `1234.5 €` becomes
+    /// `1.234,50 €`.
+    ///
+    /// # Returns
+    /// `None` if the cell is not a number in `source` format.
+    pub fn format_currency(&self, value: &str, source: NumberFormat) -> Option<String> {
+        let number = parse_number(value, source, true)?;
+        let formatted = self.format(number);
+        let value = value.trim();
+        let is_symbol = |c: &char| CURRENCY_SYMBOLS.contains(c);
+        let leading = value.chars().next().filter(is_symbol);
+        let trailing = value.chars().last().filter(is_symbol);
+        Some(match (leading, trailing) {
+            (Some(symbol), _) => format!("{}{}", symbol, formatted),
+            (None, Some(symbol)) => format!("{} {}", formatted, symbol),
+            (None, None) => formatted,
+        })
+    }
+}
+
+/// How date cells in a CSV are written, used when inferring and validating `Date` columns.
+///
+/// Serialized as the format string shown to the user (e.g. `"dd/mm/yyyy"`).
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DateFormat {
+    /// ISO-8601 calendar date, `yyyy-mm-dd` (e.g. `2023-07-31`).
+    #[default]
+    #[serde(rename = "yyyy-mm-dd")]
+    Iso,
+    /// Day first, `dd/mm/yyyy` (e.g. `31/07/2023`).
+    #[serde(rename = "dd/mm/yyyy")]
+    DayMonthYear,
+    /// Month first, `mm/dd/yyyy` (e.g. `07/31/2023`).
+    #[serde(rename = "mm/dd/yyyy")]
+    MonthDayYear,
+}
+
+impl DateFormat {
+    /// Returns the format string of this format, as used in messages.
+    pub fn pattern(&self) -> &'static str {
+        match self {
+            DateFormat::Iso => "yyyy-mm-dd",
+            DateFormat::DayMonthYear => "dd/mm/yyyy",
+            DateFormat::MonthDayYear => "mm/dd/yyyy",
+        }
+    }
+}
+
+/// Character encoding of a CSV file.
+///
+/// Excel on Windows saves "CSV" files as Windows-1252 unless told otherwise, so accented
+/// names (`José`, `Muñoz`) are not valid UTF-8. The file is transcoded to UTF-8 before it is
+/// validated. Serialized as the encoding label (e.g. `"windows-1252"`).
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CsvEncoding {
+    /// Detect the encoding: UTF-8 if the start of the file is valid UTF-8, otherwise
+    /// Windows-1252.
+    #[default]
+    #[serde(rename = "auto")]
+    Auto,
+    /// UTF-8, with or without a byte order mark.
+    #[serde(rename = "utf-8")]
+    Utf8,
+    /// Windows-1252, a superset of the printable range of ISO-8859-1 (Latin-1).
+    #[serde(rename = "windows-1252", alias = "latin-1", alias = "iso-8859-1")]
+    Windows1252,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decimal_point_numbers() {
+        let parse = |value| parse_number(value, NumberFormat::DecimalPoint, false);
+        assert_eq!(parse("1,234.56"), Some(1234.56));
+        assert_eq!(parse("-1,234,567"), Some(-1234567.0));
+        assert_eq!(parse(" 1234.5 "), Some(1234.5));
+        assert_eq!(parse("1,5"), None);
+        assert_eq!(parse("1,2,3"), None);
+        assert_eq!(parse("1234,567"), None);
+        assert_eq!(parse(",123"), None);
+        assert_eq!(parse("1,234.5,6"), None);
+        assert_eq!(parse("1.234,56"), None);
+    }
+
+    #[test]
+    fn decimal_comma_numbers() {
+        let parse = |value| parse_number(value, NumberFormat::DecimalComma, false);
+        assert_eq!(parse("1.234,56"), Some(1234.56));
+        assert_eq!(parse("1.234.567"), Some(1234567.0));
+        assert_eq!(parse("56,00"), Some(56.0));
+        assert_eq!(parse("1.5"), None);
+        assert_eq!(parse("1.2.3"), None);
+        assert_eq!(parse("1,234.56"), None);
+    }
+
+    #[test]
+    fn currency_allows_one_symbol_at_either_end() {
+        let comma = |value| parse_number(value, NumberFormat::DecimalComma, true);
+        let point = |value| parse_number(value, NumberFormat::DecimalPoint, true);
+        assert_eq!(comma("€ 56,00"), Some(56.0));
+        assert_eq!(comma("1.234,56 €"), Some(1234.56));
+        assert_eq!(point("$1,234.56"), Some(1234.56));
+        assert_eq!(point("$$5$"), None);
+        assert_eq!(point("$5$"), None);
+        assert_eq!(point("€€5"), None);
+        assert_eq!(parse_number("$5", NumberFormat::DecimalPoint, false), None);
+    }
 }
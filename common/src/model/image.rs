@@ -7,6 +7,21 @@
 
 use serde::{Deserialize, Serialize};
 
+/// Maximum number of images a single template may hold.
+///
+/// Every image travels as Base64 in the template payload and is decoded and resized when
+/// rendering, so templates with many images are slow to save, load and render. The limit
+/// is enforced by the editor (which refuses to insert more images) and by the backend
+/// `save` and `render` services (which reject payloads over it).
+pub const MAX_IMAGES: usize = 50;
+
+/// Largest image file, in bytes, the editor accepts from the image dialog or a drop.
+///
+/// Base64 makes the file about a third larger in the template payload, which must also
+/// fit in `MAX_TEMPLATE_JSON_BYTES` (`common::model::template`) together with the rest of
+/// the template.
+pub const MAX_IMAGE_BYTES: u64 = 4 * 1024 * 1024;
+
 /// Represents an image associated with a template.
 ///
 /// This struct is used as a Data Transfer Object (DTO) for sending image data between
@@ -16,7 +31,8 @@ use serde::{Deserialize, Serialize};
 /// ## Frontend Context
 /// - When a user adds an image to a template in the UI, the frontend is responsible for:
 ///   1. Generating a unique `id` for the image (e.g., a UUID).
-///   2. Reading the image file and encoding its binary data into a `base64` string.
+///   2. Reading the image file and encoding its binary data into a `base64` string. Large
+///      images are first scaled down and re-encoded by `POST /api/templates/images/compress`.
 ///   3. Including a `Vec<Image>` in the `Template` object that is sent to the backend's
 ///      `POST /api/templates/save` endpoint.
 ///
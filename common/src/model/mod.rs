@@ -2,4 +2,5 @@ pub mod template;
 pub mod image;
 pub mod place_holder;
 pub mod datasource;
-pub mod csv;
\ No newline at end of file
+pub mod csv;
+pub mod template_var;pub mod pdf;
@@ -0,0 +1,211 @@
+//! # PDF Layout Settings
+//!
+//! Settings that control the page layout of the PDFs rendered by the backend
+//! (`services::templates::pdf`), and text-styling helpers the renderer shares with the
+//! editor preview. They live in `common` so that both sides of the API agree on them.
+
+use serde::{Deserialize, Serialize};
+
+/// Paper size of a rendered PDF.
+///
+/// Serialized by name (`"A4"`, `"Letter"`, `"Legal"`, `"A5"`). Defaults to `A4`, which is
+/// what the PDF renderer has always produced.
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PageSize {
+    /// ISO A4, 210 × 297 mm.
+    #[default]
+    A4,
+    /// US Letter, 8.5 × 11 in.
+    Letter,
+    /// US Legal, 8.5 × 14 in.
+    Legal,
+    /// ISO A5, 148 × 210 mm.
+    A5,
+}
+
+impl PageSize {
+    /// Returns the `(width, height)` of the page in millimeters, in portrait orientation.
+    pub fn dimensions_mm(&self) -> (f64, f64) {
+        match self {
+            PageSize::A4 => (210.0, 297.0),
+            PageSize::Letter => (215.9, 279.4),
+            PageSize::Legal => (215.9, 355.6),
+            PageSize::A5 => (148.0, 210.0),
+        }
+    }
+
+    /// Returns the name stored in the database and used in query strings.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PageSize::A4 => "A4",
+            PageSize::Letter => "Letter",
+            PageSize::Legal => "Legal",
+            PageSize::A5 => "A5",
+        }
+    }
+
+    /// Parses a name returned by `as_str`.
+    pub fn from_name(name: &str) -> Option<PageSize> {
+        match name {
+            "A4" => Some(PageSize::A4),
+            "Letter" => Some(PageSize::Letter),
+            "Legal" => Some(PageSize::Legal),
+            "A5" => Some(PageSize::A5),
+            _ => None,
+        }
+    }
+}
+
+/// Orientation of the pages of a rendered PDF.
+///
+/// Serialized by name (`"Portrait"`, `"Landscape"`). Defaults to `Portrait`.
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Orientation {
+    /// Pages are taller than wide.
+    #[default]
+    Portrait,
+    /// Pages are wider than tall; useful for wide tables.
+    Landscape,
+}
+
+impl Orientation {
+    /// Returns the name stored in the database and used in query strings.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Orientation::Portrait => "Portrait",
+            Orientation::Landscape => "Landscape",
+        }
+    }
+
+    /// Parses a name returned by `as_str`.
+    pub fn from_name(name: &str) -> Option<Orientation> {
+        match name {
+            "Portrait" => Some(Orientation::Portrait),
+            "Landscape" => Some(Orientation::Landscape),
+            _ => None,
+        }
+    }
+
+    /// Returns the opposite orientation.
+    pub fn flipped(&self) -> Orientation {
+        match self {
+            Orientation::Portrait => Orientation::Landscape,
+            Orientation::Landscape => Orientation::Portrait,
+        }
+    }
+}
+
+/// Page margin used on every side unless a template sets its own margins.
+pub const DEFAULT_MARGIN_MM: f64 = 10.0;
+
+/// Page margins of a rendered PDF, in millimeters.
+///
+/// Stored per template (see `Template::margins`), so a layout with room for a footer or a
+/// letterhead does not have to be set on every render.
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq)]
+pub struct PageMargins {
+    pub top: f64,
+    pub right: f64,
+    pub bottom: f64,
+    pub left: f64,
+}
+
+impl Default for PageMargins {
+    fn default() -> Self {
+        PageMargins {
+            top: DEFAULT_MARGIN_MM,
+            right: DEFAULT_MARGIN_MM,
+            bottom: DEFAULT_MARGIN_MM,
+            left: DEFAULT_MARGIN_MM,
+        }
+    }
+}
+
+impl PageMargins {
+    /// Checks that every margin is a finite, non-negative number.
+    ///
+    /// # Returns
+    /// `Ok(())` if the margins are usable, or a message naming the first invalid side.
+    pub fn validate(&self) -> Result<(), String> {
+        let sides = [
+            ("top", self.top),
+            ("right", self.right),
+            ("bottom", self.bottom),
+            ("left", self.left),
+        ];
+        for (side, value) in sides {
+            if !value.is_finite() || value < 0.0 {
+                return Err(format!("Invalid {} margin: {}", side, value));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The step of PDF generation that failed, reported in `PdfErrorBody::stage`.
+///
+/// Serialized in snake case (`"font_load"`, `"db"`, `"render"`).
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PdfErrorStage {
+    /// No usable font could be loaded (neither the template's nor the default ones).
+    FontLoad,
+    /// The template could not be read from the database, or does not exist.
+    Db,
+    /// The document could not be laid out or written.
+    Render,
+}
+
+/// JSON body of a failed PDF request (`GET /api/templates/pdf/{id}`,
+/// `POST /api/templates/pdf/preview` and `POST /api/render/markdown`).
+///
+/// Lets the editor show why a PDF could not be generated instead of an empty viewer.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct PdfErrorBody {
+    /// Description of the error, in English.
+    pub error: String,
+    /// Where generation failed.
+    pub stage: PdfErrorStage,
+}
+
+/// Parses a hex color code as used by `{color:CODE}...{/color}` spans in template text.
+///
+/// Accepts `#RRGGBB` and the short form `#RGB` (each digit doubled), case-insensitive.
+/// Shared by the PDF renderer and the editor preview so both accept the same codes.
+///
+/// # Returns
+/// The `(red, green, blue)` components, or `None` if `code` is not a valid hex color.
+pub fn parse_hex_color(code: &str) -> Option<(u8, u8, u8)> {
+    let hex = code.trim().strip_prefix('#')?;
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let channel = |s: &str| u8::from_str_radix(s, 16).ok();
+    match hex.len() {
+        6 => Some((channel(&hex[0..2])?, channel(&hex[2..4])?, channel(&hex[4..6])?)),
+        3 => {
+            let short = |i: usize| channel(&hex[i..i + 1]).map(|v| v * 17);
+            Some((short(0)?, short(1)?, short(2)?))
+        }
+        _ => None,
+    }
+}
+
+/// URL schemes accepted in `[text](url)` links.
+const LINK_SCHEMES: [&str; 3] = ["http://", "https://", "mailto:"];
+
+/// Checks whether `url` may be used as the target of a `[text](url)` link.
+///
+/// Only `http://`, `https://` and `mailto:` URLs (case-insensitive) are accepted, so the
+/// preview never emits `javascript:` or other active links. The PDF renderer applies the
+/// same rule, and a link with any other target renders as its plain text on both sides.
+pub fn is_allowed_link_url(url: &str) -> bool {
+    let url = url.trim();
+    !url.chars().any(char::is_whitespace)
+        && LINK_SCHEMES.iter().any(|scheme| {
+            url.len() > scheme.len()
+                && url
+                    .get(..scheme.len())
+                    .is_some_and(|prefix| prefix.eq_ignore_ascii_case(scheme))
+        })
+}
@@ -31,7 +31,9 @@ pub struct PlaceHolder {
 /// This enum is a critical component of the data verification process. The backend service
 /// `services::data_sources::csv::mod.rs` uses heuristics to assign a `PlaceholderType` to
 /// each column of an uploaded CSV file. For example, it checks for '@' to infer `Email`,
-/// currency symbols for `Currency`, and attempts to parse a value as a float for `Number`.
+/// a valid date in the requested `DateFormat` for `Date`, currency symbols for `Currency`,
+/// a formatted or zero-prefixed run of digits for `Phone`, a yes/no word for `Boolean`, and
+/// attempts to parse a value as a float for `Number`.
 ///
 /// This type information is then packaged within the `ColumnCheck` struct and sent to the
 /// frontend upon successful verification. The frontend UI can then use this type to:
@@ -48,4 +50,34 @@ pub enum PlaceholderType {
     Currency,
     /// An email address, identified by the presence of '@' and '.' characters.
     Email,
-}
\ No newline at end of file
+    /// A calendar date written in the `DateFormat` chosen for the verification
+    /// (`common::model::csv::DateFormat`, ISO-8601 by default).
+    Date,
+    /// A phone number: digits, spaces, `+`, `-`, `(` and `)`, with at least 7 digits.
+    /// Kept as written, so prefixes such as `+34` and leading zeros are not lost as they
+    /// would be with `Number`.
+    Phone,
+    /// A yes/no value, such as a checkbox exported to CSV: any value `parse_boolean`
+    /// accepts. Templates show it through `ColumnCheck::display_value`.
+    Boolean,
+}
+
+/// Label shown for a true `Boolean` value when the column sets none.
+///
+/// Plain words rather than ✓/✗: the bundled PDF fonts have no glyphs for those marks.
+pub const DEFAULT_TRUE_LABEL: &str = "Sí";
+/// Label shown for a false `Boolean` value when the column sets none.
+pub const DEFAULT_FALSE_LABEL: &str = "No";
+
+/// Parses a `Boolean` cell, ignoring case and surrounding whitespace.
+///
+/// # Returns
+/// `Some(true)` for `true`, `1`, `yes`, `sí` and `si`; `Some(false)` for `false`, `0` and
+/// `no`; `None` for anything else.
+pub fn parse_boolean(value: &str) -> Option<bool> {
+    match value.trim().to_lowercase().as_str() {
+        "true" | "1" | "yes" | "sí" | "si" => Some(true),
+        "false" | "0" | "no" => Some(false),
+        _ => None,
+    }
+}
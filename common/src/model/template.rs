@@ -1,4 +1,13 @@
 use crate::model::image::Image;
+use crate::model::pdf::{Orientation, PageMargins, PageSize};
+use crate::model::template_var::TemplateVar;
+
+/// Largest JSON body, in bytes, the backend accepts (`web::JsonConfig` in `main.rs`).
+///
+/// A saved template travels as a single JSON document with its images in Base64, so this
+/// also caps the template's total size. The editor checks it before inserting an image
+/// and before saving, instead of waiting for the server to reject the request.
+pub const MAX_TEMPLATE_JSON_BYTES: usize = 10 * 1024 * 1024;
 
 /// Represents the core content and structure of a template.
 ///
@@ -39,6 +48,11 @@ pub struct Template {
     /// A unique identifier for the template, typically a UUID. This is used as the
     /// primary key in the database and as the reference in API routes.
     pub id: String,
+    /// Human-readable name shown in the editor and the template list. `None` (or a blank
+    /// name, which `save` stores as `None`) leaves the template unnamed. Saved like
+    /// `margins`. Defaults to `None` when omitted.
+    #[serde(default)]
+    pub name: Option<String>,
     /// The main body of the template. This string can contain plain text, Markdown-like
     /// styling (`*` for italic, `**` for bold), and special tags like `[img:image_id]`
     /// to reference an image or `{{placeholder_name}}` for data merging.
@@ -48,6 +62,46 @@ pub struct Template {
     ///   images that should be associated with the template.
     /// - When receiving from the backend (`get`), it contains all images currently linked
     ///   to the template in the database.
+    ///
     /// It is `None` if no images are associated.
     pub images: Option<Vec<Image>>,
+    /// Document-level constants referenced from `text` with `[var:NAME]` tags. Saved and
+    /// loaded with the same "complete set" semantics as `images`: a `save` with `None`
+    /// removes every variable of the template. Defaults to `None` when omitted.
+    #[serde(default)]
+    pub vars: Option<Vec<TemplateVar>>,
+    /// Page margins of the template's PDF. `None` uses `DEFAULT_MARGIN_MM` on every side.
+    /// Saved with the same semantics as `vars`: a `save` with `None` resets the template
+    /// to the default margins. Defaults to `None` when omitted.
+    #[serde(default)]
+    pub margins: Option<PageMargins>,
+    /// Page orientation of the template's PDF. `None` means portrait. Saved like `margins`.
+    #[serde(default)]
+    pub orientation: Option<Orientation>,
+    /// Paper size of the template's PDF. `None` means A4. Saved like `margins`.
+    #[serde(default)]
+    pub page_size: Option<PageSize>,
 }
+
+/// One entry of the template list returned by `GET /api/templates`.
+///
+/// Carries just enough to recognize a template in the editor's template picker, without
+/// the full text or the image data.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct TemplateSummary {
+    /// The template's unique identifier, as used in `GET /api/templates/{template_id}`.
+    pub id: String,
+    /// The template's name, if it has one.
+    pub name: Option<String>,
+    /// The first characters of the text (`TEXT_PREVIEW_CHARS`), with line breaks replaced by
+    /// spaces. Ends with `…` when the text is longer.
+    pub text_preview: String,
+    /// Number of images stored with the template.
+    pub image_count: u32,
+    /// When the template was last saved (UTC, `YYYY-MM-DDTHH:MM:SSZ`). `None` for templates
+    /// last saved before timestamps were recorded.
+    pub updated_at: Option<String>,
+}
+
+/// Number of characters of a template's text kept in `TemplateSummary::text_preview`.
+pub const TEXT_PREVIEW_CHARS: usize = 80;
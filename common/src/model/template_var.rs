@@ -0,0 +1,52 @@
+//! # Template Variable DTO
+//!
+//! Template variables are document-level constants (company name, address, ...) that are
+//! defined once per template and referenced from its text with `[var:NAME]` tags. Unlike
+//! CSV placeholders (`[ph:...]`), whose value changes per data row, a variable has a
+//! single value for the whole template, so editing it updates every occurrence.
+//!
+//! Variables travel with the `Template` DTO, are persisted in the `template_vars` table by
+//! `services::templates::save`, and are substituted by both the frontend preview and the
+//! backend PDF renderer using `substitute_vars`.
+
+use serde::{Deserialize, Serialize};
+
+/// A named constant defined for a template.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TemplateVar {
+    /// The variable name, as written in `[var:NAME]` tags.
+    pub name: String,
+    /// The text that replaces every `[var:NAME]` tag.
+    pub value: String,
+}
+
+/// Replaces every `[var:NAME]` tag in `text` with the value of the matching variable.
+///
+/// Tags whose name is not defined in `vars` are left untouched, so a missing variable is
+/// visible in the output instead of silently disappearing.
+pub fn substitute_vars(text: &str, vars: &[TemplateVar]) -> String {
+    const OPEN: &str = "[var:";
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find(OPEN) {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + OPEN.len()..];
+        match after.find(']') {
+            Some(end) => {
+                let name = &after[..end];
+                match vars.iter().find(|v| v.name == name) {
+                    Some(var) => out.push_str(&var.value),
+                    None => out.push_str(&rest[start..start + OPEN.len() + end + 1]),
+                }
+                rest = &after[end + 1..];
+            }
+            None => {
+                out.push_str(&rest[start..]);
+                rest = "";
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
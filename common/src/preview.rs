@@ -0,0 +1,428 @@
+//! # Template Preview HTML
+//!
+//! Renders template text to HTML from the nodes of `crate::template_ast`, the parser the
+//! PDF and DOCX exports use too, so the preview shows what the exports contain. This is
+//! the renderer behind the editor's preview pane, shared with the backend's
+//! `GET /api/templates/html/{template_id}` export so both produce the same HTML.
+//!
+//! Only the template syntax is rendered, not general markdown: numbered lists (`1. `),
+//! blockquotes (`> `), inline code and code blocks show as typed, because the PDF and
+//! DOCX exports do not render them either.
+//!
+//! Images are inlined as `data:` URLs, so the result needs nothing else to display. All
+//! user-provided content (text, placeholder values, link targets) is escaped.
+
+use crate::model::image::Image;
+use crate::model::template_var::{TemplateVar, substitute_vars};
+use crate::template_ast::{
+    ImageAlignment, ImageTag, Inline, LocatedNode, Span, TemplateNode, TextStyle, parse_template,
+};
+use regex::Regex;
+use std::collections::HashMap;
+
+/// Layout options of an image tag, written after the id: `[img:<id>|center|w=400]`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImageTagOptions {
+    /// `"left"`, `"center"` or `"right"`.
+    pub alignment: String,
+    /// The `w=N` width in CSS pixels, as written. The PDF and the preview fit an invalid
+    /// value to the page width.
+    pub width: Option<String>,
+}
+
+impl ImageTagOptions {
+    /// Parses the options part of a tag (`|center|w=400`, capture group 1 of
+    /// `image_tag_regex`). Unknown options are ignored.
+    pub fn parse(options: &str) -> Self {
+        let mut parsed = ImageTagOptions {
+            alignment: "left".to_string(),
+            width: None,
+        };
+        for option in options.split('|').filter(|o| !o.is_empty()) {
+            if let Some(width) = option.strip_prefix("w=") {
+                parsed.width = Some(width.to_string());
+            } else if matches!(option, "left" | "center" | "right") {
+                parsed.alignment = option.to_string();
+            }
+        }
+        parsed
+    }
+
+    /// The valid width in CSS pixels, if the tag sets one.
+    pub fn width_px(&self) -> Option<u32> {
+        self.width.as_deref()?.parse().ok().filter(|w| *w > 0)
+    }
+}
+
+/// Matches every tag of the image `id`, with or without options (`[img:<id>]`,
+/// `[img:<id>|center|w=400]`). The options, if any, are capture group 1.
+pub fn image_tag_regex(id: &str) -> Regex {
+    Regex::new(&format!(r"\[img:{}((?:\|[^]|]*)*)]", regex::escape(id))).unwrap()
+}
+
+/// Escapes a string for safe inclusion in HTML.
+///
+/// Text, placeholder values and link targets are escaped with this function before being
+/// embedded in the preview HTML, so content is never misinterpreted as HTML tags,
+/// mitigating XSS risks.
+///
+/// # Returns
+/// A new string with `&`, `<`, `>`, `"`, and `'` replaced by their respective
+/// HTML entities.
+pub fn escape_html(input: &str) -> String {
+    input
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+/// Renders template text to preview HTML.
+///
+/// `[var:NAME]` tags are replaced with the template's variable values, then the text is
+/// parsed with `parse_template` and each node becomes one block:
+/// - a paragraph, a placeholder line or an image becomes a `<div>`, so lines stack without
+///   gaps as in the PDF, and an empty line becomes an empty line (`<div><br></div>`);
+/// - a heading becomes `<h1>` to `<h6>`;
+/// - consecutive list items are grouped in a `<ul>`;
+/// - a table becomes a `<table>` with a `<thead>` row.
+///
+/// Column values are wrapped in a `<span>` titled with the column, so hovering shows where
+/// the value comes from.
+///
+/// # Arguments
+/// * `text` - The template text.
+/// * `vars` - The template's `[var:NAME]` variables.
+/// * `images` - The template's images, referenced from the text with `[img:ID]` tags.
+pub fn render_preview_html(text: &str, vars: &[TemplateVar], images: &[Image]) -> String {
+    let text = substitute_vars(text, vars);
+    let images: HashMap<&str, &str> = images
+        .iter()
+        .map(|image| (image.id.as_str(), image.base64.as_str()))
+        .collect();
+
+    let mut html = String::new();
+    let mut in_list = false;
+    for LocatedNode { node, .. } in parse_template(&text, false) {
+        let is_list_item = matches!(node, TemplateNode::ListItem(_));
+        if is_list_item && !in_list {
+            html.push_str("<ul>");
+        } else if !is_list_item && in_list {
+            html.push_str("</ul>");
+        }
+        in_list = is_list_item;
+
+        match node {
+            TemplateNode::LineBreak => html.push_str(EMPTY_LINE),
+            TemplateNode::Paragraph(inlines) => {
+                html.push_str(&format!("<div>{}</div>", inlines_html(&inlines)));
+            }
+            TemplateNode::Heading { level, content } => {
+                html.push_str(&format!("<h{level}>{}</h{level}>", inlines_html(&content)))
+            }
+            TemplateNode::ListItem(inlines) => {
+                html.push_str(&format!("<li>{}</li>", inlines_html(&inlines)));
+            }
+            TemplateNode::Image { tag, .. } => match images.get(tag.id.as_str()) {
+                Some(base64) => html.push_str(&image_html(&tag, base64)),
+                None => html.push_str(&format!(
+                    "<div>[image not found: {}]</div>",
+                    escape_html(&tag.id)
+                )),
+            },
+            TemplateNode::Placeholder(Some(lines)) => {
+                // One line per line of the content, with an empty line between them.
+                let lines: Vec<String> = lines
+                    .iter()
+                    .map(|spans| format!("<div>{}</div>", spans_html(spans)))
+                    .collect();
+                html.push_str(&lines.join(EMPTY_LINE));
+            }
+            TemplateNode::Placeholder(None) => html.push_str("<div>[invalid placeholder]</div>"),
+            TemplateNode::Table { header, rows } => html.push_str(&table_html(&header, &rows)),
+        }
+        html.push('\n');
+    }
+    if in_list {
+        html.push_str("</ul>\n");
+    }
+    html
+}
+
+/// An empty line of the template.
+const EMPTY_LINE: &str = "<div><br></div>";
+
+/// Renders inline content. Links open in a new tab, so the editor is not left.
+fn inlines_html(inlines: &[Inline]) -> String {
+    inlines
+        .iter()
+        .map(|inline| match inline {
+            Inline::Text(span) => span_html(span),
+            Inline::Link { url, spans, .. } => format!(
+                r#"<a href="{}" target="_blank" rel="noopener noreferrer">{}</a>"#,
+                escape_html(url),
+                spans_html(spans)
+            ),
+        })
+        .collect()
+}
+
+/// Renders a run of spans.
+fn spans_html(spans: &[Span]) -> String {
+    spans.iter().map(span_html).collect()
+}
+
+/// Renders a span: its escaped text in the tags of its style, inside a colored `<span>`
+/// if it has a color and a titled one if it is a column value.
+fn span_html(span: &Span) -> String {
+    let text = escape_html(&span.text);
+    let mut html = match span.style {
+        TextStyle::Regular => text,
+        TextStyle::Bold => format!("<strong>{}</strong>", text),
+        TextStyle::Italic => format!("<em>{}</em>", text),
+        TextStyle::BoldItalic => format!("<strong><em>{}</em></strong>", text),
+        TextStyle::Underline => format!("<u>{}</u>", text),
+        TextStyle::Strikethrough => format!("<s>{}</s>", text),
+    };
+    if let Some((r, g, b)) = span.color {
+        html = format!(
+            r#"<span style="color:#{:02x}{:02x}{:02x}">{}</span>"#,
+            r, g, b, html
+        );
+    }
+    if let Some(title) = &span.column {
+        html = format!(r#"<span title="{}">{}</span>"#, escape_html(title), html);
+    }
+    html
+}
+
+/// Renders a pipe table with its header row in a `<thead>`.
+fn table_html(header: &[Vec<Inline>], rows: &[Vec<Vec<Inline>>]) -> String {
+    let row_html = |cells: &[Vec<Inline>], tag: &str| -> String {
+        let cells: String = cells
+            .iter()
+            .map(|cell| format!("<{tag}>{}</{tag}>", inlines_html(cell)))
+            .collect();
+        format!("<tr>{}</tr>", cells)
+    };
+    let body: String = rows.iter().map(|row| row_html(row, "td")).collect();
+    format!(
+        "<table><thead>{}</thead><tbody>{}</tbody></table>",
+        row_html(header, "th"),
+        body
+    )
+}
+
+/// Renders an image line as an `<img>` in a `<div>`.
+///
+/// The Base64 data becomes a data URL for the `src` attribute (see `image_data_url`). As in
+/// the PDF, an image fits a 200 px box unless its tag sets a width (`|w=400`), which is
+/// capped at the available width; an invalid width fits the available width. A centered or
+/// right-aligned image is pushed to that side with auto margins.
+fn image_html(tag: &ImageTag, base64: &str) -> String {
+    let size = match tag.width_px {
+        Some(width) if width.is_finite() => format!("max-width:min({}px,100%);", width),
+        Some(_) => "max-width:100%;".to_string(),
+        None => "max-width:200px;max-height:200px;".to_string(),
+    };
+    let placement = match tag.alignment {
+        ImageAlignment::Left => "",
+        ImageAlignment::Center => "margin:0 auto;",
+        ImageAlignment::Right => "margin:0 0 0 auto;",
+    };
+    format!(
+        r#"<div><img src="{}" style="display:block;{}{}" /></div>"#,
+        image_data_url(base64),
+        size,
+        placement
+    )
+}
+
+/// Builds the `data:` URL of an image stored as Base64.
+///
+/// The media type is recognized from the first bytes of PNG, JPEG, GIF and WebP data, so
+/// mail clients, which are stricter than browsers, display the image. Other data is
+/// labeled `image/*`.
+fn image_data_url(base64: &str) -> String {
+    let media_type = if base64.starts_with("iVBORw0KGgo") {
+        "image/png"
+    } else if base64.starts_with("/9j/") {
+        "image/jpeg"
+    } else if base64.starts_with("R0lGOD") {
+        "image/gif"
+    } else if base64.starts_with("UklGR") {
+        "image/webp"
+    } else {
+        "image/*"
+    };
+    format!("data:{};base64,{}", media_type, base64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::Engine as _;
+
+    /// CSV values that look like template (or spreadsheet) syntax.
+    const SYNTAX_VALUES: [&str; 7] = ["=cmd", "**x**", "- x", "| a |", "[img:id]", "<b>", "{{X}}"];
+
+    fn image(id: &str, base64: &str) -> Image {
+        Image {
+            id: id.to_string(),
+            base64: base64.to_string(),
+        }
+    }
+
+    fn placeholder(title: &str, value: &str) -> String {
+        let b64 = base64::engine::general_purpose::STANDARD.encode(value);
+        format!("[ph:{}:{}]", title, b64)
+    }
+
+    #[test]
+    fn placeholder_values_are_escaped_in_a_titled_span() {
+        let text = format!("Hola {}", placeholder("Nombre", "<b>Ana</b>"));
+        let html = render_preview_html(&text, &[], &[]);
+        assert_eq!(
+            html,
+            "<div>Hola <span title=\"Nombre\">&lt;b&gt;Ana&lt;/b&gt;</span></div>\n"
+        );
+    }
+
+    #[test]
+    fn placeholder_value_is_not_read_as_an_image_tag() {
+        let text = placeholder("Logo", "[img:logo]");
+        let html = render_preview_html(&text, &[], &[image("logo", "iVBORw0KGgo")]);
+        assert!(html.contains("[img:logo]"), "{}", html);
+        assert!(!html.contains("<img"), "{}", html);
+    }
+
+    #[test]
+    fn styles_and_color_spans_are_rendered() {
+        let html = render_preview_html(
+            "**negrita** __subrayado__ {color:#ff0000}rojo{/color}",
+            &[],
+            &[],
+        );
+        assert!(html.contains("<strong>negrita</strong>"), "{}", html);
+        assert!(html.contains("<u>subrayado</u>"), "{}", html);
+        assert!(
+            html.contains(r#"<span style="color:#ff0000">rojo</span>"#),
+            "{}",
+            html
+        );
+    }
+
+    #[test]
+    fn headings_lists_and_tables_become_blocks() {
+        let html = render_preview_html(
+            "# Título\n- uno\n- dos\n\n| A | B |\n| --- | --- |\n| 1 | 2 |",
+            &[],
+            &[],
+        );
+        assert_eq!(
+            html,
+            "<h1>Título</h1>\n<ul><li>uno</li>\n<li>dos</li>\n</ul><div><br></div>\n\
+             <table><thead><tr><th>A</th><th>B</th></tr></thead>\
+             <tbody><tr><td>1</td><td>2</td></tr></tbody></table>\n"
+        );
+    }
+
+    #[test]
+    fn image_tags_become_data_urls_with_their_layout() {
+        let images = [image("logo", "iVBORw0KGgoAAA")];
+        let html = render_preview_html("[img:logo|center|w=400]\n[img:falta]", &[], &images);
+        assert_eq!(
+            html,
+            "<div><img src=\"data:image/png;base64,iVBORw0KGgoAAA\" \
+             style=\"display:block;max-width:min(400px,100%);margin:0 auto;\" /></div>\n\
+             <div>[image not found: falta]</div>\n"
+        );
+    }
+
+    #[test]
+    fn links_open_in_a_new_tab() {
+        let html = render_preview_html("[sitio](https://example.com/?a=1&b=2)", &[], &[]);
+        assert!(
+            html.contains(
+                r#"<a href="https://example.com/?a=1&amp;b=2" target="_blank" rel="noopener noreferrer">sitio</a>"#
+            ),
+            "{}",
+            html
+        );
+    }
+
+    #[test]
+    fn vars_are_substituted() {
+        let vars = [TemplateVar {
+            name: "Empresa".to_string(),
+            value: "ACME".to_string(),
+        }];
+        let html = render_preview_html("Hola [var:Empresa]", &vars, &[]);
+        assert_eq!(html, "<div>Hola ACME</div>\n");
+    }
+
+    #[test]
+    fn syntax_values_are_escaped_text() {
+        let images = [Image {
+            id: "id".to_string(),
+            base64: "iVBORw0KGgo".to_string(),
+        }];
+        for value in SYNTAX_VALUES {
+            let b64 = base64::engine::general_purpose::STANDARD.encode(value);
+            // The column `X` has a value, so a `{{X}}` inside a value would be visible if
+            // it were substituted.
+            let text = format!("{{{{Nota}}}}\nVer [ph:Nota:{b64}]\n[ph:Nota:{b64}]\n[ph:X:eQ==]");
+            let html = render_preview_html(&text, &[], &images);
+            let cell = format!(r#"<span title="Nota">{}</span>"#, escape_html(value));
+            let lines: Vec<&str> = html.lines().collect();
+            assert_eq!(
+                lines[..3],
+                [
+                    format!("<div>{}</div>", cell),
+                    format!("<div>Ver {}</div>", cell),
+                    format!("<div>{}</div>", cell),
+                ],
+                "value {:?}",
+                value
+            );
+            for tag in ["<b>", "<strong>", "<em>", "<img", "<ul>", "<table>"] {
+                assert!(!html.contains(tag), "value {:?} produced {}", value, tag);
+            }
+        }
+    }
+
+    #[test]
+    fn markdown_outside_the_template_syntax_shows_as_typed() {
+        let html = render_preview_html("1. uno\n> cita\n`code`\n```\nx\n```", &[], &[]);
+        assert_eq!(
+            html,
+            "<div>1. uno</div>\n<div>&gt; cita</div>\n<div>`code`</div>\n\
+             <div>```</div>\n<div>x</div>\n<div>```</div>\n"
+        );
+    }
+
+    #[test]
+    fn image_data_url_recognizes_the_media_type() {
+        for (base64, media_type) in [
+            ("iVBORw0KGgo", "image/png"),
+            ("/9j/4AAQ", "image/jpeg"),
+            ("R0lGODlh", "image/gif"),
+            ("UklGRiQ", "image/webp"),
+            ("AAAA", "image/*"),
+        ] {
+            assert_eq!(
+                image_data_url(base64),
+                format!("data:{};base64,{}", media_type, base64)
+            );
+        }
+    }
+
+    #[test]
+    fn escape_html_escapes_markup_characters() {
+        assert_eq!(
+            escape_html(r#"<a href="x">'&'</a>"#),
+            "&lt;a href=&quot;x&quot;&gt;&#39;&amp;&#39;&lt;/a&gt;"
+        );
+    }
+}
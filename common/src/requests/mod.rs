@@ -10,6 +10,10 @@
 //! `common` crate, we maintain consistency between the expectations of the backend
 //! services and the data sent by the frontend client.
 
+use crate::model::csv::{CsvEncoding, DateFormat, NumberFormat};
+use crate::model::image::Image;
+use crate::model::pdf::{Orientation, PageMargins, PageSize};
+use crate::model::template_var::TemplateVar;
 use serde::Deserialize;
 
 /// Represents the JSON payload for a request to the `POST /api/data_sources/csv/verify` endpoint.
@@ -34,4 +38,183 @@ pub struct VerifyCsvRequest {
     /// source should be verified. This ID acts as the key to link the verification
     /// request to the correct template and its corresponding data file on the server.
     pub uuid: String,
+    /// When `true`, every data row must contain exactly as many fields as the header.
+    /// Rows with extra trailing fields (usually a delimiter problem in the data) or
+    /// missing fields are reported as a column-count error. Defaults to `false`, in
+    /// which case only the cells of known columns are checked.
+    #[serde(default)]
+    pub strict_columns: bool,
+    /// Optional list of (normalized) column titles that the template actually references,
+    /// typically extracted from its `[ph:TITLE:...]` tags. When present, only these columns
+    /// are type-checked row by row; the rest are skipped, which makes verifying wide CSVs
+    /// much cheaper. When absent, every column is validated. The inferred schema returned
+    /// to the client always describes all columns.
+    #[serde(default)]
+    pub columns: Option<Vec<String>>,
+    /// When `true`, every title in `columns` must be a column of the file: a template that
+    /// references a column the CSV does not have fails verification with
+    /// `template references unknown column: X`. Defaults to `false`, in which case unknown
+    /// titles are ignored and those placeholders are later substituted with empty values.
+    #[serde(default)]
+    pub strict_references: bool,
+    /// Character that wraps quoted cells. Defaults to `"` when omitted; an explicit `null`
+    /// disables quote handling altogether, so cells are taken verbatim. Cells are parsed as
+    /// in RFC 4180: a cell starting with the quote character may contain the delimiter and
+    /// line breaks, and a doubled quote inside it stands for one quote character. Any other
+    /// value than a single character is rejected with `400 Bad Request`.
+    #[serde(default = "default_quote")]
+    pub quote: Option<String>,
+    /// Column delimiter of the file. When `None` (the default), it is detected from the
+    /// header line. Set it when detection guesses wrong, e.g. because a header cell contains
+    /// semicolons or pipes. It must be a single ASCII character other than a line break or
+    /// the quote character; anything else is rejected with `400 Bad Request`. It is a
+    /// string rather than a character so that a longer value gets that message instead of
+    /// a deserialization error.
+    #[serde(default)]
+    pub delimiter: Option<String>,
+    /// Number of leading lines to ignore before the header row. Some exports prepend
+    /// report titles or timestamps above the real header; setting this skips them.
+    /// The value is stored with the template, and a request that omits it uses the stored
+    /// one (`0` until a request sets it).
+    #[serde(default)]
+    pub skip_lines: Option<usize>,
+    /// Decimal/thousands separator convention used by `Number` and `Currency` columns.
+    /// Defaults to `NumberFormat::DecimalPoint`.
+    #[serde(default)]
+    pub number_format: NumberFormat,
+    /// Format of `Date` columns: `"yyyy-mm-dd"` (the default), `"dd/mm/yyyy"` or
+    /// `"mm/dd/yyyy"`. Columns whose first row is a valid date in this format are inferred
+    /// as `Date`, and every row must then hold a valid date in the same format.
+    #[serde(default)]
+    pub date_format: DateFormat,
+    /// Character encoding of the file: `"auto"` (the default), `"utf-8"` or
+    /// `"windows-1252"` (also accepted as `"latin-1"` or `"iso-8859-1"`). With `"auto"`,
+    /// files that are not valid UTF-8 are read as Windows-1252, which is what Excel on
+    /// Windows produces. The file is transcoded to UTF-8 before validation.
+    #[serde(default)]
+    pub encoding: CsvEncoding,
+    /// When `true`, the scan does not stop at the first invalid row: it collects up to
+    /// `MAX_COLLECTED_ERRORS` errors and, if there are any, fails with a JSON
+    /// `VerifyErrors` payload instead of a plain message. Defaults to `false`.
+    #[serde(default)]
+    pub collect_all_errors: bool,
+    /// When `true`, skips the "already verified" fast-path and always performs the full
+    /// row-by-row scan, even if the file has not changed since its last verification.
+    #[serde(default)]
+    pub force: bool,
+    /// Optional `http`/`https` URL that receives a POST with the job's final status
+    /// (a JSON `JobCallbackPayload`) once the verification finishes, so integrations do
+    /// not have to poll the job. Failed deliveries are retried with backoff. Only accepted
+    /// when the server has a callback secret configured, and not for hosts on the server's
+    /// own network unless the server allows them.
+    #[serde(default)]
+    pub callback_url: Option<String>,
+}
+
+/// Default quote character for `VerifyCsvRequest::quote`.
+fn default_quote() -> Option<String> {
+    Some("\"".to_string())
+}
+
+/// Query parameters for the `GET /api/data_sources/csv/columns/{template_id}` endpoint.
+///
+/// Settings needed to read the header and first data row of the stored CSV file. They
+/// mean the same as in `VerifyCsvRequest` and default the same way; cells are always
+/// parsed with `"` as the quote character.
+#[derive(Deserialize)]
+pub struct CsvColumnsQuery {
+    /// Column delimiter of the file; detected from the header when omitted. Checked like
+    /// `VerifyCsvRequest::delimiter`.
+    #[serde(default)]
+    pub delimiter: Option<String>,
+    /// Number of leading lines to ignore before the header row. Defaults to the value
+    /// stored by the template's last verification request.
+    #[serde(default)]
+    pub skip_lines: Option<usize>,
+    /// Separator convention used to infer `Number` and `Currency` columns.
+    #[serde(default)]
+    pub number_format: NumberFormat,
+    /// Format used to infer `Date` columns.
+    #[serde(default)]
+    pub date_format: DateFormat,
+    /// Character encoding of the file.
+    #[serde(default)]
+    pub encoding: CsvEncoding,
+}
+
+/// Query parameters for the `GET /api/data_sources/csv/hexdump/{template_id}` endpoint.
+///
+/// Selects the slice of the stored CSV file to dump. Both fields are optional: the dump
+/// starts at the beginning of the file by default and the backend applies a default and
+/// a maximum length.
+#[derive(Deserialize)]
+pub struct HexdumpQuery {
+    /// Byte offset in the file where the dump starts.
+    pub offset: Option<u64>,
+    /// Number of bytes to dump.
+    pub len: Option<u64>,
+}
+
+/// Query parameters for the `GET /api/templates/pdf/{template_id}` endpoint.
+///
+/// Unknown parameters (such as the frontend's cache-busting `t`) are ignored.
+#[derive(Deserialize)]
+pub struct PdfQuery {
+    /// When `true`, any element that fails to render aborts the whole PDF. By default
+    /// the failing element is replaced by a "[elemento no renderizable]" paragraph and
+    /// reported in the `X-PDF-Warnings` response header.
+    #[serde(default)]
+    pub strict: bool,
+    /// Paper size of the PDF (`A4`, `Letter`, `Legal` or `A5`). When omitted, the page size
+    /// saved with the template is used.
+    #[serde(default)]
+    pub page_size: Option<PageSize>,
+    /// Page orientation (`Portrait` or `Landscape`). When omitted, the orientation saved
+    /// with the template is used, so a different one can be previewed without saving.
+    #[serde(default)]
+    pub orientation: Option<Orientation>,
+    /// When `true`, bare `http://` and `https://` URLs in the text are rendered as links,
+    /// as if written `[url](url)`.
+    #[serde(default)]
+    pub autolink: bool,
+}
+
+/// JSON payload for the stateless `POST /api/render/markdown` endpoint.
+///
+/// Unlike `POST /api/templates/pdf/preview`, no template id is involved and nothing is read
+/// from or written to the database: the PDF is rendered only from what is posted here.
+#[derive(Deserialize)]
+pub struct RenderMarkdownRequest {
+    /// The markdown-like text to render, using the same syntax as template text.
+    pub text: String,
+    /// Images referenced from `text` with `[img:ID]` tags.
+    #[serde(default)]
+    pub images: Vec<Image>,
+    /// Constants referenced from `text` with `[var:NAME]` tags.
+    #[serde(default)]
+    pub vars: Vec<TemplateVar>,
+    /// Rendering settings.
+    #[serde(default)]
+    pub settings: RenderSettings,
+}
+
+/// Rendering settings accepted by `POST /api/render/markdown`.
+#[derive(Deserialize, Default)]
+pub struct RenderSettings {
+    /// When `true`, any element that fails to render aborts the PDF instead of being
+    /// replaced by a placeholder paragraph.
+    #[serde(default)]
+    pub strict: bool,
+    /// Paper size of the PDF. Defaults to `A4`.
+    #[serde(default)]
+    pub page_size: PageSize,
+    /// Page margins in millimeters. Defaults to `DEFAULT_MARGIN_MM` on every side.
+    #[serde(default)]
+    pub margins: PageMargins,
+    /// Page orientation. Defaults to `Portrait`.
+    #[serde(default)]
+    pub orientation: Orientation,
+    /// When `true`, bare `http://` and `https://` URLs are rendered as links.
+    #[serde(default)]
+    pub autolink: bool,
 }
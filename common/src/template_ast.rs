@@ -0,0 +1,1002 @@
+//! # Template AST
+//!
+//! The single parser of the template syntax. `parse_template` turns template text into a
+//! list of `TemplateNode`s (paragraphs, headings, list items, images, placeholders, tables
+//! and line breaks), each with its inline content already split into styled `Span`s. The
+//! backend's PDF and DOCX renderers and the editor preview (`crate::preview`) all render
+//! this tree, so the syntax is interpreted in one place and every output shows the same
+//! content.
+//!
+//! Before parsing, `{{TITLE}}` column references and `[ph:TITLE:BASE64]` tags written inside
+//! a line are replaced with their column value, and, optionally, bare URLs are turned into
+//! links (`autolink_urls`). Column values are kept out of the template syntax until the
+//! spans are built: see `protect_column_value`.
+//!
+//! The syntax itself is described in the backend's `services::templates::pdf` module docs.
+
+use crate::model::pdf::{is_allowed_link_url, parse_hex_color};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use std::collections::HashMap;
+use std::ops::Range;
+
+/// Marks the start of a protected column value (see `protect_column_value`). Private-use
+/// code point, never typed by users.
+const COLUMN_VALUE_START: char = '\u{E000}';
+/// Marks the end of a protected column value.
+const COLUMN_VALUE_END: char = '\u{E001}';
+
+/// A color as red, green and blue components.
+pub type Rgb = (u8, u8, u8);
+
+/// Represents the text style for a segment of text within a paragraph.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextStyle {
+    /// Standard, unstyled text.
+    Regular,
+    /// Bold text.
+    Bold,
+    /// Italic text.
+    Italic,
+    /// Bold and italic text.
+    BoldItalic,
+    /// Underlined text (`__text__`).
+    Underline,
+    /// Struck-through text (`~~text~~`).
+    Strikethrough,
+}
+
+impl TextStyle {
+    /// This style with bold added, as used for table headers and headings. Italic text
+    /// becomes bold-italic; any other style becomes plain bold.
+    pub fn bolded(self) -> TextStyle {
+        match self {
+            TextStyle::Italic | TextStyle::BoldItalic => TextStyle::BoldItalic,
+            _ => TextStyle::Bold,
+        }
+    }
+}
+
+/// A run of text with a single style.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Span {
+    /// The text, with column values already expanded.
+    pub text: String,
+    pub style: TextStyle,
+    /// Text color from a `{color:...}` span, as RGB. `None` uses the default color.
+    pub color: Option<Rgb>,
+    /// The column title, if the text is a column value (from `{{TITLE}}` or a
+    /// `[ph:TITLE:BASE64]` tag). The preview shows it on hover.
+    pub column: Option<String>,
+}
+
+/// A piece of inline content: styled text or a link.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Inline {
+    Text(Span),
+    /// A `[text](url)` link whose target is allowed (see `is_allowed_link_url`).
+    Link {
+        /// The link text as written, style markers included.
+        label: String,
+        /// The target, trimmed.
+        url: String,
+        /// The link text split into styled spans.
+        spans: Vec<Span>,
+    },
+}
+
+/// Horizontal alignment of an image.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImageAlignment {
+    Left,
+    Center,
+    Right,
+}
+
+/// The image id and layout options of an image tag.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImageTag {
+    pub id: String,
+    pub alignment: ImageAlignment,
+    /// Width requested with `w=N`, in CSS pixels. An invalid value is infinite, so only
+    /// the page width bounds the image.
+    pub width_px: Option<f64>,
+}
+
+/// A block of a parsed template.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TemplateNode {
+    /// An empty line.
+    LineBreak,
+    /// A line of text.
+    Paragraph(Vec<Inline>),
+    /// A `#` to `######` heading line, without the markers. `level` is the number of `#`.
+    Heading { level: u8, content: Vec<Inline> },
+    /// A `- ` list item, without the prefix.
+    ListItem(Vec<Inline>),
+    /// An `[img:...]` line. `source` is the line itself, for warnings.
+    Image { source: String, tag: ImageTag },
+    /// A line made of a single `[ph:...]` tag: the decoded content, one entry per line of
+    /// it, or `None` if it cannot be decoded.
+    Placeholder(Option<Vec<Vec<Span>>>),
+    /// A pipe table. Every row has exactly as many cells as the header.
+    Table {
+        header: Vec<Vec<Inline>>,
+        rows: Vec<Vec<Vec<Inline>>>,
+    },
+}
+
+/// A node and the index (0-based) of the template line it starts on.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LocatedNode {
+    pub line: usize,
+    pub node: TemplateNode,
+}
+
+/// Font size, in points, of a heading of the given level in the PDF and DOCX exports.
+///
+/// Body text is 11 pt. The first three levels follow the proportions browsers give `<h1>`
+/// to `<h3>`, so the exports match the preview; deeper levels keep the body size and are
+/// only bold.
+pub fn heading_font_size_pt(level: u8) -> u8 {
+    match level {
+        1 => 22,
+        2 => 16,
+        3 => 13,
+        _ => 11,
+    }
+}
+
+/// Parses template text into its nodes.
+///
+/// Line endings are normalized and a leading byte order mark is dropped. `{{TITLE}}`
+/// references and inline `[ph:TITLE:BASE64]` tags are then replaced with their column
+/// value, and, with `autolink`, bare URLs are linked. Each line is then read as, in this
+/// order: a pipe table (a header row followed by a `---|---` separator), a `#` heading, a
+/// `- ` list item, an `[img:...]` tag, a single `[ph:...]` tag, or a plain paragraph. Lines
+/// are trimmed; empty lines become `TemplateNode::LineBreak`.
+pub fn parse_template(template_text: &str, autolink: bool) -> Vec<LocatedNode> {
+    let template_text = normalize_text(template_text);
+    let template_text = substitute_column_refs(&template_text);
+    let mut template_text = protect_inline_placeholders(&template_text);
+    if autolink {
+        template_text = autolink_urls(&template_text);
+    }
+
+    let mut nodes = Vec::new();
+    let mut lines = template_text.lines().enumerate().peekable();
+    while let Some((line_idx, raw_line)) = lines.next() {
+        let line = raw_line.trim();
+        let node = if line.is_empty() {
+            TemplateNode::LineBreak
+        } else if is_table_row(line)
+            && lines
+                .peek()
+                .is_some_and(|(_, next)| is_table_separator(next.trim()))
+        {
+            // A pipe table starts with a header row followed by a `---|---` separator row.
+            // Its data rows are collected until the first line that is not a table row.
+            lines.next();
+            let mut rows = Vec::new();
+            while let Some((_, row)) = lines.next_if(|(_, next)| is_table_row(next.trim())) {
+                rows.push(row.trim());
+            }
+            parse_table(line, &rows)
+        } else if let Some((level, text)) = parse_heading(line) {
+            TemplateNode::Heading {
+                level,
+                content: parse_styles(text),
+            }
+        } else if let Some(item_text) = line.strip_prefix("- ") {
+            TemplateNode::ListItem(parse_styles(item_text))
+        } else if line.starts_with("[img:") && line.ends_with(']') {
+            TemplateNode::Image {
+                source: line.to_string(),
+                tag: parse_image_tag(&line[5..line.len() - 1]),
+            }
+        } else if let Some(inner) = placeholder_line(line) {
+            TemplateNode::Placeholder(parse_placeholder(inner))
+        } else {
+            TemplateNode::Paragraph(parse_styles(line))
+        };
+        nodes.push(LocatedNode {
+            line: line_idx,
+            node,
+        });
+    }
+    nodes
+}
+
+/// Normalizes line endings (CRLF/CR to LF) and removes leading zero-width characters, so
+/// text pasted from any platform or editor parses the same.
+fn normalize_text(input: &str) -> String {
+    input
+        .replace("\r\n", "\n")
+        .replace('\r', "\n")
+        .trim_start_matches(['\u{feff}', '\u{200b}'])
+        .to_string()
+}
+
+/// Returns the level and text of a heading line (`# Title` to `###### Title`).
+///
+/// The `#` markers must be followed by a space, so `#hashtag` stays a paragraph.
+fn parse_heading(line: &str) -> Option<(u8, &str)> {
+    let level = line.chars().take_while(|&c| c == '#').count();
+    let text = line[level..].strip_prefix(' ')?;
+    (1..=6)
+        .contains(&level)
+        .then(|| (level as u8, text.trim_start()))
+}
+
+/// Returns the inside of `line` if the whole line is a single `[ph:...]` tag.
+fn placeholder_line(line: &str) -> Option<&str> {
+    let inner = line.strip_prefix("[ph:")?.strip_suffix(']')?;
+    (!inner.contains(']')).then_some(inner)
+}
+
+/// Collects the values of the `[ph:TITLE:BASE64]` tags in `text`, keyed by column title.
+///
+/// Tags whose value cannot be decoded are skipped. If a column appears several times, the
+/// first tag wins.
+fn placeholder_values(text: &str) -> HashMap<String, String> {
+    const OPEN: &str = "[ph:";
+    let mut values = HashMap::new();
+    let mut rest = text;
+
+    while let Some(start) = rest.find(OPEN) {
+        let after = &rest[start + OPEN.len()..];
+        let Some(end) = after.find(']') else {
+            break;
+        };
+        let inner = &after[..end];
+        if let Some((title, _)) = inner.split_once(':')
+            && let Some(value) = decode_placeholder(inner)
+        {
+            values.entry(title.to_string()).or_insert(value);
+        }
+        rest = &after[end + 1..];
+    }
+    values
+}
+
+/// Replaces every `{{TITLE}}` or `{{TITLE|fallback}}` reference in `text` with the value of
+/// the column `TITLE`, taken from the `[ph:...]` tags of the same text.
+///
+/// Titles are trimmed before lookup. A column without a value is replaced with its fallback,
+/// or with an empty string if there is none. An unclosed `{{` is left untouched.
+///
+/// Values come from the CSV, so they are inserted protected (`protect_column_value`): the
+/// line and style parsers only see an opaque token, which is turned back into the plain
+/// value when the spans are built.
+fn substitute_column_refs(text: &str) -> String {
+    const OPEN: &str = "{{";
+    const CLOSE: &str = "}}";
+    if !text.contains(OPEN) {
+        return text.to_string();
+    }
+
+    let values = placeholder_values(text);
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find(OPEN) {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + OPEN.len()..];
+        match after.find(CLOSE) {
+            Some(end) => {
+                let reference = &after[..end];
+                let (title, fallback) = match reference.split_once('|') {
+                    Some((title, fallback)) => (title, fallback),
+                    None => (reference, ""),
+                };
+                match values.get(title.trim()) {
+                    Some(value) => out.push_str(&protect_column_value(title.trim(), value)),
+                    None => out.push_str(fallback),
+                }
+                rest = &after[end + CLOSE.len()..];
+            }
+            None => {
+                out.push_str(&rest[start..]);
+                rest = "";
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Replaces the `[ph:TITLE:BASE64]` tags written inside a line of text with their
+/// protected value (see `protect_column_value`), so they render as the value, like
+/// `{{TITLE}}`.
+///
+/// A line made of a single tag is left alone: it is a placeholder node (see
+/// `parse_placeholder`). Tags without a title or whose value does not decode are left as
+/// written.
+fn protect_inline_placeholders(text: &str) -> String {
+    const OPEN: &str = "[ph:";
+    if !text.contains(OPEN) {
+        return text.to_string();
+    }
+
+    let mut out = String::with_capacity(text.len());
+    for line in text.split_inclusive('\n') {
+        if placeholder_line(line.trim()).is_some() {
+            out.push_str(line);
+            continue;
+        }
+        let mut rest = line;
+        while let Some(start) = rest.find(OPEN) {
+            let after = &rest[start + OPEN.len()..];
+            let Some(end) = after.find(']') else {
+                break;
+            };
+            out.push_str(&rest[..start]);
+            let value = after[..end]
+                .split_once(':')
+                .filter(|(title, _)| !title.is_empty())
+                .and_then(|(title, value)| Some((title, decode_base64(value)?)));
+            match value {
+                Some((title, value)) => out.push_str(&protect_column_value(title, &value)),
+                None => out.push_str(&rest[start..start + OPEN.len() + end + 1]),
+            }
+            rest = &after[end + 1..];
+        }
+        out.push_str(rest);
+    }
+    out
+}
+
+/// Wraps a column value and its column title in `COLUMN_VALUE_START`/`COLUMN_VALUE_END`.
+///
+/// Both are Base64-encoded and separated by a `:`, so the token contains no character the
+/// template syntax reacts to (`*`, `_`, `~`, `[`, `{`, `|`, `<` or a line break).
+fn protect_column_value(title: &str, value: &str) -> String {
+    format!(
+        "{}{}:{}{}",
+        COLUMN_VALUE_START,
+        BASE64.encode(title),
+        BASE64.encode(value),
+        COLUMN_VALUE_END
+    )
+}
+
+/// A token written by `protect_column_value`, decoded.
+struct ColumnValue {
+    /// Where the token is in the searched text, in bytes.
+    range: Range<usize>,
+    title: String,
+    /// The value, with line breaks replaced by spaces since it is drawn inside a single
+    /// line of the template.
+    value: String,
+}
+
+/// Finds the first token written by `protect_column_value` in `text`. Tokens that do not
+/// decode are skipped, so they stay in the text as they are.
+fn find_column_value(text: &str) -> Option<ColumnValue> {
+    let mut search_from = 0;
+    while let Some(offset) = text[search_from..].find(COLUMN_VALUE_START) {
+        let start = search_from + offset;
+        let body = start + COLUMN_VALUE_START.len_utf8();
+        let decoded = text[body..].find(COLUMN_VALUE_END).and_then(|len| {
+            let (title, value) = text[body..body + len].split_once(':')?;
+            let end = body + len + COLUMN_VALUE_END.len_utf8();
+            Some((decode_base64(title)?, decode_base64(value)?, end))
+        });
+        match decoded {
+            Some((title, value, end)) => {
+                return Some(ColumnValue {
+                    range: start..end,
+                    title,
+                    value: value.replace(['\r', '\n'], " "),
+                });
+            }
+            None => search_from = body,
+        }
+    }
+    None
+}
+
+/// Replaces the tokens written by `protect_column_value` with the values they carry.
+fn expand_column_values(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(found) = find_column_value(rest) {
+        out.push_str(&rest[..found.range.start]);
+        out.push_str(&found.value);
+        rest = &rest[found.range.end..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Splits `text` into spans with the given `style` and `color`, expanding the tokens
+/// written by `protect_column_value`: each column value gets a span of its own that
+/// carries the column title.
+fn column_spans(text: &str, style: TextStyle, color: Option<Rgb>) -> Vec<Span> {
+    let mut spans = Vec::new();
+    let mut rest = text;
+    let span = |text: String, column: Option<String>| Span {
+        text,
+        style,
+        color,
+        column,
+    };
+    while let Some(found) = find_column_value(rest) {
+        if found.range.start > 0 {
+            spans.push(span(rest[..found.range.start].to_string(), None));
+        }
+        spans.push(span(found.value, Some(found.title)));
+        rest = &rest[found.range.end..];
+    }
+    if !rest.is_empty() {
+        spans.push(span(rest.to_string(), None));
+    }
+    spans
+}
+
+/// Decodes a Base64 string holding UTF-8 text.
+fn decode_base64(encoded: &str) -> Option<String> {
+    String::from_utf8(BASE64.decode(encoded).ok()?).ok()
+}
+
+/// Wraps every bare `http://` or `https://` URL of `text` in a `[url](url)` link.
+///
+/// A URL must start a word (at the start of the text or after whitespace), so the targets
+/// of existing `[text](url)` links are left alone. Trailing punctuation such as a final
+/// period is not considered part of the URL.
+fn autolink_urls(text: &str) -> String {
+    const TRAILING_PUNCTUATION: &[char] = &['.', ',', ';', ':', '!', '?', ')', ']', '"', '\''];
+    let mut out = String::with_capacity(text.len());
+    for piece in text.split_inclusive(char::is_whitespace) {
+        let word = piece.trim_end_matches(char::is_whitespace);
+        let url = word.trim_end_matches(TRAILING_PUNCTUATION);
+        let lower = url.to_ascii_lowercase();
+        if (lower.starts_with("http://") || lower.starts_with("https://"))
+            && is_allowed_link_url(url)
+        {
+            out.push_str(&format!("[{}]({})", url, url));
+            out.push_str(&piece[url.len()..]);
+        } else {
+            out.push_str(piece);
+        }
+    }
+    out
+}
+
+/// Parses a line of text for Markdown-like styling.
+///
+/// `[text](url)` links are split out first (see `split_links`). In the text between them,
+/// `{color:#RRGGBB}...{/color}` spans are split out next (see `split_color_spans`); the
+/// style markers (`*`, `**`, `***`, `__`, `~~`) are then parsed inside each span. A link
+/// whose target is not allowed is parsed as plain text.
+fn parse_styles(line: &str) -> Vec<Inline> {
+    let mut inlines = Vec::new();
+    for (text, url) in split_links(line) {
+        let url = url.map(|url| expand_column_values(url.trim()));
+        match url {
+            Some(url) if is_allowed_link_url(&url) => inlines.push(Inline::Link {
+                label: expand_column_values(text),
+                url,
+                spans: parse_emphasis(text, None),
+            }),
+            _ => inlines.extend(
+                split_color_spans(text)
+                    .into_iter()
+                    .flat_map(|(text, color)| parse_emphasis(text, color))
+                    .map(Inline::Text),
+            ),
+        }
+    }
+    inlines
+}
+
+/// Splits a line into `[text](url)` links and the text between them.
+///
+/// The link text may not contain brackets. Markdown images (`![alt](url)`) and brackets
+/// that are not followed by `(url)`, such as `[ph:...]` tags, are left as text.
+///
+/// # Returns
+/// The pieces of the line in order; links carry their URL, the text between them `None`.
+fn split_links(line: &str) -> Vec<(&str, Option<&str>)> {
+    let mut pieces = Vec::new();
+    let mut rest = line;
+    let mut search_from = 0;
+
+    while let Some(offset) = rest[search_from..].find('[') {
+        let start = search_from + offset;
+        let after = &rest[start + 1..];
+        let link = after.find("](").and_then(|text_end| {
+            let text = &after[..text_end];
+            let target = &after[text_end + 2..];
+            let url_end = target.find(')')?;
+            let valid = !text.is_empty() && !text.contains(['[', ']']);
+            valid.then_some((text, &target[..url_end], text_end + 2 + url_end + 1))
+        });
+        match link {
+            Some((text, url, len)) if !rest[..start].ends_with('!') => {
+                if start > 0 {
+                    pieces.push((&rest[..start], None));
+                }
+                pieces.push((text, Some(url)));
+                rest = &after[len..];
+                search_from = 0;
+            }
+            _ => search_from = start + 1,
+        }
+    }
+    if !rest.is_empty() {
+        pieces.push((rest, None));
+    }
+    pieces
+}
+
+/// Splits a line into `{color:CODE}...{/color}` spans and the text between them.
+///
+/// `CODE` is a hex color (`#1976d2` or `#19d`). A span with an invalid code keeps its text
+/// but loses its color, so it renders in the default color. A span without a closing
+/// `{/color}` is not a span: the rest of the line is returned as plain text, markers
+/// included.
+///
+/// # Returns
+/// The pieces of the line in order, each with its color (`None` for the default color).
+fn split_color_spans(line: &str) -> Vec<(&str, Option<Rgb>)> {
+    const OPEN: &str = "{color:";
+    const CLOSE: &str = "{/color}";
+    let mut pieces = Vec::new();
+    let mut rest = line;
+
+    while let Some(start) = rest.find(OPEN) {
+        let after = &rest[start + OPEN.len()..];
+        let Some(code_end) = after.find('}') else {
+            break;
+        };
+        let Some(text_len) = after[code_end + 1..].find(CLOSE) else {
+            break;
+        };
+        if start > 0 {
+            pieces.push((&rest[..start], None));
+        }
+        let color = parse_hex_color(&after[..code_end]);
+        let text = &after[code_end + 1..code_end + 1 + text_len];
+        pieces.push((text, color));
+        rest = &after[code_end + 1 + text_len + CLOSE.len()..];
+    }
+    if !rest.is_empty() {
+        pieces.push((rest, None));
+    }
+    pieces
+}
+
+/// Inline style markers, in matching order: longer markers first so that `***` is not
+/// read as `**` followed by `*`.
+const STYLE_MARKERS: [(&str, TextStyle); 5] = [
+    ("***", TextStyle::BoldItalic),
+    ("**", TextStyle::Bold),
+    ("__", TextStyle::Underline),
+    ("~~", TextStyle::Strikethrough),
+    ("*", TextStyle::Italic),
+];
+
+/// Parses the inline style markers of `line` (see `STYLE_MARKERS`) into `Span`s, all with
+/// the given `color`. Column values protected by `protect_column_value` are expanded
+/// here (see `column_spans`), after all parsing.
+///
+/// A marker without a matching closing marker is kept as plain text, and so is a pair of
+/// markers with nothing between them, so `**` is not read as an empty italic span.
+fn parse_emphasis(line: &str, color: Option<Rgb>) -> Vec<Span> {
+    let mut spans = Vec::new();
+    let mut plain = String::new();
+    let mut rest = line;
+
+    'outer: while let Some(c) = rest.chars().next() {
+        for (marker, style) in STYLE_MARKERS {
+            let Some(after) = rest.strip_prefix(marker) else {
+                continue;
+            };
+            if let Some(end) = after.find(marker).filter(|&end| end > 0) {
+                if !plain.is_empty() {
+                    spans.extend(column_spans(
+                        &std::mem::take(&mut plain),
+                        TextStyle::Regular,
+                        color,
+                    ));
+                }
+                spans.extend(column_spans(&after[..end], style, color));
+                rest = &after[end + marker.len()..];
+                continue 'outer;
+            }
+        }
+        // Not the start of a closed span: take the character literally.
+        plain.push(c);
+        rest = &rest[c.len_utf8()..];
+    }
+    if !plain.is_empty() {
+        spans.extend(column_spans(&plain, TextStyle::Regular, color));
+    }
+
+    spans
+}
+
+/// Decodes a Base64 string from a placeholder tag.
+///
+/// The placeholder format is expected to be `[ph:BASE64_STRING]`.
+/// This function extracts and decodes the `BASE64_STRING`.
+///
+/// # Arguments
+/// * `ph` - The inner content of the placeholder tag (e.g., `ph:BASE64_STRING`).
+///
+/// # Returns
+/// An `Option<String>` containing the decoded text, or `None` if decoding fails.
+fn decode_placeholder(ph: &str) -> Option<String> {
+    decode_base64(ph.rsplit(':').next()?)
+}
+
+/// Parses the inside of a `[ph:...]` tag into the lines of its decoded content.
+///
+/// Where the content comes from decides how it is read:
+/// - `[ph:TITLE:BASE64]` is a CSV column value, inserted by the editor. It is data, so it
+///   is taken literally: a cell containing `<b>` shows the tag instead of turning bold.
+/// - `[ph:BASE64]` is written by the template author, and nested `<b>` or `<i>` tags in it
+///   are applied (see `parse_tagged_spans`).
+///
+/// # Returns
+/// The spans of each line, or `None` if the content cannot be decoded.
+fn parse_placeholder(inner: &str) -> Option<Vec<Vec<Span>>> {
+    // Base64 has no `:`, so a `:` means the tag carries a column title.
+    let title = inner.split_once(':').map(|(title, _)| title.to_string());
+    let decoded = decode_placeholder(inner)?;
+    Some(
+        decoded
+            .split('\n')
+            .map(|line| {
+                if title.is_some() {
+                    vec![Span {
+                        text: line.to_string(),
+                        style: TextStyle::Regular,
+                        color: None,
+                        column: title.clone(),
+                    }]
+                } else {
+                    parse_tagged_spans(line)
+                }
+            })
+            .collect(),
+    )
+}
+
+/// Finds the first occurrence of a `<b>` or `<i>` tag in a string.
+///
+/// # Returns
+/// The style of the tag and its starting position, or `None` if there is none.
+fn find_next_tag(text: &str) -> Option<(TextStyle, usize)> {
+    let b_pos = text.find("<b>");
+    let i_pos = text.find("<i>");
+    match (b_pos, i_pos) {
+        (Some(b), Some(i)) if b < i => Some((TextStyle::Bold, b)),
+        (Some(_), Some(i)) => Some((TextStyle::Italic, i)),
+        (Some(b), None) => Some((TextStyle::Bold, b)),
+        (None, Some(i)) => Some((TextStyle::Italic, i)),
+        (None, None) => None,
+    }
+}
+
+/// Parses a string with `<b>` and `<i>` tags into spans.
+///
+/// This is used for content from placeholders which may contain simple HTML-like tags. An
+/// unclosed tag makes the rest of the line plain text.
+fn parse_tagged_spans(text: &str) -> Vec<Span> {
+    let mut spans = Vec::new();
+    let mut rest = text;
+    let plain = |text: &str| Span {
+        text: text.to_string(),
+        style: TextStyle::Regular,
+        color: None,
+        column: None,
+    };
+
+    while let Some((style, start)) = find_next_tag(rest) {
+        // Add any plain text before the tag.
+        if start > 0 {
+            spans.push(plain(&rest[..start]));
+        }
+
+        let (tag_open, tag_close) = match style {
+            TextStyle::Bold => ("<b>", "</b>"),
+            _ => ("<i>", "</i>"),
+        };
+
+        // Find the corresponding closing tag.
+        if let Some(rel_end) = rest[start + tag_open.len()..].find(tag_close) {
+            let styled_text = &rest[start + tag_open.len()..start + tag_open.len() + rel_end];
+            spans.push(Span {
+                text: styled_text.to_string(),
+                style,
+                color: None,
+                column: None,
+            });
+            // Move past the processed segment.
+            rest = &rest[start + tag_open.len() + rel_end + tag_close.len()..];
+        } else {
+            // If a tag is unclosed, treat the rest of the line as plain text.
+            spans.push(plain(&rest[start..]));
+            return spans;
+        }
+    }
+
+    // Add any remaining plain text at the end.
+    if !rest.is_empty() {
+        spans.push(plain(rest));
+    }
+
+    spans
+}
+
+/// Parses the inside of an image tag: the image id followed by `|`-separated options.
+///
+/// `center`, `right` and `left` set the alignment (left by default) and `w=N` the width.
+/// Unknown options are ignored.
+fn parse_image_tag(inner: &str) -> ImageTag {
+    let mut parts = inner.split('|');
+    let mut tag = ImageTag {
+        id: parts.next().unwrap_or_default().to_string(),
+        alignment: ImageAlignment::Left,
+        width_px: None,
+    };
+    for option in parts {
+        match option {
+            "left" => tag.alignment = ImageAlignment::Left,
+            "center" => tag.alignment = ImageAlignment::Center,
+            "right" => tag.alignment = ImageAlignment::Right,
+            _ => {
+                if let Some(width) = option.strip_prefix("w=") {
+                    let width = width.parse::<f64>().ok().filter(|w| *w > 0.0);
+                    tag.width_px = Some(width.unwrap_or(f64::INFINITY));
+                }
+            }
+        }
+    }
+    tag
+}
+
+/// Returns `true` if `line` can be a row of a pipe table, i.e. it contains a `|`.
+fn is_table_row(line: &str) -> bool {
+    line.contains('|')
+}
+
+/// Returns `true` if `line` is the separator row of a pipe table (e.g. `---|:---:|---:`).
+///
+/// Every cell must be made of at least one `-`, optionally with a leading and/or trailing
+/// `:` (the alignment markers, which are accepted but not applied).
+fn is_table_separator(line: &str) -> bool {
+    is_table_row(line)
+        && split_table_row(line).iter().all(|cell| {
+            let dashes = cell.strip_prefix(':').unwrap_or(cell);
+            let dashes = dashes.strip_suffix(':').unwrap_or(dashes);
+            !dashes.is_empty() && dashes.chars().all(|c| c == '-')
+        })
+}
+
+/// Splits a pipe table row into its trimmed cells. Leading and trailing pipes are optional.
+fn split_table_row(line: &str) -> Vec<&str> {
+    let line = line.trim();
+    let line = line.strip_prefix('|').unwrap_or(line);
+    let line = line.strip_suffix('|').unwrap_or(line);
+    line.split('|').map(str::trim).collect()
+}
+
+/// Parses a pipe table.
+///
+/// The header row sets the number of columns. Data rows with fewer cells are padded with
+/// empty cells and extra cells are dropped, as in GitHub Markdown. Cell text is parsed with
+/// `parse_styles`, so inline styles work inside cells.
+///
+/// # Arguments
+/// * `header` - The header row.
+/// * `rows` - The data rows (without the separator row).
+fn parse_table(header: &str, rows: &[&str]) -> TemplateNode {
+    let header_cells = split_table_row(header);
+    let columns = header_cells.len();
+    let parse_row = |cells: &[&str]| -> Vec<Vec<Inline>> {
+        (0..columns)
+            .map(|i| parse_styles(cells.get(i).copied().unwrap_or("")))
+            .collect()
+    };
+    TemplateNode::Table {
+        header: parse_row(&header_cells),
+        rows: rows
+            .iter()
+            .map(|row| parse_row(&split_table_row(row)))
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nodes(text: &str) -> Vec<TemplateNode> {
+        parse_template(text, false)
+            .into_iter()
+            .map(|located| located.node)
+            .collect()
+    }
+
+    fn span(text: &str, style: TextStyle) -> Span {
+        Span {
+            text: text.to_string(),
+            style,
+            color: None,
+            column: None,
+        }
+    }
+
+    fn plain(text: &str) -> Inline {
+        Inline::Text(span(text, TextStyle::Regular))
+    }
+
+    fn column(text: &str, title: &str) -> Span {
+        Span {
+            column: Some(title.to_string()),
+            ..span(text, TextStyle::Regular)
+        }
+    }
+
+    /// `- **x** | [img:logo] <b>y</b> {{Nombre}}`, a CSV value full of template syntax.
+    const SYNTAX_VALUE: &str = "- **x** | [img:logo] <b>y</b> {{Nombre}}";
+    const SYNTAX_VALUE_B64: &str = "LSAqKngqKiB8IFtpbWc6bG9nb10gPGI+eTwvYj4ge3tOb21icmV9fQ==";
+
+    #[test]
+    fn inline_placeholder_becomes_column_span() {
+        assert_eq!(
+            nodes("Hola [ph:Nombre:QW5h]!"),
+            [TemplateNode::Paragraph(vec![
+                plain("Hola "),
+                Inline::Text(column("Ana", "Nombre")),
+                plain("!"),
+            ])]
+        );
+    }
+
+    #[test]
+    fn several_tags_on_one_line() {
+        assert_eq!(
+            nodes("**Hola** [ph:Nombre:QW5h] de [ph:Ciudad:TGltYQ==]"),
+            [TemplateNode::Paragraph(vec![
+                Inline::Text(span("Hola", TextStyle::Bold)),
+                plain(" "),
+                Inline::Text(column("Ana", "Nombre")),
+                plain(" de "),
+                Inline::Text(column("Lima", "Ciudad")),
+            ])]
+        );
+    }
+
+    #[test]
+    fn invalid_base64_is_left_as_written() {
+        assert_eq!(
+            nodes("Valor: [ph:Nombre:@@@]\n[ph:@@@]"),
+            [
+                TemplateNode::Paragraph(vec![plain("Valor: [ph:Nombre:@@@]")]),
+                TemplateNode::Placeholder(None),
+            ]
+        );
+    }
+
+    #[test]
+    fn column_reference_uses_value_or_fallback() {
+        assert_eq!(
+            nodes("Hola {{Nombre|cliente}}, de {{Ciudad|Lima}}\n[ph:Nombre:QW5h]"),
+            [
+                TemplateNode::Paragraph(vec![
+                    plain("Hola "),
+                    Inline::Text(column("Ana", "Nombre")),
+                    plain(", de Lima"),
+                ]),
+                TemplateNode::Placeholder(Some(vec![vec![column("Ana", "Nombre")]])),
+            ]
+        );
+    }
+
+    #[test]
+    fn column_value_syntax_is_taken_literally() {
+        let text = format!(
+            "{{{{Nota}}}}\nVer [ph:Nota:{b64}]\n[ph:Nota:{b64}]",
+            b64 = SYNTAX_VALUE_B64
+        );
+        assert_eq!(
+            nodes(&text),
+            [
+                TemplateNode::Paragraph(vec![Inline::Text(column(SYNTAX_VALUE, "Nota"))]),
+                TemplateNode::Paragraph(vec![
+                    plain("Ver "),
+                    Inline::Text(column(SYNTAX_VALUE, "Nota")),
+                ]),
+                TemplateNode::Placeholder(Some(vec![vec![column(SYNTAX_VALUE, "Nota")]])),
+            ]
+        );
+    }
+
+    /// CSV values that look like template (or spreadsheet) syntax.
+    const SYNTAX_VALUES: [&str; 7] = ["=cmd", "**x**", "- x", "| a |", "[img:id]", "<b>", "{{X}}"];
+
+    #[test]
+    fn each_syntax_value_is_a_single_literal_span() {
+        for value in SYNTAX_VALUES {
+            let b64 = BASE64.encode(value);
+            // The column `X` has a value, so a `{{X}}` inside a value would be visible if
+            // it were substituted.
+            let text = format!("{{{{Nota}}}}\nVer [ph:Nota:{b64}]\n[ph:Nota:{b64}]\n[ph:X:eQ==]");
+            assert_eq!(
+                nodes(&text)[..3],
+                [
+                    TemplateNode::Paragraph(vec![Inline::Text(column(value, "Nota"))]),
+                    TemplateNode::Paragraph(vec![
+                        plain("Ver "),
+                        Inline::Text(column(value, "Nota")),
+                    ]),
+                    TemplateNode::Placeholder(Some(vec![vec![column(value, "Nota")]])),
+                ],
+                "value {:?}",
+                value
+            );
+        }
+    }
+
+    #[test]
+    fn heading_levels() {
+        let heading = |level, text| TemplateNode::Heading {
+            level,
+            content: vec![plain(text)],
+        };
+        assert_eq!(
+            nodes("# Uno\n### Tres\n######   Seis\n####### Siete\n#etiqueta"),
+            [
+                heading(1, "Uno"),
+                heading(3, "Tres"),
+                heading(6, "Seis"),
+                TemplateNode::Paragraph(vec![plain("####### Siete")]),
+                TemplateNode::Paragraph(vec![plain("#etiqueta")]),
+            ]
+        );
+        assert_eq!(
+            [1, 2, 3, 4, 6].map(heading_font_size_pt),
+            [22, 16, 13, 11, 11]
+        );
+    }
+
+    #[test]
+    fn unclosed_emphasis_is_kept_as_text() {
+        assert_eq!(
+            nodes("Texto **sin cerrar\n__a medias y *cursiva*\n~~"),
+            [
+                TemplateNode::Paragraph(vec![plain("Texto **sin cerrar")]),
+                TemplateNode::Paragraph(vec![
+                    plain("__a medias y "),
+                    Inline::Text(span("cursiva", TextStyle::Italic)),
+                ]),
+                TemplateNode::Paragraph(vec![plain("~~")]),
+            ]
+        );
+    }
+
+    #[test]
+    fn table_needs_a_separator_row() {
+        assert_eq!(
+            nodes("a | b\nc | d"),
+            [
+                TemplateNode::Paragraph(vec![plain("a | b")]),
+                TemplateNode::Paragraph(vec![plain("c | d")]),
+            ]
+        );
+        assert_eq!(
+            nodes("| a | b |\n|---|:-:|\n| c |\n| d | e | f |"),
+            [TemplateNode::Table {
+                header: vec![vec![plain("a")], vec![plain("b")]],
+                rows: vec![
+                    vec![vec![plain("c")], vec![]],
+                    vec![vec![plain("d")], vec![plain("e")]],
+                ],
+            }]
+        );
+    }
+}
@@ -1,22 +1,50 @@
+use crate::connection_monitor;
 use common::jobs::JobStatus;
-use common::model::csv::ColumnCheck;
-use gloo_timers::future::sleep;
+use common::model::csv::{ColumnCheck, NumberDisplay, NumberFormat, VerifyReport};
+use common::model::place_holder::{PlaceholderType, DEFAULT_FALSE_LABEL, DEFAULT_TRUE_LABEL};
 use num_format::{Locale, ToFormattedString};
-use serde_json::Value;
-use std::time::Duration;
 use wasm_bindgen::closure::Closure;
 use wasm_bindgen::JsCast;
 use wasm_bindgen_futures::spawn_local;
-use web_sys::{Event, File, HtmlInputElement};
-use yew::{html, Callback, Component, Context, Html, MouseEvent, NodeRef, Properties};
+use web_sys::{Event, EventSource, File, HtmlInputElement, HtmlSelectElement, MessageEvent};
+use yew::{classes, html, Callback, Component, Context, Html, MouseEvent, NodeRef, Properties};
 
-/// Component that triggers a CSV verification job, polls status and provides upload + modal UI.
+/// Column types the user can pick for a column, with their label, in `<select>` order.
+const COLUMN_TYPES: &[(PlaceholderType, &str)] = &[
+    (PlaceholderType::Text, "Texto"),
+    (PlaceholderType::Number, "Número"),
+    (PlaceholderType::Currency, "Moneda"),
+    (PlaceholderType::Email, "Email"),
+    (PlaceholderType::Date, "Fecha"),
+    (PlaceholderType::Phone, "Teléfono"),
+    (PlaceholderType::Boolean, "Sí/No"),
+];
+
+/// Separators the user can pick for a `Number` or `Currency` column, with their label, in
+/// `<select>` order after "Sin formato".
+const NUMBER_SEPARATORS: &[(NumberFormat, &str)] = &[
+    (NumberFormat::DecimalComma, "1.234,56"),
+    (NumberFormat::DecimalPoint, "1,234.56"),
+];
+
+/// Decimals of a number format until the user changes them.
+const DEFAULT_DECIMALS: u8 = 2;
+/// Most decimals the user can pick for a number format.
+const MAX_DECIMALS: u8 = 6;
+
+/// Component that triggers a CSV verification job, follows its status and provides upload + modal UI.
 pub struct CsvDataSourceComponent {
     is_verifying: bool,
     verify_result: Option<Result<bool, String>>,
     job_ticket: Option<String>,
     job_status: Option<JobStatus>,
     column_checks: Option<Vec<ColumnCheck>>,
+    /// Whether the last successful verification scanned every row and column
+    /// (`VerifyReport::is_complete`). `None` until a verification completes.
+    verification_complete: Option<bool>,
+    /// First data rows of the file (`VerifyReport::sample_rows`), shown under the detected
+    /// columns so the user can check they picked the right file. Empty when unknown.
+    sample_rows: Vec<Vec<String>>,
     started_for_template: Option<String>,
 
     // UI state
@@ -24,7 +52,12 @@ pub struct CsvDataSourceComponent {
     file_input_ref: NodeRef,
     uploading: bool,
     upload_error: Option<String>,
-    selected_column: Option<usize>,
+    /// Error of the last attempt to store the column types chosen by the user.
+    types_error: Option<String>,
+    /// Title of the selected column. Columns are tracked by title, not position, so the
+    /// selection stays on the same column if the CSV is re-uploaded with its columns
+    /// reordered.
+    selected_column: Option<String>,
 
     // Show a confirmation dialog before starting the file picker/upload
     show_confirm_upload: bool,
@@ -32,14 +65,172 @@ pub struct CsvDataSourceComponent {
 
 impl CsvDataSourceComponent {
     fn apply_completed(&mut self, payload: String) {
-        match serde_json::from_str::<Vec<ColumnCheck>>(&payload) {
-            Ok(cols) => {
-                self.column_checks = Some(cols);
-                self.verify_result = Some(Ok(true));
+        match serde_json::from_str::<VerifyReport>(&payload) {
+            Ok(report) => {
+                let complete = report.is_complete();
+                self.apply_columns(report.columns, complete);
+                self.sample_rows = report.sample_rows;
             }
             Err(e) => {
                 self.column_checks = None;
-                self.verify_result = Some(Err(format!("Deserialize ColumnCheck: {}", e)));
+                self.verification_complete = None;
+                self.verify_result = Some(Err(format!("Deserialize VerifyReport: {}", e)));
+            }
+        }
+    }
+
+    /// Stores the column schema of a successful verification (or of a verified file read
+    /// through `GET /api/data_sources/csv/columns`).
+    fn apply_columns(&mut self, cols: Vec<ColumnCheck>, complete: bool) {
+        self.verification_complete = Some(complete);
+        // Keep the selection only if its column still exists after a re-upload.
+        if let Some(selected) = &self.selected_column {
+            if !cols.iter().any(|c| &c.title == selected) {
+                self.selected_column = None;
+            }
+        }
+        self.column_checks = Some(cols);
+        self.verify_result = Some(Ok(true));
+    }
+
+    /// Renders the sample rows as a table under the detected columns, or nothing when the
+    /// last result carried none.
+    fn sample_table(&self, cols: &[ColumnCheck]) -> Html {
+        if self.sample_rows.is_empty() {
+            return html! {};
+        }
+        html! {
+            <>
+                <h4>{"Primeras filas"}</h4>
+                <div class="sample-table-wrapper">
+                    <table class="sample-table">
+                        <thead>
+                            <tr>{ for cols.iter().map(|c| html! { <th>{ &c.title }</th> }) }</tr>
+                        </thead>
+                        <tbody>
+                            { for self.sample_rows.iter().map(|row| html! {
+                                <tr>{ for row.iter().map(|cell| html! { <td>{ cell }</td> }) }</tr>
+                            })}
+                        </tbody>
+                    </table>
+                </div>
+            </>
+        }
+    }
+
+    /// Applies a change made by the user to the column with this title, stores the new
+    /// column types and sends them to the parent.
+    ///
+    /// # Returns
+    /// `false` (nothing to render) if there is no such column.
+    fn update_column(
+        &mut self,
+        ctx: &Context<Self>,
+        title: &str,
+        change: impl FnOnce(&mut ColumnCheck),
+    ) -> bool {
+        let Some(cols) = &mut self.column_checks else {
+            return false;
+        };
+        let Some(col) = cols.iter_mut().find(|c| c.title == title) else {
+            return false;
+        };
+        change(col);
+        self.types_error = None;
+        if let Some(id) = ctx.props().template_id.clone() {
+            save_column_types(ctx.link().clone(), id, cols.clone());
+        }
+        self.emit_columns(ctx);
+        true
+    }
+
+    /// Text inputs for the labels a `Boolean` column shows in the template. A label is
+    /// saved when its input loses focus, not on every keystroke.
+    fn boolean_label_inputs(&self, ctx: &Context<Self>, col: &ColumnCheck) -> Html {
+        let input = |value: bool, label: Option<&String>, default: &str| {
+            let title = col.title.clone();
+            let onchange = ctx.link().batch_callback(move |event: Event| {
+                let input: HtmlInputElement = event.target()?.dyn_into().ok()?;
+                Some(CsvDataSourceMsg::SetBooleanLabel(title.clone(), value, input.value()))
+            });
+            html! {
+                <input type="text"
+                    class="col-bool-label"
+                    value={label.cloned().unwrap_or_default()}
+                    placeholder={default.to_string()}
+                    title={format!("Texto que se muestra en la plantilla para '{}'", default)}
+                    {onchange} />
+            }
+        };
+        html! {
+            <>
+                { input(true, col.true_label.as_ref(), DEFAULT_TRUE_LABEL) }
+                { input(false, col.false_label.as_ref(), DEFAULT_FALSE_LABEL) }
+            </>
+        }
+    }
+
+    /// Separator select and decimals input for the format of a `Number` or `Currency`
+    /// column. The decimals input is only shown once a format is picked.
+    fn number_display_inputs(&self, ctx: &Context<Self>, col: &ColumnCheck) -> Html {
+        let current = col.number_display;
+        let title = col.title.clone();
+        let onchange_format = ctx.link().batch_callback(move |event: Event| {
+            let select: HtmlSelectElement = event.target()?.dyn_into().ok()?;
+            let index = usize::try_from(select.selected_index()).ok()?;
+            let number_display = match index.checked_sub(1) {
+                None => None,
+                Some(i) => Some(NumberDisplay {
+                    separators: NUMBER_SEPARATORS.get(i)?.0,
+                    decimals: current.map_or(DEFAULT_DECIMALS, |d| d.decimals),
+                }),
+            };
+            Some(CsvDataSourceMsg::SetNumberDisplay(title.clone(), number_display))
+        });
+        let decimals_input = match current {
+            Some(display) => {
+                let title = col.title.clone();
+                let onchange = ctx.link().batch_callback(move |event: Event| {
+                    let input: HtmlInputElement = event.target()?.dyn_into().ok()?;
+                    let decimals = input.value().parse::<u8>().ok()?.min(MAX_DECIMALS);
+                    Some(CsvDataSourceMsg::SetNumberDisplay(
+                        title.clone(),
+                        Some(NumberDisplay { decimals, ..display }),
+                    ))
+                });
+                html! {
+                    <input type="number"
+                        class="col-decimals"
+                        min="0"
+                        max={MAX_DECIMALS.to_string()}
+                        value={display.decimals.to_string()}
+                        title="Decimales con los que se escribe el valor en la plantilla"
+                        {onchange} />
+                }
+            }
+            None => html! {},
+        };
+        html! {
+            <>
+                <select
+                    class="col-type"
+                    onchange={onchange_format}
+                    title="Cómo se escribe el valor en la plantilla">
+                    <option selected={current.is_none()}>{"Sin formato"}</option>
+                    { for NUMBER_SEPARATORS.iter().map(|(separators, name)| html! {
+                        <option selected={current.map(|d| d.separators) == Some(*separators)}>{ *name }</option>
+                    })}
+                </select>
+                { decimals_input }
+            </>
+        }
+    }
+
+    /// Sends the current column schema to the parent through `on_csv_changed`.
+    fn emit_columns(&self, ctx: &Context<Self>) {
+        if let Some(cb) = &ctx.props().on_csv_changed {
+            if let Some(cols) = &self.column_checks {
+                cb.emit(cols.clone());
             }
         }
     }
@@ -61,14 +252,14 @@ impl CsvDataSourceComponent {
 
             // Create XHR
             let xhr = web_sys::XmlHttpRequest::new().expect("xhr");
-            xhr.open_with_async("POST", &url, true).expect("open");
+            xhr.open_with_async("POST", url, true).expect("open");
 
             // Handlers
             let xhr_clone = xhr.clone();
             let link_clone = link.clone();
             let onload = Closure::wrap(Box::new(move || {
                 let status = xhr_clone.status().unwrap_or_default();
-                if status >= 200 && status < 300 {
+                if (200..300).contains(&status) {
                     link_clone.send_message(CsvDataSourceMsg::UploadResult(Ok(())));
                 } else {
                     let text = xhr_clone
@@ -111,6 +302,10 @@ pub struct CsvDataSourceProps {
     pub on_column_selected: Option<Callback<ColumnCheck>>,
     #[prop_or_default]
     pub on_csv_changed: Option<Callback<Vec<ColumnCheck>>>,
+    /// Column titles referenced by the template. When set, verification only type-checks
+    /// these columns, which keeps wide CSVs fast to verify.
+    #[prop_or_default]
+    pub referenced_columns: Option<Vec<String>>,
 }
 
 pub enum CsvDataSourceMsg {
@@ -118,14 +313,29 @@ pub enum CsvDataSourceMsg {
     TicketReceived(String),
     StatusUpdated(JobStatus),
     VerifyError(String),
+    /// Columns of an already verified data source, read without a verification job.
+    ColumnsLoaded(Vec<ColumnCheck>),
 
     // UI messages
     ToggleModal,
     TriggerFilePicker,
     FilePicked(File),
     UploadResult(Result<(), String>),
-    SelectColumn(usize),
-    DoubleClickColumn(usize),
+    SelectColumn(String),
+    DoubleClickColumn(String),
+    /// The user picked another type for the column with this title.
+    ChangeColumnType(String, PlaceholderType),
+    /// The user changed whether the column with this title may have empty cells.
+    SetAllowEmpty(String, bool),
+    /// The user changed the label a `Boolean` column with this title shows for true
+    /// (`true`) or false (`false`) values. An empty label restores the default.
+    SetBooleanLabel(String, bool, String),
+    /// The user changed how the `Number` or `Currency` column with this title is written
+    /// in the template. `None` inserts the values as they are in the CSV.
+    SetNumberDisplay(String, Option<NumberDisplay>),
+    ColumnTypesSaved(Result<(), String>),
+    ForceVerify,
+    CancelVerify,
 
     // Confirmation dialog actions
     AcceptUploadWarning,
@@ -143,11 +353,14 @@ impl Component for CsvDataSourceComponent {
             job_ticket: None,
             job_status: None,
             column_checks: None,
+            verification_complete: None,
+            sample_rows: Vec::new(),
             started_for_template: None,
             show_modal: false,
             file_input_ref: NodeRef::default(),
             uploading: false,
             upload_error: None,
+            types_error: None,
             selected_column: None,
             show_confirm_upload: false,
         }
@@ -169,19 +382,13 @@ impl Component for CsvDataSourceComponent {
                 self.job_status = Some(status.clone());
                 match status {
                     JobStatus::Pending => self.is_verifying = true,
-                    JobStatus::InProgress(_) => self.is_verifying = true,
+                    JobStatus::InProgress { .. } => self.is_verifying = true,
                     JobStatus::Completed(payload) => {
                         self.is_verifying = false;
                         self.apply_completed(payload);
-
-                        // Emit callback to parent with the new column checks if provided
-                        if let Some(cb) = &ctx.props().on_csv_changed {
-                            if let Some(cols) = &self.column_checks {
-                                cb.emit(cols.clone());
-                            }
-                        }
+                        self.emit_columns(ctx);
                     }
-                    JobStatus::Failed(err_msg) => {
+                    JobStatus::Failed(err_msg) | JobStatus::Cancelled(err_msg) => {
                         self.is_verifying = false;
                         self.verify_result = Some(Err(err_msg));
                     }
@@ -193,6 +400,14 @@ impl Component for CsvDataSourceComponent {
                 self.verify_result = Some(Err(e));
                 true
             }
+            CsvDataSourceMsg::ColumnsLoaded(cols) => {
+                // Same outcome as a fast-path verification: the rows were not scanned.
+                self.is_verifying = false;
+                self.apply_columns(cols, false);
+                self.sample_rows.clear();
+                self.emit_columns(ctx);
+                true
+            }
 
             // UI
             CsvDataSourceMsg::ToggleModal => {
@@ -240,9 +455,16 @@ impl Component for CsvDataSourceComponent {
                             self.is_verifying = true;
                             // Clear previous results
                             self.column_checks = None;
+                            self.verification_complete = None;
+                            self.sample_rows.clear();
                             // Update started_for_template to avoid double starts
                             self.started_for_template = Some(id.clone());
-                            start_verification(ctx.link().clone(), id);
+                            start_verification(
+                                ctx.link().clone(),
+                                id,
+                                ctx.props().referenced_columns.clone(),
+                                false,
+                            );
                         }
                     }
                     Err(e) => {
@@ -251,23 +473,72 @@ impl Component for CsvDataSourceComponent {
                 }
                 true
             }
-            CsvDataSourceMsg::SelectColumn(idx) => {
-                self.selected_column = Some(idx);
+            CsvDataSourceMsg::SelectColumn(title) => {
+                self.selected_column = Some(title);
                 true
             }
-            CsvDataSourceMsg::DoubleClickColumn(idx) => {
-                self.selected_column = Some(idx);
+            CsvDataSourceMsg::DoubleClickColumn(title) => {
                 if let Some(cb) = &ctx.props().on_column_selected {
                     if let Some(cols) = &self.column_checks {
-                        if let Some(col) = cols.get(idx) {
+                        if let Some(col) = cols.iter().find(|c| c.title == title) {
                             cb.emit(col.clone());
                         }
                     }
                 }
+                self.selected_column = Some(title);
                 // Close modal after double-click selection
                 self.show_modal = false;
                 true
             }
+            CsvDataSourceMsg::ChangeColumnType(title, placeholder_type) => {
+                self.update_column(ctx, &title, |col| col.placeholder_type = placeholder_type)
+            }
+            CsvDataSourceMsg::SetAllowEmpty(title, allow_empty) => {
+                self.update_column(ctx, &title, |col| col.allow_empty = Some(allow_empty))
+            }
+            CsvDataSourceMsg::SetNumberDisplay(title, number_display) => {
+                self.update_column(ctx, &title, |col| col.number_display = number_display)
+            }
+            CsvDataSourceMsg::SetBooleanLabel(title, value, label) => {
+                let label = Some(label.trim().to_string()).filter(|l| !l.is_empty());
+                self.update_column(ctx, &title, |col| {
+                    if value {
+                        col.true_label = label;
+                    } else {
+                        col.false_label = label;
+                    }
+                })
+            }
+            CsvDataSourceMsg::ColumnTypesSaved(res) => {
+                self.types_error = res.err();
+                true
+            }
+            CsvDataSourceMsg::ForceVerify => {
+                // Re-scan the whole file, ignoring the cached verification result.
+                if let Some(id) = ctx.props().template_id.clone() {
+                    self.is_verifying = true;
+                    self.verify_result = None;
+                    self.job_status = None;
+                    self.column_checks = None;
+                    self.verification_complete = None;
+                    self.sample_rows.clear();
+                    self.started_for_template = Some(id.clone());
+                    start_verification(
+                        ctx.link().clone(),
+                        id,
+                        ctx.props().referenced_columns.clone(),
+                        true,
+                    );
+                }
+                true
+            }
+            CsvDataSourceMsg::CancelVerify => {
+                // The event stream closes once the job reports `Cancelled`.
+                if let Some(ticket) = self.job_ticket.clone() {
+                    cancel_verification(ticket);
+                }
+                false
+            }
         }
     }
 
@@ -277,7 +548,11 @@ impl Component for CsvDataSourceComponent {
                 if self.started_for_template.as_deref() != Some(&id) {
                     self.is_verifying = true;
                     self.started_for_template = Some(id.clone());
-                    start_verification(ctx.link().clone(), id);
+                    load_columns_or_verify(
+                        ctx.link().clone(),
+                        id,
+                        ctx.props().referenced_columns.clone(),
+                    );
                     return true;
                 }
             }
@@ -290,11 +565,14 @@ impl Component for CsvDataSourceComponent {
         let status_text = if let Some(job_status) = &self.job_status {
             match job_status {
                 JobStatus::Pending => "Verificando CSV...".to_string(),
-                JobStatus::InProgress(n) => {
-                    format!("Líneas verificadas: {}", n.to_formatted_string(&Locale::es))
-                }
+                JobStatus::InProgress { lines, percent } => format!(
+                    "Verificando: {}% ({} líneas)",
+                    percent,
+                    lines.to_formatted_string(&Locale::es)
+                ),
                 JobStatus::Completed(_) => "CSV Verificado".to_string(),
                 JobStatus::Failed(msg) => format!("Error: {}", msg),
+                JobStatus::Cancelled(_) => "Verificación cancelada".to_string(),
             }
         } else if self.is_verifying {
             "Verificando CSV...".to_string()
@@ -307,10 +585,26 @@ impl Component for CsvDataSourceComponent {
         };
 
         // Determine if error state
-        let is_error = match (&self.job_status, &self.verify_result) {
-            (Some(JobStatus::Failed(_)), _) => true,
-            (_, Some(Err(_))) => true,
-            _ => false,
+        let is_error = matches!(
+            (&self.job_status, &self.verify_result),
+            (Some(JobStatus::Failed(_) | JobStatus::Cancelled(_)), _) | (_, Some(Err(_)))
+        );
+
+        // Freshness of the verification, shown as the button color:
+        // - `verified` (green): the last run scanned every row and type-checked every column.
+        // - `sampled` (yellow): verified, but the last run took the fast-path (file unchanged
+        //   since a previous full scan) or only type-checked the columns used in the template.
+        // - `error` (red): the verification failed or was cancelled.
+        let freshness = if is_error {
+            Some("error")
+        } else if self.is_verifying {
+            None
+        } else {
+            match self.verification_complete {
+                Some(true) => Some("verified"),
+                Some(false) => Some("sampled"),
+                None => None,
+            }
         };
 
         // Compute button classes
@@ -319,10 +613,18 @@ impl Component for CsvDataSourceComponent {
         } else {
             "icon-btn".to_string()
         };
-        if is_error {
-            btn_classes.push_str(" error");
+        if let Some(class) = freshness {
+            btn_classes.push(' ');
+            btn_classes.push_str(class);
         }
-        let title_attr = status_text.clone();
+        let title_attr = if freshness == Some("sampled") {
+            format!(
+                "{} (verificación rápida o parcial; usa \"Re-verificar completo\" para revisar todo el archivo)",
+                status_text
+            )
+        } else {
+            status_text.clone()
+        };
 
         // column options from column_checks
         let column_options = if let Some(cols) = &self.column_checks {
@@ -330,24 +632,66 @@ impl Component for CsvDataSourceComponent {
                 <div class="modal-section">
                     <h3>{"Columnas detectadas"}</h3>
                     <div class="column-list">
-                        { for cols.iter().enumerate().map(|(i, c)| {
-                            let idx = i;
+                        { for cols.iter().map(|c| {
                             let label = c.title.clone();
                             let tooltip = format!("Haz doble click en '{}' para insertarla en la plantilla", label.clone());
-                            let onclick = ctx.link().callback(move |_| CsvDataSourceMsg::SelectColumn(idx));
-                            let ondblclick = ctx.link().callback(move |_| CsvDataSourceMsg::DoubleClickColumn(idx));
+                            let title_click = label.clone();
+                            let title_dblclick = label.clone();
+                            let onclick = ctx.link().callback(move |_| CsvDataSourceMsg::SelectColumn(title_click.clone()));
+                            let ondblclick = ctx.link().callback(move |_| CsvDataSourceMsg::DoubleClickColumn(title_dblclick.clone()));
+                            let is_selected = self.selected_column.as_deref() == Some(label.as_str());
+                            let title_type = label.clone();
+                            let onchange = ctx.link().batch_callback(move |event: Event| {
+                                let select: HtmlSelectElement = event.target()?.dyn_into().ok()?;
+                                let index = usize::try_from(select.selected_index()).ok()?;
+                                let (placeholder_type, _) = COLUMN_TYPES.get(index)?;
+                                Some(CsvDataSourceMsg::ChangeColumnType(title_type.clone(), placeholder_type.clone()))
+                            });
+                            let title_empty = label.clone();
+                            let onchange_empty = ctx.link().batch_callback(move |event: Event| {
+                                let input: HtmlInputElement = event.target()?.dyn_into().ok()?;
+                                Some(CsvDataSourceMsg::SetAllowEmpty(title_empty.clone(), input.checked()))
+                            });
                             html! {
-                                <button
-                                    class="col-option"
-                                    {onclick}
-                                    ondblclick={ondblclick}
-                                    title={tooltip}
-                                    aria-label={format!("Insertar columna {}", label.clone())}>
-                                    { label }
-                                </button>
+                                <div class="col-row" key={label.clone()}>
+                                    <button
+                                        class={classes!("col-option", is_selected.then_some("selected"))}
+                                        {onclick}
+                                        ondblclick={ondblclick}
+                                        title={tooltip}
+                                        aria-label={format!("Insertar columna {}", label.clone())}>
+                                        { label.clone() }
+                                    </button>
+                                    <select
+                                        class="col-type"
+                                        {onchange}
+                                        title="Tipo de dato de la columna; corrígelo si se detectó mal"
+                                        aria-label={format!("Tipo de la columna {}", label)}>
+                                        { for COLUMN_TYPES.iter().map(|(placeholder_type, name)| html! {
+                                            <option selected={*placeholder_type == c.placeholder_type}>{ *name }</option>
+                                        })}
+                                    </select>
+                                    <label class="col-empty" title="Acepta celdas vacías en esta columna al verificar">
+                                        <input type="checkbox"
+                                            checked={c.allows_empty()}
+                                            onchange={onchange_empty} />
+                                        {"Vacíos"}
+                                    </label>
+                                    { match c.placeholder_type {
+                                        PlaceholderType::Boolean => self.boolean_label_inputs(ctx, c),
+                                        PlaceholderType::Number | PlaceholderType::Currency => {
+                                            self.number_display_inputs(ctx, c)
+                                        }
+                                        _ => html! {},
+                                    } }
+                                </div>
                             }
                         })}
                     </div>
+                    { if let Some(err) = &self.types_error {
+                        html! { <p class="error">{ format!("No se pudieron guardar los tipos: {}", err) }</p> }
+                    } else { html!{} } }
+                    { self.sample_table(cols) }
                 </div>
             }
         } else {
@@ -371,6 +715,11 @@ impl Component for CsvDataSourceComponent {
                 onclick={ctx.link().callback(|_| CsvDataSourceMsg::ToggleModal)}>
                 <i class="material-icons">{"table_chart"}</i>
                 <span class="icon-label">{status_text}</span>
+                { if let Some(JobStatus::InProgress { percent, .. }) = &self.job_status {
+                    html! { <progress class="verify-progress" max="100" value={percent.to_string()}></progress> }
+                } else {
+                    html! {}
+                } }
             </button>
 
             { if self.show_modal {
@@ -425,6 +774,25 @@ impl Component for CsvDataSourceComponent {
                             </div>
 
                             <footer class="modal-footer">
+                                { if self.is_verifying && self.job_ticket.is_some() {
+                                    html! {
+                                        <button
+                                            class="secondary"
+                                            onclick={ctx.link().callback(|_| CsvDataSourceMsg::CancelVerify)}
+                                            title="Detiene la verificación en curso">
+                                            {"Cancelar verificación"}
+                                        </button>
+                                    }
+                                } else {
+                                    html! {}
+                                } }
+                                <button
+                                    class="secondary"
+                                    disabled={self.is_verifying || ctx.props().template_id.is_none()}
+                                    onclick={ctx.link().callback(|_| CsvDataSourceMsg::ForceVerify)}
+                                    title="Vuelve a comprobar todas las filas del CSV, aunque ya esté verificado">
+                                    {"Re-verificar completo"}
+                                </button>
                                 <button class="secondary close-btn" onclick={ctx.link().callback(|_| CsvDataSourceMsg::ToggleModal)}>{"Cerrar"}</button>
                             </footer>
                         </div>
@@ -468,18 +836,63 @@ impl Component for CsvDataSourceComponent {
                 if self.started_for_template.as_deref() != Some(&id) {
                     self.is_verifying = true;
                     self.started_for_template = Some(id.clone());
-                    start_verification(ctx.link().clone(), id);
+                    load_columns_or_verify(
+                        ctx.link().clone(),
+                        id,
+                        ctx.props().referenced_columns.clone(),
+                    );
                 }
             }
         }
     }
 }
 
-fn start_verification(link: html::Scope<CsvDataSourceComponent>, template_id: String) {
+/// Shows the columns of the template's data source when the component is first given a
+/// template.
+///
+/// A verified, unchanged data source is read directly through
+/// `GET /api/data_sources/csv/columns/{template_id}`, with no job to follow. Otherwise
+/// (`409 Conflict` for an unverified source, or any other failure) a verification job is
+/// started as before.
+fn load_columns_or_verify(
+    link: html::Scope<CsvDataSourceComponent>,
+    template_id: String,
+    referenced_columns: Option<Vec<String>>,
+) {
+    spawn_local(async move {
+        let url = format!("/api/data_sources/csv/columns/{}", template_id);
+        let response = gloo_net::http::Request::get(&url).send().await;
+        match &response {
+            Ok(_) => connection_monitor::report_success(),
+            Err(_) => connection_monitor::report_failure(),
+        }
+        if let Ok(resp) = response {
+            if resp.status() == 200 {
+                if let Ok(cols) = resp.json::<Vec<ColumnCheck>>().await {
+                    link.send_message(CsvDataSourceMsg::ColumnsLoaded(cols));
+                    return;
+                }
+            }
+        }
+        start_verification(link, template_id, referenced_columns, false);
+    });
+}
+
+fn start_verification(
+    link: html::Scope<CsvDataSourceComponent>,
+    template_id: String,
+    referenced_columns: Option<Vec<String>>,
+    force: bool,
+) {
     spawn_local(async move {
         let url = "/api/data_sources/csv/verify";
-        let body = serde_json::json!({ "uuid": template_id }).to_string();
-        match gloo_net::http::Request::post(&url)
+        let body = serde_json::json!({
+            "uuid": template_id,
+            "columns": referenced_columns,
+            "force": force,
+        })
+        .to_string();
+        match gloo_net::http::Request::post(url)
             .header("Content-Type", "application/json")
             .body(body)
             .unwrap()
@@ -487,6 +900,7 @@ fn start_verification(link: html::Scope<CsvDataSourceComponent>, template_id: St
             .await
         {
             Ok(response) => {
+                connection_monitor::report_success();
                 let status = response.status();
                 let text = response.text().await.unwrap_or_default();
                 if status == 200 {
@@ -501,58 +915,7 @@ fn start_verification(link: html::Scope<CsvDataSourceComponent>, template_id: St
                     };
                     link.send_message(CsvDataSourceMsg::TicketReceived(ticket.clone()));
 
-                    let poll_link = link.clone();
-                    spawn_local(async move {
-                        let mut finished = false;
-                        while !finished {
-                            sleep(Duration::from_secs(1)).await;
-                            let status_url = format!("/api/data_sources/csv/status/{}", ticket);
-                            match gloo_net::http::Request::get(&status_url).send().await {
-                                Ok(resp) => {
-                                    if let Ok(body_text) = resp.text().await {
-                                        if let Some(json_val) =
-                                            serde_json::from_str::<Value>(&body_text).ok()
-                                        {
-                                            if let Some(job_status) = parse_job_status(&json_val) {
-                                                poll_link.send_message(
-                                                    CsvDataSourceMsg::StatusUpdated(
-                                                        job_status.clone(),
-                                                    ),
-                                                );
-                                                match job_status {
-                                                    JobStatus::Completed(_)
-                                                    | JobStatus::Failed(_) => finished = true,
-                                                    _ => {}
-                                                }
-                                            } else {
-                                                poll_link.send_message(
-                                                    CsvDataSourceMsg::VerifyError(
-                                                        "Could not parse job status".into(),
-                                                    ),
-                                                );
-                                                finished = true;
-                                            }
-                                        } else {
-                                            poll_link.send_message(CsvDataSourceMsg::VerifyError(
-                                                "Response is not valid JSON".into(),
-                                            ));
-                                            finished = true;
-                                        }
-                                    } else {
-                                        poll_link.send_message(CsvDataSourceMsg::VerifyError(
-                                            "Could not read response body".into(),
-                                        ));
-                                        finished = true;
-                                    }
-                                }
-                                Err(e) => {
-                                    poll_link
-                                        .send_message(CsvDataSourceMsg::VerifyError(e.to_string()));
-                                    finished = true;
-                                }
-                            }
-                        }
-                    });
+                    follow_job_events(link, ticket);
                 } else {
                     link.send_message(CsvDataSourceMsg::VerifyCompleted(Err(format!(
                         "HTTP {}: {}",
@@ -561,12 +924,117 @@ fn start_verification(link: html::Scope<CsvDataSourceComponent>, template_id: St
                 }
             }
             Err(err) => {
+                connection_monitor::report_failure();
                 link.send_message(CsvDataSourceMsg::VerifyCompleted(Err(err.to_string())));
             }
         }
     });
 }
 
+/// Follows the status of the verification job `ticket` through
+/// `GET /api/jobs/{ticket}/events` (Server-Sent Events) until it finishes.
+///
+/// Each event carries a `JobStatus`, forwarded as `StatusUpdated`; the connection is closed
+/// after a terminal status. If the connection drops, the browser reconnects by itself and
+/// the backend starts again with the current status. Only a connection the browser gives
+/// up on (e.g. an unknown job) is reported as `VerifyError`.
+fn follow_job_events(link: html::Scope<CsvDataSourceComponent>, ticket: String) {
+    let url = format!("/api/jobs/{}/events", ticket);
+    let source = match EventSource::new(&url) {
+        Ok(source) => source,
+        Err(_) => {
+            link.send_message(CsvDataSourceMsg::VerifyError(
+                "Could not open the job event stream".into(),
+            ));
+            return;
+        }
+    };
+
+    let source_msg = source.clone();
+    let link_msg = link.clone();
+    let onmessage = Closure::wrap(Box::new(move |event: MessageEvent| {
+        connection_monitor::report_success();
+        let status = event
+            .data()
+            .as_string()
+            .and_then(|data| serde_json::from_str::<JobStatus>(&data).ok());
+        match status {
+            Some(status) => {
+                if status.is_terminal() {
+                    source_msg.close();
+                }
+                link_msg.send_message(CsvDataSourceMsg::StatusUpdated(status));
+            }
+            None => {
+                source_msg.close();
+                link_msg.send_message(CsvDataSourceMsg::VerifyError(
+                    "Could not parse job status".into(),
+                ));
+            }
+        }
+    }) as Box<dyn FnMut(MessageEvent)>);
+    source.set_onmessage(Some(onmessage.as_ref().unchecked_ref()));
+    onmessage.forget();
+
+    let source_err = source.clone();
+    let onerror = Closure::wrap(Box::new(move || {
+        connection_monitor::report_failure();
+        if source_err.ready_state() == EventSource::CLOSED {
+            link.send_message(CsvDataSourceMsg::VerifyError(
+                "Lost the job event stream".into(),
+            ));
+        }
+    }) as Box<dyn FnMut()>);
+    source.set_onerror(Some(onerror.as_ref().unchecked_ref()));
+    onerror.forget();
+}
+
+/// Stores the column types of the template through
+/// `POST /api/data_sources/csv/types/{template_id}`, so later verifications validate the
+/// rows against them instead of the inferred ones.
+fn save_column_types(
+    link: html::Scope<CsvDataSourceComponent>,
+    template_id: String,
+    columns: Vec<ColumnCheck>,
+) {
+    spawn_local(async move {
+        let url = format!("/api/data_sources/csv/types/{}", template_id);
+        let result = match gloo_net::http::Request::post(&url).json(&columns) {
+            Ok(request) => request.send().await,
+            Err(e) => Err(e),
+        };
+        let result = match result {
+            Ok(response) => {
+                connection_monitor::report_success();
+                if response.ok() {
+                    Ok(())
+                } else {
+                    Err(response.text().await.unwrap_or_default())
+                }
+            }
+            Err(e) => {
+                connection_monitor::report_failure();
+                Err(e.to_string())
+            }
+        };
+        link.send_message(CsvDataSourceMsg::ColumnTypesSaved(result));
+    });
+}
+
+/// Asks the backend to stop the verification job `ticket`.
+///
+/// The outcome is not reported here: the job's status (followed by `follow_job_events`)
+/// becomes `Cancelled` once it stops, or its regular result if it finished first.
+fn cancel_verification(ticket: String) {
+    spawn_local(async move {
+        let url = format!("/api/jobs/{}/cancel", ticket);
+        match gloo_net::http::Request::post(&url).send().await {
+            Ok(_) => connection_monitor::report_success(),
+            Err(_) => connection_monitor::report_failure(),
+        }
+    });
+}
+
 fn extract_ticket_from_text(text: &str) -> Option<String> {
     let s = text.trim();
     if s.is_empty() {
@@ -576,6 +1044,3 @@ fn extract_ticket_from_text(text: &str) -> Option<String> {
     }
 }
 
-fn parse_job_status(v: &Value) -> Option<JobStatus> {
-    serde_json::from_value(v.clone()).ok()
-}
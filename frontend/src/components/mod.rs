@@ -1,2 +1,3 @@
 pub mod statics;
-pub mod data_sources;
\ No newline at end of file
+pub mod data_sources;
+pub mod templates;
\ No newline at end of file
@@ -1,11 +1,31 @@
-use crate::components::statics::text::helpers::show_toast;
+use crate::components::statics::text::helpers::{
+    format_megabytes, image_tag_options, show_toast, IMAGE_ALIGNMENTS,
+};
 use crate::components::statics::text::{Msg, StaticTextComponent};
 use crate::tops_sheet::yw_material_top_sheet::{close_top_sheet, YwMaterialTopSheet};
+use common::model::image::MAX_IMAGE_BYTES;
 use web_sys::Event;
 use yew::html::Scope;
 use yew::prelude::*;
 
-const MAX_FILE_SIZE: u32 = 4_000_000;
+/// Checks a file picked in the image dialog or dropped on the editor.
+///
+/// Returns `Msg::FileSelected` for an image no larger than `MAX_IMAGE_BYTES`. Otherwise it
+/// shows a toast explaining why and returns `Msg::AutoResize`, which changes nothing.
+pub fn accept_image_file(file: web_sys::File) -> Msg {
+    if !file.type_().starts_with("image/") {
+        show_toast("Solo se pueden insertar imágenes.");
+        return Msg::AutoResize;
+    }
+    if file.size() > MAX_IMAGE_BYTES as f64 {
+        show_toast(&format!(
+            "El archivo es demasiado grande (máx. {}).",
+            format_megabytes(MAX_IMAGE_BYTES as f64)
+        ));
+        return Msg::AutoResize;
+    }
+    Msg::FileSelected(file)
+}
 
 pub fn image_dialog(component: &StaticTextComponent, link: &Scope<StaticTextComponent>) -> Html {
     // Close callback
@@ -19,13 +39,7 @@ pub fn image_dialog(component: &StaticTextComponent, link: &Scope<StaticTextComp
         let input = e.target_unchecked_into::<web_sys::HtmlInputElement>();
         if let Some(files) = input.files() {
             if let Some(file) = files.get(0) {
-                if file.size() > MAX_FILE_SIZE.into() {
-                    show_toast(
-                        &format!("El archivo es demasiado grande (máx. {} MB).", MAX_FILE_SIZE / 1_000_000)
-                    );
-                    return Msg::AutoResize;
-                }
-                return Msg::FileSelected(file);
+                return accept_image_file(file);
             }
         }
         Msg::AutoResize
@@ -37,12 +51,55 @@ pub fn image_dialog(component: &StaticTextComponent, link: &Scope<StaticTextComp
             if let Some(images) = &template.images {
                 if let Some(image) = images.iter().find(|img| &img.id == id) {
                     let id_cloned = id.clone();
+                    let options = image_tag_options(&component.text, id);
+                    let current = options.alignment.as_str();
+                    let alignment_buttons = IMAGE_ALIGNMENTS.iter().map(|(alignment, label)| {
+                        let msg = Msg::SetImageAlignment {
+                            id: id.clone(),
+                            alignment: alignment.to_string(),
+                        };
+                        let selected = current == *alignment;
+                        html! {
+                            <button
+                                class={classes!("image-align-button", selected.then_some("selected"))}
+                                disabled={selected}
+                                onclick={link.callback(move |_| msg.clone())}
+                            >
+                                { *label }
+                            </button>
+                        }
+                    });
+                    // An empty or invalid value goes back to the default size.
+                    let on_width_change = {
+                        let id = id.clone();
+                        link.callback(move |e: Event| {
+                            let input = e.target_unchecked_into::<web_sys::HtmlInputElement>();
+                            let width = input.value().trim().parse().ok().filter(|w| *w > 0);
+                            Msg::SetImageWidth {
+                                id: id.clone(),
+                                width,
+                            }
+                        })
+                    };
                     html! {
                         <>
                             <img
                                 src={format!("data:image/*;base64,{}", image.base64)}
                                 style="max-width:400px;max-height:400px;margin-bottom:24px;"
                             />
+                            <div class="image-align-buttons">
+                                { for alignment_buttons }
+                            </div>
+                            <label class="image-width-field">
+                                { "Ancho (px)" }
+                                <input
+                                    type="number"
+                                    min="1"
+                                    placeholder="200"
+                                    value={options.width.clone().unwrap_or_default()}
+                                    onchange={on_width_change}
+                                />
+                            </label>
                             <button
                                 style="padding:0.5rem 1rem;font-size:1rem;background:#d32f2f;color:#fff;border:none;border-radius:4px;cursor:pointer;"
                                 onclick={link.callback(move |_| Msg::DeleteImage(id_cloned.clone()))}
@@ -12,6 +12,8 @@
 //! - A loading spinner is displayed based on the `component.pdf_loading` boolean flag,
 //!   providing feedback while the PDF is being generated by the backend and loaded
 //!   by the browser.
+//! - When the backend could not generate the PDF, `component.pdf_error` holds the reason
+//!   and the dialog shows it instead of the viewer.
 //!
 //! ## Message Interaction
 //! This view dispatches two key messages to the parent component's update loop:
@@ -25,10 +27,24 @@
 //!   button. The parent's `update` function handles this by resetting the relevant state
 //!   (`pdf_url` to `None` and `pdf_loading` to `false`). The `on_close` callback also
 //!   directly calls `close_top_sheet` to hide the dialog.
+//!
+//! - **`Msg::TogglePdfOrientation`**: Sent by the "Horizontal"/"Vertical" button, shown only
+//!   for the PDF of the saved template. The parent regenerates the PDF with the other page
+//!   orientation.
+//!
+//! ## Unsaved changes
+//! `unsaved_pdf_dialog` renders the confirmation shown by `Msg::OpenPdf` when the text has
+//! unsaved changes. It offers "Guardar y generar" (`Msg::SaveAndOpenPdf`), "Generar sin
+//! guardar" (`Msg::OpenPreviewPdf`, which renders the current text through the preview
+//! endpoint) and "Cancelar" (`Msg::CloseUnsavedPdfDialog`).
 
-use crate::components::statics::text::Msg::{ClosePdfDialog, PdfLoaded};
+use crate::components::statics::text::Msg::{
+    CloseUnsavedPdfDialog, ClosePdfDialog, OpenPreviewPdf, PdfLoaded, SaveAndOpenPdf,
+    TogglePdfOrientation,
+};
 use crate::components::statics::text::StaticTextComponent;
 use crate::tops_sheet::yw_material_top_sheet::{close_top_sheet, YwMaterialTopSheet};
+use common::model::pdf::Orientation;
 use yew::html::Scope;
 use yew::prelude::*;
 
@@ -55,6 +71,11 @@ pub fn pdf_dialog(component: &StaticTextComponent, link: &Scope<StaticTextCompon
         })
     };
 
+    // Only the saved template's PDF can be regenerated with another orientation.
+    let show_orientation_toggle = (component.pdf_url.is_some() || component.pdf_loading)
+        && !component.pdf_is_preview
+        && component.pdf_error.is_none();
+
     html! {
         <YwMaterialTopSheet node_ref={dialog_ref}>
             <div style="position:fixed;top:0;left:0;width:100vw;height:100vh;background:rgba(0,0,0,0.85);z-index:9999;display:flex;flex-direction:column;align-items:center;justify-content:center;">
@@ -64,9 +85,35 @@ pub fn pdf_dialog(component: &StaticTextComponent, link: &Scope<StaticTextCompon
                 >
                     { "✕" }
                 </button>
+                {
+                    if show_orientation_toggle {
+                        let label = match component.effective_pdf_orientation() {
+                            Orientation::Portrait => "Horizontal",
+                            Orientation::Landscape => "Vertical",
+                        };
+                        html! {
+                            <button
+                                onclick={link.callback(|_| TogglePdfOrientation)}
+                                disabled={component.pdf_loading}
+                                style="position:absolute;top:24px;right:96px;z-index:10000;padding:0.5rem 1rem;font-size:1rem;background:#fff;border:none;border-radius:4px;cursor:pointer;"
+                            >
+                                { label }
+                            </button>
+                        }
+                    } else {
+                        html! { <></> }
+                    }
+                }
 
                 {
-                    if let Some(url) = &component.pdf_url {
+                    if let Some(error) = &component.pdf_error {
+                        html! {
+                            <div class="pdf-error">
+                                <span class="material-icons">{ "error_outline" }</span>
+                                <div>{ error.clone() }</div>
+                            </div>
+                        }
+                    } else if component.pdf_url.is_some() || component.pdf_loading {
                         // Hide iframe while loading to prevent showing previous content,
                         // and show a full white overlay as placeholder.
                         let iframe_style = if component.pdf_loading {
@@ -74,15 +121,23 @@ pub fn pdf_dialog(component: &StaticTextComponent, link: &Scope<StaticTextCompon
                         } else {
                             "width:100%;height:100%;border:none;background:#fff;border-radius:4px;visibility:visible;"
                         };
-
-                        html! {
-                            <div style="position:relative;width:80vw;height:80vh;">
-                                // iframe occupies the whole container but stays hidden while generating
+                        // The iframe is only mounted once the PDF has been fetched, so that
+                        // its `onload` signals the PDF itself.
+                        let iframe = match &component.pdf_url {
+                            Some(url) => html! {
                                 <iframe
                                     src={url.clone()}
                                     style={iframe_style}
                                     onload={on_iframe_load}
                                 />
+                            },
+                            None => html! { <></> },
+                        };
+
+                        html! {
+                            <div style="position:relative;width:80vw;height:80vh;">
+                                // iframe occupies the whole container but stays hidden while generating
+                                { iframe }
 
                                 {
                                     if component.pdf_loading {
@@ -111,4 +166,35 @@ pub fn pdf_dialog(component: &StaticTextComponent, link: &Scope<StaticTextCompon
             </div>
         </YwMaterialTopSheet>
     }
-}
\ No newline at end of file
+}
+/// Renders the "unsaved changes" confirmation shown before generating a PDF.
+///
+/// Nothing is rendered unless `component.show_unsaved_pdf_dialog` is set.
+pub fn unsaved_pdf_dialog(
+    component: &StaticTextComponent,
+    link: &Scope<StaticTextComponent>,
+) -> Html {
+    if !component.show_unsaved_pdf_dialog {
+        return html! {};
+    }
+
+    html! {
+        <div class="modal-overlay" onclick={link.callback(|_| CloseUnsavedPdfDialog)}>
+            <div class="modal-card" onclick={|e: MouseEvent| e.stop_propagation()}>
+                <header class="modal-header">
+                    <h2 class="modal-title">{"Cambios sin guardar"}</h2>
+                </header>
+                <div class="modal-body">
+                    <p>
+                        {"Hay cambios sin guardar. ¿Guardar y generar, o generar sin guardar (vista previa)?"}
+                    </p>
+                </div>
+                <footer class="modal-footer">
+                    <button class="secondary" onclick={link.callback(|_| CloseUnsavedPdfDialog)}>{"Cancelar"}</button>
+                    <button class="secondary" onclick={link.callback(|_| OpenPreviewPdf)}>{"Generar sin guardar"}</button>
+                    <button class="primary" onclick={link.callback(|_| SaveAndOpenPdf)}>{"Guardar y generar"}</button>
+                </footer>
+            </div>
+        </div>
+    }
+}
@@ -5,16 +5,31 @@
 //!
 //! - **Index Conversion**: Translating between Rust's native UTF-8 byte indices
 //!   and the UTF-16 code unit indices used by browser textarea APIs (`selectionStart`,
-//!   `selectionEnd`). This is crucial for accurate text manipulation.
+//!   `selectionEnd`), always landing on a `char` boundary. This is crucial for accurate
+//!   text manipulation.
 //! - **Tag Detection**: Identifying special tags like `[img:<id>]` at the cursor's
-//!   position to trigger contextual UI, such as opening an image dialog.
+//!   position to trigger contextual UI, such as opening an image dialog, and finding
+//!   or rewriting the tags of an image with their options (`[img:<id>|center|w=400]`).
+//!   The tag syntax itself (`image_tag_regex`, `ImageTagOptions`) lives in
+//!   `common::preview`, shared with the backend's HTML export.
 //! - **User Feedback**: Displaying temporary "toast" notifications to inform the
 //!   user about the status of operations like saving or loading.
 //! - **Model Instantiation**: Creating empty `Template` objects for new documents.
-//! - **Security & Hashing**: Escaping HTML to prevent XSS in previews and computing
-//!   MD5 hashes for dirty-checking unsaved changes.
+//! - **Hashing**: Computing MD5 hashes for dirty-checking unsaved changes.
+//! - **CSV Placeholders**: Building `[ph:...]` tags and starter templates from CSV columns.
+//! - **Diffing**: Comparing the current text against the last saved one for the
+//!   "Ver cambios" panel.
+//! - **Long Lines**: Detecting lines long enough to degrade the editor's performance.
+//! - **Text Statistics**: Counting the words and characters shown in the status line.
+//! - **Size Limits**: Formatting the image and payload limits shared with the backend.
+//! - **PDF Errors**: Explaining a failed PDF generation in the PDF dialog.
 
+use base64::{engine::general_purpose, Engine as _};
+use common::model::csv::ColumnCheck;
+use common::model::pdf::{PdfErrorBody, PdfErrorStage};
+pub use common::preview::{image_tag_regex, ImageTagOptions};
 use regex::Regex;
+use similar::{ChangeTag, TextDiff};
 use wasm_bindgen::JsCast;
 use web_sys::HtmlElement;
 
@@ -37,7 +52,7 @@ pub fn get_img_tag_id_at_cursor(text: &str, cursor_pos_utf16: usize) -> Option<S
     // Convert UTF-16 cursor position to a UTF-8 byte index for Rust string slicing.
     let cursor_pos_byte = utf16_to_byte_idx(text, cursor_pos_utf16);
 
-    let re = Regex::new(r"\[img:([^]]+)]").unwrap();
+    let re = Regex::new(r"\[img:([^]|]+)(?:\|[^]]*)?]").unwrap();
     for mat in re.captures_iter(text) {
         if let Some(m) = mat.get(0) {
             let (start, end) = (m.start(), m.end());
@@ -50,6 +65,57 @@ pub fn get_img_tag_id_at_cursor(text: &str, cursor_pos_utf16: usize) -> Option<S
     None
 }
 
+/// Image alignments an `[img:<id>|<alignment>]` tag can carry, with their Spanish labels.
+/// A tag without an alignment is left-aligned, as in the PDF.
+pub const IMAGE_ALIGNMENTS: [(&str, &str); 3] = [
+    ("left", "Izquierda"),
+    ("center", "Centro"),
+    ("right", "Derecha"),
+];
+
+/// Returns the options of the first tag of the image `id` in `text` (the defaults when
+/// there is no tag).
+pub fn image_tag_options(text: &str, id: &str) -> ImageTagOptions {
+    let options = image_tag_regex(id)
+        .captures(text)
+        .and_then(|caps| caps.get(1))
+        .map_or("", |m| m.as_str());
+    ImageTagOptions::parse(options)
+}
+
+/// Builds the tag of the image `id` with `options`. Defaults (left, no width) are left out.
+pub fn build_image_tag(id: &str, options: &ImageTagOptions) -> String {
+    let mut tag = format!("[img:{}", id);
+    if options.alignment != "left" {
+        tag.push('|');
+        tag.push_str(&options.alignment);
+    }
+    if let Some(width) = &options.width {
+        tag.push_str("|w=");
+        tag.push_str(width);
+    }
+    tag.push(']');
+    tag
+}
+
+/// Collects the distinct column titles referenced by `[ph:TITLE:BASE64]` tags in `text`.
+///
+/// Used by `view.rs` to tell the CSV data source which columns the template actually
+/// uses, so verification can skip the others. Titles are returned in order of first
+/// appearance.
+pub fn extract_placeholder_titles(text: &str) -> Vec<String> {
+    let re = Regex::new(r"\[ph:([^:\]]+):([A-Za-z0-9+/=]+)]").unwrap();
+    let mut titles: Vec<String> = Vec::new();
+    for caps in re.captures_iter(text) {
+        if let Some(title) = caps.get(1).map(|m| m.as_str()) {
+            if !titles.iter().any(|t| t == title) {
+                titles.push(title.to_string());
+            }
+        }
+    }
+    titles
+}
+
 /// Converts a UTF-8 byte index to its corresponding UTF-16 code unit index.
 ///
 /// This is the inverse of `utf16_to_byte_idx`. It's used when a text position is
@@ -78,13 +144,43 @@ pub fn byte_to_utf16_idx(s: &str, byte_idx: usize) -> u32 {
 /// * `s` - The full string context.
 /// * `utf16_idx` - The index in UTF-16 code units.
 ///
+/// Characters outside the Basic Multilingual Plane (most emoji) take two UTF-16 code
+/// units but one `char`, so units are counted per character. An index that falls between
+/// the two units of such a character moves to the end of it, so the result is always a
+/// valid `char` boundary.
+///
 /// # Returns
-/// The equivalent position as a UTF-8 byte index.
+/// The equivalent position as a UTF-8 byte index, or `s.len()` past the end of the string.
 pub fn utf16_to_byte_idx(s: &str, utf16_idx: usize) -> usize {
-    s.char_indices()
-        .map(|(byte_idx, _)| byte_idx)
-        .nth(s.encode_utf16().take(utf16_idx).count())
-        .unwrap_or(s.len())
+    let mut units = 0;
+    for (byte_idx, ch) in s.char_indices() {
+        if units >= utf16_idx {
+            return byte_idx;
+        }
+        units += ch.len_utf16();
+    }
+    s.len()
+}
+
+/// Moves `byte_idx` back to the nearest `char` boundary of `s`, clamping it to `s.len()`.
+///
+/// `String::insert_str` and slicing panic on an index inside a multi-byte character
+/// (accented letters, Arabic, CJK, emoji). Every byte index derived from a browser
+/// position goes through this before it is used on the text.
+pub fn snap_to_char_boundary(s: &str, byte_idx: usize) -> usize {
+    let mut idx = byte_idx.min(s.len());
+    while !s.is_char_boundary(idx) {
+        idx -= 1;
+    }
+    idx
+}
+
+/// Converts a textarea selection, in UTF-16 code units, to a byte range of `s` that is
+/// safe to slice: both ends are `char` boundaries and the end is never before the start.
+pub fn selection_byte_range(s: &str, start_utf16: usize, end_utf16: usize) -> (usize, usize) {
+    let start = snap_to_char_boundary(s, utf16_to_byte_idx(s, start_utf16));
+    let end = snap_to_char_boundary(s, utf16_to_byte_idx(s, end_utf16));
+    (start, end.max(start))
 }
 
 /// Displays a temporary notification message at the bottom of the screen.
@@ -139,32 +235,14 @@ pub fn create_empty_template() -> common::model::template::Template {
         id: uuid::Uuid::new_v4().to_string(),
         text: String::new(),
         images: None,
+        vars: None,
+        margins: None,
+        orientation: None,
+        page_size: None,
+        name: None,
     }
 }
 
-/// Escapes special HTML characters in a string.
-///
-/// This is a security and rendering utility used in the preview generation
-/// pipeline (`view.rs`). When placeholder tags like `[ph:...]` are processed,
-/// their decoded content is escaped using this function before being embedded
-/// in the final HTML. This prevents content from being misinterpreted as HTML
-/// tags, mitigating XSS risks.
-///
-/// # Arguments
-/// * `input` - The raw string to escape.
-///
-/// # Returns
-/// A new string with `&`, `<`, `>`, `"`, and `'` replaced by their respective
-/// HTML entities.
-pub fn escape_html(input: &str) -> String {
-    input
-        .replace('&', "&amp;")
-        .replace('<', "&lt;")
-        .replace('>', "&gt;")
-        .replace('"', "&quot;")
-        .replace('\'', "&#39;")
-}
-
 /// Computes the MD5 hash of a string and returns it as a hex digest.
 ///
 /// This function is central to the editor's "dirty checking" mechanism. In
@@ -181,3 +259,377 @@ pub fn escape_html(input: &str) -> String {
 pub fn compute_md5(input: &str) -> String {
     format!("{:x}", md5::compute(input))
 }
+
+/// Character counts of a diff between two texts, as reported by `diff_summary`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct DiffSummary {
+    /// Number of characters present in the new text but not in the old one.
+    pub inserted: usize,
+    /// Number of characters present in the old text but not in the new one.
+    pub deleted: usize,
+}
+
+/// A run of consecutive characters sharing the same diff status.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiffSpan {
+    Equal(String),
+    Insert(String),
+    Delete(String),
+}
+
+/// Counts the characters inserted and deleted in the spans of a diff.
+///
+/// Used by the "Ver cambios" panel to summarize what changed since the last save, from the
+/// spans it renders, so the texts are only diffed once.
+pub fn diff_summary(spans: &[DiffSpan]) -> DiffSummary {
+    let mut summary = DiffSummary::default();
+    for span in spans {
+        match span {
+            DiffSpan::Insert(s) => summary.inserted += s.chars().count(),
+            DiffSpan::Delete(s) => summary.deleted += s.chars().count(),
+            DiffSpan::Equal(_) => {}
+        }
+    }
+    summary
+}
+
+/// Computes a character-level diff between `old` and `new`, merging consecutive
+/// characters with the same status into a single `DiffSpan`.
+///
+/// The spans are in display order, so rendering them one after another (with deletions
+/// struck through) reproduces both texts.
+pub fn diff_spans(old: &str, new: &str) -> Vec<DiffSpan> {
+    let diff = TextDiff::from_chars(old, new);
+    let mut spans: Vec<DiffSpan> = Vec::new();
+    for change in diff.iter_all_changes() {
+        let value = change.value();
+        match (spans.last_mut(), change.tag()) {
+            (Some(DiffSpan::Equal(s)), ChangeTag::Equal)
+            | (Some(DiffSpan::Insert(s)), ChangeTag::Insert)
+            | (Some(DiffSpan::Delete(s)), ChangeTag::Delete) => s.push_str(value),
+            (_, ChangeTag::Equal) => spans.push(DiffSpan::Equal(value.to_string())),
+            (_, ChangeTag::Insert) => spans.push(DiffSpan::Insert(value.to_string())),
+            (_, ChangeTag::Delete) => spans.push(DiffSpan::Delete(value.to_string())),
+        }
+    }
+    spans
+}
+
+/// Builds the `[ph:TITLE:BASE64]` tag for a CSV column.
+///
+/// The Base64 part carries the column's first-row value, which the preview and the PDF
+/// show as sample data. For a `Boolean` column it is the true or false label
+/// (`ColumnCheck::display_value`) rather than the raw cell.
+pub fn build_placeholder_tag(col: &ColumnCheck) -> String {
+    let value = col
+        .first_row
+        .as_deref()
+        .map(|value| col.display_value(value))
+        .unwrap_or_default();
+    format!("[ph:{}:{}]", col.title, general_purpose::STANDARD.encode(value))
+}
+
+/// Builds a starter template that lists every CSV column as `**Title:** [ph:...]`,
+/// one per line, in column order.
+///
+/// Used by `Msg::GenerateFromCsv` to bootstrap a template from a verified CSV.
+pub fn build_template_from_columns(columns: &[ColumnCheck]) -> String {
+    columns
+        .iter()
+        .map(|col| format!("**{}:** {}", col.title, build_placeholder_tag(col)))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Wraps `selected` in the style markers `prefix` and `suffix`, for `Msg::ApplyStyle`.
+///
+/// Markdown markers must touch the text they style (`**word **` is not bold), so leading
+/// and trailing whitespace is left outside them. A selection spanning several lines has
+/// each non-blank line wrapped on its own, since emphasis does not cross line breaks and a
+/// list marker belongs at the start of every line.
+///
+/// # Returns
+/// The replacement text and the byte range within it to select afterwards: the original
+/// text of a single line, or the whole replacement for several lines.
+pub fn wrap_selection(selected: &str, prefix: &str, suffix: &str) -> (String, usize, usize) {
+    let wrap_line = |line: &str| {
+        let core = line.trim();
+        if core.is_empty() {
+            return line.to_string();
+        }
+        let lead = &line[..line.len() - line.trim_start().len()];
+        let trail = &line[line.trim_end().len()..];
+        format!("{}{}{}{}{}", lead, prefix, core, suffix, trail)
+    };
+
+    if !selected.contains('\n') {
+        let wrapped = wrap_line(selected);
+        let core = selected.trim();
+        let start = selected.len() - selected.trim_start().len() + prefix.len();
+        return (wrapped, start, start + core.len());
+    }
+    let wrapped = selected
+        .split('\n')
+        .map(wrap_line)
+        .collect::<Vec<_>>()
+        .join("\n");
+    let len = wrapped.len();
+    (wrapped, 0, len)
+}
+
+/// Lines longer than this many bytes trigger the "línea muy larga" warning in the editor.
+///
+/// No hand-written line gets close to this length; lines this long almost always come
+/// from pasted Base64 data. The browser wraps them into hundreds of visual rows, which
+/// makes typing laggy and leaves the line-number gutter showing a single number for all
+/// of them.
+pub const LONG_LINE_THRESHOLD: usize = 5_000;
+
+/// A line of the editor text longer than `LONG_LINE_THRESHOLD`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LongLine {
+    /// 1-based line number, as shown in the gutter.
+    pub number: usize,
+    /// Length of the line in bytes.
+    pub len: usize,
+}
+
+/// Finds the first line of `text` longer than `LONG_LINE_THRESHOLD`.
+///
+/// Called on every `Msg::UpdateText`, so it is kept cheap: texts shorter than the
+/// threshold return immediately, and otherwise the scan stops at the first long line.
+///
+/// # Returns
+/// `Some(LongLine)` for the first offending line, or `None` if every line is short enough.
+pub fn find_long_line(text: &str) -> Option<LongLine> {
+    if text.len() <= LONG_LINE_THRESHOLD {
+        return None;
+    }
+    text.lines()
+        .enumerate()
+        .find(|(_, line)| line.len() > LONG_LINE_THRESHOLD)
+        .map(|(idx, line)| LongLine {
+            number: idx + 1,
+            len: line.len(),
+        })
+}
+
+/// Word and character counts of the editor text, shown in the status line under the editor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TextStats {
+    /// Whitespace-separated words containing at least one letter or digit, so Markdown
+    /// markers such as a lone `-` or `**` are not counted.
+    pub words: usize,
+    /// Characters (Unicode scalar values), line breaks excluded.
+    pub chars: usize,
+}
+
+/// Counts the words and characters of `text`, leaving out `[img:...]` and `[ph:...]` tags.
+///
+/// The tags carry ids and Base64 data rather than prose, so counting them would inflate
+/// both numbers. Called on every render, which follows each `Msg::UpdateText`.
+pub fn text_stats(text: &str) -> TextStats {
+    let re = Regex::new(r"\[(?:img|ph):[^\]]*]").unwrap();
+    let prose = re.replace_all(text, " ");
+    TextStats {
+        words: prose
+            .split_whitespace()
+            .filter(|word| word.chars().any(char::is_alphanumeric))
+            .count(),
+        chars: prose.chars().filter(|c| *c != '\n' && *c != '\r').count(),
+    }
+}
+
+/// Formats a size in bytes as megabytes (1 MB = 1024 × 1024 bytes, as the backend counts
+/// `MAX_TEMPLATE_JSON_BYTES`) for the size-limit toasts, e.g. `"4 MB"` or `"10,5 MB"`.
+pub fn format_megabytes(bytes: f64) -> String {
+    let mb = bytes / (1024.0 * 1024.0);
+    if mb.fract() == 0.0 {
+        format!("{} MB", mb)
+    } else {
+        format!("{:.1} MB", mb).replace('.', ",")
+    }
+}
+
+/// Builds the message the PDF dialog shows for a `PdfErrorBody` answered with `status`.
+///
+/// The stage tells a missing template (`404` at the `db` stage) apart from other database
+/// errors and from a font that cannot be loaded. The backend's English description is
+/// appended as the detail.
+pub fn pdf_error_message(status: u16, body: &PdfErrorBody) -> String {
+    let reason = match body.stage {
+        PdfErrorStage::Db if status == 404 => "La plantilla no existe o no se ha guardado",
+        PdfErrorStage::Db => "No se pudo leer la plantilla de la base de datos",
+        PdfErrorStage::FontLoad => "No se pudo cargar la fuente del documento",
+        PdfErrorStage::Render => "No se pudo generar el PDF",
+    };
+    format!("{}. Detalle: {}", reason, body.error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::model::place_holder::PlaceholderType;
+
+    fn column(
+        title: &str,
+        placeholder_type: PlaceholderType,
+        first_row: Option<&str>,
+    ) -> ColumnCheck {
+        ColumnCheck {
+            title: title.to_string(),
+            placeholder_type,
+            first_row: first_row.map(str::to_string),
+            index: 0,
+            allow_empty: None,
+            true_label: None,
+            false_label: None,
+            number_display: None,
+        }
+    }
+
+    #[test]
+    fn template_from_columns_lists_each_column_in_order() {
+        let columns = [
+            column("Nombre", PlaceholderType::Text, Some("Ana")),
+            column("Activo", PlaceholderType::Boolean, Some("true")),
+            column("Correo", PlaceholderType::Email, None),
+        ];
+        assert_eq!(
+            build_template_from_columns(&columns),
+            format!(
+                "**Nombre:** [ph:Nombre:{}]\n**Activo:** [ph:Activo:{}]\n**Correo:** [ph:Correo:]",
+                general_purpose::STANDARD.encode("Ana"),
+                general_purpose::STANDARD.encode(columns[1].display_value("true")),
+            )
+        );
+        assert_eq!(build_template_from_columns(&[]), "");
+    }
+
+    #[test]
+    fn placeholder_tag_carries_the_boolean_label() {
+        let mut activo = column("Activo", PlaceholderType::Boolean, Some("1"));
+        activo.true_label = Some("Sí".to_string());
+        assert_eq!(
+            build_placeholder_tag(&activo),
+            format!("[ph:Activo:{}]", general_purpose::STANDARD.encode("Sí"))
+        );
+    }
+
+    #[test]
+    fn diff_summary_of_identical_texts_is_empty() {
+        let spans = diff_spans("Hola [ph:Nombre:]", "Hola [ph:Nombre:]");
+        assert_eq!(spans, [DiffSpan::Equal("Hola [ph:Nombre:]".to_string())]);
+        assert_eq!(diff_summary(&spans), DiffSummary::default());
+    }
+
+    #[test]
+    fn diff_summary_counts_characters_of_each_span() {
+        let spans = diff_spans("Hola mundo", "Hola, mundo!");
+        assert_eq!(
+            diff_summary(&spans),
+            DiffSummary {
+                inserted: 2,
+                deleted: 0
+            }
+        );
+
+        let spans = diff_spans("gato", "pato");
+        assert_eq!(
+            spans,
+            [
+                DiffSpan::Delete("g".to_string()),
+                DiffSpan::Insert("p".to_string()),
+                DiffSpan::Equal("ato".to_string()),
+            ]
+        );
+        assert_eq!(
+            diff_summary(&spans),
+            DiffSummary {
+                inserted: 1,
+                deleted: 1
+            }
+        );
+    }
+
+    #[test]
+    fn diff_summary_counts_characters_not_bytes() {
+        let spans = diff_spans("año", "año 🎉 ñandú");
+        assert_eq!(
+            diff_summary(&spans),
+            DiffSummary {
+                inserted: 8,
+                deleted: 0
+            }
+        );
+        assert_eq!(
+            diff_summary(&diff_spans("ñandú", "")),
+            DiffSummary {
+                inserted: 0,
+                deleted: 5
+            }
+        );
+    }
+
+    #[test]
+    fn utf16_offsets_count_emoji_as_two_units() {
+        let text = "a😀 bold";
+        assert_eq!(utf16_to_byte_idx(text, 1), 1);
+        // Between the two units of the emoji: moved to the end of it.
+        assert_eq!(utf16_to_byte_idx(text, 2), 5);
+        assert_eq!(utf16_to_byte_idx(text, 3), 5);
+        assert_eq!(utf16_to_byte_idx(text, 4), 6);
+        assert_eq!(utf16_to_byte_idx(text, 100), text.len());
+        assert_eq!(byte_to_utf16_idx(text, 5), 3);
+        assert_eq!(byte_to_utf16_idx(text, text.len()), 8);
+    }
+
+    #[test]
+    fn bold_after_an_emoji_wraps_the_selected_word() {
+        // What `Msg::ApplyStyle` does with "bold" selected in the textarea (UTF-16 4..8).
+        let text = "a😀 bold";
+        let (start, end) = selection_byte_range(text, 4, 8);
+        assert_eq!(&text[start..end], "bold");
+        let (replacement, sel_start, sel_end) = wrap_selection(&text[start..end], "**", "**");
+        let styled = format!("{}{}{}", &text[..start], replacement, &text[end..]);
+        assert_eq!(styled, "a😀 **bold**");
+        assert_eq!(byte_to_utf16_idx(&styled, start + sel_start), 6);
+        assert_eq!(byte_to_utf16_idx(&styled, start + sel_end), 10);
+    }
+
+    /// Arabic, emoji (with a ZWJ sequence and a flag), CJK and accented text.
+    const MIXED_TEXT: &str = "مرحبا 😀 👩‍💻🇪🇨 漢字かな ñandú\n**x**";
+
+    #[test]
+    fn every_selection_of_mixed_text_is_safe_to_slice_and_insert_into() {
+        let units = MIXED_TEXT.encode_utf16().count();
+        for start in 0..=units + 1 {
+            for end in 0..=units + 1 {
+                let (from, to) = selection_byte_range(MIXED_TEXT, start, end);
+                assert!(from <= to);
+                let parts = [
+                    &MIXED_TEXT[..from],
+                    &MIXED_TEXT[from..to],
+                    &MIXED_TEXT[to..],
+                ];
+                assert_eq!(parts.concat(), MIXED_TEXT);
+                wrap_selection(parts[1], "**", "**");
+            }
+            let mut text = MIXED_TEXT.to_string();
+            let at = snap_to_char_boundary(&text, utf16_to_byte_idx(&text, start));
+            text.insert_str(at, "[var:X]");
+            assert_eq!(text.replace("[var:X]", ""), MIXED_TEXT);
+        }
+    }
+
+    #[test]
+    fn snap_moves_back_to_a_char_boundary() {
+        let text = "a😀b";
+        assert_eq!(
+            (0..=7)
+                .map(|i| snap_to_char_boundary(text, i))
+                .collect::<Vec<_>>(),
+            [0, 1, 1, 1, 1, 5, 6, 6]
+        );
+    }
+}
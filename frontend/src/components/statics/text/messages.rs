@@ -5,6 +5,7 @@
 //!
 //! Variants
 //! - `SetTab(String)`: Switch between tabs ("editor" or "preview").
+//! - `ToggleSplitView`: Show the editor and the preview side by side, or back as tabs.
 //! - `UpdateText(String)`: Replace the editor content and push into history.
 //! - `Undo` / `Redo`: Navigate the undo/redo stack.
 //! - `ApplyStyle(String, ())`: Insert a style snippet at the selection (e.g., bold).
@@ -14,14 +15,38 @@
 //! - `AddImageToTemplate { id, base64 }`: Add the image to the current template.
 //! - `OpenImageDialogWithId(String)`: Open the modal/top sheet showing the selected image.
 //! - `DeleteImage(String)`: Remove image from template and text.
+//! - `SetImageAlignment { id, alignment }`: Align an image left, center or right.
+//! - `SetImageWidth { id, width }`: Set the width of an image, or reset it to the default.
 //! - `Save`: Persist the current template to the backend.
 //! - `SetTemplate(Option<Template>)`: Replace the in-memory template (load or reset).
+//! - `ToggleDiff`: Show or hide the panel with the changes since the last save.
+//! - `OpenPdf`: Open the PDF of the saved template, or ask what to do if there are unsaved changes.
+//! - `SaveAndOpenPdf`: Save the template, then open its PDF.
+//! - `OpenPreviewPdf`: Render the unsaved content as a preview PDF without saving it.
+//! - `PdfReady(Result<Vec<u8>, String>)`: The PDF of the saved template arrived (or failed).
+//! - `PreviewPdfReady(Result<Vec<u8>, String>)`: The preview PDF bytes arrived (or failed).
+//! - `TogglePdfOrientation`: Regenerate the open PDF with the other page orientation.
+//! - `CloseUnsavedPdfDialog`: Dismiss the "unsaved changes" dialog.
+//! - `SaveFailed`: The backend rejected a save; cancels any pending "save and open PDF".
+//! - `ToggleVarsPanel`: Show or hide the template variables panel.
+//! - `AddVar` / `RemoveVar(usize)`: Add an empty variable or remove one by index.
+//! - `UpdateVarName(usize, String)` / `UpdateVarValue(usize, String)`: Edit a variable.
+//! - `InsertVar(String)`: Insert a `[var:NAME]` tag at the cursor.
+//! - `GenerateFromCsv`: Replace the text with a starter template built from the CSV columns.
+//! - `ToggleLineWrap`: Turn soft wrapping of long lines in the textarea on or off.
+//! - `UpdateName(String)`: Edit the template name (saved with `Save`).
+//! - `OpenTemplate(String)`: Load another saved template, chosen in the template picker.
+//! - `TemplateOpened(Template)`: The template requested by `OpenTemplate` arrived.
+//! - `DuplicateTemplate`: Copy the saved template on the backend and open the copy.
+//! - `AutoSave`: The autosave delay elapsed with no new edit; save if still needed.
+//! - `ToggleAutosave`: Turn autosave on or off.
 
 use common::model::csv::ColumnCheck;
 
 #[derive(Clone)]
 pub enum Msg {
     SetTab(String),
+    ToggleSplitView,
     UpdateText(String),
     Undo,
     Redo,
@@ -32,12 +57,37 @@ pub enum Msg {
     AddImageToTemplate { id: String, base64: String },
     OpenImageDialogWithId(String),
     DeleteImage(String),
+    SetImageAlignment { id: String, alignment: String },
+    SetImageWidth { id: String, width: Option<u32> },
     Save,
-    SaveSucceeded,
+    /// The backend stored the template; carries the text that was saved.
+    SaveSucceeded(String),
     SetTemplate(Option<common::model::template::Template>),
     InsertCsvColumnPlaceholder(ColumnCheck),
     CsvColumnsUpdated(Vec<ColumnCheck>),
     OpenPdf,
     PdfLoaded,
     ClosePdfDialog,
+    ToggleDiff,
+    SaveAndOpenPdf,
+    OpenPreviewPdf,
+    PdfReady(Result<Vec<u8>, String>),
+    PreviewPdfReady(Result<Vec<u8>, String>),
+    TogglePdfOrientation,
+    CloseUnsavedPdfDialog,
+    SaveFailed,
+    ToggleVarsPanel,
+    AddVar,
+    RemoveVar(usize),
+    UpdateVarName(usize, String),
+    UpdateVarValue(usize, String),
+    InsertVar(String),
+    GenerateFromCsv,
+    ToggleLineWrap,
+    UpdateName(String),
+    OpenTemplate(String),
+    TemplateOpened(common::model::template::Template),
+    DuplicateTemplate,
+    AutoSave,
+    ToggleAutosave,
 }
\ No newline at end of file
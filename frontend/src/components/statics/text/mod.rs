@@ -23,6 +23,7 @@ mod update;
 mod view;
 mod dialogs;
 
+use crate::connection_monitor;
 use helpers::{create_empty_template, show_toast};
 pub use messages::Msg;
 pub use props::StaticTextProps;
@@ -100,6 +101,11 @@ impl Component for StaticTextComponent {
                         .send()
                         .await;
 
+                    match &response {
+                        Ok(_) => connection_monitor::report_success(),
+                        Err(_) => connection_monitor::report_failure(),
+                    }
+
                     match response {
                         Ok(resp) if resp.status() == 200 => {
                             if let Ok(template) =
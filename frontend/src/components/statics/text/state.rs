@@ -7,10 +7,15 @@
 //! The inline documentation describes each public field and the contract of
 //! the `resize_textarea` helper.
 
+use gloo_timers::callback::Timeout;
 use wasm_bindgen::JsCast;
 use web_sys::{HtmlElement, HtmlTextAreaElement};
 use yew::prelude::*;
 
+use super::helpers::LongLine;
+use common::model::csv::ColumnCheck;
+use common::model::image::Image;
+use common::model::pdf::Orientation;
 use common::model::template::Template;
 
 /// Main state container for the `StaticTextComponent`.
@@ -23,17 +28,30 @@ pub struct StaticTextComponent {
     pub text: String,
 
     /// A stack of text snapshots used for the undo/redo functionality. A new entry
-    /// is pushed on `Msg::UpdateText`. `Msg::Undo` and `Msg::Redo` navigate this stack.
+    /// is pushed on every edit, with typing bursts coalesced into one (see `set_text` in
+    /// `update.rs`), up to `MAX_HISTORY_ENTRIES`. `Msg::Undo` and `Msg::Redo` navigate
+    /// this stack.
     pub history: Vec<String>,
 
     /// The current position within the `history` stack. Points to the version of
     /// the text currently displayed in the editor.
     pub history_index: usize,
 
+    /// When the last keystroke was recorded in `history` (`js_sys::Date::now()`, in
+    /// milliseconds). The next keystroke joins the same entry if it comes soon enough.
+    /// `None` after any other edit, an undo or a redo, so the next keystroke starts a new
+    /// entry.
+    pub last_typed_at: Option<f64>,
+
     /// A string identifier for the currently visible tab, either `"editor"` or `"preview"`.
     /// Used by `view.rs` to conditionally render the correct UI.
     pub active_tab: String,
 
+    /// When `true`, the editor and the preview are rendered side by side instead of
+    /// as tabs. Toggled by `Msg::ToggleSplitView`. The preview is recomputed on every
+    /// render, so in this mode it follows the text live as the user types.
+    pub split_view: bool,
+
     /// A reference to the `<textarea>` DOM element. Used for programmatic actions like
     /// resizing (`resize_textarea`), focusing, and getting/setting selection ranges.
     pub textarea_ref: NodeRef,
@@ -59,15 +77,44 @@ pub struct StaticTextComponent {
     /// that image. It triggers the `image_dialog` to open and display the correct image.
     pub selected_image_id: Option<String>,
 
-    /// The URL for the PDF preview, constructed in `Msg::OpenPdf`. It points to the
-    /// backend endpoint and includes a cache-busting timestamp. Used as the `src` for
-    /// the `<iframe>` in the `pdf_dialog`.
+    /// Images dropped from the template because their tags left the text (`Msg::DeleteImage`
+    /// or a tag deleted by hand). `Msg::Undo` and `Msg::Redo` move an image back into the
+    /// template when its tag reappears. Cleared when another template is loaded.
+    pub removed_images: Vec<Image>,
+
+    /// The URL of the PDF shown in the `pdf_dialog`, used as the `src` of its `<iframe>`.
+    /// It points to `pdf_object_url` once the PDF has been fetched.
     pub pdf_url: Option<String>,
 
     /// A flag that is `true` while the PDF is being generated by the backend and the
     /// `<iframe>` is loading. It is used to display a loading indicator in the UI.
     pub pdf_loading: bool,
 
+    /// Object URL of the fetched PDF bytes (of the saved template or of a preview). While
+    /// set, `pdf_url` points to it. Dropping it (on close or on the next PDF) revokes the
+    /// URL and frees the blob.
+    pub pdf_object_url: Option<gloo_file::ObjectUrl>,
+
+    /// `true` when the dialog shows a preview PDF rendered from unsaved content, which
+    /// cannot be regenerated with another orientation.
+    pub pdf_is_preview: bool,
+
+    /// Why the last PDF could not be generated, shown in the `pdf_dialog` instead of the
+    /// viewer. Built from the backend's JSON error by `helpers::pdf_error_message`.
+    pub pdf_error: Option<String>,
+
+    /// Page orientation chosen in the PDF dialog with `Msg::TogglePdfOrientation`. `None`
+    /// uses the orientation saved with the template. Reset when the dialog closes.
+    pub pdf_orientation: Option<Orientation>,
+
+    /// A flag that is `true` while the "unsaved changes" dialog offered by `Msg::OpenPdf`
+    /// is shown.
+    pub show_unsaved_pdf_dialog: bool,
+
+    /// Set by `Msg::SaveAndOpenPdf` so that `Msg::SaveSucceeded` opens the PDF once the
+    /// save has gone through.
+    pub open_pdf_after_save: bool,
+
     /// A guard flag to ensure that one-time initialization logic in `rendered`
     /// (like loading a template or setting up event listeners) runs only once.
     pub loaded: bool,
@@ -76,17 +123,53 @@ pub struct StaticTextComponent {
     /// loaded or saved. It is compared against a hash of the current `text` to
     /// determine if there are unsaved changes (the "dirty" state).
     pub original_md5: Option<String>,
+
+    /// The text as it was when the template was last loaded or saved, captured alongside
+    /// `original_md5`. It is the baseline for the "Ver cambios" diff panel.
+    pub original_text: Option<String>,
+
+    /// A flag that is `true` while the "Ver cambios" diff panel is shown. Toggled by
+    /// `Msg::ToggleDiff`.
+    pub show_diff: bool,
+
+    /// A flag that is `true` while the template variables panel is shown. Toggled by
+    /// `Msg::ToggleVarsPanel`. The variables themselves live in `template.vars`.
+    pub show_vars_panel: bool,
+
+    /// The columns of the last verified CSV, as reported by `Msg::CsvColumnsUpdated`.
+    /// Used by `Msg::GenerateFromCsv` to build a starter template.
+    pub csv_columns: Option<Vec<ColumnCheck>>,
+
+    /// The first line longer than `LONG_LINE_THRESHOLD`, if any. Recomputed whenever the
+    /// text changes through `UpdateText`, `Undo` or `Redo`; while set, the editor shows
+    /// the "línea muy larga" warning.
+    pub long_line: Option<LongLine>,
+
+    /// Whether the textarea soft-wraps long lines (the browser default). Turning it off
+    /// with `Msg::ToggleLineWrap` keeps a long line on a single row, so the line-number
+    /// gutter stays aligned and the browser lays out far fewer rows.
+    pub wrap_lines: bool,
+
+    /// Whether edits are saved automatically after `AUTOSAVE_DELAY_MS` without typing.
+    /// On by default; toggled by `Msg::ToggleAutosave`.
+    pub autosave: bool,
+
+    /// The pending autosave, scheduled by `Msg::UpdateText`. Replacing or dropping it
+    /// cancels the timer, so only the last edit of a burst triggers `Msg::AutoSave`.
+    pub autosave_timer: Option<Timeout>,
 }
 
 impl StaticTextComponent {
     /// Constructs a new instance with sensible defaults:
     /// - empty `text`
-    /// - `history` initialized with one empty entry
-    /// - `active_tab` set to `"editor"`
+    /// - `history` initialized with one empty entry, with no keystroke recorded
+    /// - `active_tab` set to `"editor"` and split view off
     /// - empty `NodeRef`s
-    /// - no `template` loaded
+    /// - no `template` loaded and no removed images
     /// - PDF-related fields cleared
-    /// - `loaded` false and `original_md5` none
+    /// - `loaded` false, no saved baseline (`original_md5`/`original_text`) and diff hidden
+    /// - no long line detected and line wrapping on
+    /// - autosave on, with nothing scheduled
     ///
     /// Guarantees a consistent initial state for the UI and undo/redo logic.
     pub fn new() -> Self {
@@ -94,17 +177,34 @@ impl StaticTextComponent {
             text: String::new(),
             history: vec![String::new()],
             history_index: 0,
+            last_typed_at: None,
             active_tab: "editor".to_string(),
+            split_view: false,
             textarea_ref: Default::default(),
             file_input_ref: Default::default(),
             image_dialog_ref: Default::default(),
             pdf_viewer_dialog_ref: Default::default(),
             template: None,
             selected_image_id: None,
+            removed_images: Vec::new(),
             pdf_url: None,
             pdf_loading: false,
+            pdf_object_url: None,
+            pdf_is_preview: false,
+            pdf_error: None,
+            pdf_orientation: None,
+            show_unsaved_pdf_dialog: false,
+            open_pdf_after_save: false,
             loaded: false,
             original_md5: None,
+            original_text: None,
+            show_diff: false,
+            show_vars_panel: false,
+            csv_columns: None,
+            long_line: None,
+            wrap_lines: true,
+            autosave: true,
+            autosave_timer: None,
         }
     }
 
@@ -128,4 +228,11 @@ impl StaticTextComponent {
             }
         }
     }
+    /// Returns the page orientation the PDF dialog renders with: the one chosen with
+    /// `Msg::TogglePdfOrientation`, else the template's saved one, else portrait.
+    pub fn effective_pdf_orientation(&self) -> Orientation {
+        self.pdf_orientation
+            .or_else(|| self.template.as_ref().and_then(|t| t.orientation))
+            .unwrap_or_default()
+    }
 }
@@ -9,14 +9,19 @@
 //! - Text editing with undo/redo history.
 //! - Applying style snippets (markdown-like) at the current selection.
 //! - Auto-resizing the textarea and syncing the backing `Template` model.
-//! - Handling image insertion: upload -> base64 -> `[img:<uuid>]` tag -> template images list.
+//! - Handling image insertion: upload -> compression (backend) -> base64 -> `[img:<uuid>]` tag
+//!   -> template images list.
 //! - Deleting images, which removes both the asset and its inline tag.
 //! - Persisting the template via a backend POST, with user-facing toast messages (Spanish).
+//! - Autosaving an already saved template once the user stops typing.
 //! - Generating and displaying a PDF preview of the template.
+//! - Opening another saved template chosen in the template picker.
+//! - Duplicating the saved template and opening the copy.
 
 use base64::{engine::general_purpose, Engine as _};
-use gloo_file::{futures::read_as_bytes, Blob};
+use gloo_file::{futures::read_as_bytes, Blob, ObjectUrl};
 use gloo_net::http::Request;
+use gloo_timers::callback::Timeout;
 use js_sys::Date;
 use js_sys::Reflect;
 use regex::Regex;
@@ -24,18 +29,44 @@ use std::collections::HashSet;
 use wasm_bindgen::{JsCast, JsValue};
 use web_sys::HtmlTextAreaElement;
 
+use yew::html::Scope;
 use yew::platform::spawn_local;
 use yew::prelude::*;
 
-use common::model::image::Image;
-use common::model::template::Template;
+use common::model::image::{Image, MAX_IMAGES};
+use common::model::pdf::PdfErrorBody;
+use common::model::template::{Template, MAX_TEMPLATE_JSON_BYTES};
+use common::model::template_var::TemplateVar;
 
+use crate::connection_monitor;
 use crate::tops_sheet::yw_material_top_sheet::{close_top_sheet, open_top_sheet};
 
-use super::helpers::{byte_to_utf16_idx, compute_md5, show_toast};
+use super::helpers::{
+    build_image_tag, build_placeholder_tag, build_template_from_columns, byte_to_utf16_idx,
+    compute_md5, find_long_line, format_megabytes, image_tag_options, image_tag_regex,
+    pdf_error_message, selection_byte_range, show_toast, snap_to_char_boundary, utf16_to_byte_idx,
+    wrap_selection, ImageTagOptions,
+};
 use super::messages::Msg;
 use super::state::StaticTextComponent;
 
+/// Idle time after the last edit before an autosave, in milliseconds.
+const AUTOSAVE_DELAY_MS: u32 = 3_000;
+
+/// Longest pause between two keystrokes that still belong to the same undo step, in
+/// milliseconds. Typing without stopping for longer is undone at once.
+const HISTORY_COALESCE_MS: f64 = 1_000.0;
+
+/// Most entries kept in the undo history; the oldest are dropped beyond it.
+const MAX_HISTORY_ENTRIES: usize = 200;
+
+/// Toast shown after a manual save.
+const SAVED_TOAST: &str = "Plantilla guardada correctamente.";
+
+/// Toast shown after an autosave, worded differently so it is not mistaken for a manual
+/// save.
+const AUTOSAVED_TOAST: &str = "Guardado automáticamente";
+
 /// Central update function for the component.
 ///
 /// Contract
@@ -49,28 +80,22 @@ pub fn update(
 ) -> bool {
     match msg {
         // **`UpdateText(new_text)`**: Handles user input from the textarea.
-        // It updates the component's `text` state, manages the undo/redo history by
-        // pushing the new text onto the history stack, and sets a global 'dirty' flag
-        // to indicate unsaved changes. Returns `true` to re-render.
+        // It updates the component's `text` state through `set_typed_text`, which records
+        // the new text in the undo history (a burst of typing is a single entry), sets a
+        // global 'dirty' flag to indicate unsaved changes, re-checks the text for overly
+        // long lines (`find_long_line`) and (re)schedules the autosave. Returns `true` to
+        // re-render.
         Msg::UpdateText(new_text) => {
-            if component.text != new_text {
-                component.text = new_text.clone();
-                component.history.truncate(component.history_index + 1);
-                component.history.push(new_text);
-                component.history_index = component.history.len() - 1;
-
-                // Update dirty flag
-                set_window_dirty_flag(component);
-            }
+            set_typed_text(component, ctx, new_text);
             true
         }
         // **`Undo`**: Navigates backward in the history stack.
         // It decrements the `history_index`, updates the `text` state to the previous
-        // version, and updates the dirty flag. Returns `true` to re-render.
+        // version, restores the images whose tags come back (`restore_removed_images`) and
+        // updates the dirty flag. Returns `true` to re-render.
         Msg::Undo => {
             if component.history_index > 0 {
-                component.history_index -= 1;
-                component.text = component.history[component.history_index].clone();
+                go_to_history_entry(component, component.history_index - 1);
                 // Update dirty flag
                 set_window_dirty_flag(component);
             }
@@ -78,11 +103,11 @@ pub fn update(
         }
         // **`Redo`**: Navigates forward in the history stack.
         // It increments the `history_index`, updates the `text` state to the next
-        // version, and updates the dirty flag. Returns `true` to re-render.
+        // version, restores the images whose tags come back (`restore_removed_images`) and
+        // updates the dirty flag. Returns `true` to re-render.
         Msg::Redo => {
             if component.history_index + 1 < component.history.len() {
-                component.history_index += 1;
-                component.text = component.history[component.history_index].clone();
+                go_to_history_entry(component, component.history_index + 1);
                 // Update dirty flag
                 set_window_dirty_flag(component);
             }
@@ -104,11 +129,37 @@ pub fn update(
             }
             true
         }
-        // **`ApplyStyle(style, _)`**: Inserts a markdown-style snippet at the cursor.
-        // It wraps the selected text (or inserts a placeholder) with style markers
-        // like `**` for bold or `*` for italic, then programmatically selects the
-        // placeholder text for immediate editing. Updates the dirty flag. Returns `true`.
+        // **`ToggleSplitView`**: Switches between the tabbed layout and the side-by-side
+        // editor/preview layout. The textarea is remounted, so its height is recomputed
+        // once it is back in the DOM. Returns `true` to re-render the new layout.
+        Msg::ToggleSplitView => {
+            component.split_view = !component.split_view;
+            let link = ctx.link().clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                gloo_timers::future::TimeoutFuture::new(50).await;
+                link.send_message(Msg::AutoResize);
+            });
+            true
+        }
+        // **`ApplyStyle(style, _)`**: Applies a markdown-style snippet at the cursor.
+        // A non-blank selection is wrapped with the style markers, like `**` for bold or
+        // `*` for italic, line by line and with surrounding whitespace kept outside (see
+        // `wrap_selection`); otherwise the placeholder word "texto" is inserted between
+        // them. The wrapped text is then selected for immediate editing. Like `UpdateText`, it
+        // records the change in the undo history and updates the dirty flag. Sent by the
+        // toolbar buttons and by `Ctrl+B`/`Ctrl+I`. Returns `true`.
         Msg::ApplyStyle(style, _) => {
+            let (prefix, suffix, placeholder) = match style.as_str() {
+                "bold" => ("**", "**", "texto"),
+                "italic" => ("*", "*", "texto"),
+                "bolditalic" => ("***", "***", "texto"),
+                "underline" => ("__", "__", "texto"),
+                "strikethrough" => ("~~", "~~", "texto"),
+                "normal" => ("", "", "texto"),
+                "bulleted_list" => ("- ", "", "texto"),
+                "image" => ("[img:", "]", "url"),
+                _ => return false,
+            };
             if let Some(document) = web_sys::window().and_then(|w| w.document()) {
                 if let Some(textarea) = document
                     .get_element_by_id("static-textarea")
@@ -118,48 +169,30 @@ pub fn update(
                         textarea.selection_start().unwrap_or(Some(0)).unwrap_or(0) as usize;
                     let end_utf16 =
                         textarea.selection_end().unwrap_or(Some(0)).unwrap_or(0) as usize;
+                    let (start, end) =
+                        selection_byte_range(&component.text, start_utf16, end_utf16);
 
-                    let start = component
-                        .text
-                        .encode_utf16()
-                        .take(start_utf16)
-                        .map(|c| char::from_u32(c as u32).unwrap().len_utf8())
-                        .sum();
-                    let end = component
-                        .text
-                        .encode_utf16()
-                        .take(end_utf16)
-                        .map(|c| char::from_u32(c as u32).unwrap().len_utf8())
-                        .sum();
-
-                    let styled = match style.as_str() {
-                        "bold" => "**texto**",
-                        "italic" => "*texto*",
-                        "bolditalic" => "***texto***",
-                        "normal" => "texto",
-                        "bulleted_list" => "- texto",
-                        "image" => "[img:url]",
-                        _ => "",
+                    let selected = &component.text[start..end];
+                    let (replacement, sel_start, sel_end) = if selected.trim().is_empty() {
+                        let replacement = format!("{}{}{}", prefix, placeholder, suffix);
+                        (replacement, prefix.len(), prefix.len() + placeholder.len())
+                    } else {
+                        wrap_selection(selected, prefix, suffix)
                     };
-
-                    component.text = format!(
+                    let text = format!(
                         "{}{}{}",
                         &component.text[..start],
-                        styled,
+                        replacement,
                         &component.text[end..]
                     );
+                    set_text(component, ctx, text);
                     textarea.set_value(&component.text);
 
-                    let text_pos = component.text[start..].find("texto").unwrap_or(0) + start;
-                    let select_start = byte_to_utf16_idx(&component.text, text_pos);
-                    let select_end = byte_to_utf16_idx(&component.text, text_pos + 5);
-
+                    let select_start = byte_to_utf16_idx(&component.text, start + sel_start);
+                    let select_end = byte_to_utf16_idx(&component.text, start + sel_end);
                     textarea.set_selection_start(Some(select_start)).ok();
                     textarea.set_selection_end(Some(select_end)).ok();
                     textarea.focus().ok();
-
-                    // Update dirty flag
-                    set_window_dirty_flag(component);
                 }
             }
             true
@@ -173,13 +206,22 @@ pub fn update(
             if let Some(template) = &mut component.template {
                 template.text = component.text.clone();
                 if let Some(images) = &mut template.images {
-                    images.retain(|img| component.text.contains(&format!("[img:{}]", img.id)));
+                    let (kept, removed) = std::mem::take(images)
+                        .into_iter()
+                        .partition(|img| image_tag_regex(&img.id).is_match(&component.text));
+                    *images = kept;
+                    stash_removed_images(&mut component.removed_images, removed);
                 }
             } else {
                 component.template = Some(Template {
                     id: String::new(),
                     text: component.text.clone(),
                     images: None,
+                    vars: None,
+                    margins: None,
+                    orientation: None,
+                    page_size: None,
+                    name: None,
                 });
             }
 
@@ -196,10 +238,17 @@ pub fn update(
         }
         // **`FileSelected(file)`**: Handles the result of a file dialog selection.
         // It generates a unique ID for the new image, inserts an `[img:<id>]` tag at the
-        // cursor, and spawns an async task to read the file as bytes and send an
-        // `AddImageToTemplate` message with the Base64 data. Returns `true`.
+        // cursor, and spawns an async task that has the backend compress the file
+        // (`compress_image`) and sends an `AddImageToTemplate` message with the Base64
+        // data. If the backend cannot compress it, the original file is encoded instead.
+        // If the template already has `MAX_IMAGES` images, nothing is inserted and a toast
+        // explains why. Returns `true`.
         Msg::FileSelected(file) => {
             use uuid::Uuid;
+            if image_count(component) >= MAX_IMAGES {
+                show_max_images_toast();
+                return false;
+            }
             let uuid = Uuid::new_v4().to_string();
 
             if let Some(document) = web_sys::window().and_then(|w| w.document()) {
@@ -211,49 +260,59 @@ pub fn update(
                         textarea.selection_start().unwrap_or(Some(0)).unwrap_or(0) as usize;
                     let end_utf16 =
                         textarea.selection_end().unwrap_or(Some(0)).unwrap_or(0) as usize;
-                    let start = component
-                        .text
-                        .encode_utf16()
-                        .take(start_utf16)
-                        .map(|c| char::from_u32(c as u32).unwrap().len_utf8())
-                        .sum();
-                    let end = component
-                        .text
-                        .encode_utf16()
-                        .take(end_utf16)
-                        .map(|c| char::from_u32(c as u32).unwrap().len_utf8())
-                        .sum();
+                    let (start, end) =
+                        selection_byte_range(&component.text, start_utf16, end_utf16);
                     let styled = format!("[img:{}]", uuid);
-                    component.text = format!(
+                    let text = format!(
                         "{}{}{}",
                         &component.text[..start],
                         styled,
                         &component.text[end..]
                     );
+                    set_text(component, ctx, text);
                     textarea.set_value(&component.text);
 
                     let file_clone = file.clone();
                     let link = ctx.link().clone();
                     wasm_bindgen_futures::spawn_local(async move {
-                        let blob = Blob::from(file_clone);
-                        if let Ok(bytes) = read_as_bytes(&blob).await {
-                            let base64 = general_purpose::STANDARD.encode(&bytes);
-                            link.send_message_batch(vec![
-                                Msg::AutoResize,
-                                Msg::AddImageToTemplate { id: uuid, base64 },
-                            ]);
-                        }
+                        let base64 = match compress_image(&file_clone).await {
+                            Some(base64) => base64,
+                            None => match read_as_bytes(&Blob::from(file_clone)).await {
+                                Ok(bytes) => general_purpose::STANDARD.encode(&bytes),
+                                Err(_) => return,
+                            },
+                        };
+                        link.send_message_batch(vec![
+                            Msg::AutoResize,
+                            Msg::AddImageToTemplate { id: uuid, base64 },
+                        ]);
                     });
-                    // Update dirty flag
-                    set_window_dirty_flag(component);
                 }
             }
             true
         }
         // **`AddImageToTemplate { id, base64 }`**: Adds image data to the in-memory template.
         // This is the callback from `FileSelected`. It creates an `Image` struct and adds
-        // it to the `template.images` vector. Returns `false`.
+        // it to the `template.images` vector. If the cap was reached in the meantime (e.g.
+        // several files read concurrently), or if the image would make the template too
+        // large for the backend to save (`MAX_TEMPLATE_JSON_BYTES`), the image is dropped,
+        // a toast explains why and its `[img:...]` tag is removed from the text again.
+        // Returns `false`, or `true` if the tag was removed.
         Msg::AddImageToTemplate { id, base64 } => {
+            let too_many = image_count(component) >= MAX_IMAGES;
+            let too_large = template_json_len(component) + base64.len() > MAX_TEMPLATE_JSON_BYTES;
+            if too_many || too_large {
+                if too_many {
+                    show_max_images_toast();
+                } else {
+                    show_payload_too_large_toast();
+                }
+                let text = image_tag_regex(&id)
+                    .replace_all(&component.text, "")
+                    .into_owned();
+                set_text(component, ctx, text);
+                return true;
+            }
             let image = Image { id, base64 };
             if let Some(template) = &mut component.template {
                 match &mut template.images {
@@ -265,6 +324,11 @@ pub fn update(
                     id: String::new(),
                     text: component.text.clone(),
                     images: Some(vec![image]),
+                    vars: None,
+                    margins: None,
+                    orientation: None,
+                    page_size: None,
+                    name: None,
                 });
             }
             false
@@ -279,14 +343,21 @@ pub fn update(
         }
         // **`DeleteImage(id)`**: Removes an image from the template.
         // It removes the image data from `template.images` and strips all occurrences
-        // of its `[img:...]` tag from the editor text. Updates the dirty flag. Returns `true`.
+        // of its `[img:...]` tag from the editor text with `set_text`, so undo brings the
+        // tags back. Updates the dirty flag. Returns `true`.
         Msg::DeleteImage(id) => {
             if let Some(template) = &mut component.template {
                 if let Some(images) = &mut template.images {
-                    images.retain(|img| img.id != id);
+                    let (removed, kept) = std::mem::take(images)
+                        .into_iter()
+                        .partition(|img| img.id == id);
+                    *images = kept;
+                    stash_removed_images(&mut component.removed_images, removed);
                 }
-                component.text = component.text.replace(&format!("[img:{}]", id), "");
-                template.text = component.text.clone();
+                let text = image_tag_regex(&id)
+                    .replace_all(&component.text, "")
+                    .into_owned();
+                set_text(component, ctx, text);
             }
             component.selected_image_id = None;
             close_top_sheet(component.image_dialog_ref.clone());
@@ -295,55 +366,65 @@ pub fn update(
             set_window_dirty_flag(component);
             true
         }
-        // **`Save`**: Persists the current template to the backend.
+        // **`SetImageAlignment { id, alignment }`**: Sent by the alignment buttons of the
+        // image dialog. It rewrites every tag of the image with the new alignment, keeping
+        // its width, e.g. `[img:<id>|center]`, which the preview and the PDF honor (see
+        // `rewrite_image_tags`). Returns `true`.
+        Msg::SetImageAlignment { id, alignment } => {
+            let mut options = image_tag_options(&component.text, &id);
+            options.alignment = alignment;
+            rewrite_image_tags(component, ctx, &id, &options);
+            true
+        }
+        // **`SetImageWidth { id, width }`**: Sent by the width field of the image dialog.
+        // It rewrites every tag of the image with `|w=<width>`, keeping its alignment, or
+        // without a width (the default 200 px box) when the field is cleared. Returns `true`.
+        Msg::SetImageWidth { id, width } => {
+            let mut options = image_tag_options(&component.text, &id);
+            options.width = width.map(|w| w.to_string());
+            rewrite_image_tags(component, ctx, &id, &options);
+            true
+        }
+        // **`Save`**: Persists the current template to the backend (see `save_template`).
         // It sends the entire `template` object (ID, text, and images) to the
-        // `/api/templates/save` endpoint. On success, it dispatches `SaveSucceeded`.
+        // `/api/templates/save` endpoint. On success, it dispatches `SaveSucceeded`;
+        // on failure, `SaveFailed`. A pending autosave is cancelled.
         // Shows toast notifications for success or failure. Returns `false`.
         Msg::Save => {
-            let template = component.template.get_or_insert_with(|| Template {
-                id: String::new(),
-                text: component.text.clone(),
-                images: None,
-            });
-
-            if template.id.is_empty() {
-                template.id = uuid::Uuid::new_v4().to_string();
+            save_template(component, ctx, SAVED_TOAST);
+            false
+        }
+        // **`AutoSave`**: Sent by the timer of `schedule_autosave` once the user has stopped
+        // typing for `AUTOSAVE_DELAY_MS`. It saves like `Save`, with its own toast, but
+        // only if autosave is still on, the template was saved before (non-empty id) and
+        // the text has unsaved changes. Returns `false`.
+        Msg::AutoSave => {
+            component.autosave_timer = None;
+            let saved_before = component
+                .template
+                .as_ref()
+                .is_some_and(|t| !t.id.is_empty());
+            if component.autosave && saved_before && has_unsaved_changes(component) {
+                save_template(component, ctx, AUTOSAVED_TOAST);
             }
-
-            let template_clone = template.clone();
-            let link = ctx.link().clone();
-            spawn_local(async move {
-                match Request::post("/api/templates/save")
-                    .json(&template_clone)
-                    .unwrap()
-                    .send()
-                    .await
-                {
-                    Ok(response) if response.status() == 200 => {
-                        link.send_message(Msg::SaveSucceeded);
-                        show_toast("Plantilla guardada correctamente.");
-                    }
-                    Ok(response) => {
-                        show_toast(&format!(
-                            "Error al guardar la plantilla: {}",
-                            response.text().await.unwrap_or_default()
-                        ));
-                    }
-                    Err(err) => {
-                        show_toast(&format!("Error al guardar la plantilla: {}", err));
-                    }
-                }
-            });
-
             false
         }
+        // **`ToggleAutosave`**: Turns autosave on or off. Turning it off cancels a pending
+        // autosave. Returns `true` to update the toolbar button.
+        Msg::ToggleAutosave => {
+            component.autosave = !component.autosave;
+            component.autosave_timer = None;
+            true
+        }
         // **`SetTemplate(template_opt)`**: Replaces the component's entire template.
         // Typically used on initial load. It sets the `template` state and calculates
         // the `original_md5` hash of the text, which is used to track unsaved changes.
-        // Returns `true`.
+        // The text itself is kept in `original_text` as the diff baseline. Returns `true`.
         Msg::SetTemplate(template_opt) => {
             component.template = template_opt;
+            component.removed_images.clear();
             component.original_md5 = component.template.as_ref().map(|t| compute_md5(&t.text));
+            component.original_text = component.template.as_ref().map(|t| t.text.clone());
 
             // Update dirty flag
             set_window_dirty_flag(component);
@@ -351,23 +432,17 @@ pub fn update(
         }
         // **`InsertCsvColumnPlaceholder(col_check)`**: Inserts a CSV data placeholder.
         // It creates a `[ph:TITLE:BASE64]` tag using data from the selected CSV column
-        // and inserts it at the current cursor position in the textarea. Returns `true`.
+        // and inserts it at the current cursor position in the textarea, as an undoable
+        // edit (`set_text`). Returns `true`.
         Msg::InsertCsvColumnPlaceholder(col_check) => {
             if let Some(textarea) = component.textarea_ref.cast::<HtmlTextAreaElement>() {
                 let utf16_pos = textarea.selection_start().unwrap_or(Some(0)).unwrap_or(0) as usize;
-                let byte_pos = super::helpers::utf16_to_byte_idx(&component.text, utf16_pos);
-
-                let mut text = component.text.clone();
-                let value = col_check.first_row.clone().unwrap_or_default();
-                let base64 = general_purpose::STANDARD.encode(value);
-                let placeholder = format!("[ph:{}:{}]", col_check.title, base64);
-                text.insert_str(byte_pos, &placeholder);
-                component.text = text;
-
-                let new_utf16_pos = byte_to_utf16_idx(
+                let (text, new_utf16_pos) = insert_at_utf16(
                     &component.text,
-                    byte_pos + placeholder.len(),
+                    utf16_pos,
+                    &build_placeholder_tag(&col_check),
                 );
+                set_text(component, ctx, text);
 
                 let textarea_ref = component.textarea_ref.clone();
                 wasm_bindgen_futures::spawn_local(async move {
@@ -386,8 +461,12 @@ pub fn update(
         // **`CsvColumnsUpdated(cols)`**: Prunes placeholders for removed CSV columns.
         // When the associated CSV file changes, this message is sent with the new set of
         // valid columns. It scans the text and removes any `[ph:...]` placeholders whose
-        // title no longer exists in the new column list. Returns `true` if text changed.
+        // title no longer exists in the new column list, as an undoable edit (`set_text`).
+        // Returns `true` if text changed.
         Msg::CsvColumnsUpdated(cols) => {
+            // Keep the columns for `GenerateFromCsv`.
+            component.csv_columns = Some(cols.clone());
+
             // Build a set of allowed titles
             let allowed: HashSet<String> = cols.into_iter().map(|c| c.title).collect();
 
@@ -406,67 +485,67 @@ pub fn update(
                 })
                 .into_owned();
 
-            if new_text != component.text {
-                component.text = new_text.clone();
-                // Keep the template synchronized if present
-                if let Some(template) = &mut component.template {
-                    template.text = new_text.clone();
-                }
+            if set_text(component, ctx, new_text) {
                 // Update the textarea DOM if present
                 if let Some(textarea) = component.textarea_ref.cast::<HtmlTextAreaElement>() {
-                    textarea.set_value(&new_text);
+                    textarea.set_value(&component.text);
                 }
                 // Recalculate size and refresh images if applicable
                 ctx.link().send_message(Msg::AutoResize);
-
-                // Update dirty flag
-                set_window_dirty_flag(component);
                 return true;
             }
             false
         }
-        // **`SaveSucceeded`**: Updates the dirty-checking baseline after a successful save.
-        // It recalculates `original_md5` with the current text content, effectively marking
-        // the current state as "saved". Resets the global dirty flag. Returns `true`.
-        Msg::SaveSucceeded => {
-            component.original_md5 = Some(compute_md5(&component.text));
+        // **`SaveSucceeded(saved_text)`**: Updates the dirty-checking baseline after a
+        // successful save. It sets `original_md5` and `original_text` from the text that was
+        // sent, so edits made while the request was in flight still count as unsaved.
+        // Updates the global dirty flag. Returns `true`.
+        Msg::SaveSucceeded(saved_text) => {
+            component.original_md5 = Some(compute_md5(&saved_text));
+            component.original_text = Some(saved_text);
+
+            // Finish a "Guardar y generar" request from the unsaved-changes dialog.
+            if component.open_pdf_after_save {
+                component.open_pdf_after_save = false;
+                ctx.link().send_message(Msg::OpenPdf);
+            }
 
             // Update dirty flag
             set_window_dirty_flag(component);
             true
         }
         // **`OpenPdf`**: Prepares and opens the PDF preview dialog.
-        // It checks for unsaved changes, then sets the `pdf_url` to the backend endpoint
-        // `/api/templates/pdf/{id}`, including a cache-busting timestamp. It also sets
-        // `pdf_loading` to `true` and opens the dialog. Returns `true`.
+        // If the template was never saved or the text changed since the last save, it
+        // shows the "unsaved changes" dialog instead (see `SaveAndOpenPdf` and
+        // `OpenPreviewPdf`). Otherwise it opens the dialog with the loading indicator and
+        // fetches `/api/templates/pdf/{id}`, including a cache-busting timestamp. The
+        // result arrives as `PdfReady`. Returns `true`.
         Msg::OpenPdf => {
             if let Some(template) = &component.template {
-                if template.id.is_empty() {
-                    show_toast("Guarda la plantilla antes de generar el PDF.");
-                    return true;
-                }
-
-                // Only proceed if the text hasn't changed since last save
                 let current_md5 = compute_md5(&component.text);
-                if let Some(orig) = &component.original_md5 {
-                    if orig != &current_md5 {
-                        show_toast("Guarda la plantilla antes de generar el PDF.");
-                        return true;
-                    }
-                } else {
-                    // If no original md5, must save first
-                    show_toast("Guarda la plantilla antes de generar el PDF.");
+                let unsaved = template.id.is_empty()
+                    || component.original_md5.as_ref() != Some(&current_md5);
+                if unsaved {
+                    component.show_unsaved_pdf_dialog = true;
                     return true;
                 }
 
                 // Force a cache-busting timestamp
                 let ts = Date::now() as u64;
-                component.pdf_url = Some(format!("/api/templates/pdf/{}?t={}", template.id, ts));
+                let mut url = format!("/api/templates/pdf/{}?t={}", template.id, ts);
+                if let Some(orientation) = component.pdf_orientation {
+                    url.push_str(&format!("&orientation={}", orientation.as_str()));
+                }
 
                 // Mostrar modal de progreso hasta que el iframe cargue
-                component.pdf_loading = true;
-
+                start_pdf_loading(component, false);
                 open_top_sheet(component.pdf_viewer_dialog_ref.clone());
+
+                let link = ctx.link().clone();
+                spawn_local(async move {
+                    let result = read_pdf_response(Request::get(&url).send().await).await;
+                    link.send_message(Msg::PdfReady(result));
+                });
             } else {
                 show_toast("No hay plantilla cargada.");
             }
@@ -481,13 +560,337 @@ pub fn update(
             true
         }
         // **`ClosePdfDialog`**: Resets state related to the PDF viewer.
-        // It clears the `pdf_url` and `pdf_loading` flags, effectively closing the
-        // PDF preview dialog and cleaning up its state. Returns `true`.
+        // It clears the `pdf_url`, `pdf_loading` and `pdf_orientation` fields, effectively
+        // closing the PDF preview dialog and cleaning up its state. Returns `true`.
         Msg::ClosePdfDialog => {
             component.pdf_url = None;
+            component.pdf_object_url = None;
+            component.pdf_is_preview = false;
+            component.pdf_error = None;
+            component.pdf_orientation = None;
             component.pdf_loading = false;
             true
         }
+        // **`TogglePdfOrientation`**: Flips the page orientation of the open PDF and
+        // regenerates it through `OpenPdf`, which adds the `orientation` query parameter.
+        // Preview PDFs of unsaved content cannot be regenerated, so they are left as is.
+        Msg::TogglePdfOrientation => {
+            if component.pdf_is_preview {
+                return false;
+            }
+            component.pdf_orientation = Some(component.effective_pdf_orientation().flipped());
+            ctx.link().send_message(Msg::OpenPdf);
+            false
+        }
+        // **`SaveAndOpenPdf`**: "Guardar y generar" branch of the unsaved-changes dialog.
+        // It closes the dialog and dispatches `Save`; once `SaveSucceeded` arrives, the
+        // `open_pdf_after_save` flag makes it dispatch `OpenPdf`, which now finds the
+        // template clean and shows the PDF of the saved version. If the save fails, the
+        // usual error toast is shown and no PDF is opened. Returns `true`.
+        Msg::SaveAndOpenPdf => {
+            component.show_unsaved_pdf_dialog = false;
+            component.open_pdf_after_save = true;
+            ctx.link().send_message(Msg::Save);
+            true
+        }
+        // **`OpenPreviewPdf`**: "Generar sin guardar" branch of the unsaved-changes dialog.
+        // It posts the current in-memory template (text and images) to
+        // `/api/templates/pdf/preview`, which renders it without saving, and opens the PDF
+        // dialog with the loading indicator. The stored template and the dirty state are
+        // left untouched. The response arrives as `PreviewPdfReady`. Returns `true`.
+        Msg::OpenPreviewPdf => {
+            component.show_unsaved_pdf_dialog = false;
+            let mut template = component.template.clone().unwrap_or_else(|| Template {
+                id: String::new(),
+                text: String::new(),
+                images: None,
+                vars: None,
+                margins: None,
+                orientation: None,
+                page_size: None,
+                name: None,
+            });
+            template.text = component.text.clone();
+
+            start_pdf_loading(component, true);
+            open_top_sheet(component.pdf_viewer_dialog_ref.clone());
+
+            let link = ctx.link().clone();
+            spawn_local(async move {
+                let request = Request::post("/api/templates/pdf/preview").json(&template);
+                let result = match request {
+                    Ok(request) => read_pdf_response(request.send().await).await,
+                    Err(err) => Err(err.to_string()),
+                };
+                link.send_message(Msg::PreviewPdfReady(result));
+            });
+            true
+        }
+        // **`PdfReady(result)`** / **`PreviewPdfReady(result)`**: Show the PDF fetched by
+        // `OpenPdf` or `OpenPreviewPdf`. The bytes are wrapped in a `Blob` whose object
+        // URL becomes the iframe `src`; the iframe's `onload` then dispatches `PdfLoaded`
+        // as usual. On failure, the dialog stays open and shows the error message built by
+        // `read_pdf_response` instead of an empty viewer. Returns `true`.
+        Msg::PdfReady(result) | Msg::PreviewPdfReady(result) => {
+            match result {
+                Ok(bytes) => {
+                    let blob = Blob::new_with_options(bytes.as_slice(), Some("application/pdf"));
+                    let url = ObjectUrl::from(blob);
+                    component.pdf_url = Some(url.to_string());
+                    component.pdf_object_url = Some(url);
+                }
+                Err(err) => {
+                    component.pdf_loading = false;
+                    component.pdf_error = Some(err);
+                }
+            }
+            true
+        }
+        // **`CloseUnsavedPdfDialog`**: "Cancelar" branch of the unsaved-changes dialog.
+        // It only hides the dialog. Returns `true`.
+        Msg::CloseUnsavedPdfDialog => {
+            component.show_unsaved_pdf_dialog = false;
+            true
+        }
+        // **`SaveFailed`**: Sent by `Save` when the backend call fails (the error toast is
+        // shown there). It drops a pending "Guardar y generar" so a later, unrelated save
+        // does not pop up the PDF. Returns `false`.
+        Msg::SaveFailed => {
+            component.open_pdf_after_save = false;
+            false
+        }
+        // **`GenerateFromCsv`**: Replaces the text with a starter template listing every
+        // verified CSV column as `**Title:** [ph:...]`. If the editor has content, the
+        // user is asked to confirm first. The new text goes through `UpdateText`, so it
+        // is a single undo entry and marks the template dirty. Returns `false`.
+        Msg::GenerateFromCsv => {
+            let Some(columns) = component.csv_columns.as_ref().filter(|c| !c.is_empty()) else {
+                show_toast("Verifica un CSV antes de generar la plantilla.");
+                return false;
+            };
+            if !component.text.trim().is_empty() {
+                let confirmed = web_sys::window()
+                    .and_then(|w| {
+                        w.confirm_with_message(
+                            "Se reemplazará el contenido actual del editor. ¿Continuar?",
+                        )
+                        .ok()
+                    })
+                    .unwrap_or(false);
+                if !confirmed {
+                    return false;
+                }
+            }
+            let text = build_template_from_columns(columns);
+            if let Some(textarea) = component.textarea_ref.cast::<HtmlTextAreaElement>() {
+                textarea.set_value(&text);
+            }
+            ctx.link()
+                .send_message_batch(vec![Msg::UpdateText(text), Msg::AutoResize]);
+            false
+        }
+        // **`UpdateName(name)`**: Sets the template name from the name field. Like the
+        // variables, it is only sent to the backend by `Save`. A blank name is stored as
+        // `None`. Returns `true`.
+        Msg::UpdateName(name) => {
+            if let Some(template) = &mut component.template {
+                template.name = Some(name).filter(|n| !n.trim().is_empty());
+            }
+            true
+        }
+        // **`OpenTemplate(id)`**: Sent by the template picker. If the text has unsaved
+        // changes, the user is asked to confirm first, since they will be lost. The template
+        // is then fetched from `GET /api/templates/{id}` and handed to `TemplateOpened`.
+        // Picking the template already open does nothing. Returns `false`.
+        Msg::OpenTemplate(id) => {
+            if component.template.as_ref().is_some_and(|t| t.id == id) {
+                return false;
+            }
+            if has_unsaved_changes(component) {
+                let confirmed = web_sys::window()
+                    .and_then(|w| {
+                        w.confirm_with_message(
+                            "Hay cambios sin guardar que se perderán al abrir otra plantilla. ¿Continuar?",
+                        )
+                        .ok()
+                    })
+                    .unwrap_or(false);
+                if !confirmed {
+                    return false;
+                }
+            }
+            spawn_local(fetch_template(ctx.link().clone(), id));
+            false
+        }
+        // **`DuplicateTemplate`**: Asks the backend to copy the saved template
+        // (`POST /api/templates/{id}/clone`) and opens the copy. The copy is made from the
+        // saved version, so unsaved changes are only confirmed, never copied. Returns `false`.
+        Msg::DuplicateTemplate => {
+            let Some(id) = component.template.as_ref().map(|t| t.id.clone()) else {
+                return false;
+            };
+            if has_unsaved_changes(component) {
+                let confirmed = web_sys::window()
+                    .and_then(|w| {
+                        w.confirm_with_message(
+                            "La copia se hará desde la última versión guardada; los cambios sin guardar se perderán. ¿Continuar?",
+                        )
+                        .ok()
+                    })
+                    .unwrap_or(false);
+                if !confirmed {
+                    return false;
+                }
+            }
+            let link = ctx.link().clone();
+            spawn_local(async move {
+                let response = Request::post(&format!("/api/templates/{}/clone", id))
+                    .send()
+                    .await;
+                match &response {
+                    Ok(_) => connection_monitor::report_success(),
+                    Err(_) => connection_monitor::report_failure(),
+                }
+                match response {
+                    Ok(resp) if resp.status() == 201 => match resp.text().await {
+                        Ok(new_id) => fetch_template(link, new_id).await,
+                        Err(_) => show_toast("Error duplicando plantilla."),
+                    },
+                    Ok(resp) if resp.status() == 404 => {
+                        show_toast("Guarda la plantilla antes de duplicarla.")
+                    }
+                    _ => show_toast("Error duplicando plantilla."),
+                }
+            });
+            false
+        }
+        // **`TemplateOpened(template)`**: Replaces the editor content with a template loaded
+        // by `OpenTemplate`. The undo history starts over from its text, so undo cannot bring
+        // back the previous template, and state tied to that template (selected image, CSV
+        // columns) is cleared. The `CsvDataSourceComponent` picks up the new template id by
+        // itself. Returns `true`.
+        Msg::TemplateOpened(template) => {
+            component.text = template.text.clone();
+            component.history = vec![template.text.clone()];
+            component.history_index = 0;
+            component.last_typed_at = None;
+            component.long_line = find_long_line(&component.text);
+            component.selected_image_id = None;
+            component.removed_images.clear();
+            component.csv_columns = None;
+            // A pending autosave belongs to the template being replaced.
+            component.autosave_timer = None;
+            if let Some(textarea) = component.textarea_ref.cast::<HtmlTextAreaElement>() {
+                textarea.set_value(&component.text);
+            }
+            ctx.link().send_message_batch(vec![
+                Msg::SetTemplate(Some(template)),
+                Msg::SetTab("editor".to_string()),
+                Msg::AutoResize,
+            ]);
+            show_toast("Plantilla cargada correctamente.");
+            true
+        }
+        // **`ToggleVarsPanel`**: Shows or hides the template variables panel. Returns `true`.
+        Msg::ToggleVarsPanel => {
+            component.show_vars_panel = !component.show_vars_panel;
+            true
+        }
+        // **`AddVar`**: Appends an empty variable to `template.vars` for the user to fill in.
+        // Variables are saved together with the template by `Save`. Returns `true`.
+        Msg::AddVar => {
+            if let Some(template) = &mut component.template {
+                template.vars.get_or_insert_with(Vec::new).push(TemplateVar {
+                    name: String::new(),
+                    value: String::new(),
+                });
+            }
+            true
+        }
+        // **`RemoveVar(idx)`**: Removes a variable. `[var:...]` tags referencing it are left
+        // in the text and shown verbatim in the preview. Returns `true`.
+        Msg::RemoveVar(idx) => {
+            if let Some(vars) = component.template.as_mut().and_then(|t| t.vars.as_mut()) {
+                if idx < vars.len() {
+                    vars.remove(idx);
+                }
+            }
+            true
+        }
+        // **`UpdateVarName(idx, name)`** / **`UpdateVarValue(idx, value)`**: Edit a variable
+        // in place. The preview re-renders with the new value on every change. Returns `true`.
+        Msg::UpdateVarName(idx, name) => {
+            if let Some(var) = component
+                .template
+                .as_mut()
+                .and_then(|t| t.vars.as_mut())
+                .and_then(|vars| vars.get_mut(idx))
+            {
+                var.name = name.trim().to_string();
+            }
+            true
+        }
+        Msg::UpdateVarValue(idx, value) => {
+            if let Some(var) = component
+                .template
+                .as_mut()
+                .and_then(|t| t.vars.as_mut())
+                .and_then(|vars| vars.get_mut(idx))
+            {
+                var.value = value;
+            }
+            true
+        }
+        // **`InsertVar(name)`**: Inserts a `[var:NAME]` tag at the cursor position, as a
+        // single undo entry. Returns `true`.
+        Msg::InsertVar(name) => {
+            if name.is_empty() {
+                return false;
+            }
+            let utf16_pos = component
+                .textarea_ref
+                .cast::<HtmlTextAreaElement>()
+                .and_then(|t| t.selection_start().ok().flatten())
+                .unwrap_or(0) as usize;
+            let byte_pos = snap_to_char_boundary(
+                &component.text,
+                utf16_to_byte_idx(&component.text, utf16_pos),
+            );
+            let mut text = component.text.clone();
+            text.insert_str(byte_pos, &format!("[var:{}]", name));
+            ctx.link()
+                .send_message_batch(vec![Msg::UpdateText(text), Msg::AutoResize]);
+            false
+        }
+        // **`ToggleDiff`**: Shows or hides the "Ver cambios" panel, which compares the
+        // current text against `original_text`. Returns `true`.
+        Msg::ToggleDiff => {
+            component.show_diff = !component.show_diff;
+            true
+        }
+        // **`ToggleLineWrap`**: Switches soft wrapping of the textarea on or off. Offered by
+        // the long-line warning; the text itself is not modified. Returns `true`.
+        Msg::ToggleLineWrap => {
+            component.wrap_lines = !component.wrap_lines;
+            true
+        }
+    }
+}
+
+/// Fetches the template `id` from `GET /api/templates/{id}` and hands it to
+/// `Msg::TemplateOpened`, or shows an error toast.
+async fn fetch_template(link: Scope<StaticTextComponent>, id: String) {
+    let response = Request::get(&format!("/api/templates/{}", id)).send().await;
+    match &response {
+        Ok(_) => connection_monitor::report_success(),
+        Err(_) => connection_monitor::report_failure(),
+    }
+    match response {
+        Ok(resp) if resp.status() == 200 => match resp.json::<Template>().await {
+            Ok(template) => link.send_message(Msg::TemplateOpened(template)),
+            Err(_) => show_toast("Error cargando plantilla."),
+        },
+        _ => show_toast("Error cargando plantilla."),
     }
 }
 
@@ -495,16 +898,386 @@ pub fn update(
 /// differs from the last saved state (`original_md5`).
 fn set_window_dirty_flag(component: &StaticTextComponent) {
     if let Some(window) = web_sys::window() {
-        let dirty = component
-            .original_md5
-            .as_ref()
-            .map_or(!component.text.is_empty(), |orig| {
-                orig != &compute_md5(&component.text)
-            });
         let _ = Reflect::set(
             &window,
             &JsValue::from_str("app_dirty"),
-            &JsValue::from_bool(dirty),
+            &JsValue::from_bool(has_unsaved_changes(component)),
         );
     }
-}
\ No newline at end of file
+}
+
+/// Sends the template to `/api/templates/save`, for `Msg::Save` and `Msg::AutoSave`.
+///
+/// A template without an id gets a new one first. Any pending autosave is cancelled,
+/// since this save already includes its changes. A template whose JSON exceeds
+/// `MAX_TEMPLATE_JSON_BYTES` is not sent, as the backend would reject it. `success_toast` is shown once the
+/// backend accepts the save; errors are reported with the same toast in both cases.
+fn save_template(
+    component: &mut StaticTextComponent,
+    ctx: &Context<StaticTextComponent>,
+    success_toast: &'static str,
+) {
+    component.autosave_timer = None;
+    let template = component.template.get_or_insert_with(|| Template {
+        id: String::new(),
+        text: component.text.clone(),
+        images: None,
+        vars: None,
+        margins: None,
+        orientation: None,
+        page_size: None,
+        name: None,
+    });
+
+    if template.id.is_empty() {
+        template.id = uuid::Uuid::new_v4().to_string();
+    }
+    template.text = component.text.clone();
+
+    let template_clone = template.clone();
+    let link = ctx.link().clone();
+    let body = serde_json::to_string(&template_clone).unwrap_or_default();
+    if body.len() > MAX_TEMPLATE_JSON_BYTES {
+        // The backend would reject it; say why instead of showing its error.
+        show_payload_too_large_toast();
+        link.send_message(Msg::SaveFailed);
+        return;
+    }
+    spawn_local(async move {
+        match Request::post("/api/templates/save")
+            .header("Content-Type", "application/json")
+            .body(body)
+            .unwrap()
+            .send()
+            .await
+        {
+            Ok(response) if response.status() == 200 => {
+                connection_monitor::report_success();
+                link.send_message(Msg::SaveSucceeded(template_clone.text));
+                show_toast(success_toast);
+            }
+            Ok(response) => {
+                connection_monitor::report_success();
+                link.send_message(Msg::SaveFailed);
+                show_toast(&format!(
+                    "Error al guardar la plantilla: {}",
+                    response.text().await.unwrap_or_default()
+                ));
+            }
+            Err(err) => {
+                connection_monitor::report_failure();
+                link.send_message(Msg::SaveFailed);
+                show_toast(&format!("Error al guardar la plantilla: {}", err));
+            }
+        }
+    });
+}
+
+/// Sends `file` to `/api/templates/images/compress`, which scales large images down and
+/// re-encodes them, so the stored Base64 stays small.
+///
+/// # Returns
+/// The Base64 of the compressed image, or `None` if the request failed or the backend
+/// could not decode the file (e.g. a GIF); the caller then stores the original.
+async fn compress_image(file: &web_sys::File) -> Option<String> {
+    let response = Request::post("/api/templates/images/compress")
+        .header("Content-Type", &file.type_())
+        .body(JsValue::from(file.clone()))
+        .ok()?
+        .send()
+        .await
+        .ok()?;
+    if !response.ok() {
+        return None;
+    }
+    response
+        .text()
+        .await
+        .ok()
+        .filter(|base64| !base64.is_empty())
+}
+
+/// Resets the PDF dialog state before fetching a new PDF: the previous one and any error
+/// are cleared and the loading indicator is shown.
+fn start_pdf_loading(component: &mut StaticTextComponent, preview: bool) {
+    component.pdf_url = None;
+    component.pdf_object_url = None;
+    component.pdf_is_preview = preview;
+    component.pdf_error = None;
+    component.pdf_loading = true;
+}
+
+/// Reads the response of a PDF endpoint.
+///
+/// A successful response must be `application/pdf`; anything else is an error. The
+/// backend describes errors with a JSON `PdfErrorBody`, turned into a Spanish message by
+/// `pdf_error_message`; other bodies are shown with their status.
+///
+/// # Returns
+/// The PDF bytes, or the message to show in the PDF dialog.
+async fn read_pdf_response(
+    result: Result<gloo_net::http::Response, gloo_net::Error>,
+) -> Result<Vec<u8>, String> {
+    let response = result.map_err(|e| format!("No se pudo contactar con el servidor: {}", e))?;
+    let is_pdf = response
+        .headers()
+        .get("Content-Type")
+        .is_some_and(|t| t.starts_with("application/pdf"));
+    if response.ok() && is_pdf {
+        return response.binary().await.map_err(|e| e.to_string());
+    }
+    let status = response.status();
+    let text = response.text().await.unwrap_or_default();
+    Err(match serde_json::from_str::<PdfErrorBody>(&text) {
+        Ok(body) => pdf_error_message(status, &body),
+        Err(_) => format!("Error {} al generar el PDF: {}", status, text),
+    })
+}
+
+/// Replaces the editor text with `text` as one undoable edit. Every change to the text
+/// other than typing (`set_typed_text`), undo, redo and loading a template goes through
+/// here.
+///
+/// The new text is pushed onto the undo history (dropping any redo entries), copied into
+/// the template, and re-checked for long lines; the dirty flag is updated and the autosave
+/// scheduled.
+///
+/// # Returns
+/// `false` if `text` is the current text, in which case nothing happens.
+fn set_text(
+    component: &mut StaticTextComponent,
+    ctx: &Context<StaticTextComponent>,
+    text: String,
+) -> bool {
+    apply_text(component, ctx, text, false)
+}
+
+/// Like `set_text`, for text typed into the textarea: keystrokes less than
+/// `HISTORY_COALESCE_MS` apart replace the newest history entry instead of adding one, so
+/// a burst of typing is undone in a single step.
+fn set_typed_text(
+    component: &mut StaticTextComponent,
+    ctx: &Context<StaticTextComponent>,
+    text: String,
+) -> bool {
+    apply_text(component, ctx, text, true)
+}
+
+/// Shared body of `set_text` and `set_typed_text`.
+fn apply_text(
+    component: &mut StaticTextComponent,
+    ctx: &Context<StaticTextComponent>,
+    text: String,
+    typed: bool,
+) -> bool {
+    if !record_text(component, text, typed, Date::now()) {
+        return false;
+    }
+    set_window_dirty_flag(component);
+    schedule_autosave(component, ctx);
+    true
+}
+
+/// Pushes `text` onto the undo history and makes it the editor text, for `apply_text`.
+/// `now` is the time of the edit, in milliseconds, used to coalesce typed text.
+///
+/// # Returns
+/// `false` if `text` is the current text, in which case nothing happens.
+fn record_text(component: &mut StaticTextComponent, text: String, typed: bool, now: f64) -> bool {
+    if text == component.text {
+        return false;
+    }
+    // Never coalesce into the first entry, the text the editor was loaded with.
+    let coalesce = typed
+        && component.history_index > 0
+        && component.history_index + 1 == component.history.len()
+        && component
+            .last_typed_at
+            .is_some_and(|at| now - at < HISTORY_COALESCE_MS);
+    component.history.truncate(component.history_index + 1);
+    if coalesce {
+        component.history[component.history_index] = text.clone();
+    } else {
+        component.history.push(text.clone());
+        if component.history.len() > MAX_HISTORY_ENTRIES {
+            let excess = component.history.len() - MAX_HISTORY_ENTRIES;
+            component.history.drain(..excess);
+        }
+        component.history_index = component.history.len() - 1;
+    }
+    component.last_typed_at = typed.then_some(now);
+    component.text = text;
+    component.long_line = find_long_line(&component.text);
+    if let Some(template) = &mut component.template {
+        template.text = component.text.clone();
+    }
+    true
+}
+
+/// Makes the history entry at `index` the editor text, for undo and redo, and restores
+/// the images whose tags come back (`restore_removed_images`).
+fn go_to_history_entry(component: &mut StaticTextComponent, index: usize) {
+    component.history_index = index;
+    component.text = component.history[index].clone();
+    component.long_line = find_long_line(&component.text);
+    component.last_typed_at = None;
+    restore_removed_images(component);
+}
+
+/// Inserts `insertion` into `text` at the UTF-16 cursor position `utf16_pos`, moved back to
+/// a char boundary if it falls inside a character.
+///
+/// # Returns
+/// The new text and the UTF-16 position just after the insertion, where the cursor goes.
+fn insert_at_utf16(text: &str, utf16_pos: usize, insertion: &str) -> (String, u32) {
+    let byte_pos = snap_to_char_boundary(text, utf16_to_byte_idx(text, utf16_pos));
+    let mut text = text.to_string();
+    text.insert_str(byte_pos, insertion);
+    let new_utf16_pos = byte_to_utf16_idx(&text, byte_pos + insertion.len());
+    (text, new_utf16_pos)
+}
+
+/// Keeps images dropped from the template in `removed_images`, so undo can bring them
+/// back. An image already kept under the same id is replaced.
+fn stash_removed_images(removed_images: &mut Vec<Image>, removed: Vec<Image>) {
+    for image in removed {
+        removed_images.retain(|img| img.id != image.id);
+        removed_images.push(image);
+    }
+}
+
+/// Moves every image of `removed_images` whose tag is in the text again back into the
+/// template, after an undo or redo brought the tag back.
+fn restore_removed_images(component: &mut StaticTextComponent) {
+    let (restored, removed): (Vec<Image>, Vec<Image>) =
+        std::mem::take(&mut component.removed_images)
+            .into_iter()
+            .partition(|img| image_tag_regex(&img.id).is_match(&component.text));
+    component.removed_images = removed;
+    if restored.is_empty() {
+        return;
+    }
+    if let Some(template) = &mut component.template {
+        template
+            .images
+            .get_or_insert_with(Vec::new)
+            .extend(restored);
+    }
+}
+
+/// Rewrites every tag of the image `id` with `options` (`build_image_tag`) as an undoable
+/// edit (`set_text`); nothing happens if the text does not change.
+fn rewrite_image_tags(
+    component: &mut StaticTextComponent,
+    ctx: &Context<StaticTextComponent>,
+    id: &str,
+    options: &ImageTagOptions,
+) {
+    let tag = build_image_tag(id, options);
+    let text = image_tag_regex(id)
+        .replace_all(&component.text, regex::NoExpand(&tag))
+        .into_owned();
+    set_text(component, ctx, text);
+}
+
+/// Restarts the autosave countdown after an edit: the previous timer is dropped (which
+/// cancels it) and, if autosave is on, a new one sends `Msg::AutoSave` after
+/// `AUTOSAVE_DELAY_MS`.
+fn schedule_autosave(component: &mut StaticTextComponent, ctx: &Context<StaticTextComponent>) {
+    component.autosave_timer = None;
+    if !component.autosave {
+        return;
+    }
+    let link = ctx.link().clone();
+    component.autosave_timer = Some(Timeout::new(AUTOSAVE_DELAY_MS, move || {
+        link.send_message(Msg::AutoSave)
+    }));
+}
+
+/// Returns `true` if the text differs from the last saved or loaded version. Without a
+/// saved baseline, any text counts as unsaved.
+fn has_unsaved_changes(component: &StaticTextComponent) -> bool {
+    component
+        .original_md5
+        .as_ref()
+        .map_or(!component.text.is_empty(), |orig| {
+            orig != &compute_md5(&component.text)
+        })
+}
+/// Returns the size in bytes of the JSON the editor would send to save the template now.
+fn template_json_len(component: &StaticTextComponent) -> usize {
+    match &component.template {
+        Some(template) => {
+            let mut template = template.clone();
+            template.text = component.text.clone();
+            serde_json::to_string(&template).map_or(0, |json| json.len())
+        }
+        None => component.text.len(),
+    }
+}
+
+/// Explains that the template would exceed `MAX_TEMPLATE_JSON_BYTES`.
+fn show_payload_too_large_toast() {
+    show_toast(&format!(
+        "La plantilla superaría el tamaño máximo que acepta el servidor ({}). Reduce o quita imágenes.",
+        format_megabytes(MAX_TEMPLATE_JSON_BYTES as f64)
+    ));
+}
+
+/// Returns the number of images currently attached to the template.
+fn image_count(component: &StaticTextComponent) -> usize {
+    component
+        .template
+        .as_ref()
+        .and_then(|t| t.images.as_ref())
+        .map_or(0, Vec::len)
+}
+
+/// Tells the user that the template already has the maximum number of images.
+fn show_max_images_toast() {
+    show_toast(&format!(
+        "La plantilla ya tiene el máximo de {} imágenes.",
+        MAX_IMAGES
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::model::csv::ColumnCheck;
+    use common::model::place_holder::PlaceholderType;
+
+    #[test]
+    fn undo_removes_an_inserted_placeholder() {
+        let original = "Hola 😀 mundo".to_string();
+        let mut component = StaticTextComponent::new();
+        component.text = original.clone();
+        component.history = vec![original.clone()];
+        let col = ColumnCheck {
+            title: "Nombre".to_string(),
+            placeholder_type: PlaceholderType::Text,
+            first_row: Some("Ana".to_string()),
+            index: 0,
+            allow_empty: None,
+            true_label: None,
+            false_label: None,
+            number_display: None,
+        };
+        let tag = build_placeholder_tag(&col);
+
+        // The cursor is after the emoji and its space: 5 + 2 + 1 UTF-16 units.
+        let (text, cursor) = insert_at_utf16(&component.text, 8, &tag);
+        assert_eq!(text, format!("Hola 😀 {}mundo", tag));
+        assert_eq!(cursor, 8 + tag.encode_utf16().count() as u32);
+        assert!(record_text(&mut component, text.clone(), false, 0.0));
+        assert_eq!(component.history, [original.clone(), text.clone()]);
+        assert_eq!(component.history_index, 1);
+
+        go_to_history_entry(&mut component, 0);
+        assert_eq!(component.text, original);
+        assert_eq!(component.history_index, 0);
+
+        go_to_history_entry(&mut component, 1);
+        assert_eq!(component.text, text);
+    }
+}
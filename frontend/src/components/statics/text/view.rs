@@ -13,13 +13,21 @@
 //! - An `build_editor_tab` that renders the `<textarea>` and handles complex
 //!   events like input, selection changes, and key presses for protected text.
 //! - A `build_preview_tab` that renders the HTML generated from the markdown text.
+//! - A `build_status_line` under the active pane with the word and character counts.
 //!
 //! ## Message Dispatching
 //! The view functions dispatch the following messages to the update loop:
 //!
 //! - **`Msg::SetTab(String)`**: Dispatched from `build_tab_bar` when the user clicks the "Editor"
-//!   or "Preview" button. It changes the `active_tab` state to switch between the editing
-//!   and previewing interfaces.
+//!   or "Preview" button, or from `onkeydown` when the user presses `Ctrl+Shift+P` (in the
+//!   editor or the focused preview). It changes the `active_tab` state to switch between the
+//!   editing and previewing interfaces. `Ctrl+Shift+P` is used rather than `Ctrl+P` so the
+//!   browser's print shortcut keeps working.
+//!
+//! - **`Msg::ToggleSplitView`**: Dispatched from the "Dividir" button in `build_toolbar`. In
+//!   split mode the editor and the preview are shown side by side. The preview is computed
+//!   from `component.text` on every render, and every keystroke dispatches `UpdateText`
+//!   (which re-renders), so the preview updates live while typing.
 //!
 //! - **`Msg::UpdateText(String)`**: Dispatched from the `oninput` event of the `<textarea>` in
 //!   `build_editor_tab`. It sends the entire current content of the textarea to the update
@@ -31,8 +39,9 @@
 //!   component state, changing the current `text` to a previous or subsequent version.
 //!
 //! - **`Msg::ApplyStyle(String, ())`**: Dispatched from `build_toolbar` style buttons (e.g.,
-//!   "Bold", "Italic"). The update function inserts the corresponding markdown-like syntax
-//!   (e.g., `**text**`) at the current cursor position or around the selected text.
+//!   "Bold", "Italic"), or from `onkeydown` for `Ctrl+B` (bold) and `Ctrl+I` (italic). The
+//!   update function inserts the corresponding markdown-like syntax (e.g., `**text**`) at
+//!   the current cursor position or around the selected text.
 //!
 //! - **`Msg::AutoResize`**: Dispatched from `oninput`, `onscroll`, and `onselect` events in
 //!   `build_editor_tab`. This message triggers a recalculation of the textarea's height to
@@ -43,6 +52,10 @@
 //!   programmatically clicks a hidden `<input type="file">` element, allowing the user to
 //!   select an image for upload without exposing the raw file input UI.
 //!
+//! - **`Msg::FileSelected(File)`**: Dispatched from the `ondrop` event of the `<textarea>`
+//!   when an image file is dropped on it (after the size and type checks of
+//!   `accept_image_file`), so it is embedded like one picked through the image dialog.
+//!
 //! - **`Msg::OpenImageDialogWithId(String)`**: Dispatched from the `onselect` event in
 //!   `build_editor_tab` when the user's cursor enters an `[img:...]` tag. The update
 //!   function uses the provided ID to set `selected_image_id` and open a dialog
@@ -61,22 +74,33 @@
 //!   uses this to prune any `[ph:...]` placeholders from the text whose titles are no
 //!   longer present in the new set of columns.
 //!
+//! - **`Msg::ToggleDiff`**: Dispatched from the "Ver cambios" button in `build_toolbar`. It
+//!   shows or hides the panel rendered by `build_diff_panel`, which highlights the characters
+//!   added and removed since the template was last loaded or saved.
+//!
+//! - **`Msg::ToggleLineWrap`**: Dispatched from the button in `build_long_line_warning`,
+//!   which `build_editor_tab` shows above the textarea while `long_line` is set (a line
+//!   longer than `LONG_LINE_THRESHOLD` bytes, typically pasted Base64). Turning wrapping
+//!   off renders the textarea with `wrap="off"` and a horizontal scrollbar, so the long
+//!   line stays on one row and the gutter stays aligned. The text is never modified.
+//!
 //! - **`Msg::OpenPdf`**: Dispatched from the "PDF" button in `build_toolbar`. It signals the
 //!   update function to check for unsaved changes, then construct a URL to the PDF
-//!   generation endpoint and open the PDF viewer dialog with a loading indicator.
+//!   generation endpoint and open the PDF viewer dialog with a loading indicator. With
+//!   unsaved changes, `unsaved_pdf_dialog` asks whether to save first or preview as-is.
 
-use super::helpers::{compute_md5, escape_html, get_img_tag_id_at_cursor};
+use super::helpers::{
+    compute_md5, diff_spans, diff_summary, extract_placeholder_titles, get_img_tag_id_at_cursor,
+    text_stats, utf16_to_byte_idx, DiffSpan, LONG_LINE_THRESHOLD,
+};
 use super::messages::Msg;
 use super::state::StaticTextComponent;
 use crate::components::data_sources::csv::CsvDataSourceComponent;
-use crate::components::statics::text::dialogs::image::image_dialog;
-use base64::engine::general_purpose;
-use base64::Engine;
-use common::model::csv::ColumnCheck;
-use pulldown_cmark::{html, Parser};
-use regex::Regex;
+use crate::components::templates::picker::TemplatePickerComponent;
+use crate::components::statics::text::dialogs::image::{accept_image_file, image_dialog};
+use common::preview::render_preview_html;
 use wasm_bindgen::JsCast;
-use web_sys::{HtmlTextAreaElement, InputEvent};
+use web_sys::{HtmlInputElement, HtmlTextAreaElement, InputEvent};
 use yew::prelude::*;
 
 /// Renders the main view for the static text editor component.
@@ -93,42 +117,91 @@ pub fn view(component: &StaticTextComponent, ctx: &Context<StaticTextComponent>)
     html! {
         <div class="static-text-root">
             { build_toolbar(component, link) }
+            { build_name_field(component, link) }
             { build_tab_bar(component, link) }
+            { build_diff_panel(component) }
+            { build_vars_panel(component, link) }
 
             {
-                if component.active_tab == "editor" {
+                if component.split_view {
+                    html! {
+                        <div class="split-view" style="display: flex; gap: 16px; align-items: flex-start;">
+                            <div style="flex: 1; min-width: 0;">{ build_editor_tab(component, link) }</div>
+                            <div style="flex: 1; min-width: 0;">{ build_preview_tab(component, link, preview_html) }</div>
+                        </div>
+                    }
+                } else if component.active_tab == "editor" {
                     build_editor_tab(component, link)
                 } else {
-                    build_preview_tab(preview_html)
+                    build_preview_tab(component, link, preview_html)
                 }
             }
+            { build_status_line(component) }
+            { unsaved_pdf_dialog(component, link) }
         </div>
     }
 }
 
+/// Returns `true` if the key event is the preview toggle shortcut (`Ctrl+Shift+P`).
+fn is_toggle_preview_shortcut(e: &KeyboardEvent) -> bool {
+    e.ctrl_key() && e.shift_key() && e.key().eq_ignore_ascii_case("p")
+}
+
+/// Returns the tab that `Ctrl+Shift+P` switches to from the current one.
+fn other_tab(component: &StaticTextComponent) -> String {
+    if component.active_tab == "editor" {
+        "preview".to_string()
+    } else {
+        "editor".to_string()
+    }
+}
+
 /// Builds the left-hand toolbar containing action buttons.
 ///
 /// Each button is configured with an icon and a callback that dispatches a
 /// specific `Msg` to the update loop. This function is the primary source for
 /// user-initiated commands that are not direct text input.
 fn build_toolbar(component: &StaticTextComponent, link: &Scope<StaticTextComponent>) -> Html {
+    // Only send the referenced titles when there are some; an empty set would skip every column.
+    let referenced_columns =
+        Some(extract_placeholder_titles(&component.text)).filter(|t| !t.is_empty());
+
     html! {
         <div class="icon-toolbar">
+            <TemplatePickerComponent
+                current_id={component.template.as_ref().map(|t| t.id.clone())}
+                on_select={link.callback(Msg::OpenTemplate)}
+            />
             { icon_button("undo", "Deshacer", link.callback(|_| Msg::Undo), false) }
             { icon_button("redo", "Rehacer", link.callback(|_| Msg::Redo), false) }
             { icon_button("text_fields", "Normal", make_style_callback(link, "normal"), false) }
             { icon_button("format_bold", "Negrita", make_style_callback(link, "bold"), false) }
             { icon_button("format_italic", "Cursiva", make_style_callback(link, "italic"), false) }
             { icon_button("format_bold", "Negrita+Cursiva", make_style_callback(link, "bolditalic"), true) }
+            { icon_button("format_underlined", "Subrayado", make_style_callback(link, "underline"), false) }
+            { icon_button("format_strikethrough", "Tachado", make_style_callback(link, "strikethrough"), false) }
             { icon_button("format_list_bulleted", "Items", make_style_callback(link, "bulleted_list"), false) }
             { icon_button("image", "Imagen", link.callback(|_| Msg::OpenFileDialog), false) }
             { icon_button("picture_as_pdf", "PDF", link.callback(|_| Msg::OpenPdf), false) }
             { icon_button("save", "Guardar", link.callback(|_| Msg::Save), false) }
+            {
+                if component.autosave {
+                    icon_button("sync", "Autoguardado", link.callback(|_| Msg::ToggleAutosave), true)
+                } else {
+                    icon_button("sync_disabled", "Sin autoguardado", link.callback(|_| Msg::ToggleAutosave), true)
+                }
+            }
+            { icon_button("content_copy", "Duplicar", link.callback(|_| Msg::DuplicateTemplate), false) }
+            { icon_button("vertical_split", "Dividir", link.callback(|_| Msg::ToggleSplitView), false) }
+            { icon_button("difference", "Ver cambios", link.callback(|_| Msg::ToggleDiff), false) }
+            { icon_button("tune", "Variables", link.callback(|_| Msg::ToggleVarsPanel), false) }
+            { icon_button("auto_awesome", "Generar plantilla desde CSV", link.callback(|_| Msg::GenerateFromCsv), true) }
             <div>
                 <CsvDataSourceComponent
-                    template_id={component.template.as_ref().and_then(|t| Some(t.id.clone()))}
-                    on_column_selected={link.callback(|col_check| Msg::InsertCsvColumnPlaceholder(col_check))}
-                    on_csv_changed={link.callback(|cols: Vec<ColumnCheck>| Msg::CsvColumnsUpdated(cols))}
+                    template_id={component.template.as_ref().map(|t| t.id.clone())}
+                    on_column_selected={link.callback(Msg::InsertCsvColumnPlaceholder)}
+                    on_csv_changed={link.callback(Msg::CsvColumnsUpdated)}
+                    referenced_columns={referenced_columns}
                 />
             </div>
         </div>
@@ -157,7 +230,7 @@ fn build_tab_bar(component: &StaticTextComponent, link: &Scope<StaticTextCompone
     let dirty = component
         .original_md5
         .as_ref()
-        .map_or(false, |orig| orig != &compute_md5(&component.text));
+        .is_some_and(|orig| orig != &compute_md5(&component.text));
 
     html! {
         <div class="tab-bar">
@@ -200,6 +273,133 @@ fn build_tab_bar(component: &StaticTextComponent, link: &Scope<StaticTextCompone
     }
 }
 
+/// Builds the template variables panel shown when `show_vars_panel` is set.
+///
+/// Each variable is a row with name and value inputs, a button to insert its `[var:NAME]`
+/// tag at the cursor, and a delete button. Edits dispatch `UpdateVarName`/`UpdateVarValue`,
+/// so the preview reflects new values immediately.
+/// Renders the editable template name above the tab bar.
+///
+/// Typing updates `template.name` through `Msg::UpdateName`; the name is stored with the
+/// rest of the template by `Msg::Save`, which pressing Enter in the field also dispatches.
+fn build_name_field(component: &StaticTextComponent, link: &Scope<StaticTextComponent>) -> Html {
+    let name = component
+        .template
+        .as_ref()
+        .and_then(|t| t.name.clone())
+        .unwrap_or_default();
+    html! {
+        <input
+            type="text"
+            class="template-name"
+            placeholder="Nombre de la plantilla"
+            value={name}
+            oninput={link.callback(|e: InputEvent| {
+                Msg::UpdateName(e.target_unchecked_into::<HtmlInputElement>().value())
+            })}
+            onkeydown={link.batch_callback(|e: KeyboardEvent| {
+                (e.key() == "Enter").then_some(Msg::Save)
+            })}
+        />
+    }
+}
+
+fn build_vars_panel(component: &StaticTextComponent, link: &Scope<StaticTextComponent>) -> Html {
+    if !component.show_vars_panel {
+        return html! {};
+    }
+    let vars = component
+        .template
+        .as_ref()
+        .and_then(|t| t.vars.clone())
+        .unwrap_or_default();
+
+    html! {
+        <div class="vars-panel" style="border: 1px solid #ddd; padding: 8px; margin-bottom: 8px;">
+            <strong>{"Variables de la plantilla"}</strong>
+            <p class="muted">{"Usa [var:NOMBRE] en el texto; se reemplaza por el valor en la previsualización y el PDF."}</p>
+            { for vars.into_iter().enumerate().map(|(idx, var)| {
+                let name = var.name.clone();
+                html! {
+                    <div style="display: flex; gap: 8px; margin-bottom: 4px;">
+                        <input
+                            type="text"
+                            placeholder="Nombre"
+                            value={var.name}
+                            onchange={link.callback(move |e: Event| {
+                                Msg::UpdateVarName(idx, e.target_unchecked_into::<HtmlInputElement>().value())
+                            })}
+                        />
+                        <input
+                            type="text"
+                            placeholder="Valor"
+                            style="flex: 1;"
+                            value={var.value}
+                            oninput={link.callback(move |e: InputEvent| {
+                                Msg::UpdateVarValue(idx, e.target_unchecked_into::<HtmlInputElement>().value())
+                            })}
+                        />
+                        <button
+                            title="Insertar en el texto"
+                            onclick={link.callback(move |_| Msg::InsertVar(name.clone()))}
+                        >
+                            {"Insertar"}
+                        </button>
+                        <button title="Eliminar" onclick={link.callback(move |_| Msg::RemoveVar(idx))}>
+                            {"✕"}
+                        </button>
+                    </div>
+                }
+            }) }
+            <button onclick={link.callback(|_| Msg::AddVar)}>{"Añadir variable"}</button>
+        </div>
+    }
+}
+
+/// Builds the "Ver cambios" panel shown when `show_diff` is set.
+///
+/// It renders a character-level diff of the current text against `original_text`, with
+/// insertions highlighted in green and deletions struck through in red, preceded by a
+/// short summary of the counts taken from the same spans. Nothing is rendered while the panel is hidden.
+fn build_diff_panel(component: &StaticTextComponent) -> Html {
+    if !component.show_diff {
+        return html! {};
+    }
+    let original = component.original_text.as_deref().unwrap_or("");
+    let spans = diff_spans(original, &component.text);
+    let summary = diff_summary(&spans);
+
+    let body = if summary.inserted == 0 && summary.deleted == 0 {
+        html! { <p class="muted">{"No hay cambios desde el último guardado."}</p> }
+    } else {
+        html! {
+            <pre style="white-space: pre-wrap; font-family: inherit; margin: 0;">
+                { for spans.into_iter().map(|span| match span {
+                    DiffSpan::Equal(s) => html! { <span>{ s }</span> },
+                    DiffSpan::Insert(s) => html! {
+                        <ins style="background: #e6ffed; text-decoration: none;">{ s }</ins>
+                    },
+                    DiffSpan::Delete(s) => html! {
+                        <del style="background: #ffeef0; color: #b31d28;">{ s }</del>
+                    },
+                }) }
+            </pre>
+        }
+    };
+
+    html! {
+        <div class="diff-panel" style="border: 1px solid #ddd; padding: 8px; margin-bottom: 8px;">
+            <strong>
+                { format!(
+                    "Cambios desde el último guardado: +{} / -{} caracteres",
+                    summary.inserted, summary.deleted
+                ) }
+            </strong>
+            { body }
+        </div>
+    }
+}
+
 /// Builds the editor tab, which includes the line numbers, the main `<textarea>`,
 /// and the associated dialogs for images and PDF previews.
 ///
@@ -207,19 +407,30 @@ fn build_tab_bar(component: &StaticTextComponent, link: &Scope<StaticTextCompone
 /// - `oninput`: Dispatches `Msg::UpdateText` to sync the state with user input and
 ///   `Msg::AutoResize` to adjust the textarea's height.
 /// - `onscroll`: Dispatches `Msg::AutoResize` to ensure line numbers stay aligned.
-/// - `onkeydown`: Intercepts key presses to implement undo/redo shortcuts (`Ctrl+Z`/`Ctrl+Y`)
-///   and to protect special text spans (like `[img:...]` and `[ph:...]`) from being
+/// - `onkeydown`: Intercepts key presses to implement undo/redo shortcuts (`Ctrl+Z`/`Ctrl+Y`),
+///   bold/italic shortcuts (`Ctrl+B`/`Ctrl+I`, which dispatch `Msg::ApplyStyle`),
+///   the preview toggle (`Ctrl+Shift+P`), and to protect special text spans (like `[img:...]` and `[ph:...]`) from being
 ///   edited or deleted improperly.
 /// - `onselect`: Detects if the cursor moves inside an `[img:...]` tag and dispatches
 ///   `Msg::OpenImageDialogWithId` to show the relevant image management dialog.
+/// - `ondragover` / `ondrop`: Accept an image file dropped on the textarea. It goes through
+///   the same checks as the image dialog (`accept_image_file`) and is inserted at the
+///   cursor by `Msg::FileSelected`. The browser's default of opening the file is prevented.
 fn build_editor_tab(component: &StaticTextComponent, link: &Scope<StaticTextComponent>) -> Html {
     let line_count = component.text.lines().count().max(1);
     let line_numbers = (1..=line_count)
         .map(|n| html! { <div class="line-number">{n}</div> })
         .collect::<Html>();
+    let next_tab = other_tab(component);
+    let (wrap, textarea_style) = if component.wrap_lines {
+        ("soft", "width: 100%; min-height: 40px; resize: none; overflow: hidden;")
+    } else {
+        ("off", "width: 100%; min-height: 40px; resize: none; overflow-x: auto; overflow-y: hidden;")
+    };
 
     html! {
         <>
+            { build_long_line_warning(component, link) }
             <div style="display: flex; align-items: flex-start;">
                 <div
                     class="line-numbers"
@@ -237,7 +448,12 @@ fn build_editor_tab(component: &StaticTextComponent, link: &Scope<StaticTextComp
                         vec![ Msg::UpdateText(value), Msg::AutoResize ]
                     })}
                     onscroll={link.callback(|_: Event| Msg::AutoResize)}
-                    onkeydown={link.batch_callback(|e: KeyboardEvent| {
+                    onkeydown={link.batch_callback(move |e: KeyboardEvent| {
+                        if is_toggle_preview_shortcut(&e) {
+                            e.prevent_default();
+                            return vec![Msg::SetTab(next_tab.clone())];
+                        }
+
                         let textarea = e.target_unchecked_into::<HtmlTextAreaElement>();
                         let text = textarea.value();
                         let cursor_pos = textarea.selection_start().unwrap_or(Some(0)).unwrap_or(0) as usize;
@@ -265,10 +481,24 @@ fn build_editor_tab(component: &StaticTextComponent, link: &Scope<StaticTextComp
                             vec![Msg::Undo]
                         } else if e.ctrl_key() && e.key() == "y" {
                             vec![Msg::Redo]
+                        } else if e.ctrl_key() && (e.key() == "b" || e.key() == "i") {
+                            // Keep the browser from handling the shortcut (e.g. bookmarks).
+                            e.prevent_default();
+                            let style = if e.key() == "b" { "bold" } else { "italic" };
+                            vec![Msg::ApplyStyle(style.to_string(), ())]
                         } else {
                             vec![]
                         }
                     })}
+                    ondragover={|e: DragEvent| e.prevent_default()}
+                    ondrop={link.callback(|e: DragEvent| {
+                        // Without this the browser navigates away to the dropped file.
+                        e.prevent_default();
+                        match e.data_transfer().and_then(|dt| dt.files()).and_then(|files| files.get(0)) {
+                            Some(file) => accept_image_file(file),
+                            None => Msg::AutoResize,
+                        }
+                    })}
                     onselect={link.callback(|e: Event| {
                         let id_opt = e.target()
                             .and_then(|t| t.dyn_into::<HtmlTextAreaElement>().ok())
@@ -283,7 +513,8 @@ fn build_editor_tab(component: &StaticTextComponent, link: &Scope<StaticTextComp
                         }
                     })}
                     rows={1}
-                    style="width: 100%; min-height: 40px; resize: none; overflow: hidden;"
+                    {wrap}
+                    style={textarea_style}
                 />
             </div>
             { image_dialog(component, link) }
@@ -292,15 +523,84 @@ fn build_editor_tab(component: &StaticTextComponent, link: &Scope<StaticTextComp
     }
 }
 
+/// Builds the warning shown above the textarea while the text has a line longer than
+/// `LONG_LINE_THRESHOLD` bytes.
+///
+/// It names the offending line and offers to turn soft wrapping off (or back on) through
+/// `Msg::ToggleLineWrap`. Returns an empty node when there is no long line.
+fn build_long_line_warning(
+    component: &StaticTextComponent,
+    link: &Scope<StaticTextComponent>,
+) -> Html {
+    let Some(long_line) = component.long_line else {
+        return html! {};
+    };
+    let toggle_label = if component.wrap_lines {
+        "No ajustar líneas"
+    } else {
+        "Ajustar líneas"
+    };
+
+    html! {
+        <div
+            class="long-line-warning"
+            title={format!("Umbral: {} caracteres", LONG_LINE_THRESHOLD)}
+        >
+            <i class="material-icons">{"warning"}</i>
+            <span>
+                { format!(
+                    "Línea {} muy larga ({} caracteres), el rendimiento puede degradarse.",
+                    long_line.number, long_line.len
+                ) }
+            </span>
+            <button onclick={link.callback(|_| Msg::ToggleLineWrap)}>{ toggle_label }</button>
+        </div>
+    }
+}
+
+/// Builds the status line under the editor with the word and character counts of the
+/// text (see `text_stats`). It is recomputed on every render, so it follows each
+/// `Msg::UpdateText` while typing.
+fn build_status_line(component: &StaticTextComponent) -> Html {
+    let stats = text_stats(&component.text);
+    let words = if stats.words == 1 { "palabra" } else { "palabras" };
+    let chars = if stats.chars == 1 { "carácter" } else { "caracteres" };
+    html! {
+        <div class="editor-status-line">
+            { format!("{} {} · {} {}", stats.words, words, stats.chars, chars) }
+        </div>
+    }
+}
+
 /// Builds the preview tab's HTML container.
 ///
 /// This function is straightforward: it takes the pre-rendered HTML string
 /// (computed by `compute_preview_html`) and injects it into a `div` using
 /// `Html::from_html_unchecked`. This is safe because the pipeline in
 /// `compute_preview_html` ensures all user-provided content is properly escaped.
-fn build_preview_tab(preview_html: AttrValue) -> Html {
+///
+/// The container is focusable so `Ctrl+Shift+P` can switch back to the editor.
+fn build_preview_tab(
+    component: &StaticTextComponent,
+    link: &Scope<StaticTextComponent>,
+    preview_html: AttrValue,
+) -> Html {
+    let next_tab = other_tab(component);
     html! {
-        <div class="markdown-preview">{ Html::from_html_unchecked(preview_html) }</div>
+        <div
+            class="markdown-preview"
+            tabindex="0"
+            onkeydown={link.batch_callback(move |e: KeyboardEvent| {
+                if is_toggle_preview_shortcut(&e) {
+                    e.prevent_default();
+                    vec![Msg::SetTab(next_tab.clone())]
+                } else {
+                    vec![]
+                }
+            })}
+        >
+            { Html::from_html_unchecked(preview_html) }
+        </div>
     }
 }
 
@@ -322,189 +622,69 @@ fn icon_button(icon_name: &str, label: &str, on_click: Callback<MouseEvent>, wid
 /// that contains the given `cursor_pos` (in UTF-16 units).
 ///
 /// This is a helper used in the `onkeydown` handler to determine if the cursor
-/// is inside a protected placeholder, preventing accidental edits.
+/// is inside a protected placeholder, preventing accidental edits. The returned bounds
+/// are byte indices. A cursor right before the opening `[` is outside the placeholder, so
+/// text can still be typed in front of it.
 fn get_ph_bounds_at_cursor(text: &str, cursor_pos: usize) -> Option<(usize, usize)> {
-    let pos = cursor_pos.min(text.len());
-    if let Some(start) = text[..pos].rfind("[ph:") {
-        if let Some(rel_end) = text[start..].find(']') {
-            let end = start + rel_end + 1;
-            if pos >= start && pos < end {
-                return Some((start, end));
-            }
+    let pos = utf16_to_byte_idx(text, cursor_pos);
+    let mut from = 0;
+    while let Some(rel_start) = text[from..].find("[ph:") {
+        let start = from + rel_start;
+        if start >= pos {
+            break;
+        }
+        let end = start + text[start..].find(']')? + 1;
+        if pos < end {
+            return Some((start, end));
         }
+        from = end;
     }
     None
 }
 
-use crate::components::statics::text::dialogs::pdf::pdf_dialog;
-use uuid::Uuid;
+use crate::components::statics::text::dialogs::pdf::{pdf_dialog, unsaved_pdf_dialog};
 use yew::html::Scope;
 use yew::virtual_dom::AttrValue;
 
-/// Normalizes line endings (CRLF/CR to LF) and removes leading zero-width characters.
-/// This ensures consistent text processing across different platforms and editors.
-fn normalize_text(input: &str) -> String {
-    input
-        .replace("\r\n", "\n")
-        .replace('\r', "\n")
-        .trim_start_matches(|c: char| c == '\u{feff}' || c == '\u{200b}')
-        .to_string()
-}
-
-/// Inserts a space before each single newline.
-/// This is a trick to force the `pulldown_cmark` parser to treat single newlines
-/// as significant whitespace (like a `<br>`), which is often desired in this
-/// type of editor, rather than collapsing them.
-fn preserve_single_newline_trick(input: &str) -> String {
-    input.replace("\n", " \n")
-}
-
-/// Finds all `[ph:TITLE:BASE64]` placeholders, replaces them with unique temporary
-/// tokens, and returns the modified text along with a list of token-to-HTML mappings.
-///
-/// This is a key step in the preview pipeline. It extracts placeholders before
-/// markdown parsing to prevent them from being misinterpreted. The Base64 content
-/// is decoded and escaped to create a safe HTML `<span>` for later re-insertion.
-fn replace_ph_placeholders(input: &str) -> (String, Vec<(String, String)>) {
-    let ph_re = Regex::new(r"\[ph:([^:\]]+):([A-Za-z0-9+/=]+)]").unwrap();
-    let mut replacements: Vec<(String, String)> = Vec::new();
-
-    let text_with_tokens = ph_re
-        .replace_all(input, |caps: &regex::Captures| {
-            let title = caps.get(1).map(|m| m.as_str()).unwrap_or("");
-            let b64 = caps.get(2).map(|m| m.as_str()).unwrap_or("");
-
-            let replacement_html = match general_purpose::STANDARD.decode(b64) {
-                Ok(bytes) => match String::from_utf8(bytes) {
-                    Ok(decoded) => {
-                        let unquoted = match serde_json::from_str::<serde_json::Value>(&decoded) {
-                            Ok(serde_json::Value::String(s)) => s,
-                            _ => decoded,
-                        };
-                        let title_esc = escape_html(title);
-                        let decoded_esc = escape_html(&unquoted);
-                        format!(r#"<span title="{}">{}</span>"#, title_esc, decoded_esc)
-                    }
-                    Err(_) => r#"<span>[invalid utf8]</span>"#.to_string(),
-                },
-                Err(_) => r#"<span>[invalid base64]</span>"#.to_string(),
-            };
-
-            let uuid = Uuid::new_v4().simple().to_string();
-            let token = format!("PH{}", uuid);
-            replacements.push((token.clone(), replacement_html));
-            token
-        })
-        .into_owned();
-
-    (text_with_tokens, replacements)
-}
-
-/// Parses a markdown string into an HTML string using `pulldown_cmark`.
-fn parse_markdown_to_html(input: &str) -> String {
-    let parser = Parser::new(input);
-    let mut html_output = String::new();
-    html::push_html(&mut html_output, parser);
-    html_output
-}
-
-/// Replaces `BR_MARKER{N}` placeholders with `N` repeated `<br>` tags.
-/// This function re-inflates the compressed newline sequences after markdown parsing.
-fn expand_br_markers(input: &str) -> String {
-    let re_br = Regex::new(r"BR_MARKER(\d+)").unwrap();
-    re_br
-        .replace_all(input, |caps: &regex::Captures| {
-            let n = caps[1].parse::<usize>().unwrap_or(1);
-            "<br>".repeat(n)
-        })
-        .into_owned()
-}
-
-/// Re-inserts the HTML for placeholders by replacing the temporary tokens.
-/// This step happens after markdown parsing to ensure the placeholder HTML is
-/// rendered verbatim and not processed as markdown.
-fn replace_tokens_with_html(mut html: String, replacements: &[(String, String)]) -> String {
-    for (token, snippet) in replacements {
-        html = html.replace(token, snippet);
-    }
-    html
+/// Computes the HTML shown in the preview pane with `render_preview_html`, from the
+/// component's text and the variables and images of its template. The text is parsed by
+/// `common::template_ast`, like for the PDF, DOCX and HTML exports, so the preview shows
+/// what they contain.
+pub fn compute_preview_html(component: &StaticTextComponent) -> AttrValue {
+    let template = component.template.as_ref();
+    let vars = template.and_then(|t| t.vars.as_deref()).unwrap_or_default();
+    let images = template.and_then(|t| t.images.as_deref()).unwrap_or_default();
+    AttrValue::from(render_preview_html(&component.text, vars, images))
 }
 
-/// Finds `[img:<id>]` tags in the final HTML and replaces them with `<img>` elements.
-///
-/// It looks up each image ID in the `component.template.images` vector to find the
-/// corresponding Base64 data, constructing a data URL for the `src` attribute.
-fn resolve_inline_images(mut html: String, component: &StaticTextComponent) -> String {
-    if let Some(template) = &component.template {
-        if let Some(images) = &template.images {
-            for image in images {
-                let img_tag = format!("[img:{}]", image.id);
-                let img_html = format!(
-                    r#"<img src="data:image/*;base64,{}" style="max-width:200px;max-height:200px;vertical-align:middle;" />"#,
-                    image.base64
-                );
-                html = html.replace(&img_tag, &img_html);
-            }
-        }
-    }
-    html
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-/// Compresses sequences of multiple empty lines into a single `BR_MARKER{N}` token.
-///
-/// This allows the editor to preserve intentional vertical spacing (e.g., multiple
-/// blank lines) which would otherwise be collapsed by the markdown parser. The
-/// markers are expanded back into `<br>` tags later in the pipeline.
-fn compress_newlines_after_any_line(input: &str) -> String {
-    let lines: Vec<&str> = input.lines().collect();
-    let mut result = String::new();
-    let mut i = 0;
-    while i < lines.len() {
-        result.push_str(lines[i]);
-        result.push('\n');
-        // Detect multiple empty lines after any line
-        let mut count = 0;
-        let mut j = i + 1;
-        while j < lines.len() && lines[j].trim().is_empty() {
-            count += 1;
-            j += 1;
-        }
-        if count > 1 {
-            result.push_str(&format!("BR_MARKER{}\n", count));
-            i += count;
-            continue;
+    #[test]
+    fn placeholder_bounds_after_multibyte_text() {
+        let tag = "[ph:Nombre:QW5h]";
+        let text = format!("مرحبا 😀 漢字 {} fin", tag);
+        let start = text.find(tag).unwrap();
+        // The cursor is in UTF-16 units: 5 Arabic letters, a space, the emoji (2 units),
+        // a space, 2 CJK characters and a space.
+        let tag_start_utf16 = 5 + 1 + 2 + 1 + 2 + 1;
+        assert_eq!(
+            get_ph_bounds_at_cursor(&text, tag_start_utf16 + 3),
+            Some((start, start + tag.len()))
+        );
+        assert_eq!(
+            get_ph_bounds_at_cursor(&text, tag_start_utf16 + 1),
+            Some((start, start + tag.len()))
+        );
+        assert_eq!(get_ph_bounds_at_cursor(&text, tag_start_utf16), None);
+        assert_eq!(get_ph_bounds_at_cursor(&text, tag_start_utf16 - 1), None);
+        assert_eq!(
+            get_ph_bounds_at_cursor(&text, tag_start_utf16 + tag.len()),
+            None
+        );
+        for cursor in 0..text.encode_utf16().count() + 2 {
+            get_ph_bounds_at_cursor(&text, cursor);
         }
-        i += 1;
     }
-    result
-}
-
-/// Orchestrates the entire pipeline for generating the preview HTML.
-///
-/// This function executes a series of transformations on the raw text to produce
-/// a final, safe, and correctly formatted HTML string for the preview pane.
-/// The steps are carefully ordered to handle placeholders, newlines, markdown,
-/// and inline images correctly.
-///
-/// Pipeline:
-/// 1. `normalize_text`: Clean up line endings and invisible characters.
-/// 2. `compress_newlines_after_any_line`: Convert multiple blank lines to markers.
-/// 3. `preserve_single_newline_trick`: Ensure single newlines become `<br>`.
-/// 4. `replace_ph_placeholders`: Extract placeholders into tokens.
-/// 5. `parse_markdown_to_html`: Process the cleaned text with `pulldown_cmark`.
-/// 6. `expand_br_markers`: Convert newline markers back to `<br>` tags.
-/// 7. `replace_tokens_with_html`: Re-insert placeholder HTML.
-/// 8. `resolve_inline_images`: Convert `[img:...]` tags to `<img>` elements.
-pub fn compute_preview_html(component: &StaticTextComponent) -> AttrValue {
-    let text = normalize_text(&component.text);
-    let text = compress_newlines_after_any_line(&text);
-    let text = preserve_single_newline_trick(&text);
-    let (text, replacements) = replace_ph_placeholders(&text);
-
-    let parsed_html = parse_markdown_to_html(&text);
-    let expanded_html = expand_br_markers(&parsed_html);
-    let replaced_html = replace_tokens_with_html(expanded_html, &replacements);
-    let final_html = resolve_inline_images(replaced_html, component);
-
-    AttrValue::from(final_html)
 }
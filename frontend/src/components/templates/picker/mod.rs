@@ -0,0 +1,166 @@
+//! Template picker: lists the stored templates so one can be opened in the editor.
+//!
+//! Renders an "Abrir" toolbar button. Clicking it opens a modal and fetches
+//! `GET /api/templates`; the list is fetched every time the modal opens, so templates saved
+//! since the last time show up, most recently saved first. Each entry shows the template
+//! name (if any), the start of its text and its number of images, and choosing one emits
+//! `on_select` with the template id. The parent
+//! (the static text editor) does the actual loading.
+
+use crate::connection_monitor;
+use common::model::template::TemplateSummary;
+use gloo_net::http::Request;
+use wasm_bindgen_futures::spawn_local;
+use yew::{classes, html, Callback, Component, Context, Html, MouseEvent, Properties};
+
+/// Component that lists the stored templates and lets the user pick one.
+pub struct TemplatePickerComponent {
+    show_modal: bool,
+    /// The fetched list, or the error message. `None` while loading.
+    templates: Option<Result<Vec<TemplateSummary>, String>>,
+}
+
+#[derive(Properties, PartialEq)]
+pub struct TemplatePickerProps {
+    /// Id of the template open in the editor, highlighted in the list.
+    #[prop_or_default]
+    pub current_id: Option<String>,
+    /// Called with the id of the chosen template.
+    pub on_select: Callback<String>,
+}
+
+pub enum TemplatePickerMsg {
+    ToggleModal,
+    Loaded(Result<Vec<TemplateSummary>, String>),
+    Select(String),
+}
+
+impl Component for TemplatePickerComponent {
+    type Message = TemplatePickerMsg;
+    type Properties = TemplatePickerProps;
+
+    fn create(_ctx: &Context<Self>) -> Self {
+        TemplatePickerComponent {
+            show_modal: false,
+            templates: None,
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            TemplatePickerMsg::ToggleModal => {
+                self.show_modal = !self.show_modal;
+                if self.show_modal {
+                    self.templates = None;
+                    fetch_templates(ctx.link().clone());
+                }
+                true
+            }
+            TemplatePickerMsg::Loaded(result) => {
+                self.templates = Some(result);
+                true
+            }
+            TemplatePickerMsg::Select(id) => {
+                self.show_modal = false;
+                ctx.props().on_select.emit(id);
+                true
+            }
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let list = match &self.templates {
+            None => html! { <p class="muted">{"Cargando plantillas..."}</p> },
+            Some(Err(err)) => html! { <p class="error">{ err }</p> },
+            Some(Ok(templates)) if templates.is_empty() => {
+                html! { <p class="muted">{"No hay plantillas guardadas."}</p> }
+            }
+            Some(Ok(templates)) => html! {
+                <div class="column-list">
+                    { for templates.iter().map(|t| {
+                        let id = t.id.clone();
+                        let onclick = ctx.link().callback(move |_| TemplatePickerMsg::Select(id.clone()));
+                        let is_current = ctx.props().current_id.as_deref() == Some(t.id.as_str());
+                        let preview = match (&t.name, t.text_preview.is_empty()) {
+                            (Some(name), true) => name.clone(),
+                            (Some(name), false) => format!("{} — {}", name, t.text_preview),
+                            (None, true) => "(sin texto)".to_string(),
+                            (None, false) => t.text_preview.clone(),
+                        };
+                        let images = match t.image_count {
+                            0 => String::new(),
+                            1 => " · 1 imagen".to_string(),
+                            n => format!(" · {} imágenes", n),
+                        };
+                        html! {
+                            <button
+                                key={t.id.clone()}
+                                class={classes!("col-option", is_current.then_some("selected"))}
+                                {onclick}
+                                title={t.id.clone()}>
+                                { format!("{}{}", preview, images) }
+                            </button>
+                        }
+                    })}
+                </div>
+            },
+        };
+
+        html! {
+            <>
+            <button
+                class="icon-btn"
+                onclick={ctx.link().callback(|_| TemplatePickerMsg::ToggleModal)}>
+                <i class="material-icons">{"folder_open"}</i>
+                <span class="icon-label">{"Abrir"}</span>
+            </button>
+
+            { if self.show_modal {
+                html! {
+                    <div class="modal-overlay" onclick={ctx.link().callback(|_| TemplatePickerMsg::ToggleModal)}>
+                        <div class="modal-card" onclick={|e: MouseEvent| e.stop_propagation()}>
+                            <header class="modal-header">
+                                <div class="modal-header-left">
+                                    <i class="material-icons header-icon">{"folder_open"}</i>
+                                    <h2 class="modal-title">{"Abrir plantilla"}</h2>
+                                </div>
+                                <button class="close-btn" onclick={ctx.link().callback(|_| TemplatePickerMsg::ToggleModal)}>{"✕"}</button>
+                            </header>
+                            <div class="modal-body">
+                                <section class="modal-section">
+                                    { list }
+                                </section>
+                            </div>
+                            <footer class="modal-footer">
+                                <button class="secondary close-btn" onclick={ctx.link().callback(|_| TemplatePickerMsg::ToggleModal)}>{"Cerrar"}</button>
+                            </footer>
+                        </div>
+                    </div>
+                }
+            } else {
+                html! {}
+            } }
+            </>
+        }
+    }
+}
+
+/// Fetches the template list from `GET /api/templates` and sends it as `Loaded`.
+fn fetch_templates(link: yew::html::Scope<TemplatePickerComponent>) {
+    spawn_local(async move {
+        let response = Request::get("/api/templates").send().await;
+        match &response {
+            Ok(_) => connection_monitor::report_success(),
+            Err(_) => connection_monitor::report_failure(),
+        }
+        let result = match response {
+            Ok(resp) if resp.ok() => resp
+                .json::<Vec<TemplateSummary>>()
+                .await
+                .map_err(|e| format!("Respuesta inválida: {}", e)),
+            Ok(resp) => Err(format!("Error al listar plantillas ({})", resp.status())),
+            Err(e) => Err(format!("Error de conexión: {}", e)),
+        };
+        link.send_message(TemplatePickerMsg::Loaded(result));
+    });
+}
@@ -0,0 +1,108 @@
+//! Detects a lost backend connection and recovers from it.
+//!
+//! The editor runs against a local backend that may be restarted while a page is open.
+//! Without this module, every request made in the meantime just fails with a toast and
+//! the user has no clear signal that the session is broken.
+//!
+//! ## Detection
+//! Request sites (template load/save, CSV verification and its status stream) call
+//! `report_failure` when the request fails at the network level (no HTTP response at all)
+//! and `report_success` when a response arrives. HTTP error statuses do not count: they
+//! prove the server is reachable. After `FAILURE_THRESHOLD` consecutive network failures
+//! the connection is considered lost.
+//!
+//! ## Recovery
+//! Once lost, a persistent banner ("Sin conexión con el servidor, reintentando...") is
+//! pinned to the top of the page and `GET /api/health` is polled every
+//! `HEALTH_CHECK_INTERVAL_MS`. As soon as it answers `200 OK`, the banner is removed and the
+//! failure counter is reset. Only one polling loop runs at a time. The failed requests are
+//! not replayed; the user repeats the action once the banner is gone.
+
+use gloo_net::http::Request;
+use std::cell::Cell;
+use wasm_bindgen::JsCast;
+use web_sys::HtmlElement;
+
+/// Consecutive network failures after which the backend is considered unreachable.
+const FAILURE_THRESHOLD: u32 = 2;
+/// Delay between health checks while the backend is unreachable.
+const HEALTH_CHECK_INTERVAL_MS: u32 = 3000;
+/// DOM id of the banner element.
+const BANNER_ID: &str = "connection-lost-banner";
+
+thread_local! {
+    /// Number of consecutive network failures reported since the last success.
+    static CONSECUTIVE_FAILURES: Cell<u32> = const { Cell::new(0) };
+    /// `true` while the health-check loop is running.
+    static RECOVERING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Records a request that got a response from the backend.
+pub fn report_success() {
+    CONSECUTIVE_FAILURES.with(|c| c.set(0));
+}
+
+/// Records a request that failed without reaching the backend. Once the failures reach
+/// `FAILURE_THRESHOLD`, shows the banner and starts polling the health endpoint.
+pub fn report_failure() {
+    let failures = CONSECUTIVE_FAILURES.with(|c| {
+        let n = c.get() + 1;
+        c.set(n);
+        n
+    });
+    if failures < FAILURE_THRESHOLD || RECOVERING.with(|r| r.replace(true)) {
+        return;
+    }
+
+    show_banner();
+    wasm_bindgen_futures::spawn_local(async {
+        loop {
+            gloo_timers::future::TimeoutFuture::new(HEALTH_CHECK_INTERVAL_MS).await;
+            if let Ok(resp) = Request::get("/api/health").send().await {
+                if resp.status() == 200 {
+                    break;
+                }
+            }
+        }
+        CONSECUTIVE_FAILURES.with(|c| c.set(0));
+        RECOVERING.with(|r| r.set(false));
+        hide_banner();
+    });
+}
+
+/// Inserts the "connection lost" banner at the top of the page, if not already present.
+fn show_banner() {
+    let Some(document) = web_sys::window().and_then(|w| w.document()) else {
+        return;
+    };
+    if document.get_element_by_id(BANNER_ID).is_some() {
+        return;
+    }
+    if let (Ok(banner), Some(body)) = (document.create_element("div"), document.body()) {
+        banner.set_id(BANNER_ID);
+        banner.set_text_content(Some("Sin conexión con el servidor, reintentando..."));
+        let html_banner: HtmlElement = banner.unchecked_into();
+        let style = html_banner.style();
+        style.set_property("position", "fixed").ok();
+        style.set_property("top", "0").ok();
+        style.set_property("left", "0").ok();
+        style.set_property("right", "0").ok();
+        style.set_property("background", "#c62828").ok();
+        style.set_property("color", "#fff").ok();
+        style.set_property("text-align", "center").ok();
+        style.set_property("padding", "8px").ok();
+        style.set_property("z-index", "10002").ok();
+        style.set_property("font-family", "Arial, sans-serif").ok();
+        body.append_child(&html_banner).ok();
+    }
+}
+
+/// Removes the "connection lost" banner, if present.
+fn hide_banner() {
+    if let Some(banner) = web_sys::window()
+        .and_then(|w| w.document())
+        .and_then(|d| d.get_element_by_id(BANNER_ID))
+    {
+        banner.remove();
+    }
+}
@@ -1,6 +1,7 @@
 use crate::app::App;
 
 mod app;
+mod connection_monitor;
 mod tops_sheet;
 mod components;
 mod workspace_grid;